@@ -0,0 +1,106 @@
+//! Checks that the eval and verify loops run without allocating once their buffers are set
+//! up, and reports the time per iteration.
+//!
+//! GMP is pointed at the Rust allocator, so that a counting global allocator sees its
+//! allocations as well as those made on the Rust side. Each loop is run for two iteration
+//! counts: any allocation made per iteration shows up as a difference between the two.
+
+use gmp_mpfr_sys::gmp;
+use miner_lottery::config::DEFAULT_MODULUS;
+use miner_lottery::unicorn::{construct_seed, EvalMode, Unicorn};
+use rug::Integer;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Alignment of the blocks handed to GMP
+const GMP_ALIGN: usize = 16;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+extern "C" fn gmp_alloc(size: usize) -> *mut c_void {
+    // SAFETY: GMP never asks for an empty block
+    unsafe { std::alloc::alloc(Layout::from_size_align_unchecked(size, GMP_ALIGN)) as _ }
+}
+
+unsafe extern "C" fn gmp_realloc(ptr: *mut c_void, old: usize, new: usize) -> *mut c_void {
+    let layout = Layout::from_size_align_unchecked(old, GMP_ALIGN);
+    std::alloc::realloc(ptr as _, layout, new) as _
+}
+
+unsafe extern "C" fn gmp_free(ptr: *mut c_void, size: usize) {
+    std::alloc::dealloc(ptr as _, Layout::from_size_align_unchecked(size, GMP_ALIGN))
+}
+
+/// Allocations made by a run, and its duration per iteration in microseconds
+fn measure(iterations: u64, run: impl Fn(u64)) -> (usize, f64) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    run(iterations);
+    let micros = start.elapsed().as_secs_f64() * 1e6 / iterations as f64;
+
+    (ALLOCATIONS.load(Ordering::Relaxed) - before, micros)
+}
+
+/// Runs a loop for two iteration counts, asserting that both allocate the same amount
+fn check(name: &str, run: impl Fn(u64)) {
+    let (short, _) = measure(1_000, &run);
+    let (long, micros) = measure(4_000, &run);
+
+    println!(
+        "{:<14} {:>8.2} us/iteration, {} allocations per run",
+        name, micros, long
+    );
+    assert_eq!(short, long, "{} allocates per iteration", name);
+}
+
+fn main() {
+    // SAFETY: no GMP memory has been allocated yet, so every block is freed by the allocator
+    // that allocated it
+    unsafe { gmp::set_memory_functions(Some(gmp_alloc), Some(gmp_realloc), Some(gmp_free)) };
+
+    let modulus = Integer::from_str_radix(DEFAULT_MODULUS, 10).unwrap();
+    let unicorn = |iterations| Unicorn {
+        seed: construct_seed(&["miner_a".to_string(), "miner_b".to_string()]),
+        modulus: modulus.clone(),
+        iterations,
+        security_level: 1,
+        ..Default::default()
+    };
+
+    check("eval", |iterations| {
+        unicorn(iterations).eval().unwrap();
+    });
+    check("eval hardened", |iterations| {
+        unicorn(iterations)
+            .eval_with_mode(EvalMode::Hardened)
+            .unwrap();
+    });
+    check("verify", |iterations| {
+        let uni = unicorn(iterations);
+        let witness = Integer::from(0x5eed);
+        uni.verify(uni.seed.clone(), witness);
+    });
+}
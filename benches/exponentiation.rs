@@ -0,0 +1,58 @@
+//! Compares GMP's modular exponentiation with the fixed-window exponentiation with Barrett
+//! reduction of `window::FixedWindow`, for the eval exponent `(p + 1) / 4` over calibration
+//! moduli of several sizes, and reports the speedup of the latter.
+
+use miner_lottery::calibrate::calibration_modulus;
+use miner_lottery::window::FixedWindow;
+use rug::Integer;
+use std::time::{Duration, Instant};
+
+/// Modulus sizes to compare, in bits
+const MODULUS_BITS: [u32; 5] = [256, 521, 1024, 2048, 4096];
+
+/// Minimum duration of each measurement
+const MIN_TIME: Duration = Duration::from_millis(500);
+
+/// Time per exponentiation of a chain of exponentiations, doubling its length until it
+/// runs for at least `MIN_TIME`
+fn time_per_pow(mut pow: impl FnMut(&mut Integer)) -> Duration {
+    let mut runs = 16;
+
+    loop {
+        let mut w = Integer::from(0x5eed);
+        let start = Instant::now();
+        for _ in 0..runs {
+            pow(&mut w);
+        }
+        let elapsed = start.elapsed();
+
+        if elapsed >= MIN_TIME {
+            return elapsed / runs;
+        }
+        runs *= 2;
+    }
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>12} {:>14} {:>8}",
+        "bits", "gmp", "fixed-window", "speedup"
+    );
+
+    for bits in MODULUS_BITS {
+        let modulus = calibration_modulus(bits);
+        let exponent = Integer::from(&modulus + 1u32) / 4;
+        let mut window = FixedWindow::new(&modulus, &exponent);
+
+        let gmp = time_per_pow(|w| w.pow_mod_mut(&exponent, &modulus).unwrap());
+        let fixed = time_per_pow(|w| window.pow_mut(w));
+
+        println!(
+            "{:>6} {:>10.1}us {:>12.1}us {:>7.2}x",
+            bits,
+            gmp.as_secs_f64() * 1e6,
+            fixed.as_secs_f64() * 1e6,
+            gmp.as_secs_f64() / fixed.as_secs_f64()
+        );
+    }
+}
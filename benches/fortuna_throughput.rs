@@ -0,0 +1,87 @@
+//! Measures the throughput of the Fortuna CSPRNG that PRNs are drawn from, in bytes per
+//! second, across request sizes on each side of the 16-byte block boundary.
+//!
+//! `get_bytes` allocates its output, `fill_bytes` writes into a reused buffer and the
+//! streaming benchmark draws a large output in small `fill_bytes` requests, so that most of
+//! its bytes go through the remainder kept between blocks.
+//!
+//! The AES backend is picked at runtime and named in each group. Run with
+//! `RUSTFLAGS="--cfg aes_force_soft"` to measure the software backend on a CPU with AES-NI.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use miner_lottery::fortuna::Fortuna;
+
+/// Request sizes to measure, in bytes
+const REQUEST_SIZES: [usize; 7] = [1, 15, 16, 17, 64, 1024, 65_536];
+
+/// Output size of the streaming benchmark, in bytes
+const STREAM_LEN: usize = 65_536;
+
+/// Request sizes the streaming benchmark draws its output in, in bytes
+const STREAM_CHUNKS: [usize; 3] = [4, 8, 24];
+
+/// Name of the AES backend in use
+fn backend() -> &'static str {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if !cfg!(aes_force_soft) && std::arch::is_x86_feature_detected!("aes") {
+        return "aes-ni";
+    }
+
+    "soft"
+}
+
+fn fortuna() -> Fortuna {
+    Fortuna::new(&[0x5e; 32], 1).unwrap()
+}
+
+fn get_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("get_bytes/{}", backend()));
+    let mut rng = fortuna();
+
+    for len in REQUEST_SIZES {
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &len, |b, &len| {
+            b.iter(|| rng.get_bytes(black_box(len)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn fill_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("fill_bytes/{}", backend()));
+    let mut rng = fortuna();
+
+    for len in REQUEST_SIZES {
+        let mut buf = vec![0; len];
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &len, |b, _| {
+            b.iter(|| rng.fill_bytes(black_box(&mut buf)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn streaming(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("streaming/{}", backend()));
+    group.throughput(Throughput::Bytes(STREAM_LEN as u64));
+    let mut rng = fortuna();
+    let mut buf = vec![0; STREAM_LEN];
+
+    for chunk in STREAM_CHUNKS {
+        group.bench_with_input(BenchmarkId::from_parameter(chunk), &chunk, |b, &chunk| {
+            b.iter(|| {
+                for dest in buf.chunks_mut(chunk) {
+                    rng.fill_bytes(dest).unwrap();
+                }
+                black_box(&buf);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, get_bytes, fill_bytes, streaming);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Generates the gRPC service stubs when the `grpc` feature is enabled. The messages of
+//! `proto/lottery.proto` and `proto/types.proto` are defined by hand in `src/grpc.rs` and
+//! `src/proto.rs`, so that building does not require `protoc`.
+//!
+//! With the `cdylib` feature, also writes the C header for `src/ffi.rs` to
+//! `include/miner_lottery.h`, and with the `napi` feature sets up linking of the Node.js
+//! addon.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+    #[cfg(feature = "cdylib")]
+    ffi::header();
+    #[cfg(feature = "napi")]
+    napi_build::setup();
+}
+
+#[cfg(feature = "cdylib")]
+mod ffi {
+    pub fn header() {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("Could not read cbindgen.toml");
+
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .generate()
+            .expect("Could not generate the C header")
+            .write_to_file(format!("{}/include/miner_lottery.h", crate_dir));
+    }
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    const CODEC: &str = "tonic::codec::ProstCodec";
+
+    pub fn compile() {
+        println!("cargo:rerun-if-changed=build.rs");
+
+        let method = |name: &str, route: &str, input: &str, output: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("super::{}", input))
+                .output_type(format!("super::{}", output))
+                .codec_path(CODEC)
+        };
+
+        let service = Service::builder()
+            .name("Lottery")
+            .package("lottery")
+            .method(
+                method(
+                    "submit_contribution",
+                    "SubmitContribution",
+                    "Contribution",
+                    "SubmitReply",
+                )
+                .build(),
+            )
+            .method(method("get_round", "GetRound", "GetRoundRequest", "RoundReply").build())
+            .method(
+                method(
+                    "verify_proof",
+                    "VerifyProof",
+                    "SelectionProof",
+                    "VerifyReply",
+                )
+                .build(),
+            )
+            .method(
+                method(
+                    "watch_rounds",
+                    "WatchRounds",
+                    "WatchRequest",
+                    "PublishedRound",
+                )
+                .server_streaming()
+                .build(),
+            )
+            .build();
+
+        Builder::new().compile(&[service]);
+    }
+}
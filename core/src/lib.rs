@@ -0,0 +1,16 @@
+//! The verification path of miner-lottery, built under `no_std + alloc` so that UNICORNs and
+//! the PRNs drawn from them can be checked inside constrained runtimes.
+//!
+//! Verification matches `miner_lottery::unicorn::Unicorn::verify` and `g_from_witness`, and
+//! PRNs are drawn from the same Fortuna CSPRNG that `miner_lottery` uses, which lives here.
+//! Big integers use the pure-Rust num-bigint backend. The `std` feature implements
+//! `std::error::Error` for the error types.
+
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod fortuna;
+pub mod verify;
@@ -0,0 +1,44 @@
+//! Starts Fortuna at a fuzzed counter, often next to the wrap-around at `u128::MAX`, and
+//! checks each 16-byte block of the stream against a fresh instance started at that
+//! block's counter, after first misaligning the stream with a fuzzed-length request.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use miner_lottery_core::fortuna::Fortuna;
+
+/// Largest number of blocks compared per input
+const MAX_BLOCKS: usize = 64;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 32 + 16 + 3 {
+        return;
+    }
+
+    let key: [u8; 32] = data[..32].try_into().unwrap();
+    let offset = u128::from_be_bytes(data[32..48].try_into().unwrap());
+    let usage = data[48] as u128;
+    let skip = data[49] as usize % 16;
+    let blocks = 1 + data[50] as usize % MAX_BLOCKS;
+
+    // Bias half of the inputs towards the last few counter values before wrapping.
+    let start = if offset & 1 == 0 {
+        u128::MAX - (offset >> 1) % (MAX_BLOCKS as u128)
+    } else {
+        offset
+    };
+
+    let mut fortuna = Fortuna::with_counter(&key, usage, start).unwrap();
+    let head = fortuna.get_bytes(skip).unwrap();
+    let tail = fortuna.get_bytes(blocks * 16 - skip).unwrap();
+    let stream = [head, tail].concat();
+
+    for (i, block) in stream.chunks_exact(16).enumerate() {
+        let cb = start.wrapping_add(i as u128);
+        let expected = Fortuna::with_counter(&key, usage, cb)
+            .unwrap()
+            .get_bytes(16)
+            .unwrap();
+        assert_eq!(block, &expected[..]);
+    }
+});
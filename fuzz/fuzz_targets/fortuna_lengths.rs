@@ -0,0 +1,61 @@
+//! Draws a fuzzed sequence of request lengths from one Fortuna instance and checks that the
+//! bytes are the same stream that a single request of the total length returns.
+//!
+//! Lengths are biased towards the block boundaries (0, 1, 15, 16, 17) and occasional large
+//! requests, since those are where the `bits_remainder` bookkeeping can go wrong. Requests
+//! alternate between `get_bytes` and `fill_bytes` so both paths are compared.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use miner_lottery_core::fortuna::Fortuna;
+
+/// Upper bound on the bytes drawn per input, to keep executions fast
+const MAX_TOTAL: usize = 1 << 20;
+
+/// Maps two fuzzed bytes to a request length
+fn request_len(selector: u8, extra: u8) -> usize {
+    match selector % 8 {
+        0 => 0,
+        1 => 1,
+        2 => 15,
+        3 => 16,
+        4 => 17,
+        5 => 16 * extra as usize + (selector as usize >> 3) % 3,
+        6 => 4096 * extra as usize + 1,
+        _ => extra as usize,
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 33 {
+        return;
+    }
+
+    let (key, rest) = data.split_at(32);
+    let key: [u8; 32] = key.try_into().unwrap();
+    let usage = rest[0] as u128;
+
+    let mut lengths = Vec::new();
+    let mut total = 0;
+    for pair in rest[1..].chunks_exact(2) {
+        let len = request_len(pair[0], pair[1]).min(MAX_TOTAL - total);
+        lengths.push(len);
+        total += len;
+    }
+
+    let mut fortuna = Fortuna::new(&key, usage).unwrap();
+    let mut stream = Vec::with_capacity(total);
+    for (i, len) in lengths.iter().enumerate() {
+        if i % 2 == 0 {
+            stream.extend(fortuna.get_bytes(*len).unwrap());
+        } else {
+            let mut buf = vec![0; *len];
+            fortuna.fill_bytes(&mut buf).unwrap();
+            stream.extend(buf);
+        }
+    }
+
+    let expected = Fortuna::new(&key, usage).unwrap().get_bytes(total).unwrap();
+    assert_eq!(stream, expected);
+});
@@ -0,0 +1,112 @@
+/* Generated by cbindgen from src/ffi.rs with the `cdylib` feature; do not edit. */
+
+#ifndef MINER_LOTTERY_H
+#define MINER_LOTTERY_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of a call. Values are stable and will not be reused
+typedef enum MlStatus {
+  // The call succeeded.
+  ML_STATUS_OK = 0,
+  // A required pointer was null.
+  ML_STATUS_NULL_POINTER = 1,
+  // An input was not valid UTF-8 or not valid JSON.
+  ML_STATUS_INVALID_INPUT = 2,
+  // The UNICORN parameters are not valid.
+  ML_STATUS_INVALID_PARAMS = 3,
+  // The UNICORN did not verify.
+  ML_STATUS_VERIFY_FAILED = 4,
+  // Winners could not be drawn from the participants.
+  ML_STATUS_SELECTION_FAILED = 5,
+  // The library panicked; this is a bug.
+  ML_STATUS_PANIC = 6,
+} MlStatus;
+
+// Bytes allocated by the library, to be released with `ml_buffer_free`
+typedef struct MlBuffer {
+  uint8_t *data;
+  size_t len;
+} MlBuffer;
+
+// Evaluates the UNICORN for a seed, writing the evaluated `UnicornInfo` to `out` as JSON
+//
+// ### Arguments
+//
+// * `seed`       - Seed as big-endian bytes
+// * `seed_len`   - Length of `seed`
+// * `params`     - `UnicornFixedParam` as JSON
+// * `params_len` - Length of `params`
+// * `out`        - Buffer receiving the result
+//
+// # Safety
+//
+// `seed` and `params` must point to at least `seed_len` and `params_len` readable bytes, and
+// `out` must point to a writable `MlBuffer`.
+enum MlStatus ml_eval(const uint8_t *seed,
+                      size_t seed_len,
+                      const uint8_t *params,
+                      size_t params_len,
+                      struct MlBuffer *out);
+
+// Verifies an evaluated UNICORN, returning `ML_STATUS_OK` if its modulus, witness and `g`
+// value all check out and `ML_STATUS_VERIFY_FAILED` otherwise
+//
+// ### Arguments
+//
+// * `unicorn`     - `UnicornInfo` as JSON, as written by `ml_eval`
+// * `unicorn_len` - Length of `unicorn`
+//
+// # Safety
+//
+// `unicorn` must point to at least `unicorn_len` readable bytes.
+enum MlStatus ml_verify(const uint8_t *unicorn, size_t unicorn_len);
+
+// Draws distinct winners from a UNICORN, writing the `WinnersProof` of the draw to `out` as
+// JSON. Its `winners` are indices into the participants
+//
+// ### Arguments
+//
+// * `unicorn`          - `UnicornInfo` as JSON, as written by `ml_eval`
+// * `unicorn_len`      - Length of `unicorn`
+// * `participants`     - Identities of the participants, one per line, in input order
+// * `participants_len` - Length of `participants`
+// * `usage_number`     - Usage number of the draw
+// * `winners`          - Number of winners
+// * `out`              - Buffer receiving the result
+//
+// # Safety
+//
+// `unicorn` and `participants` must point to at least `unicorn_len` and `participants_len`
+// readable bytes, and `out` must point to a writable `MlBuffer`.
+enum MlStatus ml_select(const uint8_t *unicorn,
+                        size_t unicorn_len,
+                        const uint8_t *participants,
+                        size_t participants_len,
+                        uint64_t usage_number,
+                        size_t winners,
+                        struct MlBuffer *out);
+
+// Releases a buffer written by the library. Releasing an empty buffer does nothing
+//
+// ### Arguments
+//
+// * `buffer` - Buffer to release
+//
+// # Safety
+//
+// `buffer` must have been written by this library and not released before.
+void ml_buffer_free(struct MlBuffer buffer);
+
+// Static, NUL-terminated description of a status
+//
+// ### Arguments
+//
+// * `status` - Status returned by a call
+const char *ml_status_message(enum MlStatus status);
+
+#endif  /* MINER_LOTTERY_H */
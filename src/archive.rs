@@ -0,0 +1,325 @@
+//! Archival of finished rounds to object storage.
+//!
+//! Rounds are archived as their `.round` bundle (see `bundle`), gzip-compressed. Bundles are
+//! checked again when they are retrieved, so that a corrupted or tampered archive is reported
+//! rather than trusted.
+//!
+//! `S3Archive` uploads bundles to and retrieves them from an S3-compatible object store.
+//! Objects are addressed by path, as `{endpoint}/{bucket}/{key}`, and requests are signed
+//! with AWS Signature Version 4, which MinIO, Ceph and most other S3-compatible stores accept.
+
+use crate::bundle::{BundleError, RoundBundle};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::io::{Read, Write};
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The archived bundle could not be decompressed, or the object store address is invalid.
+    Malformed(String),
+    /// The bundle is invalid.
+    Bundle(BundleError),
+    /// The object store could not be reached.
+    Http(reqwest::Error),
+    /// The object store refused the request with the given status.
+    Status(u16),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Malformed(e) => write!(f, "Malformed archive: {}", e),
+            ArchiveError::Bundle(e) => write!(f, "{}", e),
+            ArchiveError::Http(e) => write!(f, "Object store request failed: {}", e),
+            ArchiveError::Status(status) => {
+                write!(f, "Object store refused the request with status {}", status)
+            }
+        }
+    }
+}
+
+impl Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArchiveError::Bundle(e) => Some(e),
+            ArchiveError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BundleError> for ArchiveError {
+    fn from(e: BundleError) -> Self {
+        ArchiveError::Bundle(e)
+    }
+}
+
+impl From<reqwest::Error> for ArchiveError {
+    fn from(e: reqwest::Error) -> Self {
+        ArchiveError::Http(e)
+    }
+}
+
+/// Compresses a bundle for archival
+///
+/// ### Arguments
+///
+/// * `bundle` - Bundle to archive
+pub fn compress(bundle: &RoundBundle) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bundle.export().as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Decompresses and verifies an archived bundle
+///
+/// ### Arguments
+///
+/// * `bytes` - Compressed bundle
+pub fn decompress(bytes: &[u8]) -> Result<RoundBundle, ArchiveError> {
+    let mut contents = String::new();
+    GzDecoder::new(bytes)
+        .read_to_string(&mut contents)
+        .map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+
+    Ok(RoundBundle::import(&contents)?)
+}
+
+/// Key under which the bundle of a round is archived. Ids are zero-padded so that keys list
+/// in round order
+///
+/// ### Arguments
+///
+/// * `round_id` - Id of the round
+pub fn object_key(round_id: u64) -> String {
+    format!("rounds/{:020}.round.gz", round_id)
+}
+
+/// Location of, and credentials for, an S3-compatible bucket
+#[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct S3Config {
+    /// Base URL of the object store, e.g. `https://s3.eu-west-1.amazonaws.com`
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key", &self.access_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Archive of round bundles in an S3-compatible bucket
+#[derive(Debug, Clone)]
+pub struct S3Archive {
+    config: S3Config,
+    http: reqwest::Client,
+}
+
+impl S3Archive {
+    /// Creates an archive in a bucket
+    ///
+    /// ### Arguments
+    ///
+    /// * `config` - Bucket and credentials
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Uploads a bundle, returning its key
+    ///
+    /// ### Arguments
+    ///
+    /// * `bundle` - Bundle to archive
+    /// * `now`    - Current unix time in seconds, used to sign the request
+    pub async fn upload(&self, bundle: &RoundBundle, now: u64) -> Result<String, ArchiveError> {
+        let key = object_key(bundle.round.id);
+        let response = self
+            .request(reqwest::Method::PUT, &key, compress(bundle), now)?
+            .header(reqwest::header::CONTENT_TYPE, "application/gzip")
+            .send()
+            .await?;
+
+        match response.status().is_success() {
+            true => Ok(key),
+            false => Err(ArchiveError::Status(response.status().as_u16())),
+        }
+    }
+
+    /// Retrieves and verifies the bundle of a round
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round
+    /// * `now`      - Current unix time in seconds, used to sign the request
+    pub async fn retrieve(&self, round_id: u64, now: u64) -> Result<RoundBundle, ArchiveError> {
+        let key = object_key(round_id);
+        let response = self
+            .request(reqwest::Method::GET, &key, Vec::new(), now)?
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(ArchiveError::Status(response.status().as_u16()));
+        }
+
+        decompress(&response.bytes().await?)
+    }
+
+    /// Builds a request for an object, signed with AWS Signature Version 4
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+        now: u64,
+    ) -> Result<reqwest::RequestBuilder, ArchiveError> {
+        let path = format!("/{}/{}", self.config.bucket, key);
+        let url = reqwest::Url::parse(&self.config.endpoint)
+            .and_then(|endpoint| endpoint.join(&path))
+            .map_err(|e| ArchiveError::Malformed(e.to_string()))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let (amz_date, date) = amz_date(now);
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&self.config.secret_key, &date, &self.config.region, "s3");
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key, scope, SIGNED_HEADERS, signature
+        );
+
+        Ok(self
+            .http
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .body(body))
+    }
+}
+
+/// Headers covered by request signatures
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Key signing requests to a service on a given day
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// Formats a unix time as the `YYYYMMDDTHHMMSSZ` timestamp and `YYYYMMDD` date of a request
+fn amz_date(now: u64) -> (String, String) {
+    let (days, secs) = ((now / 86_400) as i64, now % 86_400);
+
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let timestamp = format!(
+        "{}T{:02}{:02}{:02}Z",
+        date,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    );
+    (timestamp, date)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+    use crate::rounds::{Round, RoundTimeouts};
+    use crate::unicorn::UnicornFixedParam;
+
+    #[test]
+    /// Checks that a compressed bundle decompresses unchanged and that damage is reported
+    fn compressed_bundles() {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 600,
+        };
+        let mut round = Round::new(7, params, 1, timeouts, 0);
+        round.contribute("miner_a", 0).unwrap();
+        round.seal().unwrap();
+        round.evaluate(0).unwrap();
+        let bundle = RoundBundle::new(round).unwrap();
+
+        assert_eq!(object_key(7), "rounds/00000000000000000007.round.gz");
+        assert_eq!(decompress(&compress(&bundle)).unwrap(), bundle);
+        assert!(matches!(
+            decompress(b"not gzip"),
+            Err(ArchiveError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    /// Checks request dates and the signing key against the AWS Signature Version 4 examples
+    fn signs_requests() {
+        assert_eq!(
+            amz_date(1_369_353_600),
+            ("20130524T000000Z".to_string(), "20130524".to_string())
+        );
+        assert_eq!(amz_date(951_825_599).0, "20000229T115959Z");
+
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
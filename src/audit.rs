@@ -0,0 +1,240 @@
+//! Pass/fail audit of published lottery artifacts.
+//!
+//! Where `disputes` looks for the steps that break a particular claim, an audit reports the
+//! outcome of every check on a published artifact, so that a round can be signed off in one
+//! go. A bare `UnicornInfo` can only be checked up to its `g` value; a selection transcript is
+//! also checked through to the winner.
+//!
+//! Artifacts can be audited without knowing their kind up front: a published `Round`, a
+//! `SelectionTranscript` or a `UnicornInfo` are recognised from their JSON.
+
+use crate::config::validate_params;
+use crate::disputes::{check_claim, DisputeFinding};
+use crate::rounds::Round;
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{g_matches, UnicornFixedParam, UnicornInfo};
+use serde::{Deserialize, Serialize};
+
+/// A check made by an audit
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The modulus is a prime congruent to 3 mod 4 of sufficient size.
+    Modulus,
+    /// The seed matches the inputs.
+    Seed,
+    /// The commitment matches the seed.
+    Commitment,
+    /// The witness chain leads back to the seed.
+    Witness,
+    /// The `g` value is the hash of the witness.
+    GValue,
+    /// The PRN was drawn with the recorded usage number.
+    UsageNumber,
+    /// The winner index follows from the PRN.
+    WinnerIndex,
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CheckResult {
+    pub check: Check,
+    pub passed: bool,
+    /// Reason the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Outcome of every check made on an artifact
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl AuditReport {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    fn record(&mut self, check: Check, failure: Option<String>) {
+        self.checks.push(CheckResult {
+            check,
+            passed: failure.is_none(),
+            detail: failure,
+        });
+    }
+}
+
+/// A published artifact that can be audited
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Artifact {
+    Round(Box<Round>),
+    Transcript(Box<SelectionTranscript>),
+    Unicorn(UnicornInfo),
+}
+
+impl Artifact {
+    /// Recognises an artifact from its JSON, trying a round, then a transcript, then a
+    /// UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `json` - JSON of the artifact
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        if let Ok(round) = serde_json::from_str(json) {
+            return Ok(Artifact::Round(Box::new(round)));
+        }
+        if let Ok(transcript) = serde_json::from_str(json) {
+            return Ok(Artifact::Transcript(Box::new(transcript)));
+        }
+
+        serde_json::from_str(json).map(Artifact::Unicorn)
+    }
+
+    /// The selection transcript of the artifact, if it has one
+    pub fn transcript(&self) -> Option<&SelectionTranscript> {
+        match self {
+            Artifact::Round(round) => round.transcript.as_ref(),
+            Artifact::Transcript(transcript) => Some(transcript),
+            Artifact::Unicorn(_) => None,
+        }
+    }
+
+    /// Audits the artifact. Returns `None` for a round that has not been published
+    pub fn audit(&self) -> Option<AuditReport> {
+        match self {
+            Artifact::Unicorn(info) => Some(audit_unicorn(info)),
+            _ => self.transcript().map(audit_transcript),
+        }
+    }
+}
+
+/// Audits an evaluated UNICORN: its modulus, witness chain and `g` value
+///
+/// ### Arguments
+///
+/// * `info` - Evaluated UNICORN
+pub fn audit_unicorn(info: &UnicornInfo) -> AuditReport {
+    let uni = &info.unicorn;
+    let params = UnicornFixedParam::from(uni);
+
+    let mut report = AuditReport::default();
+    report.record(
+        Check::Modulus,
+        validate_params(&params).err().map(|e| e.to_string()),
+    );
+    report.record(
+        Check::Witness,
+        (!uni.verify_ref(&uni.seed, &info.witness))
+            .then(|| "witness does not lead back to the seed".to_string()),
+    );
+    report.record(
+        Check::GValue,
+        (!g_matches(&info.witness, &info.g_value))
+            .then(|| "g value is not the hash of the witness".to_string()),
+    );
+
+    report
+}
+
+/// Audits a selection transcript from its modulus through to its winner
+///
+/// ### Arguments
+///
+/// * `transcript` - Published transcript
+pub fn audit_transcript(transcript: &SelectionTranscript) -> AuditReport {
+    let findings = check_claim(&transcript.winner, transcript).findings;
+    let failure = |check: Check| {
+        findings
+            .iter()
+            .find(|f| finding_check(f) == Some(check))
+            .map(|f| serde_json::to_string(f).unwrap())
+    };
+
+    let mut report = AuditReport::default();
+    report.record(
+        Check::Modulus,
+        validate_params(&transcript.fixed_params)
+            .err()
+            .map(|e| e.to_string()),
+    );
+    for check in [
+        Check::Seed,
+        Check::Commitment,
+        Check::Witness,
+        Check::GValue,
+        Check::UsageNumber,
+        Check::WinnerIndex,
+    ] {
+        report.record(check, failure(check));
+    }
+
+    report
+}
+
+fn finding_check(finding: &DisputeFinding) -> Option<Check> {
+    match finding {
+        DisputeFinding::SeedMismatch { .. } => Some(Check::Seed),
+        DisputeFinding::InvalidParams => Some(Check::Modulus),
+        DisputeFinding::CommitmentMismatch => Some(Check::Commitment),
+        DisputeFinding::BadWitness => Some(Check::Witness),
+        DisputeFinding::GValueMismatch => Some(Check::GValue),
+        DisputeFinding::WrongUsageNumber { .. } => Some(Check::UsageNumber),
+        DisputeFinding::WrongIndexMapping { .. } | DisputeFinding::NoInputs => {
+            Some(Check::WinnerIndex)
+        }
+        DisputeFinding::ClaimMismatch { .. } => None,
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod audit_tests {
+    use super::*;
+
+    fn test_transcript() -> SelectionTranscript {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+
+        SelectionTranscript::record(&inputs, &params, 1).unwrap()
+    }
+
+    #[test]
+    /// Checks that every check of a valid transcript passes and failures are reported per check
+    fn audit_checks() {
+        let transcript = test_transcript();
+        let mut tampered = transcript.clone();
+        tampered.winner_index ^= 1;
+        tampered.winner = tampered.inputs[tampered.winner_index].clone();
+
+        let report = audit_transcript(&tampered);
+        let failed: Vec<Check> = report
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.check)
+            .collect();
+
+        assert!(audit_transcript(&transcript).passed());
+        assert_eq!(report.checks.len(), 7);
+        assert_eq!(failed, vec![Check::WinnerIndex]);
+    }
+
+    #[test]
+    /// Checks that artifacts are recognised from their JSON
+    fn artifact_kinds() {
+        let transcript = test_transcript();
+        let json = serde_json::to_string(&transcript).unwrap();
+        let artifact = Artifact::from_json(&json).unwrap();
+
+        assert!(matches!(artifact, Artifact::Transcript(_)));
+        assert_eq!(artifact.audit(), Some(audit_transcript(&transcript)));
+        assert!(Artifact::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! Append-only, hash-chained log of round events.
+//!
+//! Every event in a round's life is appended to the log as one JSON object per line: accepted
+//! contributions, the sealed inputs digest, the published witness and the selected winner.
+//! Each entry carries its sequence number, the hash of the previous entry and its own hash,
+//! computed over the entry's other fields, so that the log forms a chain:
+//!
+//! - editing an entry breaks its hash
+//! - removing or reordering entries breaks the sequence and the chain
+//! - a partially written final entry is reported as truncation
+//!
+//! Removing entries from the end leaves a valid, shorter chain. To detect that, the verifier
+//! can be given a `LogHead` recorded earlier, such as one published alongside a round, which
+//! the log must still contain.
+
+use crate::rounds::Round;
+use bincode::serialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Previous hash of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuditLogError {
+    /// The log file could not be read or written.
+    Io(String),
+    /// A line is not a log entry.
+    Malformed { line: usize },
+    /// An entry's hash does not match its contents.
+    BadHash { seq: u64 },
+    /// An entry does not follow on from the previous one.
+    BrokenChain { seq: u64 },
+    /// Entries are missing or out of order.
+    OutOfSequence { expected: u64, actual: u64 },
+    /// The log ends before an entry it should contain.
+    Truncated { entries: u64 },
+    /// The log does not contain the expected head.
+    HeadMismatch { seq: u64 },
+}
+
+impl From<io::Error> for AuditLogError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl fmt::Display for AuditLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditLogError::Io(e) => write!(f, "Audit log I/O error: {}", e),
+            AuditLogError::Malformed { line } => {
+                write!(f, "Audit log line {} is not an entry", line)
+            }
+            AuditLogError::BadHash { seq } => {
+                write!(f, "Audit log entry {} does not match its hash", seq)
+            }
+            AuditLogError::BrokenChain { seq } => {
+                write!(
+                    f,
+                    "Audit log entry {} does not follow the previous entry",
+                    seq
+                )
+            }
+            AuditLogError::OutOfSequence { expected, actual } => write!(
+                f,
+                "Audit log entry {} found where entry {} was expected",
+                actual, expected
+            ),
+            AuditLogError::Truncated { entries } => {
+                write!(f, "Audit log is truncated after {} entries", entries)
+            }
+            AuditLogError::HeadMismatch { seq } => {
+                write!(f, "Audit log entry {} differs from the expected head", seq)
+            }
+        }
+    }
+}
+
+impl Error for AuditLogError {}
+
+/// Event recorded in the log
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A contribution was accepted into a round.
+    ContributionAccepted {
+        round_id: u64,
+        identity: String,
+        fingerprint: String,
+    },
+    /// A round was sealed with the given inputs digest.
+    Sealed {
+        round_id: u64,
+        participants: usize,
+        sealed_digest: Option<String>,
+    },
+    /// The eval of a round completed with the given witness.
+    WitnessPublished {
+        round_id: u64,
+        /// Hex-encoded witness
+        witness: String,
+        g_value: String,
+    },
+    /// A round's winner was selected.
+    WinnerSelected {
+        round_id: u64,
+        winner: String,
+        winner_index: usize,
+    },
+}
+
+impl AuditEvent {
+    /// Event for a sealed round
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Sealed round
+    pub fn sealed(round: &Round) -> Self {
+        AuditEvent::Sealed {
+            round_id: round.id,
+            participants: round.registry.len(),
+            sealed_digest: round.sealed_digest.clone(),
+        }
+    }
+
+    /// Events for a published round: its witness and then its winner. Empty if the round
+    /// has not been published
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Published round
+    pub fn published(round: &Round) -> Vec<Self> {
+        let Some(transcript) = &round.transcript else {
+            return Vec::new();
+        };
+
+        vec![
+            AuditEvent::WitnessPublished {
+                round_id: round.id,
+                witness: transcript.witness.to_string_radix(16),
+                g_value: transcript.g_value.clone(),
+            },
+            AuditEvent::WinnerSelected {
+                round_id: round.id,
+                winner: transcript.winner.clone(),
+                winner_index: transcript.winner_index,
+            },
+        ]
+    }
+}
+
+/// A line of the log
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct LogEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    /// Hash of the previous entry
+    pub prev_hash: String,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// Hash of this entry
+    pub hash: String,
+}
+
+impl LogEntry {
+    /// Hash of the entry's fields other than its own hash
+    pub fn compute_hash(&self) -> String {
+        let input = serialize(&(self.seq, self.timestamp, &self.prev_hash, &self.event)).unwrap();
+        hex::encode(Sha256::digest(input))
+    }
+}
+
+/// Number of entries in a log and the hash of the last one
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct LogHead {
+    pub entries: u64,
+    pub hash: String,
+}
+
+impl Default for LogHead {
+    fn default() -> Self {
+        Self {
+            entries: 0,
+            hash: GENESIS_HASH.to_string(),
+        }
+    }
+}
+
+/// Checks every entry of a log, returning its head
+///
+/// ### Arguments
+///
+/// * `contents` - Contents of the log
+/// * `expected` - Head recorded earlier, which the log must still contain
+pub fn verify_log(contents: &str, expected: Option<&LogHead>) -> Result<LogHead, AuditLogError> {
+    let mut head = LogHead::default();
+
+    for (i, line) in contents.split_inclusive('\n').enumerate() {
+        if !line.ends_with('\n') {
+            return Err(AuditLogError::Truncated {
+                entries: head.entries,
+            });
+        }
+        let entry: LogEntry =
+            serde_json::from_str(line).map_err(|_| AuditLogError::Malformed { line: i + 1 })?;
+
+        if entry.seq != head.entries {
+            return Err(AuditLogError::OutOfSequence {
+                expected: head.entries,
+                actual: entry.seq,
+            });
+        }
+        if entry.prev_hash != head.hash {
+            return Err(AuditLogError::BrokenChain { seq: entry.seq });
+        }
+        if entry.compute_hash() != entry.hash {
+            return Err(AuditLogError::BadHash { seq: entry.seq });
+        }
+
+        head = LogHead {
+            entries: entry.seq + 1,
+            hash: entry.hash,
+        };
+        if matches!(expected, Some(e) if e.entries == head.entries && e.hash != head.hash) {
+            return Err(AuditLogError::HeadMismatch { seq: entry.seq });
+        }
+    }
+
+    match expected {
+        Some(e) if e.entries > head.entries => Err(AuditLogError::Truncated {
+            entries: head.entries,
+        }),
+        _ => Ok(head),
+    }
+}
+
+/// Writer appending to a log file
+#[derive(Debug)]
+pub struct AuditLog {
+    file: File,
+    head: LogHead,
+}
+
+impl AuditLog {
+    /// Opens a log for appending, creating it if needed. An existing log is verified first,
+    /// so that new entries are never chained onto a tampered log
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Path of the log file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditLogError> {
+        let path = path.as_ref();
+        let head = match fs::read_to_string(path) {
+            Ok(contents) => verify_log(&contents, None)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => LogHead::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self { file, head })
+    }
+
+    /// Head of the log
+    pub fn head(&self) -> &LogHead {
+        &self.head
+    }
+
+    /// Appends an event, returning the new entry
+    ///
+    /// ### Arguments
+    ///
+    /// * `event`     - Event to record
+    /// * `timestamp` - Unix time of the event in seconds
+    pub fn append(&mut self, event: AuditEvent, timestamp: u64) -> Result<LogEntry, AuditLogError> {
+        let mut entry = LogEntry {
+            seq: self.head.entries,
+            timestamp,
+            prev_hash: self.head.hash.clone(),
+            event,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+
+        let mut line = serde_json::to_string(&entry).unwrap();
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+
+        self.head = LogHead {
+            entries: entry.seq + 1,
+            hash: entry.hash.clone(),
+        };
+        Ok(entry)
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod auditlog_tests {
+    use super::*;
+
+    fn write_log(name: &str, events: u64) -> (std::path::PathBuf, LogHead) {
+        let path = std::env::temp_dir().join(format!(
+            "miner-lottery-auditlog-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let mut log = AuditLog::open(&path).unwrap();
+        for i in 0..events {
+            let event = AuditEvent::ContributionAccepted {
+                round_id: 1,
+                identity: format!("miner_{}", i),
+                fingerprint: format!("{:02x}", i),
+            };
+            log.append(event, 100 + i).unwrap();
+        }
+
+        (path, log.head().clone())
+    }
+
+    #[test]
+    /// Checks that an untouched log verifies and reopening continues the chain
+    fn append_and_reopen() {
+        let (path, head) = write_log("reopen", 2);
+        let mut log = AuditLog::open(&path).unwrap();
+        assert_eq!(log.head(), &head);
+
+        let entry = log
+            .append(
+                AuditEvent::Sealed {
+                    round_id: 1,
+                    participants: 2,
+                    sealed_digest: None,
+                },
+                200,
+            )
+            .unwrap();
+        assert_eq!(entry.prev_hash, head.hash);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(verify_log(&contents, Some(&head)), Ok(log.head().clone()));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    /// Checks that edits, removals and truncation are detected
+    fn detects_tampering() {
+        let (path, head) = write_log("tamper", 3);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+
+        let edited = contents.replace("miner_1", "miner_9");
+        assert_eq!(
+            verify_log(&edited, None),
+            Err(AuditLogError::BadHash { seq: 1 })
+        );
+
+        let removed = [lines[0], lines[2]].concat();
+        assert_eq!(
+            verify_log(&removed, None),
+            Err(AuditLogError::OutOfSequence {
+                expected: 1,
+                actual: 2
+            })
+        );
+
+        let shortened = lines[..2].concat();
+        assert!(verify_log(&shortened, None).is_ok());
+        assert_eq!(
+            verify_log(&shortened, Some(&head)),
+            Err(AuditLogError::Truncated { entries: 2 })
+        );
+
+        let partial = &contents[..contents.len() - 5];
+        assert_eq!(
+            verify_log(partial, None),
+            Err(AuditLogError::Truncated { entries: 2 })
+        );
+    }
+}
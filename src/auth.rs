@@ -0,0 +1,277 @@
+//! Authorization of contribution submissions.
+//!
+//! Contribution endpoints hand the caller's credentials and the submitted contribution to an
+//! `Authorizer`, so that only registered miners can contribute to the seed. Credentials are
+//! taken from the request headers:
+//!
+//! - `Authorization: Bearer <key>`: an API key issued to a miner
+//! - `X-Signature: <hex>`: an Ed25519 signature over the contribution, as made by
+//!   `Signed::sign`, by the key the contribution's identity names
+//!
+//! `ApiKeyAuthorizer` accepts contributions whose identity matches the miner an API key was
+//! issued to, and `SignatureAuthorizer` accepts contributions signed by a registered miner
+//! key. Custom policies implement `Authorizer` themselves. Servers accept every contribution
+//! unless an authorizer is set.
+
+use crate::rounds::Contribution;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Header carrying a contribution signature
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuthError {
+    /// No usable credentials were presented.
+    MissingCredentials,
+    /// The credentials were not recognised.
+    InvalidCredentials,
+    /// The credentials do not allow contributing for this identity.
+    Forbidden(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::MissingCredentials => write!(f, "Missing credentials"),
+            AuthError::InvalidCredentials => write!(f, "Invalid credentials"),
+            AuthError::Forbidden(identity) => {
+                write!(f, "Not authorized to contribute as {}", identity)
+            }
+        }
+    }
+}
+
+impl Error for AuthError {}
+
+/// Credentials presented with a request
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct Credentials {
+    /// API key from an `Authorization: Bearer` header
+    pub api_key: Option<String>,
+    /// Hex-encoded signature from the `X-Signature` header
+    pub signature: Option<String>,
+}
+
+impl Credentials {
+    /// Reads credentials from request headers
+    ///
+    /// ### Arguments
+    ///
+    /// * `header` - Looks up a header value by lowercase name
+    pub fn from_headers<'a>(header: impl Fn(&str) -> Option<&'a str>) -> Self {
+        Self {
+            api_key: header("authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(|key| key.trim().to_string()),
+            signature: header(SIGNATURE_HEADER).map(|s| s.trim().to_string()),
+        }
+    }
+}
+
+/// Policy deciding who may submit a contribution
+pub trait Authorizer: Send + Sync {
+    /// Checks that the credentials allow submitting the contribution
+    ///
+    /// ### Arguments
+    ///
+    /// * `credentials`  - Credentials presented with the request
+    /// * `contribution` - Submitted contribution
+    fn authorize(
+        &self,
+        credentials: &Credentials,
+        contribution: &Contribution,
+    ) -> Result<(), AuthError>;
+}
+
+/// Accepts every contribution
+#[derive(Default, Debug, Clone, Copy)]
+pub struct AllowAll;
+
+impl Authorizer for AllowAll {
+    fn authorize(&self, _: &Credentials, _: &Contribution) -> Result<(), AuthError> {
+        Ok(())
+    }
+}
+
+/// Accepts contributions presenting an API key issued to their identity
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct ApiKeyAuthorizer {
+    /// Miner identity by API key
+    keys: BTreeMap<String, String>,
+}
+
+impl ApiKeyAuthorizer {
+    /// Creates an authorizer from the identity each API key was issued to
+    ///
+    /// ### Arguments
+    ///
+    /// * `keys` - Miner identity by API key
+    pub fn new(keys: BTreeMap<String, String>) -> Self {
+        Self { keys }
+    }
+}
+
+impl Authorizer for ApiKeyAuthorizer {
+    fn authorize(
+        &self,
+        credentials: &Credentials,
+        contribution: &Contribution,
+    ) -> Result<(), AuthError> {
+        let key = credentials
+            .api_key
+            .as_ref()
+            .ok_or(AuthError::MissingCredentials)?;
+        let identity = self.keys.get(key).ok_or(AuthError::InvalidCredentials)?;
+
+        match *identity == contribution.identity {
+            true => Ok(()),
+            false => Err(AuthError::Forbidden(contribution.identity.clone())),
+        }
+    }
+}
+
+/// Accepts contributions signed by the registered miner key their identity names
+#[cfg(feature = "signing")]
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct SignatureAuthorizer {
+    /// Hex-encoded public keys of the registered miners
+    keys: std::collections::BTreeSet<String>,
+}
+
+#[cfg(feature = "signing")]
+impl SignatureAuthorizer {
+    /// Creates an authorizer for a set of registered miner keys
+    ///
+    /// ### Arguments
+    ///
+    /// * `keys` - Hex-encoded public keys of the registered miners
+    pub fn new(keys: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            keys: keys
+                .into_iter()
+                .map(|key| key.trim().to_ascii_lowercase())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+impl Authorizer for SignatureAuthorizer {
+    fn authorize(
+        &self,
+        credentials: &Credentials,
+        contribution: &Contribution,
+    ) -> Result<(), AuthError> {
+        use crate::signing::Signed;
+        use ed25519_dalek::PublicKey;
+
+        let signature = credentials
+            .signature
+            .as_ref()
+            .ok_or(AuthError::MissingCredentials)?;
+        let identity = contribution.identity.to_ascii_lowercase();
+        if !self.keys.contains(&identity) {
+            return Err(AuthError::Forbidden(contribution.identity.clone()));
+        }
+
+        let public_key = hex::decode(&identity)
+            .ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or(AuthError::InvalidCredentials)?;
+        let signed = Signed {
+            payload: contribution.clone(),
+            public_key: identity,
+            signature: signature.clone(),
+        };
+
+        signed
+            .verify_signature(&public_key)
+            .map_err(|_| AuthError::InvalidCredentials)
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod auth_tests {
+    use super::*;
+
+    fn contribution(identity: &str) -> Contribution {
+        Contribution {
+            round_id: 1,
+            identity: identity.to_string(),
+            timestamp: 10,
+            nonce: None,
+        }
+    }
+
+    #[test]
+    /// Checks that API keys only authorize their own identity
+    fn api_keys() {
+        let keys = BTreeMap::from([("k1".to_string(), "miner_a".to_string())]);
+        let authorizer = ApiKeyAuthorizer::new(keys);
+        let headers = |name: &str| (name == "authorization").then_some("Bearer k1");
+        let credentials = Credentials::from_headers(headers);
+
+        assert_eq!(credentials.api_key.as_deref(), Some("k1"));
+        assert_eq!(
+            authorizer.authorize(&credentials, &contribution("miner_a")),
+            Ok(())
+        );
+        assert_eq!(
+            authorizer.authorize(&credentials, &contribution("miner_b")),
+            Err(AuthError::Forbidden("miner_b".to_string()))
+        );
+        assert_eq!(
+            authorizer.authorize(&Credentials::default(), &contribution("miner_a")),
+            Err(AuthError::MissingCredentials)
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    /// Checks that signatures must come from the registered key the identity names
+    fn signatures() {
+        use crate::signing::Signed;
+        use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+
+        let keypair = |seed: u8| {
+            let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+            let public = PublicKey::from(&secret);
+            Keypair { secret, public }
+        };
+        let miner = keypair(1);
+        let outsider = keypair(2);
+        let authorizer = SignatureAuthorizer::new([hex::encode(miner.public.as_bytes())]);
+
+        let signed = |keypair: &Keypair| {
+            let signed = Signed::sign(
+                contribution(&hex::encode(keypair.public.as_bytes())),
+                keypair,
+            );
+            let credentials = Credentials {
+                api_key: None,
+                signature: Some(signed.signature),
+            };
+            (credentials, signed.payload)
+        };
+
+        let (credentials, valid) = signed(&miner);
+        assert_eq!(authorizer.authorize(&credentials, &valid), Ok(()));
+
+        let mut tampered = valid;
+        tampered.timestamp += 1;
+        assert_eq!(
+            authorizer.authorize(&credentials, &tampered),
+            Err(AuthError::InvalidCredentials)
+        );
+
+        let (credentials, unregistered) = signed(&outsider);
+        assert!(matches!(
+            authorizer.authorize(&credentials, &unregistered),
+            Err(AuthError::Forbidden(_))
+        ));
+    }
+}
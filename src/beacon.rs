@@ -0,0 +1,409 @@
+//! Pipelined scheduling of the beacon chain.
+//!
+//! Where the daemon runs one round after another, the pipeline lets them overlap on separate
+//! cores: once a round is sealed the next round is opened straight away and collects
+//! contributions while the sealed round is evaluated on a worker thread. Results announced
+//! by peers are verified on their own workers meanwhile, so that checking a peer never
+//! holds up the local eval.
+//!
+//! At most one eval runs at a time. A round sealed while an eval is still running waits for
+//! it, so that rounds are always published in order. Like the daemon, the pipeline is driven
+//! by `tick`, which returns what happened since the last call.
+
+use crate::audit::audit_unicorn;
+use crate::config::LotteryConfig;
+use crate::daemon::open_round;
+use crate::rounds::{Contribution, Round, RoundError, RoundPhase};
+use crate::unicorn::{EvalMode, Unicorn, UnicornInfo};
+use rug::Integer;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+/// Number of published rounds whose seeds are kept to check late peer results against
+const KEPT_SEEDS: usize = 16;
+
+/// Something that happened in the pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineEvent {
+    /// A round was sealed and the next round opened.
+    Sealed { round_id: u64, participants: usize },
+    /// A round's eval was started on a worker.
+    EvalStarted { round_id: u64 },
+    /// A round was published.
+    Published(Box<Round>),
+    /// A round failed to publish: its eval did not complete or timed out.
+    Failed { round_id: u64, error: RoundError },
+    /// A result announced by a peer was verified.
+    PeerVerified {
+        round_id: u64,
+        peer: String,
+        valid: bool,
+    },
+}
+
+/// Eval running on a worker thread
+struct Eval {
+    round: Round,
+    result: Receiver<Option<UnicornInfo>>,
+}
+
+/// Peer result waiting for or under verification
+struct PeerCheck {
+    round_id: u64,
+    peer: String,
+    result: Receiver<bool>,
+}
+
+/// Pipeline scheduler overlapping collection, eval and peer verification
+pub struct Pipeline {
+    config: LotteryConfig,
+    collecting: Round,
+    evaluating: Option<Eval>,
+    queued: VecDeque<Round>,
+    seeds: BTreeMap<u64, Integer>,
+    pending: VecDeque<(u64, String, UnicornInfo)>,
+    checks: Vec<PeerCheck>,
+    verify_workers: usize,
+}
+
+impl Pipeline {
+    /// Creates a pipeline collecting for its first round, verifying peer results on as many
+    /// workers as there are cores
+    ///
+    /// ### Arguments
+    ///
+    /// * `config`   - Lottery configuration
+    /// * `round_id` - Id of the first round
+    /// * `now`      - Current unix time in seconds
+    pub fn new(config: LotteryConfig, round_id: u64, now: u64) -> Self {
+        let collecting = open_round(&config, round_id, now);
+        let verify_workers = thread::available_parallelism().map_or(1, |n| n.get());
+
+        Self {
+            config,
+            collecting,
+            evaluating: None,
+            queued: VecDeque::new(),
+            seeds: BTreeMap::new(),
+            pending: VecDeque::new(),
+            checks: Vec::new(),
+            verify_workers,
+        }
+    }
+
+    /// Caps the number of peer results verified at once
+    ///
+    /// ### Arguments
+    ///
+    /// * `workers` - Maximum number of verification workers, at least 1
+    pub fn with_verify_workers(mut self, workers: usize) -> Self {
+        self.verify_workers = workers.max(1);
+        self
+    }
+
+    /// The round currently collecting contributions
+    pub fn collecting(&self) -> &Round {
+        &self.collecting
+    }
+
+    /// Id of the round being evaluated, if any
+    pub fn evaluating(&self) -> Option<u64> {
+        self.evaluating.as_ref().map(|e| e.round.id)
+    }
+
+    /// Whether no eval or verification is in flight or waiting
+    pub fn is_idle(&self) -> bool {
+        self.evaluating.is_none()
+            && self.queued.is_empty()
+            && self.pending.is_empty()
+            && self.checks.is_empty()
+    }
+
+    /// Submits a contribution to the collecting round, returning its fingerprint
+    ///
+    /// ### Arguments
+    ///
+    /// * `contribution` - Contribution to submit
+    /// * `now`          - Current unix time in seconds
+    pub fn contribute(
+        &mut self,
+        contribution: Contribution,
+        now: u64,
+    ) -> Result<String, RoundError> {
+        self.collecting.submit(contribution, now)
+    }
+
+    /// Queues a result announced by a peer for verification against the round's sealed seed.
+    /// Results for rounds this node has not sealed, or has long since published, are found
+    /// invalid
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round the peer claims to have evaluated
+    /// * `peer`     - Identity of the peer
+    /// * `info`     - Evaluated UNICORN announced by the peer
+    pub fn submit_peer_result(&mut self, round_id: u64, peer: &str, info: UnicornInfo) {
+        self.pending.push_back((round_id, peer.to_string(), info));
+    }
+
+    /// Advances the pipeline: seals the collecting round at its cutoff, starts and collects
+    /// evals and verifications, and returns what happened
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn tick(&mut self, now: u64) -> Vec<PipelineEvent> {
+        let mut events = Vec::new();
+
+        self.seal_collecting(now, &mut events);
+        self.poll_eval(now, &mut events);
+        if self.evaluating.is_none() {
+            if let Some(round) = self.queued.pop_front() {
+                self.start_eval(round, now, &mut events);
+            }
+        }
+        self.poll_checks(&mut events);
+
+        events
+    }
+
+    /// Seals the collecting round once its cutoff has passed and opens the next one with the
+    /// contributions carried over into it. A round that closes without participants is
+    /// reopened under the same id
+    fn seal_collecting(&mut self, now: u64, events: &mut Vec<PipelineEvent>) {
+        if !self.collecting.is_timed_out(now) {
+            return;
+        }
+
+        let id = self.collecting.id;
+        let seed = match self.collecting.seal() {
+            Ok(seed) => seed.clone(),
+            Err(_) => {
+                let mut reopened = open_round(&self.config, id, now);
+                let _ = reopened.carry_in(&self.collecting.carried_over);
+                self.collecting = reopened;
+                return;
+            }
+        };
+
+        let mut next = open_round(&self.config, id + 1, now);
+        let _ = next.carry_in(&self.collecting.carried_over);
+        let sealed = std::mem::replace(&mut self.collecting, next);
+        self.seeds.insert(id, seed);
+        events.push(PipelineEvent::Sealed {
+            round_id: id,
+            participants: sealed.registry.len(),
+        });
+
+        if self.evaluating.is_none() {
+            self.start_eval(sealed, now, events);
+        } else {
+            self.queued.push_back(sealed);
+        }
+    }
+
+    /// Starts the eval of a sealed round on a worker thread
+    fn start_eval(&mut self, mut round: Round, now: u64, events: &mut Vec<PipelineEvent>) {
+        let unicorn = match round.start_eval(now) {
+            Ok(unicorn) => unicorn,
+            Err(error) => {
+                events.push(PipelineEvent::Failed {
+                    round_id: round.id,
+                    error,
+                });
+                return;
+            }
+        };
+
+        let mode = self.config.daemon.eval_mode;
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            let _ = tx.send(evaluate(unicorn, mode));
+        });
+
+        events.push(PipelineEvent::EvalStarted { round_id: round.id });
+        self.evaluating = Some(Eval { round, result: rx });
+    }
+
+    /// Completes the running eval if its worker has finished
+    fn poll_eval(&mut self, now: u64, events: &mut Vec<PipelineEvent>) {
+        let Some(eval) = self.evaluating.as_mut() else {
+            return;
+        };
+
+        let info = match eval.result.try_recv() {
+            Ok(info) => info,
+            Err(TryRecvError::Empty) if !eval.round.is_timed_out(now) => return,
+            Err(_) => None,
+        };
+
+        let mut round = self.evaluating.take().unwrap().round;
+        let result = match info {
+            Some(info) => round.complete_eval(info, now).map(|_| ()),
+            None if round.is_timed_out(now) => Err(RoundError::TimedOut {
+                phase: RoundPhase::Evaluating,
+            }),
+            None => Err(RoundError::InvalidUnicorn),
+        };
+
+        match result {
+            Ok(()) => {
+                self.prune_seeds(round.id);
+                events.push(PipelineEvent::Published(Box::new(round)));
+            }
+            Err(error) => events.push(PipelineEvent::Failed {
+                round_id: round.id,
+                error,
+            }),
+        }
+    }
+
+    /// Starts pending verifications up to the worker cap and collects finished ones
+    fn poll_checks(&mut self, events: &mut Vec<PipelineEvent>) {
+        let mut running = Vec::with_capacity(self.checks.len());
+        for check in self.checks.drain(..) {
+            match check.result.try_recv() {
+                Ok(valid) => events.push(PipelineEvent::PeerVerified {
+                    round_id: check.round_id,
+                    peer: check.peer,
+                    valid,
+                }),
+                Err(TryRecvError::Empty) => running.push(check),
+                Err(TryRecvError::Disconnected) => events.push(PipelineEvent::PeerVerified {
+                    round_id: check.round_id,
+                    peer: check.peer,
+                    valid: false,
+                }),
+            }
+        }
+        self.checks = running;
+
+        while self.checks.len() < self.verify_workers {
+            let Some((round_id, peer, info)) = self.pending.pop_front() else {
+                break;
+            };
+
+            let seed = self.seeds.get(&round_id).cloned();
+            let (tx, rx) = channel();
+            thread::spawn(move || {
+                let valid =
+                    seed.is_some_and(|s| s == info.unicorn.seed) && audit_unicorn(&info).passed();
+                let _ = tx.send(valid);
+            });
+
+            self.checks.push(PeerCheck {
+                round_id,
+                peer,
+                result: rx,
+            });
+        }
+    }
+
+    /// Forgets the seeds of rounds published long enough ago
+    fn prune_seeds(&mut self, published: u64) {
+        let oldest = published.saturating_sub(KEPT_SEEDS as u64 - 1);
+        self.seeds = self.seeds.split_off(&oldest);
+    }
+}
+
+/// Evaluates a UNICORN on a worker thread
+fn evaluate(unicorn: Unicorn, mode: EvalMode) -> Option<UnicornInfo> {
+    let (witness, g_value) = unicorn.eval_with_mode(mode)?;
+
+    Some(UnicornInfo::new(unicorn, witness, g_value))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod beacon_tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn contribution(round_id: u64, identity: &str, timestamp: u64) -> Contribution {
+        Contribution {
+            round_id,
+            identity: identity.to_string(),
+            timestamp,
+            nonce: None,
+        }
+    }
+
+    fn run_until_published(pipeline: &mut Pipeline, now: u64) -> (Round, Vec<PipelineEvent>) {
+        let mut seen = Vec::new();
+        loop {
+            for event in pipeline.tick(now) {
+                match event {
+                    PipelineEvent::Published(round) => return (*round, seen),
+                    event => seen.push(event),
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    /// Checks that the next round collects while the sealed round is evaluated, and that
+    /// peer results are verified against the sealed seed meanwhile
+    fn pipeline_overlaps_rounds() {
+        let mut config = LotteryConfig::default();
+        config.unicorn.iterations = 2_000;
+        config.daemon.collect_secs = 10;
+        let mut pipeline = Pipeline::new(config, 1, 0).with_verify_workers(2);
+
+        // An empty round is reopened under the same id
+        assert!(pipeline.tick(11).is_empty());
+        assert_eq!(pipeline.collecting().id, 1);
+
+        pipeline
+            .contribute(contribution(1, "miner_a", 12), 12)
+            .unwrap();
+        let events = pipeline.tick(22);
+        assert_eq!(
+            events,
+            vec![
+                PipelineEvent::Sealed {
+                    round_id: 1,
+                    participants: 1
+                },
+                PipelineEvent::EvalStarted { round_id: 1 },
+            ]
+        );
+
+        // Round 2 collects while round 1 is evaluated
+        assert_eq!(pipeline.collecting().id, 2);
+        pipeline
+            .contribute(contribution(2, "miner_b", 23), 23)
+            .unwrap();
+
+        let (round, _) = run_until_published(&mut pipeline, 23);
+        assert_eq!(round.id, 1);
+        assert_eq!(round.phase, RoundPhase::Published);
+        assert!(round.transcript.as_ref().unwrap().replay().is_ok());
+
+        let unicorn = round.unicorn().unwrap();
+        let (witness, g_value) = unicorn.eval().unwrap();
+        let honest = UnicornInfo::new(unicorn, witness, g_value);
+        let mut tampered = honest.clone();
+        tampered.witness += 1;
+        pipeline.submit_peer_result(1, "peer_a", honest.clone());
+        pipeline.submit_peer_result(1, "peer_b", tampered);
+        pipeline.submit_peer_result(9, "peer_c", honest);
+
+        let mut verdicts = BTreeMap::new();
+        while !pipeline.is_idle() {
+            for event in pipeline.tick(24) {
+                if let PipelineEvent::PeerVerified { peer, valid, .. } = event {
+                    verdicts.insert(peer, valid);
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(verdicts.get("peer_a"), Some(&true));
+        assert_eq!(verdicts.get("peer_b"), Some(&false));
+        assert_eq!(verdicts.get("peer_c"), Some(&false));
+        assert_eq!(pipeline.collecting().registry.len(), 1);
+    }
+}
@@ -0,0 +1,5 @@
+//! Generates the Kotlin and Swift bindings of the `uniffi` feature from the built library.
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
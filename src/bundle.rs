@@ -0,0 +1,330 @@
+//! Self-contained `.round` bundles of finished rounds.
+//!
+//! A `RoundBundle` holds everything needed to check a round on another machine, or long after
+//! it was run: the round itself, with its inputs, parameters and transcript, the evaluated
+//! UNICORN and any coordinator signatures over the transcript. Bundles are checked again when
+//! they are imported, so a round attached to a dispute still verifies offline.
+//!
+//! A `.round` file is a header line naming the format and its version, a line with the
+//! SHA-256 checksum of the body, and the bundle as JSON:
+//!
+//! ```text
+//! miner-lottery-round v1
+//! sha256:<hex digest of the JSON below>
+//! {"round": ..., "unicorn": ..., "signatures": [...]}
+//! ```
+
+use crate::audit::{audit_transcript, audit_unicorn, Check};
+use crate::rounds::Round;
+use crate::unicorn::UnicornInfo;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+/// Name of the bundle format, at the start of every `.round` file
+pub const BUNDLE_MAGIC: &str = "miner-lottery-round";
+
+/// Version of the bundle format written by `export_round`
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Extension of bundle files
+pub const BUNDLE_EXTENSION: &str = "round";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BundleError {
+    /// The round has not been published.
+    Unpublished,
+    /// The bundle could not be decoded.
+    Malformed(String),
+    /// The bundle was written in a format version this build does not read.
+    UnsupportedVersion(u32),
+    /// The body does not match the bundle's checksum.
+    ChecksumMismatch,
+    /// The UNICORN does not match the round's transcript.
+    UnicornMismatch,
+    /// The round failed the listed audit checks.
+    AuditFailed(Vec<Check>),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Unpublished => write!(f, "Round has not been published"),
+            BundleError::Malformed(e) => write!(f, "Malformed bundle: {}", e),
+            BundleError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported bundle format version {}", v)
+            }
+            BundleError::ChecksumMismatch => write!(f, "Bundle does not match its checksum"),
+            BundleError::UnicornMismatch => {
+                write!(f, "Bundled UNICORN does not match the transcript")
+            }
+            BundleError::AuditFailed(checks) => {
+                write!(f, "Bundled round failed audit at {:?}", checks)
+            }
+        }
+    }
+}
+
+impl Error for BundleError {}
+
+/// A coordinator's signature over a round's transcript, as made by `signing::Signed`
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TranscriptSignature {
+    /// Hex-encoded Ed25519 public key of the signer
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature
+    pub signature: String,
+}
+
+/// A finished round with everything needed to check it offline
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RoundBundle {
+    /// Published round, including its inputs, parameters and transcript
+    pub round: Round,
+    /// UNICORN the round's winners were drawn from
+    pub unicorn: UnicornInfo,
+    /// Coordinator signatures over the transcript
+    #[serde(default)]
+    pub signatures: Vec<TranscriptSignature>,
+}
+
+impl RoundBundle {
+    /// Bundles a published round
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Published round
+    pub fn new(round: Round) -> Result<Self, BundleError> {
+        let transcript = round.transcript.as_ref().ok_or(BundleError::Unpublished)?;
+        let unicorn = round.unicorn().map_err(|_| BundleError::UnicornMismatch)?;
+        let unicorn = UnicornInfo::new(
+            unicorn,
+            transcript.witness.clone(),
+            transcript.g_value.clone(),
+        )
+        .with_input_digest(round.sealed_digest.clone());
+
+        Ok(Self {
+            round,
+            unicorn,
+            signatures: Vec::new(),
+        })
+    }
+
+    /// Adds the signature of a coordinator key over the transcript
+    ///
+    /// ### Arguments
+    ///
+    /// * `keypair` - Coordinator key
+    #[cfg(feature = "signing")]
+    pub fn sign(mut self, keypair: &ed25519_dalek::Keypair) -> Self {
+        if let Some(transcript) = &self.round.transcript {
+            let signed = crate::signing::Signed::sign(transcript.clone(), keypair);
+            self.signatures.push(TranscriptSignature {
+                public_key: signed.public_key,
+                signature: signed.signature,
+            });
+        }
+        self
+    }
+
+    /// Checks that the transcript was signed by a trusted coordinator key
+    ///
+    /// ### Arguments
+    ///
+    /// * `trusted` - Public key of the trusted coordinator
+    #[cfg(feature = "signing")]
+    pub fn verify_signature(
+        &self,
+        trusted: &ed25519_dalek::PublicKey,
+    ) -> Result<(), crate::signing::SigningError> {
+        use crate::signing::{Signed, SigningError};
+
+        let transcript = self
+            .round
+            .transcript
+            .as_ref()
+            .ok_or(SigningError::MissingTranscript)?;
+        let trusted_hex = hex::encode(trusted.as_bytes());
+        let signature = self
+            .signatures
+            .iter()
+            .find(|s| s.public_key == trusted_hex)
+            .ok_or(SigningError::UntrustedSigner)?;
+
+        Signed {
+            payload: transcript.clone(),
+            public_key: signature.public_key.clone(),
+            signature: signature.signature.clone(),
+        }
+        .verify_signature(trusted)
+    }
+
+    /// Checks that the UNICORN matches the transcript and that both pass audit
+    pub fn verify(&self) -> Result<(), BundleError> {
+        let transcript = self
+            .round
+            .transcript
+            .as_ref()
+            .ok_or(BundleError::Unpublished)?;
+        let unicorn = &self.unicorn;
+        let matches = unicorn.unicorn.seed == transcript.seed
+            && unicorn.witness == transcript.witness
+            && unicorn.g_value == transcript.g_value
+            && unicorn.unicorn.iterations == transcript.fixed_params.iterations
+            && unicorn.unicorn.modulus.to_string_radix(10) == transcript.fixed_params.modulus
+            && unicorn.params_match()
+            && (unicorn.input_digest.is_none() || unicorn.input_digest == self.round.sealed_digest);
+        if !matches {
+            return Err(BundleError::UnicornMismatch);
+        }
+
+        let mut failed = Vec::new();
+        for report in [audit_unicorn(unicorn), audit_transcript(transcript)] {
+            for check in report.checks.iter().filter(|c| !c.passed) {
+                if !failed.contains(&check.check) {
+                    failed.push(check.check);
+                }
+            }
+        }
+
+        match failed.is_empty() {
+            true => Ok(()),
+            false => Err(BundleError::AuditFailed(failed)),
+        }
+    }
+
+    /// Encodes the bundle as a `.round` file
+    pub fn export(&self) -> String {
+        let body = serde_json::to_string(self).unwrap();
+
+        format!(
+            "{} v{}\nsha256:{}\n{}\n",
+            BUNDLE_MAGIC,
+            BUNDLE_VERSION,
+            hex::encode(Sha256::digest(body.as_bytes())),
+            body
+        )
+    }
+
+    /// Decodes a `.round` file, checking its checksum and verifying the bundled round
+    ///
+    /// ### Arguments
+    ///
+    /// * `contents` - Contents of the file
+    pub fn import(contents: &str) -> Result<Self, BundleError> {
+        let mut lines = contents.splitn(3, '\n');
+        let header = lines.next().unwrap_or_default();
+        let checksum = lines.next().unwrap_or_default();
+        let body = lines.next().unwrap_or_default().trim_end();
+
+        let version = header
+            .strip_prefix(BUNDLE_MAGIC)
+            .and_then(|v| v.strip_prefix(" v"))
+            .and_then(|v| v.trim_end().parse::<u32>().ok())
+            .ok_or_else(|| BundleError::Malformed("not a round bundle".to_string()))?;
+        if version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+
+        let checksum = checksum
+            .trim_end()
+            .strip_prefix("sha256:")
+            .ok_or_else(|| BundleError::Malformed("missing checksum".to_string()))?;
+        if hex::encode(Sha256::digest(body.as_bytes())) != checksum {
+            return Err(BundleError::ChecksumMismatch);
+        }
+
+        let bundle: Self =
+            serde_json::from_str(body).map_err(|e| BundleError::Malformed(e.to_string()))?;
+        bundle.verify()?;
+
+        Ok(bundle)
+    }
+}
+
+/// Exports a published round as a `.round` file
+///
+/// ### Arguments
+///
+/// * `round` - Published round
+pub fn export_round(round: &Round) -> Result<String, BundleError> {
+    Ok(RoundBundle::new(round.clone())?.export())
+}
+
+/// Imports a `.round` file, returning the round once its checksum and draw have been verified
+///
+/// ### Arguments
+///
+/// * `contents` - Contents of the file
+pub fn import_round(contents: &str) -> Result<Round, BundleError> {
+    Ok(RoundBundle::import(contents)?.round)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod bundle_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn published_round() -> Round {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 600,
+        };
+        let mut round = Round::new(7, params, 1, timeouts, 0);
+        round.contribute("miner_a", 0).unwrap();
+        round.contribute("miner_b", 0).unwrap();
+        round.seal().unwrap();
+        round.evaluate(0).unwrap();
+        round
+    }
+
+    #[test]
+    /// Checks that an exported round imports unchanged and that damaged bundles are rejected
+    fn export_import_round() {
+        let round = published_round();
+        let exported = export_round(&round).unwrap();
+        assert!(exported.starts_with("miner-lottery-round v1\nsha256:"));
+        assert_eq!(import_round(&exported), Ok(round.clone()));
+
+        let damaged = exported.replacen("miner_a", "miner_c", 1);
+        assert_eq!(import_round(&damaged), Err(BundleError::ChecksumMismatch));
+        let newer = exported.replacen(" v1\n", " v2\n", 1);
+        assert_eq!(
+            import_round(&newer),
+            Err(BundleError::UnsupportedVersion(2))
+        );
+        assert!(matches!(import_round("{}"), Err(BundleError::Malformed(_))));
+
+        let bundle = RoundBundle::new(round.clone()).unwrap();
+        assert_eq!(bundle.unicorn.input_digest, round.sealed_digest);
+        assert!(bundle.unicorn.params_match());
+        let mut tampered = bundle.clone();
+        tampered.unicorn.input_digest = Some("00".repeat(32));
+        assert_eq!(tampered.verify(), Err(BundleError::UnicornMismatch));
+
+        let mut tampered = RoundBundle::new(round).unwrap();
+        tampered.unicorn.g_value = "00".to_string();
+        assert_eq!(
+            RoundBundle::import(&tampered.export()),
+            Err(BundleError::UnicornMismatch)
+        );
+
+        let mut tampered = RoundBundle::new(published_round()).unwrap();
+        let transcript = tampered.round.transcript.as_mut().unwrap();
+        transcript.winner_index = 1 - transcript.winner_index;
+        assert!(matches!(
+            tampered.verify(),
+            Err(BundleError::AuditFailed(_))
+        ));
+        assert_eq!(
+            export_round(&Round::new(1, Default::default(), 1, Default::default(), 0)),
+            Err(BundleError::Unpublished)
+        );
+    }
+}
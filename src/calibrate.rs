@@ -0,0 +1,233 @@
+//! Calibration of the UNICORN iteration count against wall-clock time.
+//!
+//! The eval time grows linearly with the number of iterations, so the iteration count for a
+//! target delay can be estimated from a short sample run. The sample is repeated with twice
+//! as many iterations until it runs for at least `MIN_SAMPLE_TIME`, so that timer resolution
+//! and start-up costs do not skew the estimate.
+//!
+//! The recommendation only holds for hardware like the machine it was measured on; a faster
+//! evaluator finishes sooner.
+//!
+//! Benchmarks measure eval and verify speed along with the Fortuna output rate in the same
+//! way, so that operators can check that a host keeps up with the configured round timing.
+
+use crate::fortuna::Fortuna;
+use crate::unicorn::{Unicorn, MR_PRIME_ITERS};
+use rug::integer::IsPrime;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Minimum duration of the sample run
+pub const MIN_SAMPLE_TIME: Duration = Duration::from_millis(250);
+
+/// Result of a calibration run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Calibration {
+    /// Size of the modulus in bits
+    pub modulus_bits: u32,
+    /// Iterations in the final sample run
+    pub sample_iterations: u64,
+    /// Duration of the final sample run
+    pub sample_time: Duration,
+    /// Measured eval speed
+    pub iterations_per_sec: f64,
+    /// Iterations expected to take the target time
+    pub recommended_iterations: u64,
+}
+
+/// Measures the eval speed for a modulus and estimates the iterations that take the target
+/// time. Returns `None` if the modulus is not valid for a UNICORN
+///
+/// ### Arguments
+///
+/// * `modulus` - UNICORN modulus
+/// * `target`  - Target eval time
+pub fn calibrate(modulus: &Integer, target: Duration) -> Option<Calibration> {
+    let mut unicorn = sample_unicorn(modulus);
+    let (sample_iterations, sample_time) = sample(|iterations| {
+        unicorn.iterations = iterations;
+        unicorn.eval().map(|_| ())
+    })?;
+    let iterations_per_sec = sample_iterations as f64 / sample_time.as_secs_f64();
+
+    Some(Calibration {
+        modulus_bits: modulus.significant_bits(),
+        sample_iterations,
+        sample_time,
+        iterations_per_sec,
+        recommended_iterations: ((iterations_per_sec * target.as_secs_f64()) as u64).max(1),
+    })
+}
+
+/// Result of a benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Benchmark {
+    /// Size of the modulus in bits
+    pub modulus_bits: u32,
+    /// Measured eval speed
+    pub eval_iterations_per_sec: f64,
+    /// Measured verify speed
+    pub verify_iterations_per_sec: f64,
+    /// Measured Fortuna output rate
+    pub fortuna_bytes_per_sec: f64,
+}
+
+/// Time to run an iteration count at a rate, saturating at `Duration::MAX` where the count
+/// would take longer than a `Duration` can hold or the rate is not positive
+///
+/// ### Arguments
+///
+/// * `iterations` - UNICORN iterations
+/// * `per_sec`    - Iterations per second
+pub(crate) fn time_at_rate(iterations: u64, per_sec: f64) -> Duration {
+    Duration::try_from_secs_f64(iterations as f64 / per_sec).unwrap_or(Duration::MAX)
+}
+
+impl Benchmark {
+    /// Expected eval time for an iteration count
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - UNICORN iterations
+    pub fn eval_time(&self, iterations: u64) -> Duration {
+        time_at_rate(iterations, self.eval_iterations_per_sec)
+    }
+
+    /// Expected verify time for an iteration count
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - UNICORN iterations
+    pub fn verify_time(&self, iterations: u64) -> Duration {
+        time_at_rate(iterations, self.verify_iterations_per_sec)
+    }
+}
+
+/// Measures eval and verify speed for a modulus, and the Fortuna output rate. Returns `None`
+/// if the modulus is not valid for a UNICORN
+///
+/// ### Arguments
+///
+/// * `modulus` - UNICORN modulus
+pub fn benchmark(modulus: &Integer) -> Option<Benchmark> {
+    let mut unicorn = sample_unicorn(modulus);
+    let (eval_iterations, eval_time) = sample(|iterations| {
+        unicorn.iterations = iterations;
+        unicorn.eval().map(|_| ())
+    })?;
+
+    // Verify runs the same number of steps whether or not the witness is valid
+    let witness = Integer::from(0x5eed);
+    let (verify_iterations, verify_time) = sample(|iterations| {
+        unicorn.iterations = iterations;
+        unicorn.verify_ref(&unicorn.seed, &witness);
+        Some(())
+    })?;
+
+    let mut csprng = Fortuna::new(&[0; 32], 1).ok()?;
+    let (fortuna_bytes, fortuna_time) =
+        sample(|len| csprng.get_bytes(len as usize).ok().map(|_| ()))?;
+
+    Some(Benchmark {
+        modulus_bits: modulus.significant_bits(),
+        eval_iterations_per_sec: eval_iterations as f64 / eval_time.as_secs_f64(),
+        verify_iterations_per_sec: verify_iterations as f64 / verify_time.as_secs_f64(),
+        fortuna_bytes_per_sec: fortuna_bytes as f64 / fortuna_time.as_secs_f64(),
+    })
+}
+
+fn sample_unicorn(modulus: &Integer) -> Unicorn {
+    Unicorn {
+        seed: Integer::from(0x5eed),
+        modulus: modulus.clone(),
+        iterations: 16,
+        security_level: 1,
+        ..Default::default()
+    }
+}
+
+/// Runs `run` with twice the work each time until it takes at least `MIN_SAMPLE_TIME`,
+/// returning the work and duration of the final run
+fn sample(mut run: impl FnMut(u64) -> Option<()>) -> Option<(u64, Duration)> {
+    let mut work = 16;
+
+    loop {
+        let start = Instant::now();
+        run(work)?;
+        let sample_time = start.elapsed();
+
+        if sample_time >= MIN_SAMPLE_TIME {
+            return Some((work, sample_time));
+        }
+
+        work *= 2;
+    }
+}
+
+/// Deterministic modulus of a given size for calibration: the first prime congruent to
+/// 3 mod 4 above `2^(bits - 1)`. The eval time only depends on the size of the modulus, so
+/// this stands in for the production modulus; it must not be used for a real lottery
+///
+/// ### Arguments
+///
+/// * `bits` - Size of the modulus in bits; at least 3
+pub fn calibration_modulus(bits: u32) -> Integer {
+    let mut candidate = Integer::from(1) << (bits - 1);
+
+    loop {
+        candidate.next_prime_mut();
+        if candidate.mod_u(4) == 3
+            && !matches!(candidate.is_probably_prime(MR_PRIME_ITERS), IsPrime::No)
+        {
+            return candidate;
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod calibrate_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that the calibration modulus has the requested size and form
+    fn calibration_modulus_form() {
+        let modulus = calibration_modulus(128);
+
+        assert_eq!(modulus.significant_bits(), 128);
+        assert_eq!(modulus.mod_u(4), 3);
+    }
+
+    #[test]
+    /// Checks that calibration scales the measured speed to the target time
+    fn calibrate_scales() {
+        let calibration = calibrate(&calibration_modulus(64), Duration::from_secs(2)).unwrap();
+        let expected = calibration.iterations_per_sec * 2.0;
+
+        assert!(calibration.sample_time >= MIN_SAMPLE_TIME);
+        assert!((calibration.recommended_iterations as f64 - expected).abs() <= 1.0);
+        assert_eq!(calibrate(&Integer::from(15), Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    /// Checks that verify is measured faster than eval and expected times scale linearly
+    fn benchmark_rates() {
+        let benchmark = benchmark(&calibration_modulus(512)).unwrap();
+
+        assert!(benchmark.verify_iterations_per_sec > benchmark.eval_iterations_per_sec);
+        assert!(benchmark.fortuna_bytes_per_sec > 0.0);
+        let ratio = benchmark.eval_time(2_000_000).as_secs_f64()
+            / benchmark.eval_time(1_000_000).as_secs_f64();
+        assert!((ratio - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    /// Checks that times too long for a `Duration`, or at a zero rate, saturate
+    fn time_at_rate_saturates() {
+        assert_eq!(time_at_rate(u64::MAX, 1e-3), Duration::MAX);
+        assert_eq!(time_at_rate(1, 0.0), Duration::MAX);
+        assert_eq!(time_at_rate(10, 5.0), Duration::from_secs(2));
+    }
+}
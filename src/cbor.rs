@@ -0,0 +1,158 @@
+//! CBOR encoding of rounds, transcripts and the other public structs.
+//!
+//! Two profiles are offered. The standard profile encodes values exactly as their serde
+//! implementations describe them, so big integers are hex strings as in the JSON formats.
+//! The compact profile writes big integers as big-endian byte strings instead, which halves
+//! their size and lets CBOR tooling treat them as binary data. A value must be decoded with
+//! the profile it was encoded with.
+
+use crate::utils::rug_integer::with_byte_integers;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+
+/// How big integers are written in CBOR
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CborProfile {
+    /// Big integers as hex strings, as in JSON
+    #[default]
+    Standard,
+    /// Big integers as big-endian byte strings
+    Compact,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CborError {
+    /// The value could not be encoded.
+    Encode(String),
+    /// The bytes could not be decoded.
+    Decode(String),
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CborError::Encode(e) => write!(f, "Could not encode CBOR: {}", e),
+            CborError::Decode(e) => write!(f, "Could not decode CBOR: {}", e),
+        }
+    }
+}
+
+impl Error for CborError {}
+
+/// Encodes a value as CBOR
+///
+/// ### Arguments
+///
+/// * `value`   - Value to encode
+/// * `profile` - Profile to encode with
+pub fn to_vec<T: Serialize>(value: &T, profile: CborProfile) -> Result<Vec<u8>, CborError> {
+    let encode = || {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map(|_| bytes)
+    };
+
+    match profile {
+        CborProfile::Standard => encode(),
+        CborProfile::Compact => with_byte_integers(encode),
+    }
+    .map_err(|e| CborError::Encode(e.to_string()))
+}
+
+/// Decodes a value from CBOR
+///
+/// ### Arguments
+///
+/// * `bytes`   - CBOR to decode
+/// * `profile` - Profile the value was encoded with
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8], profile: CborProfile) -> Result<T, CborError> {
+    let decode = || ciborium::from_reader(bytes);
+
+    match profile {
+        CborProfile::Standard => decode(),
+        CborProfile::Compact => with_byte_integers(decode),
+    }
+    .map_err(|e| CborError::Decode(e.to_string()))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod cbor_tests {
+    use super::*;
+    use crate::bundle::RoundBundle;
+    use crate::config::DEFAULT_MODULUS;
+    use crate::rounds::{Round, RoundTimeouts};
+    use crate::unicorn::UnicornFixedParam;
+    use crate::vrf::VrfPublicKey;
+    use serde::Deserialize;
+    use std::fmt::Debug;
+
+    fn test_bundle() -> RoundBundle {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 600,
+        };
+        let mut round = Round::new(7, params, 1, timeouts, 0);
+        round.contribute("miner_a", 0).unwrap();
+        round.contribute("miner_b", 0).unwrap();
+        round.seal().unwrap();
+        round.evaluate(0).unwrap();
+        RoundBundle::new(round).unwrap()
+    }
+
+    /// Encodes a value in each format and checks it decodes to the same value, and to the
+    /// same JSON, in every other format
+    fn assert_round_trips<T>(value: &T)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Debug + PartialEq,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        let from_json: T = serde_json::from_str(&json).unwrap();
+        let from_bincode: T = bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap();
+        assert_eq!(&from_json, value);
+        assert_eq!(&from_bincode, value);
+
+        for profile in [CborProfile::Standard, CborProfile::Compact] {
+            let from_cbor: T = from_slice(&to_vec(&from_json, profile).unwrap(), profile).unwrap();
+            assert_eq!(&from_cbor, value);
+            assert_eq!(serde_json::to_string(&from_cbor).unwrap(), json);
+        }
+    }
+
+    #[test]
+    /// Checks that the public structs round-trip through CBOR, JSON and bincode
+    fn cross_format_round_trips() {
+        let bundle = test_bundle();
+        let transcript = bundle.round.transcript.clone().unwrap();
+        let key = VrfPublicKey {
+            n: DEFAULT_MODULUS.parse().unwrap(),
+            e: 65_537.into(),
+        };
+
+        assert_round_trips(&bundle);
+        assert_round_trips(&bundle.round);
+        assert_round_trips(&bundle.unicorn);
+        assert_round_trips(&bundle.unicorn.unicorn);
+        assert_round_trips(&transcript);
+        assert_round_trips(&transcript.fixed_params);
+        assert_round_trips(&transcript.usage_allocations);
+        assert_round_trips(&key);
+    }
+
+    #[test]
+    /// Checks that the compact profile is smaller and is not read as the standard one
+    fn compact_profile() {
+        let unicorn = test_bundle().unicorn;
+        let standard = to_vec(&unicorn, CborProfile::Standard).unwrap();
+        let compact = to_vec(&unicorn, CborProfile::Compact).unwrap();
+
+        assert!(compact.len() < standard.len());
+        assert!(matches!(
+            from_slice::<crate::unicorn::UnicornInfo>(&compact, CborProfile::Standard),
+            Err(CborError::Decode(_))
+        ));
+    }
+}
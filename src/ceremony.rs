@@ -0,0 +1,786 @@
+//! Distributed seed ceremony between independent contributors.
+//!
+//! Instead of one operator fixing the seed, each of N known contributors picks a secret and
+//! the seed is composed from all of them. The ceremony runs in two phases:
+//!
+//! - `Committing`: each contributor submits a signed commitment to their secret
+//! - `Revealing`: each contributor who committed reveals their secret, which must match the
+//!   commitment
+//!
+//! Once every committed secret has been revealed the ceremony is `Complete` and only then
+//! yields the UNICORN to evaluate. Nobody can choose their secret after seeing the others,
+//! and as long as one contributor is honest the seed is unpredictable.
+//!
+//! The commit phase ends early once every contributor has committed, or at its deadline if at
+//! least `threshold` have. A contributor who committed but withholds their reveal could bias
+//! the seed by choosing whether to reveal, so a missing reveal at the reveal deadline aborts
+//! the ceremony rather than completing without it.
+//!
+//! Plain commitments bind the ceremony id and the secret alone, so a guessable secret can be
+//! recovered from its commitment. A ceremony can instead require blinded commitments, which
+//! also hash a random `r` of `BLINDING_LEN` bytes revealed alongside the secret, along with
+//! the contributor's public key. Commitments then leak nothing about their secrets and can be
+//! published as soon as they arrive, with the secrets staying hidden until every commitment is
+//! in; binding the ceremony id and the key stops a published commitment from being replayed
+//! in another ceremony or copied by another contributor.
+//!
+//! All times are unix timestamps in seconds, passed in by the caller as for rounds. The
+//! ceremony transcript records every signed message, so that anyone can check the seed
+//! without trusting the coordinator.
+
+pub mod modulus;
+
+use crate::signing::{Signed, SigningError};
+use crate::unicorn::{Unicorn, UnicornFixedParam};
+use crate::utils::rug_integer;
+use bincode::serialize;
+use ed25519_dalek::PublicKey;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Phase of a seed ceremony
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum CeremonyPhase {
+    Committing,
+    Revealing,
+    Complete,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CeremonyError {
+    /// Operation is not valid in the current phase.
+    WrongPhase(CeremonyPhase),
+    /// The deadline for the current phase has passed.
+    TimedOut { phase: CeremonyPhase },
+    /// Message is for another ceremony.
+    WrongCeremony { expected: u64, actual: u64 },
+    /// Message is not signed by a listed contributor.
+    UnknownContributor(String),
+    /// The signature did not verify.
+    Signature(SigningError),
+    /// Contributor already submitted a different message for this phase.
+    Equivocation(String),
+    /// Reveal from a contributor who did not commit.
+    NotCommitted(String),
+    /// Revealed secret is malformed or does not match the commitment.
+    CommitmentMismatch(String),
+    /// Fewer contributors committed than the threshold requires.
+    InsufficientCommitments { committed: usize, threshold: usize },
+    /// Committed contributors did not reveal before the deadline.
+    MissingReveals(Vec<String>),
+    /// The recorded seed does not match the revealed secrets.
+    SeedMismatch,
+}
+
+impl From<SigningError> for CeremonyError {
+    fn from(e: SigningError) -> Self {
+        Self::Signature(e)
+    }
+}
+
+impl fmt::Display for CeremonyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CeremonyError::WrongPhase(phase) => write!(f, "Ceremony is in phase {:?}", phase),
+            CeremonyError::TimedOut { phase } => write!(f, "Ceremony timed out in {:?}", phase),
+            CeremonyError::WrongCeremony { expected, actual } => write!(
+                f,
+                "Message for ceremony {} submitted to ceremony {}",
+                actual, expected
+            ),
+            CeremonyError::UnknownContributor(key) => write!(f, "Unknown contributor {}", key),
+            CeremonyError::Signature(e) => write!(f, "Signature error: {}", e),
+            CeremonyError::Equivocation(key) => {
+                write!(f, "Contributor {} sent conflicting messages", key)
+            }
+            CeremonyError::NotCommitted(key) => {
+                write!(f, "Contributor {} revealed without committing", key)
+            }
+            CeremonyError::CommitmentMismatch(key) => {
+                write!(f, "Reveal from {} does not match its commitment", key)
+            }
+            CeremonyError::InsufficientCommitments {
+                committed,
+                threshold,
+            } => write!(
+                f,
+                "Only {} contributors committed; {} required",
+                committed, threshold
+            ),
+            CeremonyError::MissingReveals(keys) => {
+                write!(f, "Missing reveals from {}", keys.join(", "))
+            }
+            CeremonyError::SeedMismatch => write!(f, "Seed does not match the revealed secrets"),
+        }
+    }
+}
+
+impl Error for CeremonyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CeremonyError::Signature(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Timeouts of the ceremony phases, in seconds
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CeremonyTimeouts {
+    /// Time after the ceremony starts during which commitments are accepted
+    pub commit_secs: u64,
+    /// Time after the commit phase ends during which secrets can be revealed
+    pub reveal_secs: u64,
+}
+
+/// A contributor's commitment to their secret
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SeedCommitment {
+    pub ceremony_id: u64,
+    /// Result of `commit_secret`
+    pub commitment: String,
+}
+
+/// A contributor's revealed secret
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SeedReveal {
+    pub ceremony_id: u64,
+    /// Hex-encoded secret
+    pub secret: String,
+    /// Hex-encoded blinding factor, if the secret was committed with `commit_blinded`
+    #[serde(default)]
+    pub blinding: Option<String>,
+}
+
+/// Length in bytes of a blinding factor
+pub const BLINDING_LEN: usize = 32;
+
+/// Commitment to a secret for a ceremony, as the hex-encoded SHA-256 of both. Returns
+/// `None` if the secret is not hex or is empty
+///
+/// ### Arguments
+///
+/// * `ceremony_id` - Ceremony identifier
+/// * `secret`      - Hex-encoded secret
+pub fn commit_secret(ceremony_id: u64, secret: &str) -> Option<String> {
+    let secret = hex::decode(secret).ok().filter(|s| !s.is_empty())?;
+    let input = serialize(&(ceremony_id, secret)).unwrap();
+    Some(hex::encode(Sha256::digest(input)))
+}
+
+/// Blinded commitment to a secret for a ceremony and contributor, as the hex-encoded SHA-256
+/// of the ceremony id, the contributor's public key, the blinding factor and the secret.
+/// Returns `None` if any of them is not hex, the secret is empty or the blinding factor is not
+/// `BLINDING_LEN` bytes
+///
+/// ### Arguments
+///
+/// * `ceremony_id` - Ceremony identifier
+/// * `contributor` - Hex-encoded public key of the contributor
+/// * `blinding`    - Hex-encoded random blinding factor
+/// * `secret`      - Hex-encoded secret
+pub fn commit_blinded(
+    ceremony_id: u64,
+    contributor: &str,
+    blinding: &str,
+    secret: &str,
+) -> Option<String> {
+    let contributor = hex::decode(contributor).ok()?;
+    let blinding = hex::decode(blinding)
+        .ok()
+        .filter(|b| b.len() == BLINDING_LEN)?;
+    let secret = hex::decode(secret).ok().filter(|s| !s.is_empty())?;
+    let input = serialize(&(ceremony_id, contributor, blinding, secret)).unwrap();
+    Some(hex::encode(Sha256::digest(input)))
+}
+
+/// Draws a fresh hex-encoded blinding factor from the OS
+pub fn blinding_factor() -> Result<String, getrandom::Error> {
+    let mut blinding = [0u8; BLINDING_LEN];
+    getrandom::getrandom(&mut blinding)?;
+    Ok(hex::encode(blinding))
+}
+
+/// Whether a contributor's reveal opens their commitment. Blinded ceremonies only accept
+/// blinded reveals
+fn opens(
+    id: u64,
+    blinded: bool,
+    key: &str,
+    reveal: &SeedReveal,
+    commitment: &SeedCommitment,
+) -> bool {
+    let opened = match &reveal.blinding {
+        Some(blinding) => commit_blinded(id, key, blinding, &reveal.secret),
+        None if blinded => None,
+        None => commit_secret(id, &reveal.secret),
+    };
+    opened.as_ref() == Some(&commitment.commitment)
+}
+
+/// A seed ceremony
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Ceremony {
+    pub id: u64,
+    pub phase: CeremonyPhase,
+    /// Hex-encoded public keys of the contributors
+    pub contributors: Vec<String>,
+    /// Commitments needed to proceed when not everyone commits in time
+    pub threshold: usize,
+    pub fixed_params: UnicornFixedParam,
+    pub timeouts: CeremonyTimeouts,
+    /// Whether commitments must be blinded
+    #[serde(default)]
+    pub blinded: bool,
+    /// Commitments by contributor key
+    pub commitments: BTreeMap<String, Signed<SeedCommitment>>,
+    /// Reveals by contributor key
+    pub reveals: BTreeMap<String, Signed<SeedReveal>>,
+    /// Deadline for the current phase
+    pub deadline: u64,
+    /// Composed seed, once complete
+    #[serde(with = "rug_integer")]
+    pub seed: Integer,
+    /// Why the ceremony was aborted, if it was
+    pub abort_reason: Option<String>,
+}
+
+impl Ceremony {
+    /// Starts a ceremony in the `Committing` phase
+    ///
+    /// ### Arguments
+    ///
+    /// * `id`           - Ceremony identifier
+    /// * `contributors` - Hex-encoded public keys of the contributors
+    /// * `threshold`    - Commitments needed to proceed at the commit deadline
+    /// * `fixed_params` - UNICORN parameters to evaluate the seed with
+    /// * `timeouts`     - Phase timeouts
+    /// * `now`          - Current unix time in seconds
+    pub fn new(
+        id: u64,
+        contributors: Vec<String>,
+        threshold: usize,
+        fixed_params: UnicornFixedParam,
+        timeouts: CeremonyTimeouts,
+        now: u64,
+    ) -> Self {
+        Self {
+            id,
+            phase: CeremonyPhase::Committing,
+            contributors: contributors
+                .into_iter()
+                .map(|key| key.to_ascii_lowercase())
+                .collect(),
+            threshold: threshold.max(1),
+            fixed_params,
+            timeouts,
+            blinded: false,
+            commitments: BTreeMap::new(),
+            reveals: BTreeMap::new(),
+            deadline: now.saturating_add(timeouts.commit_secs),
+            seed: Integer::new(),
+            abort_reason: None,
+        }
+    }
+
+    /// Requires every commitment to be blinded with `commit_blinded`
+    pub fn with_blinding(mut self) -> Self {
+        self.blinded = true;
+        self
+    }
+
+    /// Accepts a signed commitment. Resubmitting the same commitment is a no-op; a different
+    /// one from the same contributor is refused
+    ///
+    /// ### Arguments
+    ///
+    /// * `signed` - Commitment signed by a contributor
+    /// * `now`    - Current unix time in seconds
+    pub fn commit(
+        &mut self,
+        signed: Signed<SeedCommitment>,
+        now: u64,
+    ) -> Result<CeremonyPhase, CeremonyError> {
+        self.expect_open(CeremonyPhase::Committing, now)?;
+        let key = check_signed(
+            &self.contributors,
+            self.id,
+            &signed,
+            signed.payload.ceremony_id,
+        )?;
+
+        match self.commitments.get(&key) {
+            Some(existing) if existing.payload != signed.payload => {
+                return Err(CeremonyError::Equivocation(key))
+            }
+            Some(_) => {}
+            None => {
+                self.commitments.insert(key, signed);
+            }
+        }
+
+        Ok(self.poll(now))
+    }
+
+    /// Accepts a signed reveal, checking it against the contributor's commitment
+    ///
+    /// ### Arguments
+    ///
+    /// * `signed` - Reveal signed by a contributor
+    /// * `now`    - Current unix time in seconds
+    pub fn reveal(
+        &mut self,
+        signed: Signed<SeedReveal>,
+        now: u64,
+    ) -> Result<CeremonyPhase, CeremonyError> {
+        self.expect_open(CeremonyPhase::Revealing, now)?;
+        let key = check_signed(
+            &self.contributors,
+            self.id,
+            &signed,
+            signed.payload.ceremony_id,
+        )?;
+
+        let commitment = self
+            .commitments
+            .get(&key)
+            .ok_or_else(|| CeremonyError::NotCommitted(key.clone()))?;
+        if !opens(
+            self.id,
+            self.blinded,
+            &key,
+            &signed.payload,
+            &commitment.payload,
+        ) {
+            return Err(CeremonyError::CommitmentMismatch(key));
+        }
+
+        self.reveals.insert(key, signed);
+        Ok(self.poll(now))
+    }
+
+    /// Applies the phase rules: moves on once everyone has committed or revealed, and at a
+    /// deadline either moves on or aborts
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn poll(&mut self, now: u64) -> CeremonyPhase {
+        let timed_out = now > self.deadline;
+
+        match self.phase {
+            CeremonyPhase::Committing
+                if self.commitments.len() == self.contributors.len() || timed_out =>
+            {
+                if self.commitments.len() >= self.threshold {
+                    self.phase = CeremonyPhase::Revealing;
+                    self.deadline = now.saturating_add(self.timeouts.reveal_secs);
+                } else {
+                    self.abort(CeremonyError::InsufficientCommitments {
+                        committed: self.commitments.len(),
+                        threshold: self.threshold,
+                    });
+                }
+            }
+            CeremonyPhase::Revealing if self.reveals.len() == self.commitments.len() => {
+                self.seed = compose_seed(&self.reveals);
+                self.phase = CeremonyPhase::Complete;
+            }
+            CeremonyPhase::Revealing if timed_out => {
+                let missing = self
+                    .commitments
+                    .keys()
+                    .filter(|key| !self.reveals.contains_key(*key))
+                    .cloned()
+                    .collect();
+                self.abort(CeremonyError::MissingReveals(missing));
+            }
+            _ => {}
+        }
+
+        self.phase
+    }
+
+    /// Aborts the ceremony, recording the reason
+    ///
+    /// ### Arguments
+    ///
+    /// * `reason` - Why the ceremony is aborted
+    pub fn abort(&mut self, reason: impl fmt::Display) {
+        if self.phase != CeremonyPhase::Complete {
+            self.phase = CeremonyPhase::Aborted;
+            self.abort_reason = Some(reason.to_string());
+        }
+    }
+
+    /// The UNICORN to evaluate, only available once the ceremony is complete
+    pub fn unicorn(&self) -> Result<Unicorn, CeremonyError> {
+        if self.phase != CeremonyPhase::Complete {
+            return Err(CeremonyError::WrongPhase(self.phase));
+        }
+
+        Ok(Unicorn {
+            seed: self.seed.clone(),
+            modulus: Integer::from_str_radix(&self.fixed_params.modulus, 10).unwrap_or_default(),
+            iterations: self.fixed_params.iterations,
+            security_level: self.fixed_params.security,
+            primality_rounds: self.fixed_params.primality_rounds,
+        })
+    }
+
+    /// Record of the ceremony for publishing
+    pub fn transcript(&self) -> CeremonyTranscript {
+        CeremonyTranscript {
+            id: self.id,
+            phase: self.phase,
+            contributors: self.contributors.clone(),
+            threshold: self.threshold,
+            fixed_params: self.fixed_params.clone(),
+            blinded: self.blinded,
+            commitments: self.commitments.values().cloned().collect(),
+            reveals: self.reveals.values().cloned().collect(),
+            seed: (self.phase == CeremonyPhase::Complete).then(|| self.seed.to_string_radix(16)),
+            abort_reason: self.abort_reason.clone(),
+        }
+    }
+
+    fn expect_open(&self, phase: CeremonyPhase, now: u64) -> Result<(), CeremonyError> {
+        if self.phase != phase {
+            return Err(CeremonyError::WrongPhase(self.phase));
+        }
+        if now > self.deadline {
+            return Err(CeremonyError::TimedOut { phase });
+        }
+
+        Ok(())
+    }
+}
+
+/// Published record of a ceremony
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CeremonyTranscript {
+    pub id: u64,
+    pub phase: CeremonyPhase,
+    pub contributors: Vec<String>,
+    pub threshold: usize,
+    pub fixed_params: UnicornFixedParam,
+    #[serde(default)]
+    pub blinded: bool,
+    pub commitments: Vec<Signed<SeedCommitment>>,
+    pub reveals: Vec<Signed<SeedReveal>>,
+    /// Hex-encoded seed, if the ceremony completed
+    pub seed: Option<String>,
+    pub abort_reason: Option<String>,
+}
+
+impl CeremonyTranscript {
+    /// Checks every signature and reveal, and that the seed is composed from the revealed
+    /// secrets of everyone who committed. Returns the seed
+    pub fn verify(&self) -> Result<Integer, CeremonyError> {
+        if self.phase != CeremonyPhase::Complete {
+            return Err(CeremonyError::WrongPhase(self.phase));
+        }
+
+        let mut commitments = BTreeMap::new();
+        for signed in &self.commitments {
+            let key = check_signed(
+                &self.contributors,
+                self.id,
+                signed,
+                signed.payload.ceremony_id,
+            )?;
+            if commitments.insert(key.clone(), signed).is_some() {
+                return Err(CeremonyError::Equivocation(key));
+            }
+        }
+        if commitments.len() < self.threshold {
+            return Err(CeremonyError::InsufficientCommitments {
+                committed: commitments.len(),
+                threshold: self.threshold,
+            });
+        }
+
+        let mut reveals = BTreeMap::new();
+        for signed in &self.reveals {
+            let key = check_signed(
+                &self.contributors,
+                self.id,
+                signed,
+                signed.payload.ceremony_id,
+            )?;
+            let commitment = commitments
+                .get(&key)
+                .ok_or_else(|| CeremonyError::NotCommitted(key.clone()))?;
+            if !opens(
+                self.id,
+                self.blinded,
+                &key,
+                &signed.payload,
+                &commitment.payload,
+            ) {
+                return Err(CeremonyError::CommitmentMismatch(key));
+            }
+            reveals.insert(key, signed.clone());
+        }
+
+        let missing: Vec<_> = commitments
+            .keys()
+            .filter(|key| !reveals.contains_key(*key))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(CeremonyError::MissingReveals(missing));
+        }
+
+        let seed = compose_seed(&reveals);
+        if self.seed.as_deref() != Some(&seed.to_string_radix(16)) {
+            return Err(CeremonyError::SeedMismatch);
+        }
+
+        Ok(seed)
+    }
+}
+
+/// Checks that a message is for the ceremony and signed by a contributor, returning the
+/// contributor's key
+fn check_signed<T: Serialize>(
+    contributors: &[String],
+    id: u64,
+    signed: &Signed<T>,
+    ceremony_id: u64,
+) -> Result<String, CeremonyError> {
+    if ceremony_id != id {
+        return Err(CeremonyError::WrongCeremony {
+            expected: id,
+            actual: ceremony_id,
+        });
+    }
+
+    let key = signed.public_key.to_ascii_lowercase();
+    if !contributors.contains(&key) {
+        return Err(CeremonyError::UnknownContributor(key));
+    }
+
+    let public_key = hex::decode(&key)
+        .ok()
+        .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+        .ok_or(SigningError::Malformed)?;
+    signed.verify_signature(&public_key)?;
+
+    Ok(key)
+}
+
+/// Seed composed from the revealed secrets in contributor key order. Secrets are hashed as
+/// the bytes they decode to, so that re-casing a reveal's hex does not change the seed
+fn compose_seed(reveals: &BTreeMap<String, Signed<SeedReveal>>) -> Integer {
+    let secrets: Vec<(&String, Vec<u8>)> = reveals
+        .iter()
+        .map(|(key, signed)| {
+            // Every reveal opened its commitment, so its secret is hex
+            (key, hex::decode(&signed.payload.secret).unwrap_or_default())
+        })
+        .collect();
+    let digest = hex::encode(Sha256::digest(serialize(&secrets).unwrap()));
+    Integer::from_str_radix(&digest, 16).unwrap()
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod ceremony_tests {
+    use super::*;
+    use crate::signing::test_keypair;
+    use ed25519_dalek::Keypair;
+
+    fn ceremony(keys: &[Keypair], threshold: usize) -> Ceremony {
+        let contributors = keys
+            .iter()
+            .map(|k| hex::encode(k.public.as_bytes()))
+            .collect();
+        let timeouts = CeremonyTimeouts {
+            commit_secs: 10,
+            reveal_secs: 10,
+        };
+        let params = UnicornFixedParam::test();
+        Ceremony::new(7, contributors, threshold, params, timeouts, 0)
+    }
+
+    fn commitment(keypair: &Keypair, secret: &str) -> Signed<SeedCommitment> {
+        let commitment = SeedCommitment {
+            ceremony_id: 7,
+            commitment: commit_secret(7, secret).unwrap(),
+        };
+        Signed::sign(commitment, keypair)
+    }
+
+    fn reveal(keypair: &Keypair, secret: &str) -> Signed<SeedReveal> {
+        let reveal = SeedReveal {
+            ceremony_id: 7,
+            secret: secret.to_string(),
+            ..Default::default()
+        };
+        Signed::sign(reveal, keypair)
+    }
+
+    #[test]
+    /// Checks that the seed is only available once every contributor has revealed, and that
+    /// the transcript verifies
+    fn complete_ceremony() {
+        let keys = [test_keypair(1), test_keypair(2)];
+        let mut ceremony = ceremony(&keys, 2);
+
+        assert_eq!(
+            ceremony.commit(commitment(&keys[0], "aa"), 1),
+            Ok(CeremonyPhase::Committing)
+        );
+        assert!(ceremony.unicorn().is_err());
+        assert_eq!(
+            ceremony.commit(commitment(&keys[1], "bb"), 2),
+            Ok(CeremonyPhase::Revealing)
+        );
+        assert_eq!(
+            ceremony.reveal(reveal(&keys[0], "ab"), 3),
+            Err(CeremonyError::CommitmentMismatch(
+                ceremony.contributors[0].clone()
+            ))
+        );
+        assert_eq!(
+            ceremony.reveal(reveal(&keys[0], "aa"), 3),
+            Ok(CeremonyPhase::Revealing)
+        );
+        assert_eq!(
+            ceremony.reveal(reveal(&keys[1], "bb"), 4),
+            Ok(CeremonyPhase::Complete)
+        );
+
+        let seed = ceremony.unicorn().unwrap().seed;
+        let mut transcript = ceremony.transcript();
+        assert_eq!(transcript.verify(), Ok(seed));
+
+        transcript.reveals.pop();
+        assert!(matches!(
+            transcript.verify(),
+            Err(CeremonyError::MissingReveals(_))
+        ));
+    }
+
+    #[test]
+    /// Checks that re-casing a revealed secret does not change the seed
+    fn recased_reveal() {
+        let keys = [test_keypair(1), test_keypair(2)];
+        let seed = |second: &str| {
+            let mut ceremony = ceremony(&keys, 2);
+            ceremony.commit(commitment(&keys[0], "aa"), 1).unwrap();
+            ceremony.commit(commitment(&keys[1], "bb"), 2).unwrap();
+            ceremony.reveal(reveal(&keys[0], "aa"), 3).unwrap();
+            ceremony.reveal(reveal(&keys[1], second), 4).unwrap();
+            ceremony.transcript().verify().unwrap()
+        };
+
+        assert_eq!(seed("BB"), seed("bb"));
+        assert_eq!(seed("Bb"), seed("bb"));
+    }
+
+    #[test]
+    /// Checks the refusal of outsiders and equivocation, and the timeout rules
+    fn timeouts_and_refusals() {
+        let keys = [test_keypair(1), test_keypair(2), test_keypair(3)];
+        let mut ceremony = ceremony(&keys, 2);
+
+        assert!(matches!(
+            ceremony.commit(commitment(&test_keypair(9), "aa"), 1),
+            Err(CeremonyError::UnknownContributor(_))
+        ));
+        ceremony.commit(commitment(&keys[0], "aa"), 1).unwrap();
+        assert!(matches!(
+            ceremony.commit(commitment(&keys[0], "cc"), 1),
+            Err(CeremonyError::Equivocation(_))
+        ));
+        ceremony.commit(commitment(&keys[1], "bb"), 2).unwrap();
+
+        // Two of three committed, which meets the threshold at the deadline
+        assert_eq!(ceremony.poll(11), CeremonyPhase::Revealing);
+        assert!(matches!(
+            ceremony.reveal(reveal(&keys[2], "cc"), 12),
+            Err(CeremonyError::NotCommitted(_))
+        ));
+        ceremony.reveal(reveal(&keys[0], "aa"), 12).unwrap();
+
+        // The second contributor withholds their reveal
+        assert_eq!(ceremony.poll(22), CeremonyPhase::Aborted);
+        assert!(ceremony.abort_reason.is_some());
+
+        let mut short = self::ceremony(&keys, 2);
+        short.commit(commitment(&keys[0], "aa"), 1).unwrap();
+        assert_eq!(short.poll(11), CeremonyPhase::Aborted);
+    }
+
+    #[test]
+    /// Checks that a blinded ceremony completes from blinded reveals only, and that the
+    /// blinding factor, ceremony id and contributor key are bound into the commitment
+    fn blinded_ceremony() {
+        let keys = [test_keypair(1), test_keypair(2)];
+        let key_hex = |i: usize| hex::encode(keys[i].public.to_bytes());
+        let mut ceremony = ceremony(&keys, 2).with_blinding();
+        let blindings = [blinding_factor().unwrap(), blinding_factor().unwrap()];
+        let blinded = |i: usize, secret: &str| {
+            let commitment = SeedCommitment {
+                ceremony_id: 7,
+                commitment: commit_blinded(7, &key_hex(i), &blindings[i], secret).unwrap(),
+            };
+            Signed::sign(commitment, &keys[i])
+        };
+        let opening = |i: usize, blinding: &str| {
+            let reveal = SeedReveal {
+                ceremony_id: 7,
+                secret: "aa".to_string(),
+                blinding: Some(blinding.to_string()),
+            };
+            Signed::sign(reveal, &keys[i])
+        };
+
+        assert_eq!(commit_blinded(7, &key_hex(0), "00", "aa"), None);
+        assert_ne!(blinded(0, "aa"), blinded(1, "aa"));
+        assert_ne!(
+            commit_blinded(8, &key_hex(0), &blindings[0], "aa"),
+            commit_blinded(7, &key_hex(0), &blindings[0], "aa")
+        );
+        assert_ne!(
+            commit_blinded(7, &key_hex(1), &blindings[0], "aa"),
+            commit_blinded(7, &key_hex(0), &blindings[0], "aa")
+        );
+
+        // A contributor copying another's commitment cannot open it
+        let mut copying = self::ceremony(&keys, 2).with_blinding();
+        copying.commit(blinded(0, "aa"), 1).unwrap();
+        copying
+            .commit(Signed::sign(blinded(0, "aa").payload, &keys[1]), 2)
+            .unwrap();
+        copying.reveal(opening(0, &blindings[0]), 3).unwrap();
+        assert_eq!(
+            copying.reveal(opening(1, &blindings[0]), 4),
+            Err(CeremonyError::CommitmentMismatch(key_hex(1)))
+        );
+
+        ceremony.commit(blinded(0, "aa"), 1).unwrap();
+        ceremony.commit(blinded(1, "aa"), 2).unwrap();
+
+        let mismatch = Err(CeremonyError::CommitmentMismatch(
+            ceremony.contributors[0].clone(),
+        ));
+        assert_eq!(ceremony.reveal(reveal(&keys[0], "aa"), 3), mismatch);
+        assert_eq!(ceremony.reveal(opening(0, &blindings[1]), 3), mismatch);
+        ceremony.reveal(opening(0, &blindings[0]), 3).unwrap();
+        assert_eq!(
+            ceremony.reveal(opening(1, &blindings[1]), 4),
+            Ok(CeremonyPhase::Complete)
+        );
+
+        assert_eq!(ceremony.transcript().verify(), Ok(ceremony.seed.clone()));
+    }
+}
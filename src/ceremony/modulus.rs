@@ -0,0 +1,241 @@
+//! Multi-party selection of UNICORN moduli.
+//!
+//! A modulus generated by a single party is only as trustworthy as that party: they could
+//! keep drawing until they find a prime with structure only they know of, or reuse one they
+//! have precomputed against. Instead, the contributors of a seed ceremony roll the dice
+//! together. The seed composed from their revealed secrets, tagged with the ceremony id and
+//! the requested size, is the entropy of `generate_provable_modulus`, so no contributor can
+//! steer the choice of prime as long as one of them is honest.
+//!
+//! The `ModulusTranscript` holds the ceremony transcript alongside the modulus certificate
+//! and its primality proof. Anyone can check the ceremony, rerun the deterministic search
+//! from its seed and confirm that it lands on the certified modulus.
+
+use super::{Ceremony, CeremonyError, CeremonyTranscript};
+use crate::modulus::{
+    generate_provable_modulus, ModulusCertificate, PrimalityProof, CERTIFICATE_ROUNDS,
+};
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+/// Domain separation for the entropy of a modulus ceremony
+const MODULUS_CONTEXT: &[u8] = b"miner-lottery modulus ceremony";
+
+/// Smallest modulus size a ceremony can select
+pub const MIN_CEREMONY_BITS: u32 = 3;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ModulusCeremonyError {
+    /// The underlying seed ceremony is incomplete or does not verify.
+    Ceremony(CeremonyError),
+    /// Requested modulus size is below `MIN_CEREMONY_BITS`.
+    TooSmall(u32),
+    /// The certified modulus is not the one the ceremony seed selects.
+    ModulusMismatch,
+    /// The certificate or its primality proof does not verify.
+    InvalidCertificate,
+}
+
+impl From<CeremonyError> for ModulusCeremonyError {
+    fn from(e: CeremonyError) -> Self {
+        Self::Ceremony(e)
+    }
+}
+
+impl fmt::Display for ModulusCeremonyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModulusCeremonyError::Ceremony(e) => write!(f, "Ceremony error: {}", e),
+            ModulusCeremonyError::TooSmall(bits) => write!(
+                f,
+                "Modulus of {} bits is below the minimum of {}",
+                bits, MIN_CEREMONY_BITS
+            ),
+            ModulusCeremonyError::ModulusMismatch => {
+                write!(f, "Modulus was not selected by the ceremony seed")
+            }
+            ModulusCeremonyError::InvalidCertificate => {
+                write!(f, "Modulus certificate does not verify")
+            }
+        }
+    }
+}
+
+impl Error for ModulusCeremonyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ModulusCeremonyError::Ceremony(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Published record of a modulus selected by a ceremony
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ModulusTranscript {
+    /// Transcript of the seed ceremony that rolled the dice
+    pub ceremony: CeremonyTranscript,
+    /// Requested size of the modulus in bits
+    pub bits: u32,
+    /// Certificate of the selected modulus, with its primality proof
+    pub certificate: ModulusCertificate,
+}
+
+impl ModulusTranscript {
+    /// Checks the ceremony, reruns the modulus search from its seed and checks the
+    /// certificate. Returns the modulus
+    pub fn verify(&self) -> Result<Integer, ModulusCeremonyError> {
+        let seed = self.ceremony.verify()?;
+        let (modulus, _) = select(self.ceremony.id, &seed, self.bits)?;
+
+        if self.certificate.modulus != modulus.to_string_radix(10) {
+            return Err(ModulusCeremonyError::ModulusMismatch);
+        }
+        if self.certificate.proof.is_none() || !self.certificate.verify() {
+            return Err(ModulusCeremonyError::InvalidCertificate);
+        }
+
+        Ok(modulus)
+    }
+}
+
+/// Selects a modulus from the seed of a completed ceremony
+///
+/// ### Arguments
+///
+/// * `ceremony` - Completed seed ceremony
+/// * `bits`     - Size of the modulus in bits
+pub fn select_modulus(
+    ceremony: &Ceremony,
+    bits: u32,
+) -> Result<ModulusTranscript, ModulusCeremonyError> {
+    let transcript = ceremony.transcript();
+    let seed = transcript.verify()?;
+    let (modulus, proof) = select(transcript.id, &seed, bits)?;
+    let certificate = ModulusCertificate::new(&modulus, CERTIFICATE_ROUNDS)
+        .and_then(|c| c.with_proof(proof))
+        .ok_or(ModulusCeremonyError::InvalidCertificate)?;
+
+    Ok(ModulusTranscript {
+        ceremony: transcript,
+        bits,
+        certificate,
+    })
+}
+
+/// Entropy a ceremony seed gives the modulus search
+///
+/// ### Arguments
+///
+/// * `ceremony_id` - Ceremony identifier
+/// * `seed`        - Seed composed by the ceremony
+/// * `bits`        - Size of the modulus in bits
+pub fn modulus_entropy(ceremony_id: u64, seed: &Integer, bits: u32) -> [u8; 32] {
+    let input = (MODULUS_CONTEXT, ceremony_id, bits, seed.to_string_radix(16));
+    Sha256::digest(serialize(&input).unwrap()).into()
+}
+
+fn select(
+    ceremony_id: u64,
+    seed: &Integer,
+    bits: u32,
+) -> Result<(Integer, PrimalityProof), ModulusCeremonyError> {
+    if bits < MIN_CEREMONY_BITS {
+        return Err(ModulusCeremonyError::TooSmall(bits));
+    }
+
+    let entropy = modulus_entropy(ceremony_id, seed, bits);
+    Ok(generate_provable_modulus(bits, &entropy))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod modulus_ceremony_tests {
+    use super::*;
+    use crate::ceremony::{commit_secret, CeremonyTimeouts, SeedCommitment, SeedReveal};
+    use crate::signing::test_keypair;
+    use crate::signing::Signed;
+    use ed25519_dalek::Keypair;
+
+    fn completed_ceremony(secrets: &[&str]) -> Ceremony {
+        let keys: Vec<Keypair> = (1..=secrets.len() as u8).map(test_keypair).collect();
+        let contributors = keys
+            .iter()
+            .map(|k| hex::encode(k.public.as_bytes()))
+            .collect();
+        let timeouts = CeremonyTimeouts {
+            commit_secs: 10,
+            reveal_secs: 10,
+        };
+        let mut ceremony = Ceremony::new(5, contributors, 2, Default::default(), timeouts, 0);
+
+        for (key, secret) in keys.iter().zip(secrets) {
+            let commitment = SeedCommitment {
+                ceremony_id: 5,
+                commitment: commit_secret(5, secret).unwrap(),
+            };
+            ceremony.commit(Signed::sign(commitment, key), 1).unwrap();
+        }
+        for (key, secret) in keys.iter().zip(secrets) {
+            let reveal = SeedReveal {
+                ceremony_id: 5,
+                secret: secret.to_string(),
+                ..Default::default()
+            };
+            ceremony.reveal(Signed::sign(reveal, key), 2).unwrap();
+        }
+        ceremony
+    }
+
+    #[test]
+    /// Checks that the selected modulus replays from the transcript and depends on every
+    /// contributor's secret
+    fn modulus_replays() {
+        let ceremony = completed_ceremony(&["aa", "bb"]);
+        let transcript = select_modulus(&ceremony, 128).unwrap();
+        let modulus = transcript.verify().unwrap();
+
+        assert_eq!(modulus.significant_bits(), 128);
+        assert_eq!(modulus.mod_u(4), 3);
+        assert_eq!(select_modulus(&ceremony, 128), Ok(transcript.clone()));
+
+        let other = select_modulus(&completed_ceremony(&["aa", "bc"]), 128).unwrap();
+        assert_ne!(other.certificate.modulus, transcript.certificate.modulus);
+
+        let mut swapped = transcript.clone();
+        swapped.certificate = other.certificate;
+        assert_eq!(swapped.verify(), Err(ModulusCeremonyError::ModulusMismatch));
+    }
+
+    #[test]
+    /// Checks that incomplete ceremonies and undersized moduli are refused
+    fn refuses_incomplete() {
+        let keys = [test_keypair(1), test_keypair(2)];
+        let contributors = keys
+            .iter()
+            .map(|k| hex::encode(k.public.as_bytes()))
+            .collect();
+        let open = Ceremony::new(
+            5,
+            contributors,
+            2,
+            Default::default(),
+            Default::default(),
+            0,
+        );
+
+        assert!(matches!(
+            select_modulus(&open, 128),
+            Err(ModulusCeremonyError::Ceremony(CeremonyError::WrongPhase(_)))
+        ));
+        assert_eq!(
+            select_modulus(&completed_ceremony(&["aa", "bb"]), 2),
+            Err(ModulusCeremonyError::TooSmall(2))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Client verifying published lottery results from a URL.
+//!
+//! The client fetches a published artifact, such as a round written by the daemon or the
+//! result of `GET /rounds/{id}/result`, and audits it locally. Nothing the server says about
+//! the result is trusted: the typed `Verification` carries the artifact together with the
+//! outcome of every check, so that wallets and explorers can show both the winner and why it
+//! holds.
+
+use crate::audit::{Artifact, AuditReport};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The artifact could not be fetched.
+    Http(reqwest::Error),
+    /// The response is not a round, transcript or UNICORN.
+    Malformed(serde_json::Error),
+    /// The round has not been published yet.
+    Unpublished,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "Fetch failed: {}", e),
+            ClientError::Malformed(e) => write!(f, "Malformed artifact: {}", e),
+            ClientError::Unpublished => write!(f, "Round has not been published"),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            ClientError::Malformed(e) => Some(e),
+            ClientError::Unpublished => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+/// A fetched artifact and the outcome of auditing it locally
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    pub artifact: Artifact,
+    pub report: AuditReport,
+}
+
+impl Verification {
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.report.passed()
+    }
+
+    /// The winner and their index among the inputs, if the artifact records a draw. Only
+    /// meaningful if the verification passed
+    pub fn winner(&self) -> Option<(&str, usize)> {
+        self.artifact
+            .transcript()
+            .map(|t| (t.winner.as_str(), t.winner_index))
+    }
+}
+
+/// Client fetching and verifying published results
+#[derive(Default, Debug, Clone)]
+pub struct VerifyClient {
+    http: reqwest::Client,
+}
+
+impl VerifyClient {
+    /// Creates a client
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetches a published artifact
+    ///
+    /// ### Arguments
+    ///
+    /// * `url` - URL of the artifact
+    pub async fn fetch(&self, url: &str) -> Result<Artifact, ClientError> {
+        let body = self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Artifact::from_json(&body).map_err(ClientError::Malformed)
+    }
+
+    /// Fetches a published artifact and audits it locally
+    ///
+    /// ### Arguments
+    ///
+    /// * `url` - URL of the artifact
+    pub async fn verify(&self, url: &str) -> Result<Verification, ClientError> {
+        let artifact = self.fetch(url).await?;
+        let report = artifact.audit().ok_or(ClientError::Unpublished)?;
+
+        Ok(Verification { artifact, report })
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(all(test, feature = "server"))]
+mod client_tests {
+    use super::*;
+    use crate::transcript::SelectionTranscript;
+    use crate::unicorn::UnicornFixedParam;
+    use axum::routing::get;
+    use axum::{Json, Router};
+
+    #[tokio::test]
+    /// Checks that a served transcript is verified and a tampered one fails
+    async fn verify_served_transcript() {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let transcript = SelectionTranscript::record(&inputs, &params, 1).unwrap();
+        let mut tampered = transcript.clone();
+        tampered.g_value = "00".to_string();
+
+        let app = Router::new()
+            .route("/valid", get(move || async move { Json(transcript) }))
+            .route("/tampered", get(move || async move { Json(tampered) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let client = VerifyClient::new();
+        let valid = client.verify(&format!("{}/valid", base)).await.unwrap();
+        let tampered = client.verify(&format!("{}/tampered", base)).await.unwrap();
+
+        assert!(valid.passed());
+        assert_eq!(
+            valid.winner().map(|(w, _)| w.starts_with("miner_")),
+            Some(true)
+        );
+        assert!(!tampered.passed());
+        assert!(matches!(
+            client.verify(&format!("{}/missing", base)).await,
+            Err(ClientError::Http(_))
+        ));
+    }
+}
@@ -0,0 +1,709 @@
+//! Lottery configuration, loaded from TOML.
+//!
+//! A configuration file holds the fixed UNICORN parameters along with selection settings and
+//! the sources of entropy bound into the seed:
+//!
+//! ```toml
+//! [unicorn]
+//! modulus = "6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151"
+//! iterations = 1000
+//! security = 1
+//! primality_rounds = 15
+//!
+//! [selection]
+//! usage_number = 1
+//! winners = 1
+//! backups = 0
+//!
+//! [entropy]
+//! inputs = ["miner_a", "miner_b"]
+//! pow_hashes = []
+//!
+//! [daemon]
+//! collect_secs = 60
+//! eval_secs = 600
+//! merkle_root = false
+//! seed_mode = "serialized"
+//! socket = "miner-lottery.sock"
+//! publish_dir = "."
+//! audit_log = "rounds.log"
+//! checkpoint_iterations = 1048576
+//! eval_mode = "standard"
+//! timestamp_url = "https://freetsa.org/tsr"
+//!
+//! [daemon.roughtime]
+//! address = "roughtime.cloudflare.com:2002"
+//! public_key = "803eb78528f749c4bec2e39e1abb9b5e5ab7e4dd5ce4b6f2fd2f93ecc3538f1a"
+//!
+//! [daemon.schedule]
+//! policy = "interval"
+//! interval_secs = 60
+//! cutoff_offset_secs = 0
+//!
+//! [daemon.grace]
+//! mode = "flag"
+//! grace_secs = 5
+//!
+//! [limits]
+//! burst = 10
+//! refill_secs = 1
+//! round_quota = 10
+//!
+//! [primality]
+//! rounds = 15
+//! certificate = "modulus.json"
+//!
+//! [policy]
+//! min_delay_secs = 60
+//! calibration = "calibration.json"
+//!
+//! [p2p]
+//! announcers = ["d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"]
+//! ```
+//!
+//! Only the `[unicorn]` table is required. Loading validates the parameters, so that a bad
+//! modulus is reported when the configuration is read rather than when the eval fails. A
+//! configured modulus certificate must carry a primality proof of the configured modulus,
+//! and with a minimum delay configured, the parameters must imply at least that delay at the
+//! eval speed recorded in the calibration file, as written by `calibrate --json`.
+
+use crate::calibrate::Calibration;
+use crate::modulus::{is_probable_prime, ModulusCertificate};
+use crate::params::{check_recommended, Warning};
+use crate::policy::DelayPolicy;
+use crate::rounds::GracePolicy;
+use crate::schedule::Schedule;
+use crate::unicorn::{EvalMode, SeedMode, UnicornFixedParam, MR_PRIME_ITERS};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Example modulus, for use when no other modulus is configured
+pub const DEFAULT_MODULUS: &str = "6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151";
+
+/// Default number of UNICORN iterations
+pub const DEFAULT_ITERATIONS: u64 = 1_000;
+
+/// Default UNICORN security level
+pub const DEFAULT_SECURITY: u32 = 1;
+
+/// Default number of eval iterations between checkpoints
+pub const DEFAULT_CHECKPOINT_ITERATIONS: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Io(String),
+    /// The configuration is not valid TOML or does not match the schema.
+    Parse(String),
+    /// A configured value is invalid.
+    Invalid { field: &'static str, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "Could not read configuration: {}", e),
+            ConfigError::Parse(e) => write!(f, "Invalid configuration: {}", e),
+            ConfigError::Invalid { field, reason } => {
+                write!(f, "Invalid configuration value for `{}`: {}", field, reason)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Selection settings
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectionConfig {
+    /// Usage number of the winner draw
+    pub usage_number: u64,
+    /// Number of winners to draw
+    pub winners: usize,
+    /// Number of ordered backups to draw after the winners
+    pub backups: usize,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        Self {
+            usage_number: 1,
+            winners: 1,
+            backups: 0,
+        }
+    }
+}
+
+/// Sources of entropy bound into the seed
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntropyConfig {
+    /// Public key inputs of the participants
+    pub inputs: Vec<String>,
+    /// Hashes of the winning PoWs from 2 blocks ago
+    pub pow_hashes: Vec<String>,
+}
+
+/// Settings of the long-running daemon
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Time after round creation during which contributions are accepted, in seconds
+    pub collect_secs: u64,
+    /// Time after eval start within which the eval must complete, in seconds
+    pub eval_secs: u64,
+    /// Proof-of-work difficulty required of contributions, if any
+    pub pow_difficulty: Option<u32>,
+    /// Whether seeds commit to the Merkle root of the participants
+    pub merkle_root: bool,
+    /// How seeds are built from the participants otherwise; `tree_hash` for large rounds
+    pub seed_mode: SeedMode,
+    /// Path of the local status socket
+    pub socket: PathBuf,
+    /// Directory published rounds are written to
+    pub publish_dir: PathBuf,
+    /// Hash-chained log round events are appended to, if any
+    pub audit_log: Option<PathBuf>,
+    /// Eval iterations between stored checkpoints, from which an interrupted eval is resumed;
+    /// 0 to never checkpoint. Checkpoints are taken at progress reports, so the interval is
+    /// rounded up to a multiple of `unicorn::PROGRESS_INTERVAL`
+    pub checkpoint_iterations: u64,
+    /// How evals guard against timing side channels; `hardened` for coordinators evaluating
+    /// on shared hardware
+    pub eval_mode: EvalMode,
+    /// URL of the RFC 3161 time-stamping authority published rounds are timestamped by, if
+    /// any. Only used with the `timestamp` feature
+    pub timestamp_url: Option<String>,
+    /// Roughtime server round cutoffs are anchored to, if any. Only used with the
+    /// `roughtime` feature
+    pub roughtime: Option<RoughtimeConfig>,
+    /// When rounds are cut off; see `schedule` for the policies
+    pub schedule: Schedule,
+    /// How contributions timestamped after the cutoff are treated; mode `flag` or `next_round`
+    /// to take them for a grace period
+    pub grace: GracePolicy,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            collect_secs: 60,
+            eval_secs: 600,
+            pow_difficulty: None,
+            merkle_root: false,
+            seed_mode: SeedMode::Serialized,
+            socket: PathBuf::from("miner-lottery.sock"),
+            publish_dir: PathBuf::from("."),
+            audit_log: None,
+            checkpoint_iterations: DEFAULT_CHECKPOINT_ITERATIONS,
+            eval_mode: EvalMode::Standard,
+            timestamp_url: None,
+            roughtime: None,
+            schedule: Schedule::Rolling,
+            grace: GracePolicy::default(),
+        }
+    }
+}
+
+/// Roughtime server attesting round cutoffs
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RoughtimeConfig {
+    /// Address of the server, as `host:port`
+    pub address: String,
+    /// Hex-encoded long-term Ed25519 public key of the server
+    pub public_key: String,
+}
+
+/// Limits on contributions per identity, applied by the server
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Contributions an identity can make in a burst
+    pub burst: u32,
+    /// Time after which an identity may make one more contribution, in seconds
+    pub refill_secs: u64,
+    /// Contributions an identity may make per round; 0 for no quota
+    pub round_quota: u32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            burst: 10,
+            refill_secs: 1,
+            round_quota: 10,
+        }
+    }
+}
+
+/// How the modulus is checked to be prime
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrimalityConfig {
+    /// Miller-Rabin rounds the modulus must pass
+    pub rounds: u32,
+    /// Modulus certificate, as written by `genmod --provable`, whose primality proof must
+    /// prove the configured modulus
+    pub certificate: Option<PathBuf>,
+}
+
+impl Default for PrimalityConfig {
+    fn default() -> Self {
+        Self {
+            rounds: MR_PRIME_ITERS,
+            certificate: None,
+        }
+    }
+}
+
+/// Floor on the delay implied by the UNICORN parameters
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Shortest delay the parameters may imply, in seconds; 0 for no floor
+    pub min_delay_secs: u64,
+    /// Calibration of the eval speed, as JSON, from which the delay is estimated
+    pub calibration: Option<PathBuf>,
+}
+
+/// Gossip network settings
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct P2pConfig {
+    /// Hex-encoded Ed25519 public keys of the coordinator and verifiers whose UNICORN
+    /// announcements are relayed. Only used with the `p2p` feature
+    pub announcers: Vec<String>,
+}
+
+/// Full lottery configuration
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LotteryConfig {
+    pub unicorn: UnicornFixedParam,
+    #[serde(default)]
+    pub selection: SelectionConfig,
+    #[serde(default)]
+    pub entropy: EntropyConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub primality: PrimalityConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub p2p: P2pConfig,
+}
+
+impl Default for LotteryConfig {
+    fn default() -> Self {
+        Self {
+            unicorn: UnicornFixedParam {
+                modulus: DEFAULT_MODULUS.to_string(),
+                iterations: DEFAULT_ITERATIONS,
+                security: DEFAULT_SECURITY,
+                ..Default::default()
+            },
+            selection: SelectionConfig::default(),
+            entropy: EntropyConfig::default(),
+            daemon: DaemonConfig::default(),
+            limits: LimitsConfig::default(),
+            primality: PrimalityConfig::default(),
+            policy: PolicyConfig::default(),
+            p2p: P2pConfig::default(),
+        }
+    }
+}
+
+impl LotteryConfig {
+    /// Loads and validates a configuration file
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Path of the TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+
+        let config = Self::from_toml(&contents)?;
+        if let Some(path) = &config.primality.certificate {
+            config.check_certificate(path)?;
+        }
+        if let Some(policy) = config.delay_policy()? {
+            policy
+                .check(&config.unicorn)
+                .map_err(|e| invalid("unicorn.iterations", e.to_string()))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Minimum-delay policy, if a floor is configured, with its calibration read from file
+    pub fn delay_policy(&self) -> Result<Option<DelayPolicy>, ConfigError> {
+        if self.policy.min_delay_secs == 0 {
+            return Ok(None);
+        }
+        let Some(path) = &self.policy.calibration else {
+            return Err(invalid(
+                "policy.calibration",
+                "a calibration is required for a minimum delay",
+            ));
+        };
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+        let calibration: Calibration = serde_json::from_str(&contents)
+            .map_err(|e| invalid("policy.calibration", e.to_string()))?;
+        if !calibration.iterations_per_sec.is_finite() || calibration.iterations_per_sec <= 0.0 {
+            return Err(invalid("policy.calibration", "eval speed must be positive"));
+        }
+
+        Ok(Some(DelayPolicy::new(
+            Duration::from_secs(self.policy.min_delay_secs),
+            calibration,
+        )))
+    }
+
+    /// Checks that a modulus certificate holds a primality proof of the configured modulus
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Path of the JSON certificate
+    fn check_certificate(&self, path: &Path) -> Result<(), ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+        let certificate: ModulusCertificate = serde_json::from_str(&contents)
+            .map_err(|e| invalid("primality.certificate", e.to_string()))?;
+
+        if certificate.modulus != self.unicorn.modulus {
+            return Err(invalid(
+                "primality.certificate",
+                "certifies a different modulus",
+            ));
+        }
+        if certificate.proof.is_none() {
+            return Err(invalid("primality.certificate", "holds no primality proof"));
+        }
+        if !certificate.verify() {
+            return Err(invalid("primality.certificate", "does not verify"));
+        }
+
+        Ok(())
+    }
+
+    /// Parses and validates a configuration
+    ///
+    /// ### Arguments
+    ///
+    /// * `toml` - TOML configuration
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(toml).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Serializes the configuration to TOML
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap()
+    }
+
+    /// Parameter recommendations the configured UNICORN parameters fall short of
+    pub fn warnings(&self) -> Vec<Warning> {
+        check_recommended(&self.unicorn)
+    }
+
+    /// Checks the configured values
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.primality.rounds == 0 {
+            return Err(invalid(
+                "primality.rounds",
+                "at least one round is required",
+            ));
+        }
+        let rounds = self.primality.rounds.max(self.unicorn.primality_rounds);
+        validate_params_with_rounds(&self.unicorn, rounds)?;
+
+        if self.selection.winners == 0 {
+            return Err(invalid(
+                "selection.winners",
+                "at least one winner is required",
+            ));
+        }
+        if self.daemon.collect_secs == 0 {
+            return Err(invalid(
+                "daemon.collect_secs",
+                "the collection window must be at least 1s",
+            ));
+        }
+        if let Schedule::Interval {
+            interval_secs: 0, ..
+        } = self.daemon.schedule
+        {
+            return Err(invalid(
+                "daemon.schedule.interval_secs",
+                "the interval must be at least 1s",
+            ));
+        }
+        if self.limits.burst == 0 {
+            return Err(invalid("limits.burst", "at least one token is required"));
+        }
+        if self.limits.refill_secs == 0 {
+            return Err(invalid(
+                "limits.refill_secs",
+                "the refill interval must be at least 1s",
+            ));
+        }
+        if self
+            .p2p
+            .announcers
+            .iter()
+            .any(|key| hex::decode(key).map_or(true, |bytes| bytes.len() != 32))
+        {
+            return Err(invalid(
+                "p2p.announcers",
+                "announcers must be hex-encoded Ed25519 public keys",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks fixed UNICORN parameters: the modulus must pass the parameters' Miller-Rabin
+/// rounds, be congruent to 3 mod 4 and be at least `2^2k` for security level `k`, and there
+/// must be at least one iteration and one Miller-Rabin round
+///
+/// ### Arguments
+///
+/// * `params` - Parameters to check
+pub fn validate_params(params: &UnicornFixedParam) -> Result<(), ConfigError> {
+    validate_params_with_rounds(params, params.primality_rounds)
+}
+
+/// Checks fixed UNICORN parameters as `validate_params` does, with the given number of
+/// Miller-Rabin rounds on the modulus
+///
+/// ### Arguments
+///
+/// * `params` - Parameters to check
+/// * `rounds` - Number of Miller-Rabin rounds
+pub fn validate_params_with_rounds(
+    params: &UnicornFixedParam,
+    rounds: u32,
+) -> Result<(), ConfigError> {
+    let modulus = Integer::from_str_radix(&params.modulus, 10)
+        .map_err(|_| invalid("unicorn.modulus", "not a decimal integer"))?;
+
+    if modulus.mod_u(4) != 3 {
+        return Err(invalid("unicorn.modulus", "not congruent to 3 mod 4"));
+    }
+    if !is_probable_prime(&modulus, rounds) {
+        return Err(invalid("unicorn.modulus", "not prime"));
+    }
+    if modulus.significant_bits() <= 2 * params.security {
+        return Err(invalid(
+            "unicorn.security",
+            format!("modulus must be at least 2^{}", 2 * params.security),
+        ));
+    }
+    if params.iterations == 0 {
+        return Err(invalid("unicorn.iterations", "must be at least 1"));
+    }
+    if params.primality_rounds == 0 {
+        return Err(invalid("unicorn.primality_rounds", "must be at least 1"));
+    }
+
+    Ok(())
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+impl UnicornFixedParam {
+    /// Parameters with the default modulus and few iterations, for tests
+    pub(crate) fn test() -> Self {
+        Self {
+            modulus: DEFAULT_MODULUS.to_string(),
+            iterations: 1_000,
+            security: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that a configuration survives a TOML round trip with defaults filled in
+    fn toml_round_trip() {
+        let toml = format!(
+            "[unicorn]\nmodulus = \"{}\"\niterations = 10\nsecurity = 1\n",
+            DEFAULT_MODULUS
+        );
+        let config = LotteryConfig::from_toml(&toml).unwrap();
+
+        assert_eq!(config.unicorn.iterations, 10);
+        assert_eq!(config.selection, SelectionConfig::default());
+        assert_eq!(LotteryConfig::from_toml(&config.to_toml()), Ok(config));
+    }
+
+    #[test]
+    /// Checks that a round schedule is read from its table and survives a TOML round trip
+    fn schedule_table() {
+        let toml = format!(
+            "[unicorn]\nmodulus = \"{}\"\niterations = 10\nsecurity = 1\n\n\
+             [daemon.schedule]\npolicy = \"block_height\"\ncutoff_offset_blocks = 2\n",
+            DEFAULT_MODULUS
+        );
+        let config = LotteryConfig::from_toml(&toml).unwrap();
+
+        assert_eq!(
+            config.daemon.schedule,
+            Schedule::BlockHeight {
+                cutoff_offset_blocks: 2
+            }
+        );
+        assert_eq!(LotteryConfig::from_toml(&config.to_toml()), Ok(config));
+
+        let zero_interval = toml.replace(
+            "policy = \"block_height\"\ncutoff_offset_blocks = 2",
+            "policy = \"interval\"\ninterval_secs = 0",
+        );
+        assert!(matches!(
+            LotteryConfig::from_toml(&zero_interval),
+            Err(ConfigError::Invalid { field, .. }) if field == "daemon.schedule.interval_secs"
+        ));
+    }
+
+    #[test]
+    /// Checks that invalid values are reported by field
+    fn invalid_values() {
+        let with_modulus = |modulus: &str| {
+            format!(
+                "[unicorn]\nmodulus = \"{}\"\niterations = 10\nsecurity = 1\n",
+                modulus
+            )
+        };
+
+        assert!(matches!(
+            LotteryConfig::from_toml(&with_modulus("21")),
+            Err(ConfigError::Invalid { reason, .. }) if reason == "not congruent to 3 mod 4"
+        ));
+        assert!(matches!(
+            LotteryConfig::from_toml(&with_modulus("15")),
+            Err(ConfigError::Invalid { reason, .. }) if reason == "not prime"
+        ));
+        assert!(matches!(
+            LotteryConfig::from_toml("[unicorn]\nmodulus = 7\n"),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            LotteryConfig::from_toml(&format!(
+                "{}\n[p2p]\nannouncers = [\"00\"]\n",
+                with_modulus(DEFAULT_MODULUS)
+            )),
+            Err(ConfigError::Invalid { field, .. }) if field == "p2p.announcers"
+        ));
+    }
+
+    #[test]
+    /// Checks that a configured certificate must prove the configured modulus
+    fn primality_certificate() {
+        use crate::modulus::generate_provable_modulus;
+
+        let dir = std::env::temp_dir();
+        let (modulus, proof) = generate_provable_modulus(128, &[1; 32]);
+        let certificate = ModulusCertificate::new(&modulus, 8).unwrap();
+        let write = |name: &str, certificate: &ModulusCertificate, modulus: &str| {
+            let path = dir.join(format!(
+                "miner-lottery-config-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::write(
+                path.with_extension("json"),
+                serde_json::to_string(certificate).unwrap(),
+            )
+            .unwrap();
+            let toml = format!(
+                "[unicorn]\nmodulus = \"{}\"\niterations = 10\nsecurity = 1\n\n\
+                 [primality]\nrounds = 4\ncertificate = {:?}\n",
+                modulus,
+                path.with_extension("json")
+            );
+            fs::write(path.with_extension("toml"), toml).unwrap();
+            LotteryConfig::load(path.with_extension("toml"))
+        };
+
+        let unproven = write("unproven", &certificate, &certificate.modulus);
+        let proven = certificate.with_proof(proof).unwrap();
+        let other = write("other", &proven, DEFAULT_MODULUS);
+        let config = write("proven", &proven, &proven.modulus).unwrap();
+
+        assert!(
+            matches!(unproven, Err(ConfigError::Invalid { reason, .. }) if reason == "holds no primality proof")
+        );
+        assert!(
+            matches!(other, Err(ConfigError::Invalid { reason, .. }) if reason == "certifies a different modulus")
+        );
+        assert_eq!(config.primality.rounds, 4);
+    }
+
+    #[test]
+    /// Checks that a configured minimum delay rejects parameters that imply a shorter one
+    fn minimum_delay() {
+        let path = std::env::temp_dir().join(format!(
+            "miner-lottery-config-policy-{}",
+            std::process::id()
+        ));
+        let calibration = Calibration {
+            modulus_bits: 521,
+            sample_iterations: 1_000,
+            sample_time: Duration::from_millis(500),
+            iterations_per_sec: 2_000.0,
+            recommended_iterations: 120_000,
+        };
+        fs::write(
+            path.with_extension("json"),
+            serde_json::to_string(&calibration).unwrap(),
+        )
+        .unwrap();
+        let load = |iterations: u64| {
+            let toml = format!(
+                "[unicorn]\nmodulus = \"{}\"\niterations = {}\nsecurity = 1\n\n\
+                 [policy]\nmin_delay_secs = 60\ncalibration = {:?}\n",
+                DEFAULT_MODULUS,
+                iterations,
+                path.with_extension("json")
+            );
+            fs::write(path.with_extension("toml"), toml).unwrap();
+            LotteryConfig::load(path.with_extension("toml"))
+        };
+
+        assert!(load(120_000).is_ok());
+        assert!(matches!(
+            load(1_000),
+            Err(ConfigError::Invalid { field, .. }) if field == "unicorn.iterations"
+        ));
+    }
+}
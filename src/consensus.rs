@@ -0,0 +1,373 @@
+//! Payloads for agreeing on lottery results over RAFT.
+//!
+//! A round is agreed in two steps. The node that ran the eval proposes the resulting UNICORN
+//! for the round's sealed inputs, and once the proposal is accepted the selection is committed
+//! alongside it. Both payloads carry the round id and the sealed inputs digest, so that a node
+//! whose sealed inputs differ from the leader's refuses to apply them.
+//!
+//! Payloads are encoded with bincode behind a version byte. Bincode's default configuration
+//! (fixed-width little-endian integers, length-prefixed sequences) does not depend on the
+//! platform, so every node encodes the same payload to the same bytes.
+//!
+//! Validation only checks the witness through the fast `verify` path, so applying a committed
+//! entry does not require followers to re-run the eval.
+
+use crate::rounds::{Round, RoundError, RoundPhase};
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{g_matches, UnicornInfo};
+use bincode::{deserialize, serialize};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Version byte prefixed to encoded payloads
+pub const PAYLOAD_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConsensusError {
+    /// Payload bytes could not be decoded.
+    Malformed,
+    /// Payload was encoded with an unsupported version.
+    UnsupportedVersion(u8),
+    /// Payload cannot be applied in the round's current phase.
+    InvalidPhase(RoundPhase),
+    /// Payload is for another round.
+    WrongRound { expected: u64, actual: u64 },
+    /// Payload's sealed inputs digest differs from the round's.
+    DigestMismatch,
+    /// UNICORN seed or parameters differ from the round's.
+    UnicornMismatch,
+    /// Witness or `g` value does not verify.
+    InvalidWitness,
+    /// Committed selection differs from the one derived from the UNICORN.
+    SelectionMismatch,
+    /// Round state machine failure.
+    Round(RoundError),
+}
+
+impl From<RoundError> for ConsensusError {
+    fn from(e: RoundError) -> Self {
+        Self::Round(e)
+    }
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::Malformed => write!(f, "Malformed consensus payload"),
+            ConsensusError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported consensus payload version {}", v)
+            }
+            ConsensusError::InvalidPhase(phase) => {
+                write!(f, "Payload cannot be applied to a round in {:?}", phase)
+            }
+            ConsensusError::WrongRound { expected, actual } => write!(
+                f,
+                "Payload for round {} applied to round {}",
+                actual, expected
+            ),
+            ConsensusError::DigestMismatch => write!(f, "Sealed inputs digest mismatch"),
+            ConsensusError::UnicornMismatch => write!(f, "UNICORN does not match the round"),
+            ConsensusError::InvalidWitness => write!(f, "UNICORN witness does not verify"),
+            ConsensusError::SelectionMismatch => write!(f, "Committed selection mismatch"),
+            ConsensusError::Round(e) => write!(f, "Round error: {}", e),
+        }
+    }
+}
+
+impl Error for ConsensusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConsensusError::Round(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Winner selected for a round
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Selection {
+    pub usage_number: u128,
+    /// Index of the winner within the round's seed inputs
+    pub winner_index: usize,
+    pub winner: String,
+}
+
+impl From<&SelectionTranscript> for Selection {
+    fn from(transcript: &SelectionTranscript) -> Self {
+        Self {
+            usage_number: transcript.usage_number,
+            winner_index: transcript.winner_index,
+            winner: transcript.winner.clone(),
+        }
+    }
+}
+
+/// Proposal of an evaluated UNICORN for a sealed round
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UnicornProposal {
+    pub round_id: u64,
+    pub sealed_digest: String,
+    pub unicorn: UnicornInfo,
+}
+
+/// Commit of the selection made from an accepted proposal
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelectionCommit {
+    pub round_id: u64,
+    pub sealed_digest: String,
+    pub unicorn: UnicornInfo,
+    pub selection: Selection,
+}
+
+/// Payload of a RAFT log entry
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ConsensusPayload {
+    Proposal(UnicornProposal),
+    Commit(SelectionCommit),
+}
+
+impl ConsensusPayload {
+    /// Encodes the payload as a version byte followed by its bincode encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![PAYLOAD_VERSION];
+        bytes.extend(serialize(self).unwrap());
+        bytes
+    }
+
+    /// Decodes a payload produced by `to_bytes`
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes` - Encoded payload
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ConsensusError> {
+        match bytes.split_first() {
+            Some((&PAYLOAD_VERSION, rest)) => {
+                deserialize(rest).map_err(|_| ConsensusError::Malformed)
+            }
+            Some((&version, _)) => Err(ConsensusError::UnsupportedVersion(version)),
+            None => Err(ConsensusError::Malformed),
+        }
+    }
+}
+
+impl UnicornProposal {
+    /// Creates a proposal for a sealed round. Returns `None` if the round has not been sealed
+    ///
+    /// ### Arguments
+    ///
+    /// * `round`   - Sealed round
+    /// * `unicorn` - UNICORN evaluated for the round's seed
+    pub fn new(round: &Round, unicorn: UnicornInfo) -> Option<Self> {
+        Some(Self {
+            round_id: round.id,
+            sealed_digest: round.sealed_digest.clone()?,
+            unicorn,
+        })
+    }
+
+    /// Checks that the proposal can be applied to a round: the round is sealed or
+    /// evaluating, the round id and sealed inputs digest match, and the UNICORN was
+    /// evaluated for the round's seed and parameters with a valid witness
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to check against
+    pub fn validate(&self, round: &Round) -> Result<(), ConsensusError> {
+        validate_unicorn(self.round_id, &self.sealed_digest, &self.unicorn, round)
+    }
+
+    /// Builds the commit for the proposal, selecting the winner from the round's inputs.
+    /// Returns `None` if the round has no inputs
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round the proposal was accepted for
+    pub fn commit(self, round: &Round) -> Option<SelectionCommit> {
+        let selection = Selection::from(&transcript_for(&self.unicorn, round)?);
+
+        Some(SelectionCommit {
+            round_id: self.round_id,
+            sealed_digest: self.sealed_digest,
+            unicorn: self.unicorn,
+            selection,
+        })
+    }
+}
+
+impl SelectionCommit {
+    /// Checks that the commit can be applied to a round. On top of the proposal checks, the
+    /// selection must match the one derived from the UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to check against
+    pub fn validate(&self, round: &Round) -> Result<(), ConsensusError> {
+        validate_unicorn(self.round_id, &self.sealed_digest, &self.unicorn, round)?;
+
+        let transcript = transcript_for(&self.unicorn, round);
+        if transcript.map(|t| Selection::from(&t)).as_ref() != Some(&self.selection) {
+            return Err(ConsensusError::SelectionMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Validates the commit and applies it to the round, moving it to `Published`. A round
+    /// that is still sealed starts its eval at `now` and completes it with the committed
+    /// UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to apply the commit to
+    /// * `now`   - Current unix time in seconds
+    pub fn apply<'a>(
+        &self,
+        round: &'a mut Round,
+        now: u64,
+    ) -> Result<&'a SelectionTranscript, ConsensusError> {
+        self.validate(round)?;
+
+        if round.phase == RoundPhase::Sealed {
+            round.start_eval(now)?;
+        }
+
+        Ok(round.complete_eval(self.unicorn.clone(), now)?)
+    }
+}
+
+fn validate_unicorn(
+    round_id: u64,
+    sealed_digest: &str,
+    info: &UnicornInfo,
+    round: &Round,
+) -> Result<(), ConsensusError> {
+    if !matches!(round.phase, RoundPhase::Sealed | RoundPhase::Evaluating) {
+        return Err(ConsensusError::InvalidPhase(round.phase));
+    }
+    if round_id != round.id {
+        return Err(ConsensusError::WrongRound {
+            expected: round.id,
+            actual: round_id,
+        });
+    }
+    if round.sealed_digest.as_deref() != Some(sealed_digest)
+        || round.inputs_digest() != sealed_digest
+    {
+        return Err(ConsensusError::DigestMismatch);
+    }
+
+    let uni = &info.unicorn;
+    let modulus = Integer::from_str_radix(&round.fixed_params.modulus, 10).ok();
+    if uni.seed != round.seed
+        || modulus.as_ref() != Some(&uni.modulus)
+        || uni.iterations != round.fixed_params.iterations
+        || uni.security_level != round.fixed_params.security
+    {
+        return Err(ConsensusError::UnicornMismatch);
+    }
+
+    if !uni.verify_ref(&uni.seed, &info.witness) || !g_matches(&info.witness, &info.g_value) {
+        return Err(ConsensusError::InvalidWitness);
+    }
+
+    Ok(())
+}
+
+fn transcript_for(info: &UnicornInfo, round: &Round) -> Option<SelectionTranscript> {
+    SelectionTranscript::from_unicorn(
+        &round.registry.seed_inputs(),
+        &round.last_winning_hashes,
+        &round.fixed_params,
+        info,
+        round.usage_number,
+    )
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod consensus_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn sealed_round() -> Round {
+        let fixed_params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 60,
+        };
+
+        let mut round = Round::new(1, fixed_params, 1, timeouts, 0);
+        round.contribute("miner_a", 10).unwrap();
+        round.contribute("miner_b", 20).unwrap();
+        round.seal().unwrap();
+        round
+    }
+
+    fn proposal(round: &Round) -> UnicornProposal {
+        let unicorn = round.clone().start_eval(70).unwrap();
+        let (witness, g_value) = unicorn.eval().unwrap();
+
+        UnicornProposal::new(round, UnicornInfo::new(unicorn, witness, g_value)).unwrap()
+    }
+
+    #[test]
+    /// Checks that a commit survives encoding and is applied by a follower
+    fn encode_and_apply() {
+        let leader = sealed_round();
+        let mut follower = leader.clone();
+        let commit = proposal(&leader).commit(&leader).unwrap();
+
+        let payload = ConsensusPayload::Commit(commit.clone());
+        let bytes = payload.to_bytes();
+        assert_eq!(ConsensusPayload::from_bytes(&bytes), Ok(payload));
+
+        let transcript = commit.apply(&mut follower, 80).unwrap();
+        assert_eq!(Selection::from(transcript), commit.selection);
+        assert_eq!(follower.phase, RoundPhase::Published);
+    }
+
+    #[test]
+    /// Checks that mismatching payloads are refused
+    fn rejects_mismatches() {
+        let round = sealed_round();
+        let proposal = proposal(&round);
+
+        let mut wrong_round = proposal.clone();
+        wrong_round.round_id = 2;
+        let mut bad_witness = proposal.clone();
+        bad_witness.unicorn.witness += 2;
+        let mut bad_selection = proposal.clone().commit(&round).unwrap();
+        bad_selection.selection.winner_index ^= 1;
+        let mut other = round.clone();
+        other.last_winning_hashes.push("00ab".to_string());
+
+        assert!(proposal.validate(&round).is_ok());
+        assert_eq!(
+            wrong_round.validate(&round),
+            Err(ConsensusError::WrongRound {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            bad_witness.validate(&round),
+            Err(ConsensusError::InvalidWitness)
+        );
+        assert_eq!(
+            bad_selection.validate(&round),
+            Err(ConsensusError::SelectionMismatch)
+        );
+        assert_eq!(
+            proposal.validate(&other),
+            Err(ConsensusError::DigestMismatch)
+        );
+        assert_eq!(
+            ConsensusPayload::from_bytes(&[2]),
+            Err(ConsensusError::UnsupportedVersion(2))
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! Long-running round scheduler.
+//!
+//! The daemon runs one round after another as configured by the `[daemon]` table: it accepts
+//! contributions while a round is collecting, seals the round at its cutoff, evaluates it and
+//! publishes the result before opening the next round. A round that closes without
+//! participants is reopened under the same id. Cutoffs follow the configured `Schedule`;
+//! rounds cut off by block height or by hand are triggered through `Height` and `Trigger`
+//! requests.
+//!
+//! The eval is handed out by `poll` and its result handed back through `finish`, so that the
+//! caller can run it without blocking status requests. Requests and responses are serde
+//! types, sent as one JSON object per line over the daemon's local socket. A subscribed
+//! connection is answered with the latest published round and then sent each new one as it
+//! is published.
+
+use crate::config::LotteryConfig;
+#[cfg(feature = "roughtime")]
+use crate::roughtime::cutoff_nonce;
+use crate::roughtime::ClockAttestation;
+use crate::rounds::{Contribution, Round, RoundError, RoundPhase, RoundTimeouts};
+use crate::unicorn::{Unicorn, UnicornInfo};
+use serde::{Deserialize, Serialize};
+
+/// Request to a running daemon
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    Status,
+    Contribute(Contribution),
+    Subscribe,
+    /// Cuts off the collecting round, under the manual schedule.
+    Trigger,
+    /// Reports the height the chain has reached, under the block-height schedule.
+    Height {
+        height: u64,
+    },
+}
+
+/// Response from a running daemon
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DaemonResponse {
+    Status(DaemonStatus),
+    Accepted { fingerprint: String },
+    Subscribed { latest: Option<PublishedRound> },
+    Published(PublishedRound),
+    Error { message: String },
+}
+
+/// Lifecycle event of a round, as pushed to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RoundEvent {
+    /// The participant set was frozen at the cutoff.
+    Sealed {
+        round_id: u64,
+        participants: usize,
+        sealed_digest: Option<String>,
+    },
+    /// The eval started, to complete before the deadline.
+    EvalStarted {
+        round_id: u64,
+        deadline: Option<u64>,
+    },
+    /// The result was published.
+    Published(PublishedRound),
+}
+
+/// Summary of a published round
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PublishedRound {
+    pub round_id: u64,
+    pub g_value: String,
+    pub winner: String,
+    pub winner_index: usize,
+}
+
+impl PublishedRound {
+    /// Summarizes a round, if it has been published
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to summarize
+    pub fn from_round(round: &Round) -> Option<Self> {
+        let transcript = round.transcript.as_ref()?;
+
+        Some(Self {
+            round_id: round.id,
+            g_value: transcript.g_value.clone(),
+            winner: transcript.winner.clone(),
+            winner_index: transcript.winner_index,
+        })
+    }
+}
+
+/// Status of a running daemon
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DaemonStatus {
+    pub round_id: u64,
+    pub phase: RoundPhase,
+    pub participants: usize,
+    /// Time after which contributions to the current round are refused
+    pub cutoff: u64,
+    pub rounds_published: u64,
+    pub last_published: Option<u64>,
+}
+
+/// Round scheduler of a running daemon
+#[derive(Debug, Clone)]
+pub struct Daemon {
+    config: LotteryConfig,
+    round: Round,
+    rounds_published: u64,
+    latest: Option<PublishedRound>,
+}
+
+impl Daemon {
+    /// Creates a daemon collecting for its first round
+    ///
+    /// ### Arguments
+    ///
+    /// * `config`   - Lottery configuration
+    /// * `round_id` - Id of the first round
+    /// * `now`      - Current unix time in seconds
+    pub fn new(config: LotteryConfig, round_id: u64, now: u64) -> Self {
+        let round = open_round(&config, round_id, now);
+
+        Self {
+            config,
+            round,
+            rounds_published: 0,
+            latest: None,
+        }
+    }
+
+    /// Creates a daemon picking up a round whose eval was interrupted. Returns `None` if
+    /// the round is not being evaluated
+    ///
+    /// ### Arguments
+    ///
+    /// * `config` - Lottery configuration
+    /// * `round`  - Round left in the `Evaluating` phase
+    pub fn resume(config: LotteryConfig, round: Round) -> Option<Self> {
+        if round.phase != RoundPhase::Evaluating {
+            return None;
+        }
+
+        Some(Self {
+            config,
+            round,
+            rounds_published: 0,
+            latest: None,
+        })
+    }
+
+    /// The current round
+    pub fn round(&self) -> &Round {
+        &self.round
+    }
+
+    /// Status of the daemon
+    pub fn status(&self) -> DaemonStatus {
+        DaemonStatus {
+            round_id: self.round.id,
+            phase: self.round.phase,
+            participants: self.round.registry.len(),
+            cutoff: self.round.cutoff,
+            rounds_published: self.rounds_published,
+            last_published: self.latest.as_ref().map(|r| r.round_id),
+        }
+    }
+
+    /// Handles a request received over the socket
+    ///
+    /// ### Arguments
+    ///
+    /// * `request` - Request to handle
+    /// * `now`     - Current unix time in seconds
+    pub fn handle(&mut self, request: DaemonRequest, now: u64) -> DaemonResponse {
+        match request {
+            DaemonRequest::Status => DaemonResponse::Status(self.status()),
+            DaemonRequest::Contribute(contribution) => match self.round.submit(contribution, now) {
+                Ok(fingerprint) => DaemonResponse::Accepted { fingerprint },
+                Err(e) => DaemonResponse::Error {
+                    message: e.to_string(),
+                },
+            },
+            DaemonRequest::Subscribe => DaemonResponse::Subscribed {
+                latest: self.latest.clone(),
+            },
+            DaemonRequest::Trigger => match self.trigger(now) {
+                Some(_) => DaemonResponse::Status(self.status()),
+                None => DaemonResponse::Error {
+                    message: "Round is not waiting for a manual trigger".to_string(),
+                },
+            },
+            DaemonRequest::Height { height } => {
+                self.observe_height(height, now);
+                DaemonResponse::Status(self.status())
+            }
+        }
+    }
+
+    /// Cuts off the current round as triggered by an operator, returning its cutoff. Returns
+    /// `None` if rounds are not triggered manually or the round is not waiting for a trigger
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn trigger(&mut self, now: u64) -> Option<u64> {
+        let cutoff = self.config.daemon.schedule.manual_cutoff(now)?;
+        self.cut_off(cutoff)
+    }
+
+    /// Records the height the chain has reached, cutting off the current round once the
+    /// block-height schedule says so. Returns the cutoff if the round was cut off
+    ///
+    /// ### Arguments
+    ///
+    /// * `height` - Height the chain has reached
+    /// * `now`    - Current unix time in seconds
+    pub fn observe_height(&mut self, height: u64, now: u64) -> Option<u64> {
+        let schedule = self.config.daemon.schedule;
+        let cutoff = schedule.height_cutoff(self.round.id, height, now)?;
+        self.cut_off(cutoff)
+    }
+
+    /// Sets the cutoff of a round still waiting for its trigger
+    fn cut_off(&mut self, cutoff: u64) -> Option<u64> {
+        if self.round.deadline.is_some() {
+            return None;
+        }
+
+        self.round.set_cutoff(cutoff).ok().map(|_| cutoff)
+    }
+
+    /// Advances the current round, returning the UNICORN to evaluate once the round has been
+    /// sealed at its cutoff
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn poll(&mut self, now: u64) -> Option<Unicorn> {
+        self.seal_at(now, None)
+    }
+
+    /// Nonce committing to the inputs of the current round, to have its cutoff attested with
+    /// once the cutoff has passed by the local clock. Returns `None` if the round is not
+    /// due to be sealed or has no participants to commit to
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    #[cfg(feature = "roughtime")]
+    pub fn cutoff_nonce(&self, now: u64) -> Option<[u8; 64]> {
+        let round = &self.round;
+        let due = round.phase == RoundPhase::Collecting && round.is_timed_out(now);
+
+        (due && !round.registry.is_empty())
+            .then(|| cutoff_nonce(&round.registry.seed_inputs(), &round.last_winning_hashes))
+    }
+
+    /// Advances the current round as `poll` does, at the earliest time an attested clock
+    /// reading allows for rather than by the local clock. The reading is recorded in the round
+    /// if it seals the round. Returns `None` if the reading is not over the nonce of the
+    /// round's current inputs
+    ///
+    /// ### Arguments
+    ///
+    /// * `attestation` - Reading attested over the nonce from `cutoff_nonce`
+    #[cfg(feature = "roughtime")]
+    pub fn poll_attested(&mut self, attestation: ClockAttestation) -> Option<Unicorn> {
+        let nonce = cutoff_nonce(
+            &self.round.registry.seed_inputs(),
+            &self.round.last_winning_hashes,
+        );
+        if attestation.nonce != hex::encode(nonce) {
+            return None;
+        }
+
+        self.seal_at(attestation.earliest_secs(), Some(attestation))
+    }
+
+    /// Seals the current round once its cutoff has passed at the given time, recording the
+    /// attested reading if there is one, and starts its eval
+    fn seal_at(&mut self, now: u64, attestation: Option<ClockAttestation>) -> Option<Unicorn> {
+        if self.round.phase != RoundPhase::Collecting || !self.round.is_timed_out(now) {
+            return None;
+        }
+
+        if self.round.seal().is_err() {
+            self.reopen(self.round.id, now);
+            return None;
+        }
+
+        self.round.cutoff_attestation = attestation;
+        self.round.start_eval(now).ok()
+    }
+
+    /// Completes the eval of the current round and opens the next one, returning the
+    /// published round
+    ///
+    /// ### Arguments
+    ///
+    /// * `info` - Evaluated UNICORN handed out by `poll`
+    /// * `now`  - Current unix time in seconds
+    pub fn finish(&mut self, info: UnicornInfo, now: u64) -> Result<Round, RoundError> {
+        let result = self.round.complete_eval(info, now).map(|_| ());
+        let round = self.reopen(self.round.id + 1, now);
+        result?;
+
+        self.rounds_published += 1;
+        self.latest = PublishedRound::from_round(&round);
+
+        Ok(round)
+    }
+
+    /// Aborts the current round after its eval failed and opens the next one, returning the
+    /// aborted round
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn abort(&mut self, now: u64) -> Round {
+        self.round.phase = RoundPhase::Aborted;
+        self.round.deadline = None;
+        self.reopen(self.round.id + 1, now)
+    }
+
+    /// Replaces the current round with a new one, carrying over the contributions held for
+    /// it, and returns the replaced round
+    fn reopen(&mut self, id: u64, now: u64) -> Round {
+        let mut next = open_round(&self.config, id, now);
+        let _ = next.carry_in(&self.round.carried_over);
+
+        std::mem::replace(&mut self.round, next)
+    }
+}
+
+/// Opens a round collecting under the daemon's configuration
+pub(crate) fn open_round(config: &LotteryConfig, id: u64, now: u64) -> Round {
+    let timeouts = RoundTimeouts {
+        collect_secs: config.daemon.collect_secs,
+        eval_secs: config.daemon.eval_secs,
+    };
+    let mut round = Round::new(
+        id,
+        config.unicorn.clone(),
+        config.selection.usage_number as u128,
+        timeouts,
+        now,
+    )
+    .with_cutoff(
+        config
+            .daemon
+            .schedule
+            .cutoff(config.daemon.collect_secs, now),
+    )
+    .with_grace(config.daemon.grace)
+    .with_last_winning_hashes(config.entropy.pow_hashes.clone())
+    .with_seed_mode(config.daemon.seed_mode);
+
+    if let Some(difficulty) = config.daemon.pow_difficulty {
+        round = round.with_pow_difficulty(difficulty);
+    }
+    if config.daemon.merkle_root {
+        round = round.with_merkle_root();
+    }
+
+    round
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod daemon_tests {
+    use super::*;
+    use crate::schedule::Schedule;
+
+    fn contribution(round_id: u64, identity: &str, timestamp: u64) -> DaemonRequest {
+        DaemonRequest::Contribute(Contribution {
+            round_id,
+            identity: identity.to_string(),
+            timestamp,
+            nonce: None,
+        })
+    }
+
+    #[test]
+    /// Checks that rounds are sealed at the cutoff, published and followed by the next round
+    fn daemon_round_cycle() {
+        let mut config = LotteryConfig::default();
+        config.daemon.collect_secs = 10;
+        let mut daemon = Daemon::new(config, 7, 0);
+
+        // An empty round is reopened under the same id
+        assert_eq!(daemon.poll(11), None);
+        assert_eq!(daemon.status().round_id, 7);
+        assert_eq!(daemon.status().cutoff, 21);
+
+        for identity in ["miner_a", "miner_b"] {
+            let response = daemon.handle(contribution(7, identity, 12), 12);
+            assert!(matches!(response, DaemonResponse::Accepted { .. }));
+        }
+        assert!(matches!(
+            daemon.handle(contribution(6, "miner_c", 12), 12),
+            DaemonResponse::Error { .. }
+        ));
+        assert_eq!(daemon.poll(20), None);
+
+        let unicorn = daemon.poll(22).unwrap();
+        let (witness, g_value) = unicorn.eval().unwrap();
+        let info = UnicornInfo::new(unicorn, witness, g_value);
+        let round = daemon.finish(info, 23).unwrap();
+
+        assert_eq!(round.phase, RoundPhase::Published);
+        assert!(round.transcript.as_ref().unwrap().replay().is_ok());
+        assert_eq!(
+            daemon.handle(DaemonRequest::Subscribe, 23),
+            DaemonResponse::Subscribed {
+                latest: PublishedRound::from_round(&round)
+            }
+        );
+        assert_eq!(
+            daemon.handle(DaemonRequest::Status, 23),
+            DaemonResponse::Status(DaemonStatus {
+                round_id: 8,
+                phase: RoundPhase::Collecting,
+                participants: 0,
+                cutoff: 33,
+                rounds_published: 1,
+                last_published: Some(7),
+            })
+        );
+    }
+
+    #[test]
+    /// Checks that rounds under a manual schedule collect until triggered
+    fn daemon_manual_schedule() {
+        let mut config = LotteryConfig::default();
+        config.daemon.schedule = Schedule::Manual {
+            cutoff_offset_secs: 5,
+        };
+        let mut daemon = Daemon::new(config, 7, 0);
+        daemon.handle(contribution(7, "miner_a", 1_000), 1_000);
+
+        assert_eq!(daemon.poll(1_000), None);
+        assert_eq!(daemon.observe_height(100, 1_000), None);
+        assert!(matches!(
+            daemon.handle(DaemonRequest::Trigger, 1_000),
+            DaemonResponse::Status(DaemonStatus { cutoff: 1_005, .. })
+        ));
+        assert!(matches!(
+            daemon.handle(DaemonRequest::Trigger, 1_001),
+            DaemonResponse::Error { .. }
+        ));
+
+        assert_eq!(daemon.poll(1_005), None);
+        assert!(daemon.poll(1_006).is_some());
+
+        let aborted = daemon.abort(1_007);
+        assert_eq!((aborted.id, aborted.phase), (7, RoundPhase::Aborted));
+        assert_eq!(daemon.status().round_id, 8);
+        assert_eq!(daemon.status().phase, RoundPhase::Collecting);
+    }
+
+    #[cfg(feature = "roughtime")]
+    #[test]
+    /// Checks that rounds are only sealed by readings over their inputs that are past the cutoff
+    fn daemon_attested_cutoff() {
+        let mut config = LotteryConfig::default();
+        config.daemon.collect_secs = 10;
+        let mut daemon = Daemon::new(config, 7, 0);
+        daemon.handle(contribution(7, "miner_a", 2), 2);
+
+        assert_eq!(daemon.cutoff_nonce(9), None);
+        let nonce = daemon.cutoff_nonce(11).unwrap();
+        let reading = |nonce: &[u8; 64], secs: u64| ClockAttestation {
+            nonce: hex::encode(nonce),
+            midpoint: secs * 1_000_000,
+            radius: 2_000_000,
+            ..Default::default()
+        };
+
+        assert_eq!(daemon.poll_attested(reading(&[0; 64], 20)), None);
+        assert_eq!(daemon.poll_attested(reading(&nonce, 11)), None);
+        assert!(daemon.poll_attested(reading(&nonce, 20)).is_some());
+        assert_eq!(daemon.round().cutoff_attestation, Some(reading(&nonce, 20)));
+    }
+}
@@ -0,0 +1,243 @@
+//! Dispute checks for claimed lottery winners.
+//!
+//! Given a claimed winner and the published selection transcript, `check_claim` re-derives
+//! every step of the draw independently and reports each step that disagrees with the
+//! transcript, rather than stopping at the first failure. Later steps are checked against the
+//! values recorded for earlier ones, so a single tampered value is reported once instead of
+//! cascading. The witness is checked with the fast `verify` path, so a dispute check does not
+//! require re-running the eval. The resulting `DisputeOutcome` serializes to JSON for use in
+//! governance processes.
+
+use crate::transcript::{winner_index, SelectionTranscript};
+use crate::unicorn::{g_matches, Unicorn, UnicornInfo};
+use crate::utils::unicorn_selection::get_unicorn_prn_bytes;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+
+/// Number of usage numbers searched when the recorded PRN comes from another usage number
+pub const USAGE_SEARCH_LIMIT: u128 = 1 << 10;
+
+/// A step of the draw that failed to check out
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum DisputeFinding {
+    /// The seed does not match the one constructed from the inputs.
+    SeedMismatch { recorded: String, expected: String },
+    /// The UNICORN modulus could not be parsed.
+    InvalidParams,
+    /// The commitment does not match the seed.
+    CommitmentMismatch,
+    /// The witness does not verify against the seed.
+    BadWitness,
+    /// The `g` value does not match the witness.
+    GValueMismatch,
+    /// The PRN was drawn with another usage number, if one was found.
+    WrongUsageNumber {
+        recorded: u128,
+        actual: Option<u128>,
+    },
+    /// The winner does not correspond to the PRN.
+    WrongIndexMapping { recorded: usize, expected: usize },
+    /// The claimed winner is not the winner of the draw.
+    ClaimMismatch { claimed: String, winner: String },
+    /// The transcript has no inputs to draw from.
+    NoInputs,
+}
+
+/// Machine-readable outcome of a dispute check
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DisputeOutcome {
+    pub claimed_winner: String,
+    /// Whether the claim and the proof check out
+    pub upheld: bool,
+    /// Winner re-derived from the proof, if the draw could be followed that far
+    pub winner: Option<String>,
+    pub findings: Vec<DisputeFinding>,
+}
+
+impl DisputeOutcome {
+    /// Serializes the outcome to pretty JSON
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Checks a claimed winner against the proof of the draw
+///
+/// ### Arguments
+///
+/// * `claimed_winner` - Winner being claimed
+/// * `proof`          - Published transcript of the draw
+pub fn check_claim(claimed_winner: &str, proof: &SelectionTranscript) -> DisputeOutcome {
+    let mut findings = Vec::new();
+    let winner = check_steps(claimed_winner, proof, &mut findings);
+
+    DisputeOutcome {
+        claimed_winner: claimed_winner.to_string(),
+        upheld: findings.is_empty(),
+        winner,
+        findings,
+    }
+}
+
+fn check_steps(
+    claimed_winner: &str,
+    proof: &SelectionTranscript,
+    findings: &mut Vec<DisputeFinding>,
+) -> Option<String> {
+    let expected_seed = proof.expected_seed();
+    if expected_seed != proof.seed {
+        findings.push(DisputeFinding::SeedMismatch {
+            recorded: proof.seed.to_string_radix(16),
+            expected: expected_seed.to_string_radix(16),
+        });
+    }
+
+    let Ok(modulus) = Integer::from_str_radix(&proof.fixed_params.modulus, 10) else {
+        findings.push(DisputeFinding::InvalidParams);
+        return None;
+    };
+    let unicorn = Unicorn {
+        seed: proof.seed.clone(),
+        modulus,
+        iterations: proof.fixed_params.iterations,
+        security_level: proof.fixed_params.security,
+        primality_rounds: proof.fixed_params.primality_rounds,
+    };
+
+    if unicorn.commitment() != proof.commitment {
+        findings.push(DisputeFinding::CommitmentMismatch);
+    }
+    if !unicorn.verify_ref(&proof.seed, &proof.witness) {
+        findings.push(DisputeFinding::BadWitness);
+    }
+    if !g_matches(&proof.witness, &proof.g_value) {
+        findings.push(DisputeFinding::GValueMismatch);
+    }
+    // A `g` value too short to key the CSPRNG never matches its witness, so it is already
+    // reported above
+    let info = UnicornInfo::new(unicorn, proof.witness.clone(), proof.g_value.clone());
+    let prn_bytes = get_unicorn_prn_bytes(&info, proof.usage_number)?;
+    if proof.inputs.is_empty() {
+        findings.push(DisputeFinding::NoInputs);
+        return None;
+    }
+
+    if hex::encode(prn_bytes) != proof.prn_bytes {
+        findings.push(DisputeFinding::WrongUsageNumber {
+            recorded: proof.usage_number,
+            actual: (0..USAGE_SEARCH_LIMIT).find(|u| {
+                get_unicorn_prn_bytes(&info, *u).is_some_and(|b| hex::encode(b) == proof.prn_bytes)
+            }),
+        });
+    }
+
+    let expected = winner_index(&info, proof.usage_number, proof.inputs.len())?;
+    if proof.winner_index != expected || proof.winner != proof.inputs[expected] {
+        findings.push(DisputeFinding::WrongIndexMapping {
+            recorded: proof.winner_index,
+            expected,
+        });
+    }
+
+    let winner = proof.inputs[expected].clone();
+    if claimed_winner != winner {
+        findings.push(DisputeFinding::ClaimMismatch {
+            claimed: claimed_winner.to_string(),
+            winner: winner.clone(),
+        });
+    }
+
+    Some(winner)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod disputes_tests {
+    use super::*;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn test_proof() -> SelectionTranscript {
+        let params = UnicornFixedParam::test();
+        let inputs: Vec<String> = (0..5).map(|i| format!("miner_{}", i)).collect();
+
+        SelectionTranscript::record(&inputs, &params, 7).unwrap()
+    }
+
+    #[test]
+    /// Checks that a correct claim is upheld
+    fn valid_claim() {
+        let proof = test_proof();
+        let outcome = check_claim(&proof.winner, &proof);
+
+        assert!(outcome.upheld);
+        assert_eq!(outcome.winner, Some(proof.winner.clone()));
+        assert!(outcome.to_json_pretty().is_ok());
+    }
+
+    #[test]
+    /// Checks that each tampered step is pinpointed
+    fn pinpoints_failures() {
+        let proof = test_proof();
+        let other = proof.inputs[(proof.winner_index + 1) % 5].clone();
+
+        let mut bad_usage = proof.clone();
+        bad_usage.usage_number = 8;
+        let mut bad_index = proof.clone();
+        bad_index.winner_index = (proof.winner_index + 1) % 5;
+        bad_index.winner = other.clone();
+        let mut bad_witness = proof.clone();
+        bad_witness.witness += 2;
+        let mut bad_seed = proof.clone();
+        bad_seed.inputs.swap(0, 1);
+
+        assert_eq!(
+            check_claim(&other, &proof).findings,
+            vec![DisputeFinding::ClaimMismatch {
+                claimed: other.clone(),
+                winner: proof.winner.clone()
+            }]
+        );
+        assert_eq!(
+            check_claim(&proof.winner, &bad_usage).findings,
+            vec![DisputeFinding::WrongUsageNumber {
+                recorded: 8,
+                actual: Some(7)
+            }]
+        );
+        assert_eq!(
+            check_claim(&other, &bad_index).findings,
+            vec![
+                DisputeFinding::WrongIndexMapping {
+                    recorded: bad_index.winner_index,
+                    expected: proof.winner_index
+                },
+                DisputeFinding::ClaimMismatch {
+                    claimed: other,
+                    winner: proof.winner.clone()
+                }
+            ]
+        );
+        assert_eq!(
+            check_claim(&proof.winner, &bad_witness).findings[..2],
+            [DisputeFinding::BadWitness, DisputeFinding::GValueMismatch]
+        );
+        assert!(matches!(
+            check_claim(&proof.winner, &bad_seed).findings[0],
+            DisputeFinding::SeedMismatch { .. }
+        ));
+
+        let mut short_g = proof.clone();
+        short_g.g_value.truncate(8);
+        assert_eq!(
+            check_claim(&proof.winner, &short_g).findings,
+            vec![DisputeFinding::GValueMismatch]
+        );
+        let mut no_inputs = proof.clone();
+        no_inputs.inputs.clear();
+        assert_eq!(
+            check_claim(&proof.winner, &no_inputs).findings.last(),
+            Some(&DisputeFinding::NoInputs)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Adapter exposing published rounds as a generic randomness feed.
+//!
+//! Oracle consumers, such as smart contracts fed by a relayer or bridges to other chains,
+//! expect a beacon to publish a round number, a fixed-length randomness value and an opaque
+//! proof they or a verifier can check. Each published round maps onto one such entry:
+//!
+//! * `round`      - Id of the round
+//! * `randomness` - The key derived from the round's witness, as by `unicorn::g_key`
+//! * `proof`      - The bincode encoding of the round's selection transcript
+//!
+//! Entries serialize to JSON with hex-encoded bytes, and to the Solidity ABI encoding of
+//! `(uint256 round, bytes32 randomness, bytes proof)` for on-chain consumers.
+
+use crate::rounds::Round;
+use crate::transcript::{SelectionTranscript, TranscriptStep};
+use crate::unicorn::{g_key, G_KEY_LEN};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Length of the randomness of a feed entry, in bytes
+pub const RANDOMNESS_LEN: usize = G_KEY_LEN;
+
+/// Length of a word of the Solidity ABI encoding, in bytes
+const ABI_WORD_LEN: usize = 32;
+
+/// Errors from checking a feed entry
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FeedError {
+    /// The randomness or proof could not be decoded.
+    Malformed,
+    /// The transcript in the proof failed to replay at the given step.
+    Replay(TranscriptStep),
+    /// The randomness was not derived from the witness in the proof.
+    RandomnessMismatch,
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "Malformed feed entry"),
+            Self::Replay(step) => write!(f, "Proof failed to replay at step {:?}", step),
+            Self::RandomnessMismatch => {
+                write!(f, "Randomness was not derived from the proven witness")
+            }
+        }
+    }
+}
+
+impl Error for FeedError {}
+
+/// Output of a published round, in the shape of a randomness feed entry
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FeedEntry {
+    /// Id of the round
+    pub round: u64,
+    /// Hex-encoded randomness derived from the round's witness
+    pub randomness: String,
+    /// Hex-encoded bincode encoding of the round's selection transcript
+    pub proof: String,
+}
+
+impl FeedEntry {
+    /// Creates the entry for a round, if it has been published
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to expose
+    pub fn from_round(round: &Round) -> Option<Self> {
+        Self::from_transcript(round.id, round.transcript.as_ref()?)
+    }
+
+    /// Creates the entry for the transcript of a round. Returns `None` if the transcript
+    /// could not be encoded
+    ///
+    /// ### Arguments
+    ///
+    /// * `round`      - Id of the round
+    /// * `transcript` - Selection transcript of the round
+    pub fn from_transcript(round: u64, transcript: &SelectionTranscript) -> Option<Self> {
+        let randomness = g_key(&transcript.witness)?;
+        let proof = bincode::serialize(transcript).ok()?;
+
+        Some(Self {
+            round,
+            randomness: hex::encode(randomness),
+            proof: hex::encode(proof),
+        })
+    }
+
+    /// Decoded randomness of the entry
+    pub fn randomness_bytes(&self) -> Option<[u8; RANDOMNESS_LEN]> {
+        hex::decode(&self.randomness).ok()?.try_into().ok()
+    }
+
+    /// Decoded proof of the entry
+    pub fn proof_bytes(&self) -> Option<Vec<u8>> {
+        hex::decode(&self.proof).ok()
+    }
+
+    /// Checks the entry by replaying the transcript in its proof and deriving the randomness
+    /// from its witness, returning the transcript
+    pub fn verify(&self) -> Result<SelectionTranscript, FeedError> {
+        let randomness = self.randomness_bytes().ok_or(FeedError::Malformed)?;
+        let proof = self.proof_bytes().ok_or(FeedError::Malformed)?;
+        let transcript: SelectionTranscript =
+            bincode::deserialize(&proof).map_err(|_| FeedError::Malformed)?;
+
+        transcript.replay().map_err(FeedError::Replay)?;
+        if g_key(&transcript.witness) != Some(randomness) {
+            return Err(FeedError::RandomnessMismatch);
+        }
+
+        Ok(transcript)
+    }
+
+    /// Solidity ABI encoding of `(uint256 round, bytes32 randomness, bytes proof)`, as taken
+    /// by on-chain consumers. Returns `None` if the entry could not be decoded
+    pub fn abi_encode(&self) -> Option<Vec<u8>> {
+        let randomness = self.randomness_bytes()?;
+        let proof = self.proof_bytes()?;
+
+        let mut encoded = abi_word(self.round as u128);
+        encoded.extend_from_slice(&randomness);
+        encoded.extend(abi_word(3 * ABI_WORD_LEN as u128));
+        encoded.extend(abi_word(proof.len() as u128));
+        encoded.extend_from_slice(&proof);
+        encoded.resize(encoded.len().next_multiple_of(ABI_WORD_LEN), 0);
+
+        Some(encoded)
+    }
+}
+
+/// Big-endian word of the Solidity ABI encoding for an unsigned value
+fn abi_word(value: u128) -> Vec<u8> {
+    let mut word = vec![0; ABI_WORD_LEN - 16];
+    word.extend_from_slice(&value.to_be_bytes());
+    word
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod feed_tests {
+    use super::*;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn test_transcript() -> SelectionTranscript {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+
+        SelectionTranscript::record(&inputs, &params, 1).unwrap()
+    }
+
+    #[test]
+    /// Checks that an entry verifies against its proof and rejects swapped randomness
+    fn feed_entry_verifies() {
+        let transcript = test_transcript();
+        let entry = FeedEntry::from_transcript(7, &transcript).unwrap();
+
+        assert_eq!(entry.verify(), Ok(transcript));
+
+        let mut forged = entry.clone();
+        forged.randomness = hex::encode([0; RANDOMNESS_LEN]);
+        assert_eq!(forged.verify(), Err(FeedError::RandomnessMismatch));
+
+        forged.randomness = "zz".to_string();
+        assert_eq!(forged.verify(), Err(FeedError::Malformed));
+    }
+
+    #[test]
+    /// Checks the layout of the ABI encoding of an entry
+    fn feed_entry_abi_encoding() {
+        let entry = FeedEntry::from_transcript(7, &test_transcript()).unwrap();
+        let proof = entry.proof_bytes().unwrap();
+        let encoded = entry.abi_encode().unwrap();
+
+        assert_eq!(encoded[..32], abi_word(7)[..]);
+        assert_eq!(encoded[32..64], entry.randomness_bytes().unwrap());
+        assert_eq!(encoded[64..96], abi_word(96)[..]);
+        assert_eq!(encoded[96..128], abi_word(proof.len() as u128)[..]);
+        assert_eq!(encoded[128..128 + proof.len()], proof[..]);
+        assert_eq!(encoded.len() % 32, 0);
+    }
+}
@@ -0,0 +1,318 @@
+//! C interface to the lottery, for mining stacks written in C or C++.
+//!
+//! Built with the `cdylib` feature, which also writes the matching header to
+//! `include/miner_lottery.h`. Every function returns an `MlStatus`, whose values are stable
+//! across releases. Inputs are passed as a pointer and a length; structured values, such as
+//! parameters and evaluated UNICORNs, are UTF-8 JSON in the same shape as the CLI and HTTP API
+//! use. Outputs are written to an `MlBuffer` allocated by the library, which the caller must
+//! release with `ml_buffer_free`. Nothing is written to an output buffer unless the call
+//! returns `ML_STATUS_OK`.
+
+use crate::audit::audit_unicorn;
+use crate::config::validate_params;
+use crate::selection::prove_many_from;
+use crate::unicorn::{Unicorn, UnicornFixedParam, UnicornInfo};
+use rug::integer::Order;
+use rug::Integer;
+use serde::de::DeserializeOwned;
+use std::ffi::c_char;
+use std::panic::{catch_unwind, UnwindSafe};
+use std::ptr;
+use std::slice;
+
+/// Outcome of a call. Values are stable and will not be reused
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MlStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer = 1,
+    /// An input was not valid UTF-8 or not valid JSON.
+    InvalidInput = 2,
+    /// The UNICORN parameters are not valid.
+    InvalidParams = 3,
+    /// The UNICORN did not verify.
+    VerifyFailed = 4,
+    /// Winners could not be drawn from the participants.
+    SelectionFailed = 5,
+    /// The library panicked; this is a bug.
+    Panic = 6,
+}
+
+/// Bytes allocated by the library, to be released with `ml_buffer_free`
+#[repr(C)]
+#[derive(Debug)]
+pub struct MlBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl MlBuffer {
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+
+        Self {
+            data: bytes as *mut u8,
+            len: bytes.len(),
+        }
+    }
+}
+
+/// Evaluates the UNICORN for a seed, writing the evaluated `UnicornInfo` to `out` as JSON
+///
+/// ### Arguments
+///
+/// * `seed`       - Seed as big-endian bytes
+/// * `seed_len`   - Length of `seed`
+/// * `params`     - `UnicornFixedParam` as JSON
+/// * `params_len` - Length of `params`
+/// * `out`        - Buffer receiving the result
+///
+/// # Safety
+///
+/// `seed` and `params` must point to at least `seed_len` and `params_len` readable bytes, and
+/// `out` must point to a writable `MlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn ml_eval(
+    seed: *const u8,
+    seed_len: usize,
+    params: *const u8,
+    params_len: usize,
+    out: *mut MlBuffer,
+) -> MlStatus {
+    if out.is_null() {
+        return MlStatus::NullPointer;
+    }
+    let seed = bytes(seed, seed_len);
+    let params = bytes(params, params_len);
+
+    guard(out, move || {
+        let params: UnicornFixedParam = json(params.ok_or(MlStatus::NullPointer)?)?;
+        validate_params(&params).map_err(|_| MlStatus::InvalidParams)?;
+        let unicorn = Unicorn {
+            seed: Integer::from_digits(seed.ok_or(MlStatus::NullPointer)?, Order::MsfBe),
+            modulus: Integer::from_str_radix(&params.modulus, 10)
+                .map_err(|_| MlStatus::InvalidParams)?,
+            iterations: params.iterations,
+            security_level: params.security,
+            primality_rounds: params.primality_rounds,
+        };
+
+        let (witness, g_value) = unicorn.eval().ok_or(MlStatus::InvalidParams)?;
+        let info = UnicornInfo::new(unicorn, witness, g_value);
+        Ok(Some(serde_json::to_vec(&info).unwrap()))
+    })
+}
+
+/// Verifies an evaluated UNICORN, returning `ML_STATUS_OK` if its modulus, witness and `g`
+/// value all check out and `ML_STATUS_VERIFY_FAILED` otherwise
+///
+/// ### Arguments
+///
+/// * `unicorn`     - `UnicornInfo` as JSON, as written by `ml_eval`
+/// * `unicorn_len` - Length of `unicorn`
+///
+/// # Safety
+///
+/// `unicorn` must point to at least `unicorn_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ml_verify(unicorn: *const u8, unicorn_len: usize) -> MlStatus {
+    let unicorn = bytes(unicorn, unicorn_len);
+
+    guard(ptr::null_mut(), move || {
+        let info: UnicornInfo = json(unicorn.ok_or(MlStatus::NullPointer)?)?;
+
+        match audit_unicorn(&info).passed() {
+            true => Ok(None),
+            false => Err(MlStatus::VerifyFailed),
+        }
+    })
+}
+
+/// Draws distinct winners from a UNICORN, writing the `WinnersProof` of the draw to `out` as
+/// JSON. Its `winners` are indices into the participants
+///
+/// ### Arguments
+///
+/// * `unicorn`          - `UnicornInfo` as JSON, as written by `ml_eval`
+/// * `unicorn_len`      - Length of `unicorn`
+/// * `participants`     - Identities of the participants, one per line, in input order
+/// * `participants_len` - Length of `participants`
+/// * `usage_number`     - Usage number of the draw
+/// * `winners`          - Number of winners
+/// * `out`              - Buffer receiving the result
+///
+/// # Safety
+///
+/// `unicorn` and `participants` must point to at least `unicorn_len` and `participants_len`
+/// readable bytes, and `out` must point to a writable `MlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn ml_select(
+    unicorn: *const u8,
+    unicorn_len: usize,
+    participants: *const u8,
+    participants_len: usize,
+    usage_number: u64,
+    winners: usize,
+    out: *mut MlBuffer,
+) -> MlStatus {
+    if out.is_null() {
+        return MlStatus::NullPointer;
+    }
+    let unicorn = bytes(unicorn, unicorn_len);
+    let participants = bytes(participants, participants_len);
+
+    guard(out, move || {
+        let info: UnicornInfo = json(unicorn.ok_or(MlStatus::NullPointer)?)?;
+        let participants = std::str::from_utf8(participants.ok_or(MlStatus::NullPointer)?)
+            .map_err(|_| MlStatus::InvalidInput)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let proof = prove_many_from(&info, usage_number as u128, &participants, winners)
+            .map_err(|_| MlStatus::SelectionFailed)?;
+        Ok(Some(serde_json::to_vec(&proof).unwrap()))
+    })
+}
+
+/// Releases a buffer written by the library. Releasing an empty buffer does nothing
+///
+/// ### Arguments
+///
+/// * `buffer` - Buffer to release
+///
+/// # Safety
+///
+/// `buffer` must have been written by this library and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn ml_buffer_free(buffer: MlBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Static, NUL-terminated description of a status
+///
+/// ### Arguments
+///
+/// * `status` - Status returned by a call
+#[no_mangle]
+pub extern "C" fn ml_status_message(status: MlStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        MlStatus::Ok => b"ok\0",
+        MlStatus::NullPointer => b"required pointer was null\0",
+        MlStatus::InvalidInput => b"input is not valid UTF-8 JSON\0",
+        MlStatus::InvalidParams => b"invalid UNICORN parameters\0",
+        MlStatus::VerifyFailed => b"UNICORN did not verify\0",
+        MlStatus::SelectionFailed => b"winners could not be drawn\0",
+        MlStatus::Panic => b"internal error\0",
+    };
+
+    message.as_ptr() as *const c_char
+}
+
+/// Borrows an input buffer, allowing a null pointer for an empty one
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(data, len)),
+    }
+}
+
+fn json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, MlStatus> {
+    serde_json::from_slice(bytes).map_err(|_| MlStatus::InvalidInput)
+}
+
+/// Runs a call, catching panics and writing its output, if any, to `out`
+fn guard(
+    out: *mut MlBuffer,
+    call: impl FnOnce() -> Result<Option<Vec<u8>>, MlStatus> + UnwindSafe,
+) -> MlStatus {
+    match catch_unwind(call) {
+        Ok(Ok(Some(_))) if out.is_null() => MlStatus::NullPointer,
+        Ok(Ok(Some(output))) => {
+            // Safety: checked non-null above; the caller guarantees it is writable
+            unsafe { out.write(MlBuffer::from_vec(output)) };
+            MlStatus::Ok
+        }
+        Ok(Ok(None)) => MlStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => MlStatus::Panic,
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod ffi_tests {
+    use super::*;
+    use crate::selection::WinnersProof;
+
+    #[test]
+    /// Checks that a UNICORN evaluated through the C interface verifies and draws winners,
+    /// and that bad inputs map to their status codes
+    fn eval_verify_select() {
+        let params = serde_json::to_vec(&UnicornFixedParam::test()).unwrap();
+        let seed = [0x1e, 0xeb, 0x30, 0xc7];
+        let mut out = MlBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+
+        unsafe {
+            let status = ml_eval(seed.as_ptr(), 4, params.as_ptr(), params.len(), &mut out);
+            assert_eq!(status, MlStatus::Ok);
+            let info = slice::from_raw_parts(out.data, out.len).to_vec();
+            ml_buffer_free(out);
+            assert_eq!(ml_verify(info.as_ptr(), info.len()), MlStatus::Ok);
+
+            let participants = b"miner_a\nminer_b\nminer_c\n";
+            let mut out = MlBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            let status = ml_select(
+                info.as_ptr(),
+                info.len(),
+                participants.as_ptr(),
+                participants.len(),
+                1,
+                2,
+                &mut out,
+            );
+            assert_eq!(status, MlStatus::Ok);
+            let proof: WinnersProof =
+                serde_json::from_slice(slice::from_raw_parts(out.data, out.len)).unwrap();
+            ml_buffer_free(out);
+            assert_eq!(proof.winners.len(), 2);
+            assert_eq!(proof.num_participants, 3);
+
+            let mut tampered: UnicornInfo = serde_json::from_slice(&info).unwrap();
+            tampered.g_value = "00".to_string();
+            let tampered = serde_json::to_vec(&tampered).unwrap();
+            assert_eq!(
+                ml_verify(tampered.as_ptr(), tampered.len()),
+                MlStatus::VerifyFailed
+            );
+            assert_eq!(ml_verify(b"{".as_ptr(), 1), MlStatus::InvalidInput);
+            assert_eq!(ml_verify(ptr::null(), 1), MlStatus::NullPointer);
+            assert_eq!(
+                ml_eval(
+                    seed.as_ptr(),
+                    4,
+                    params.as_ptr(),
+                    params.len(),
+                    ptr::null_mut()
+                ),
+                MlStatus::NullPointer
+            );
+        }
+    }
+}
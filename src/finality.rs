@@ -0,0 +1,331 @@
+//! Quorum finalization of published rounds.
+//!
+//! Publishing a round only shows that the coordinator ran the eval. A round is final once a
+//! quorum of designated verifiers have each replayed its draw, re-running the eval to check
+//! the witness themselves, and signed an attestation of its result. The attestation commits to
+//! the round id, the sealed inputs digest, the seed and the `g` value, and is signed with the
+//! verifier's Ed25519 key over a context distinct from that of published results.
+//!
+//! The signatures gathered for a round are stored in its record, together with the
+//! attestation they sign, so that anyone holding the quorum rule can check finality later
+//! without re-running the eval.
+//!
+//! Finality records are kept without the `signing` feature, which adds signing attestations
+//! and checking them against a quorum rule.
+
+use crate::rounds::Round;
+use crate::transcript::TranscriptStep;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Context prepended to every signed attestation
+pub const ATTESTATION_CONTEXT: &[u8] = b"miner-lottery/witness-attestation/v1";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FinalityError {
+    /// The round has not been published.
+    NotPublished,
+    /// A public key or signature could not be decoded.
+    Malformed,
+    /// A signature does not match the attestation.
+    BadSignature,
+    /// The published draw failed to replay at the given step.
+    Replay(TranscriptStep),
+    /// Fewer valid signatures from designated verifiers than the quorum requires.
+    NoQuorum { valid: usize, quorum: usize },
+    /// The finality record attests to a different result than the round's.
+    AttestationMismatch,
+}
+
+impl fmt::Display for FinalityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinalityError::NotPublished => write!(f, "Round has not been published"),
+            FinalityError::Malformed => write!(f, "Malformed public key or signature"),
+            FinalityError::BadSignature => write!(f, "Invalid signature"),
+            FinalityError::Replay(step) => write!(f, "Draw failed replay at {:?}", step),
+            FinalityError::NoQuorum { valid, quorum } => write!(
+                f,
+                "{} valid signatures from designated verifiers, {} required",
+                valid, quorum
+            ),
+            FinalityError::AttestationMismatch => {
+                write!(f, "Finality record attests to a different result")
+            }
+        }
+    }
+}
+
+impl Error for FinalityError {}
+
+/// Result of a round as attested by a verifier
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WitnessAttestation {
+    pub round_id: u64,
+    /// Digest of the sealed inputs
+    pub sealed_digest: Option<String>,
+    /// Decimal seed of the UNICORN
+    pub seed: String,
+    /// `g` value derived from the witness
+    pub g_value: String,
+}
+
+impl WitnessAttestation {
+    /// Attestation of a round's published result, if it has been published
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to attest
+    pub fn for_round(round: &Round) -> Option<Self> {
+        let transcript = round.transcript.as_ref()?;
+
+        Some(Self {
+            round_id: round.id,
+            sealed_digest: round.sealed_digest.clone(),
+            seed: round.seed.to_string(),
+            g_value: transcript.g_value.clone(),
+        })
+    }
+
+    /// Message signed for the attestation: the context followed by its bincode encoding
+    #[cfg(feature = "signing")]
+    fn message(&self) -> Vec<u8> {
+        let mut message = ATTESTATION_CONTEXT.to_vec();
+        message.extend(bincode::serialize(self).unwrap());
+        message
+    }
+}
+
+/// A verifier's signature over an attestation
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VerifierSignature {
+    /// Hex-encoded Ed25519 public key of the verifier
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature
+    pub signature: String,
+}
+
+/// Signatures of a quorum of verifiers over a round's result, stored in the round record
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Finality {
+    pub attestation: WitnessAttestation,
+    /// Valid signatures of designated verifiers, in the order they were gathered
+    pub signatures: Vec<VerifierSignature>,
+}
+
+/// Verifiers designated to finalize rounds and how many of them must sign
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuorumRule {
+    /// Hex-encoded Ed25519 public keys of the designated verifiers
+    pub verifiers: Vec<String>,
+    /// Number of distinct verifiers that must sign
+    pub quorum: usize,
+}
+
+#[cfg(feature = "signing")]
+impl WitnessAttestation {
+    /// Replays the round's published draw, re-running the eval to check the witness, and
+    /// signs the attestation of its result
+    ///
+    /// ### Arguments
+    ///
+    /// * `round`   - Published round to verify
+    /// * `keypair` - Verifier key pair
+    pub fn verify_and_sign(
+        round: &Round,
+        keypair: &ed25519_dalek::Keypair,
+    ) -> Result<VerifierSignature, FinalityError> {
+        use ed25519_dalek::Signer;
+
+        let attestation = Self::for_round(round).ok_or(FinalityError::NotPublished)?;
+        let transcript = round
+            .transcript
+            .as_ref()
+            .ok_or(FinalityError::NotPublished)?;
+        if transcript.seed != round.seed {
+            return Err(FinalityError::Replay(TranscriptStep::Seed));
+        }
+        transcript.replay().map_err(FinalityError::Replay)?;
+
+        Ok(VerifierSignature {
+            public_key: hex::encode(keypair.public.as_bytes()),
+            signature: hex::encode(keypair.sign(&attestation.message()).to_bytes()),
+        })
+    }
+}
+
+#[cfg(feature = "signing")]
+impl VerifierSignature {
+    /// Checks the signature over an attestation
+    ///
+    /// ### Arguments
+    ///
+    /// * `attestation` - Attestation the signature should be over
+    pub fn verify(&self, attestation: &WitnessAttestation) -> Result<(), FinalityError> {
+        use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+        let decode = |hex_str: &str| hex::decode(hex_str).map_err(|_| FinalityError::Malformed);
+        let public_key = PublicKey::from_bytes(&decode(&self.public_key)?)
+            .map_err(|_| FinalityError::Malformed)?;
+        let signature = Signature::try_from(&decode(&self.signature)?[..])
+            .map_err(|_| FinalityError::Malformed)?;
+
+        public_key
+            .verify(&attestation.message(), &signature)
+            .map_err(|_| FinalityError::BadSignature)
+    }
+}
+
+#[cfg(feature = "signing")]
+impl QuorumRule {
+    /// Marks a published round as final if a quorum of designated verifiers signed its
+    /// result, storing their signatures in the round. Signatures that are invalid, from
+    /// verifiers that are not designated, or from a verifier that already signed are left
+    /// out. Returns the number of signatures stored
+    ///
+    /// ### Arguments
+    ///
+    /// * `round`      - Published round to finalize
+    /// * `signatures` - Signatures gathered from verifiers
+    pub fn finalize(
+        &self,
+        round: &mut Round,
+        signatures: &[VerifierSignature],
+    ) -> Result<usize, FinalityError> {
+        let attestation =
+            WitnessAttestation::for_round(round).ok_or(FinalityError::NotPublished)?;
+        let signatures = self.valid_signatures(&attestation, signatures);
+        if signatures.len() < self.quorum.max(1) {
+            return Err(FinalityError::NoQuorum {
+                valid: signatures.len(),
+                quorum: self.quorum.max(1),
+            });
+        }
+
+        let stored = signatures.len();
+        round.finality = Some(Finality {
+            attestation,
+            signatures,
+        });
+
+        Ok(stored)
+    }
+
+    /// Checks the finality record stored in a round against the rule
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Finalized round
+    pub fn check(&self, round: &Round) -> Result<(), FinalityError> {
+        let finality = round.finality.as_ref().ok_or(FinalityError::NoQuorum {
+            valid: 0,
+            quorum: self.quorum.max(1),
+        })?;
+        if WitnessAttestation::for_round(round).as_ref() != Some(&finality.attestation) {
+            return Err(FinalityError::AttestationMismatch);
+        }
+
+        let valid = self
+            .valid_signatures(&finality.attestation, &finality.signatures)
+            .len();
+        if valid < self.quorum.max(1) {
+            return Err(FinalityError::NoQuorum {
+                valid,
+                quorum: self.quorum.max(1),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Valid signatures over the attestation from distinct designated verifiers
+    fn valid_signatures(
+        &self,
+        attestation: &WitnessAttestation,
+        signatures: &[VerifierSignature],
+    ) -> Vec<VerifierSignature> {
+        let mut valid: Vec<VerifierSignature> = Vec::new();
+        for signature in signatures {
+            let signer = signature.public_key.to_ascii_lowercase();
+            let designated = self
+                .verifiers
+                .iter()
+                .any(|v| v.to_ascii_lowercase() == signer);
+            let repeated = valid
+                .iter()
+                .any(|v| v.public_key.to_ascii_lowercase() == signer);
+
+            if designated && !repeated && signature.verify(attestation).is_ok() {
+                valid.push(signature.clone());
+            }
+        }
+
+        valid
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(all(test, feature = "signing"))]
+mod finality_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::signing::test_keypair;
+    use crate::unicorn::UnicornFixedParam;
+    use ed25519_dalek::Keypair;
+
+    fn published_round() -> Round {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 60,
+        };
+        let mut round = Round::new(3, params, 1, timeouts, 0);
+        round.contribute("miner_a", 1).unwrap();
+        round.contribute("miner_b", 2).unwrap();
+        round.seal().unwrap();
+        round.evaluate(3).unwrap();
+        round
+    }
+
+    #[test]
+    /// Checks that a round is only final with signatures from a quorum of distinct
+    /// designated verifiers, and that the stored record checks out
+    fn quorum_finalization() {
+        let mut round = published_round();
+        let verifiers: Vec<Keypair> = (1..=3).map(test_keypair).collect();
+        let rule = QuorumRule {
+            verifiers: verifiers
+                .iter()
+                .map(|k| hex::encode(k.public.as_bytes()))
+                .collect(),
+            quorum: 2,
+        };
+        let sign = |k: &Keypair| WitnessAttestation::verify_and_sign(&round, k).unwrap();
+
+        let outsider = sign(&test_keypair(9));
+        let first = sign(&verifiers[0]);
+        assert_eq!(
+            rule.finalize(
+                &mut round.clone(),
+                &[first.clone(), first.clone(), outsider]
+            ),
+            Err(FinalityError::NoQuorum {
+                valid: 1,
+                quorum: 2
+            })
+        );
+
+        let second = sign(&verifiers[1]);
+        assert_eq!(rule.finalize(&mut round, &[first, second]), Ok(2));
+        assert_eq!(rule.check(&round), Ok(()));
+
+        round.finality.as_mut().unwrap().attestation.g_value = "00".to_string();
+        assert_eq!(rule.check(&round), Err(FinalityError::AttestationMismatch));
+    }
+}
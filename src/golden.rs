@@ -0,0 +1,171 @@
+//! Golden-file regression tests for the serialized forms of the public structs.
+//!
+//! Consensus depends on every node encoding rounds and transcripts to the same bytes, so a
+//! change to a struct's serde layout is a protocol change even when it still round-trips.
+//! Each fixture here is encoded as bincode, JSON and (with the `cbor` feature) both CBOR
+//! profiles, and the encodings are compared against the files committed under `golden/`.
+//! The committed files are also decoded, so values written by older builds stay readable.
+//!
+//! Binary encodings are stored as hex so that changes show up in review. After an
+//! intentional encoding change, regenerate the files with
+//!
+//! ```sh
+//! UPDATE_GOLDEN=1 cargo test --all-features golden
+//! ```
+//!
+//! and commit them alongside the change.
+
+use crate::bundle::RoundBundle;
+use crate::config::DEFAULT_MODULUS;
+use crate::feed::FeedEntry;
+use crate::finality::WitnessAttestation;
+use crate::rounds::{GracePolicy, LateMode, Round, RoundTimeouts};
+use crate::unicorn::UnicornFixedParam;
+use crate::vrf::VrfPublicKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variable that rewrites the golden files instead of checking them
+const UPDATE_VAR: &str = "UPDATE_GOLDEN";
+
+/// Builds the published round the fixtures are taken from
+fn golden_round() -> Round {
+    let params = UnicornFixedParam::test();
+    let timeouts = RoundTimeouts {
+        collect_secs: 60,
+        eval_secs: 600,
+    };
+    let grace = GracePolicy {
+        mode: LateMode::Flag,
+        grace_secs: 5,
+    };
+    let mut round = Round::new(7, params, 1, timeouts, 0).with_grace(grace);
+    round.contribute("miner_a", 0).unwrap();
+    round.contribute("miner_b", 0).unwrap();
+    round.contribute("miner_c", 0).unwrap();
+    round.seal().unwrap();
+    round.evaluate(0).unwrap();
+    round
+}
+
+/// Path of a golden file
+///
+/// ### Arguments
+///
+/// * `name`      - Name of the fixture
+/// * `extension` - Extension naming the encoding
+fn golden_path(name: &str, extension: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(format!("{}.{}", name, extension))
+}
+
+/// Compares an encoding against its golden file, or rewrites the file if `UPDATE_GOLDEN`
+/// is set, and returns the committed contents
+///
+/// ### Arguments
+///
+/// * `name`      - Name of the fixture
+/// * `extension` - Extension naming the encoding
+/// * `encoded`   - Encoding produced by this build
+fn check_file(name: &str, extension: &str, encoded: &str) -> String {
+    let path = golden_path(name, extension);
+
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, encoded).unwrap();
+        return encoded.to_string();
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "Missing golden file {}, run with {}=1 to create it",
+            path.display(),
+            UPDATE_VAR
+        )
+    });
+    assert_eq!(
+        committed.trim_end(),
+        encoded.trim_end(),
+        "Encoding of {} changed, run with {}=1 if this is intended",
+        path.display(),
+        UPDATE_VAR
+    );
+    committed
+}
+
+/// Checks a value's encodings in every format against the golden files, and that the
+/// committed files decode back to the value
+///
+/// ### Arguments
+///
+/// * `name`  - Name of the fixture
+/// * `value` - Value to encode
+fn check_golden<T>(name: &str, value: &T)
+where
+    T: Serialize + DeserializeOwned + Debug + PartialEq,
+{
+    let json = serde_json::to_string_pretty(value).unwrap() + "\n";
+    let committed = check_file(name, "json", &json);
+    assert_eq!(&serde_json::from_str::<T>(&committed).unwrap(), value);
+
+    let bincode = hex::encode(bincode::serialize(value).unwrap()) + "\n";
+    let committed = check_file(name, "bincode.hex", &bincode);
+    let bytes = hex::decode(committed.trim_end()).unwrap();
+    assert_eq!(&bincode::deserialize::<T>(&bytes).unwrap(), value);
+
+    #[cfg(feature = "cbor")]
+    {
+        use crate::cbor::{from_slice, to_vec, CborProfile};
+
+        for (profile, extension) in [
+            (CborProfile::Standard, "cbor.hex"),
+            (CborProfile::Compact, "cbor-compact.hex"),
+        ] {
+            let cbor = hex::encode(to_vec(value, profile).unwrap()) + "\n";
+            let committed = check_file(name, extension, &cbor);
+            let bytes = hex::decode(committed.trim_end()).unwrap();
+            assert_eq!(&from_slice::<T>(&bytes, profile).unwrap(), value);
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[test]
+/// Checks that the round and everything published with it encode to the golden bytes
+fn golden_round_encodings() {
+    let round = golden_round();
+    let transcript = round.transcript.clone().unwrap();
+    let bundle = RoundBundle::new(round.clone()).unwrap();
+
+    check_golden("round", &round);
+    check_golden("transcript", &transcript);
+    check_golden("fixed_params", &transcript.fixed_params);
+    check_golden("usage_allocations", &transcript.usage_allocations);
+    check_golden("contributions", &round.contributions);
+    check_golden("grace_policy", &round.grace);
+    check_golden("bundle", &bundle);
+    check_golden("unicorn_info", &bundle.unicorn);
+    check_golden("unicorn", &bundle.unicorn.unicorn);
+}
+
+#[test]
+/// Checks that the artifacts derived from a round encode to the golden bytes
+fn golden_derived_encodings() {
+    let round = golden_round();
+    let key = VrfPublicKey {
+        n: DEFAULT_MODULUS.parse().unwrap(),
+        e: 65_537.into(),
+    };
+
+    check_golden("feed_entry", &FeedEntry::from_round(&round).unwrap());
+    check_golden(
+        "witness_attestation",
+        &WitnessAttestation::for_round(&round).unwrap(),
+    );
+    check_golden("vrf_public_key", &key);
+}
@@ -0,0 +1,313 @@
+//! gRPC service for node software that speaks gRPC internally.
+//!
+//! The service offers the operations of the HTTP API over the messages of
+//! `proto/lottery.proto`: submitting contributions, getting rounds with their selection
+//! proofs, auditing proofs and streaming rounds as they are published. It runs against the
+//! same `ServerState` as the HTTP API, so both can be served by one process. Contributions are
+//! authorized from the `authorization` and `x-signature` metadata, as from the HTTP headers.
+//!
+//! The messages below are written by hand to match the proto file, and the service stubs are
+//! generated from them by the build script, so that building does not require `protoc`.
+//! The round data messages and their conversions are shared with other transports in
+//! `crate::proto`.
+
+use crate::auth::{AuthError, Credentials};
+use crate::daemon::{DaemonResponse, PublishedRound, RoundEvent};
+use crate::rounds::{self, Round};
+use crate::server::{audit_blocking, ServerState, VerifyRequest};
+use crate::transcript::SelectionTranscript;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+pub use proto::lottery_client::LotteryClient;
+pub use proto::lottery_server::LotteryServer;
+
+/// Messages of `proto/lottery.proto` and the generated service stubs
+pub mod proto {
+    pub use crate::proto::{FixedParams, SelectionProof, UsageNumber};
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Contribution {
+        #[prost(uint64, tag = "1")]
+        pub round_id: u64,
+        #[prost(string, tag = "2")]
+        pub identity: String,
+        #[prost(uint64, tag = "3")]
+        pub timestamp: u64,
+        #[prost(uint64, optional, tag = "4")]
+        pub nonce: Option<u64>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubmitReply {
+        #[prost(string, tag = "1")]
+        pub fingerprint: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GetRoundRequest {
+        #[prost(uint64, tag = "1")]
+        pub round_id: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RoundReply {
+        #[prost(uint64, tag = "1")]
+        pub round_id: u64,
+        #[prost(string, tag = "2")]
+        pub phase: String,
+        #[prost(uint64, tag = "3")]
+        pub participants: u64,
+        #[prost(message, optional, tag = "4")]
+        pub proof: Option<SelectionProof>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckResult {
+        #[prost(string, tag = "1")]
+        pub check: String,
+        #[prost(bool, tag = "2")]
+        pub passed: bool,
+        #[prost(string, optional, tag = "3")]
+        pub detail: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct VerifyReply {
+        #[prost(bool, tag = "1")]
+        pub passed: bool,
+        #[prost(message, repeated, tag = "2")]
+        pub checks: Vec<CheckResult>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct WatchRequest {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PublishedRound {
+        #[prost(uint64, tag = "1")]
+        pub round_id: u64,
+        #[prost(string, tag = "2")]
+        pub g_value: String,
+        #[prost(string, tag = "3")]
+        pub winner: String,
+        #[prost(uint64, tag = "4")]
+        pub winner_index: u64,
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/lottery.Lottery.rs"));
+}
+
+impl From<proto::Contribution> for rounds::Contribution {
+    fn from(c: proto::Contribution) -> Self {
+        Self {
+            round_id: c.round_id,
+            identity: c.identity,
+            timestamp: c.timestamp,
+            nonce: c.nonce,
+        }
+    }
+}
+
+impl From<PublishedRound> for proto::PublishedRound {
+    fn from(round: PublishedRound) -> Self {
+        Self {
+            round_id: round.round_id,
+            g_value: round.g_value,
+            winner: round.winner,
+            winner_index: round.winner_index as u64,
+        }
+    }
+}
+
+/// Implementation of the `Lottery` service
+#[derive(Clone)]
+pub struct LotteryService {
+    state: ServerState,
+}
+
+impl LotteryService {
+    /// Creates the service over the shared server state
+    ///
+    /// ### Arguments
+    ///
+    /// * `state` - Shared server state
+    pub fn new(state: ServerState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl proto::lottery_server::Lottery for LotteryService {
+    async fn submit_contribution(
+        &self,
+        request: Request<proto::Contribution>,
+    ) -> Result<Response<proto::SubmitReply>, Status> {
+        let credentials = Credentials::from_headers(|name| {
+            request.metadata().get(name).and_then(|v| v.to_str().ok())
+        });
+        let contribution: rounds::Contribution = request.into_inner().into();
+        self.state
+            .authorize(&credentials, &contribution)
+            .map_err(|e| match e {
+                AuthError::Forbidden(_) => Status::permission_denied(e.to_string()),
+                _ => Status::unauthenticated(e.to_string()),
+            })?;
+        self.state
+            .check_limits(&contribution)
+            .map_err(|e| Status::resource_exhausted(e.to_string()))?;
+
+        match self.state.contribute(contribution) {
+            DaemonResponse::Accepted { fingerprint } => {
+                Ok(Response::new(proto::SubmitReply { fingerprint }))
+            }
+            DaemonResponse::Error { message } => Err(Status::failed_precondition(message)),
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+
+    async fn get_round(
+        &self,
+        request: Request<proto::GetRoundRequest>,
+    ) -> Result<Response<proto::RoundReply>, Status> {
+        let id = request.into_inner().round_id;
+        let current = self.state.daemon().lock().unwrap().round().clone();
+        let round = match current.id == id {
+            true => current,
+            false => self
+                .state
+                .published(id)
+                .ok_or_else(|| Status::not_found(format!("Round {} is not known", id)))?,
+        };
+
+        Ok(Response::new(round_reply(&round)))
+    }
+
+    async fn verify_proof(
+        &self,
+        request: Request<proto::SelectionProof>,
+    ) -> Result<Response<proto::VerifyReply>, Status> {
+        let transcript = SelectionTranscript::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let request = VerifyRequest::Transcript(Box::new(transcript));
+        request
+            .check_iterations(self.state.config().unicorn.iterations)
+            .map_err(Status::invalid_argument)?;
+        let report = audit_blocking(request)
+            .await
+            .ok_or_else(|| Status::internal("Audit failed"))?;
+
+        Ok(Response::new(proto::VerifyReply {
+            passed: report.passed(),
+            checks: report
+                .checks
+                .into_iter()
+                .map(|c| proto::CheckResult {
+                    check: serde_json::to_value(c.check)
+                        .ok()
+                        .and_then(|v| v.as_str().map(str::to_string))
+                        .unwrap_or_default(),
+                    passed: c.passed,
+                    detail: c.detail,
+                })
+                .collect(),
+        }))
+    }
+
+    type WatchRoundsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::PublishedRound, Status>> + Send + 'static>>;
+
+    // The stream items are fixed by the generated trait
+    #[allow(clippy::result_large_err)]
+    async fn watch_rounds(
+        &self,
+        _request: Request<proto::WatchRequest>,
+    ) -> Result<Response<Self::WatchRoundsStream>, Status> {
+        // Rounds missed by a lagging subscriber are skipped rather than ending the stream
+        let stream = BroadcastStream::new(self.state.subscribe())
+            .filter_map(|event| match event {
+                Ok(RoundEvent::Published(round)) => Some(round),
+                _ => None,
+            })
+            .map(|round| Ok(round.into()));
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serves the gRPC service until the process is stopped
+///
+/// ### Arguments
+///
+/// * `addr`  - Address to listen on
+/// * `state` - Shared server state
+pub async fn serve(addr: SocketAddr, state: ServerState) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(LotteryServer::new(LotteryService::new(state)))
+        .serve(addr)
+        .await
+}
+
+fn round_reply(round: &Round) -> proto::RoundReply {
+    proto::RoundReply {
+        round_id: round.id,
+        phase: format!("{:?}", round.phase),
+        participants: round.registry.len() as u64,
+        proof: round.transcript.as_ref().map(Into::into),
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod grpc_tests {
+    use super::proto::lottery_server::Lottery;
+    use super::*;
+    use crate::config::LotteryConfig;
+
+    #[tokio::test]
+    /// Checks that proofs survive the proto conversion and rounds are streamed as published
+    async fn service_operations() {
+        let state = ServerState::new(LotteryConfig::default(), 0);
+        let service = LotteryService::new(state.clone());
+        let mut stream = service
+            .watch_rounds(Request::new(proto::WatchRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut round = state.daemon().lock().unwrap().round().clone();
+        round.contribute("miner_a", 0).unwrap();
+        round.seal().unwrap();
+        let transcript = round.evaluate(0).unwrap().clone();
+        state.publish(round);
+
+        let published = stream.next().await.unwrap().unwrap();
+        let reply = service
+            .get_round(Request::new(proto::GetRoundRequest { round_id: 0 }))
+            .await
+            .unwrap()
+            .into_inner();
+        let proof = proto::SelectionProof::from(&transcript);
+        let verified = service
+            .verify_proof(Request::new(proof.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(published.winner, "miner_a");
+        assert_eq!(reply.phase, "Collecting");
+        assert_eq!(SelectionTranscript::try_from(proof).unwrap(), transcript);
+        assert!(verified.passed);
+
+        let mut slow = transcript;
+        slow.fixed_params.iterations = u64::MAX;
+        let refused = service
+            .verify_proof(Request::new(proto::SelectionProof::from(&slow)))
+            .await;
+        assert_eq!(refused.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+}
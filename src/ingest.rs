@@ -0,0 +1,473 @@
+//! Ingestion of participant lists from text.
+//!
+//! Three formats are accepted:
+//!
+//! - `List`: one public key per line, optionally followed by a comma and a selection weight
+//! - `Csv`: an `id,pubkey,weight` header followed by one row per participant, where the
+//!   weight may be left empty
+//! - `Jsonl`: one `{"id": ..., "pubkey": ..., "weight": ...}` object per line, where only
+//!   `pubkey` is required
+//!
+//! Blank lines and lines starting with `#` are skipped in every format. The CSV and JSON-lines
+//! schemas are strict: missing or unknown columns and fields are errors, as are repeated ids.
+//! Keys are normalized as in the `ParticipantRegistry`, and the entries are returned in the
+//! registry's canonical order, so the seed does not depend on the order of the file.
+//!
+//! Duplicate keys do not fail ingestion: the first occurrence is kept and the others are
+//! reported with their line numbers, so operators can clean up their exports.
+
+use crate::participants::{fingerprint, normalize_identity, ParticipantRegistry};
+use crate::selection::Selectable;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Columns of a CSV participant list
+const CSV_HEADER: [&str; 3] = ["id", "pubkey", "weight"];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IngestError {
+    /// A line could not be parsed.
+    Line { line: usize, reason: String },
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::Line { line, reason } => write!(f, "Line {}: {}", line, reason),
+        }
+    }
+}
+
+impl Error for IngestError {}
+
+/// Format of a participant list
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    List,
+    Csv,
+    Jsonl,
+}
+
+impl InputFormat {
+    /// Format implied by a file extension: `.csv`, `.jsonl` or `.ndjson`, and a plain list
+    /// otherwise
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Path of the list
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => InputFormat::Csv,
+            Some("jsonl" | "ndjson") => InputFormat::Jsonl,
+            _ => InputFormat::List,
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(InputFormat::List),
+            "csv" => Ok(InputFormat::Csv),
+            "jsonl" => Ok(InputFormat::Jsonl),
+            _ => Err(format!("Unknown input format `{}`", s)),
+        }
+    }
+}
+
+/// A participant read from a list
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct InputEntry {
+    /// Normalized identity
+    pub identity: String,
+    pub weight: Option<u64>,
+    /// Operator-assigned id, if the list has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+impl Selectable for InputEntry {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.identity.as_bytes().to_vec()
+    }
+
+    fn weight(&self) -> Option<u64> {
+        self.weight
+    }
+}
+
+/// A key that appeared more than once in a list
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DuplicateEntry {
+    /// Normalized identity
+    pub identity: String,
+    /// Line of the duplicate, starting from 1
+    pub line: usize,
+    /// Line of the first occurrence, which was kept
+    pub first_line: usize,
+}
+
+/// Participants read from a list
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Ingested {
+    /// Unique entries in canonical order
+    pub entries: Vec<InputEntry>,
+    pub duplicates: Vec<DuplicateEntry>,
+}
+
+impl Ingested {
+    /// Normalized identities in canonical order, as passed to `construct_seed`
+    pub fn identities(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.identity.clone()).collect()
+    }
+}
+
+/// A participant as read from a single line, before normalization
+struct Record {
+    line: usize,
+    id: Option<String>,
+    key: String,
+    weight: Option<u64>,
+}
+
+/// Schema of a JSON-lines record
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonRecord {
+    #[serde(default)]
+    id: Option<String>,
+    pubkey: String,
+    #[serde(default)]
+    weight: Option<u64>,
+}
+
+/// Parses a plain participant list
+///
+/// ### Arguments
+///
+/// * `text` - Contents of the list
+pub fn parse_inputs(text: &str) -> Result<Ingested, IngestError> {
+    parse_participants(text, InputFormat::List)
+}
+
+/// Parses a participant list in the given format
+///
+/// ### Arguments
+///
+/// * `text`   - Contents of the list
+/// * `format` - Format of the list
+pub fn parse_participants(text: &str, format: InputFormat) -> Result<Ingested, IngestError> {
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let records = match format {
+        InputFormat::List => lines.map(list_record).collect::<Result<Vec<_>, _>>()?,
+        InputFormat::Csv => csv_records(lines)?,
+        InputFormat::Jsonl => lines.map(json_record).collect::<Result<Vec<_>, _>>()?,
+    };
+
+    collect(records)
+}
+
+impl ParticipantRegistry {
+    /// Loads a registry from a participant list. Unlike `parse_participants`, duplicate keys
+    /// are errors. Weights are not kept by the registry
+    ///
+    /// ### Arguments
+    ///
+    /// * `text`   - Contents of the list
+    /// * `format` - Format of the list
+    pub fn load(text: &str, format: InputFormat) -> Result<Self, IngestError> {
+        let ingested = parse_participants(text, format)?;
+        if let Some(duplicate) = ingested.duplicates.first() {
+            return Err(line_error(
+                duplicate.line,
+                format!("duplicate key, first on line {}", duplicate.first_line),
+            ));
+        }
+
+        Ok(ParticipantRegistry::from_identities(ingested.identities())
+            .expect("ingested identities are non-empty and unique"))
+    }
+}
+
+fn list_record((line, text): (usize, &str)) -> Result<Record, IngestError> {
+    let (key, weight) = match text.split_once(',') {
+        Some((key, weight)) => (key, Some(parse_weight(line, weight)?)),
+        None => (text, None),
+    };
+
+    Ok(Record {
+        line,
+        id: None,
+        key: key.to_string(),
+        weight,
+    })
+}
+
+fn csv_records<'a>(
+    mut lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Vec<Record>, IngestError> {
+    let Some((header_line, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    if columns != CSV_HEADER {
+        return Err(line_error(
+            header_line,
+            format!("expected header `{}`", CSV_HEADER.join(",")),
+        ));
+    }
+
+    lines
+        .map(|(line, text)| {
+            if text.contains('"') {
+                return Err(line_error(line, "quoted fields are not supported"));
+            }
+
+            let fields: Vec<&str> = text.split(',').map(str::trim).collect();
+            let [id, key, weight] = fields[..] else {
+                return Err(line_error(
+                    line,
+                    format!("expected 3 fields, found {}", fields.len()),
+                ));
+            };
+            if id.is_empty() {
+                return Err(line_error(line, "empty id"));
+            }
+
+            Ok(Record {
+                line,
+                id: Some(id.to_string()),
+                key: key.to_string(),
+                weight: match weight {
+                    "" => None,
+                    weight => Some(parse_weight(line, weight)?),
+                },
+            })
+        })
+        .collect()
+}
+
+fn json_record((line, text): (usize, &str)) -> Result<Record, IngestError> {
+    let record: JsonRecord = serde_json::from_str(text).map_err(|e| {
+        // Positions are relative to the record, so only the message is kept
+        let message = e.to_string();
+        let message = message.split(" at line ").next().unwrap_or_default();
+        line_error(line, message)
+    })?;
+
+    Ok(Record {
+        line,
+        id: record.id,
+        key: record.pubkey,
+        weight: record.weight,
+    })
+}
+
+fn parse_weight(line: usize, weight: &str) -> Result<u64, IngestError> {
+    weight
+        .trim()
+        .parse()
+        .map_err(|_| line_error(line, format!("invalid weight `{}`", weight.trim())))
+}
+
+fn line_error(line: usize, reason: impl Into<String>) -> IngestError {
+    IngestError::Line {
+        line,
+        reason: reason.into(),
+    }
+}
+
+/// Normalizes the records into canonical order, reporting duplicate keys and failing on
+/// empty keys or repeated ids
+fn collect(records: Vec<Record>) -> Result<Ingested, IngestError> {
+    let mut by_fingerprint = BTreeMap::new();
+    let mut duplicates = Vec::new();
+    let mut ids = BTreeSet::new();
+
+    for Record {
+        line,
+        id,
+        key,
+        weight,
+    } in records
+    {
+        let identity = normalize_identity(&key);
+        if identity.is_empty() {
+            return Err(line_error(line, "empty key"));
+        }
+        if let Some(id) = &id {
+            if !ids.insert(id.clone()) {
+                return Err(line_error(line, format!("duplicate id `{}`", id)));
+            }
+        }
+
+        match by_fingerprint.get(&fingerprint(&identity)) {
+            Some((first_line, _)) => duplicates.push(DuplicateEntry {
+                identity,
+                line,
+                first_line: *first_line,
+            }),
+            None => {
+                by_fingerprint.insert(
+                    fingerprint(&identity),
+                    (
+                        line,
+                        InputEntry {
+                            identity,
+                            weight,
+                            id,
+                        },
+                    ),
+                );
+            }
+        }
+    }
+
+    Ok(Ingested {
+        entries: by_fingerprint.into_values().map(|(_, e)| e).collect(),
+        duplicates,
+    })
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod ingest_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that lists are normalized into registry order with duplicates reported
+    fn parse_list() {
+        let ingested = parse_inputs("# miners\n a1b2 \n\nc3d4,5\n\ta1b2\n").unwrap();
+        let registry = ParticipantRegistry::from_identities(["a1b2", "c3d4"]).unwrap();
+
+        assert_eq!(ingested.identities(), registry.seed_inputs());
+        assert_eq!(
+            ingested.duplicates,
+            vec![DuplicateEntry {
+                identity: "a1b2".to_string(),
+                line: 5,
+                first_line: 2
+            }]
+        );
+        assert!(ingested
+            .entries
+            .iter()
+            .any(|e| e.identity == "c3d4" && e.weight == Some(5)));
+    }
+
+    #[test]
+    /// Checks that malformed lines are reported by line number
+    fn malformed_lines() {
+        assert_eq!(
+            parse_inputs("a1b2\nc3d4,heavy\n"),
+            Err(IngestError::Line {
+                line: 2,
+                reason: "invalid weight `heavy`".to_string()
+            })
+        );
+        assert!(matches!(
+            parse_inputs(" ,1"),
+            Err(IngestError::Line { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    /// Checks that CSV and JSON-lines lists give the same entries as a plain list
+    fn structured_formats() {
+        let list = parse_inputs(
+            "a1b2,3
+c3d4
+",
+        )
+        .unwrap();
+        let csv = parse_participants(
+            "id,pubkey,weight
+m1, a1b2,3
+# retired
+m2,c3d4,
+",
+            InputFormat::Csv,
+        )
+        .unwrap();
+        let jsonl = parse_participants(
+            "{\"id\":\"m1\",\"pubkey\":\"a1b2\",\"weight\":3}\n{\"pubkey\":\"c3d4\"}\n",
+            InputFormat::Jsonl,
+        )
+        .unwrap();
+
+        assert_eq!(csv.identities(), list.identities());
+        assert_eq!(jsonl.identities(), list.identities());
+        assert!(csv
+            .entries
+            .iter()
+            .any(|e| e.id.as_deref() == Some("m1") && e.weight == Some(3)));
+        assert_eq!(
+            InputFormat::from_path(Path::new("miners.ndjson")),
+            InputFormat::Jsonl
+        );
+    }
+
+    #[test]
+    /// Checks that structured lists are validated strictly, with line-numbered errors
+    fn structured_schema_errors() {
+        let csv_error = |text: &str| match parse_participants(text, InputFormat::Csv) {
+            Err(IngestError::Line { line, reason }) => (line, reason),
+            other => panic!("unexpected {:?}", other),
+        };
+
+        assert_eq!(
+            csv_error(
+                "id,key
+m1,a1b2
+"
+            ),
+            (1, "expected header `id,pubkey,weight`".to_string())
+        );
+        assert_eq!(
+            csv_error(
+                "id,pubkey,weight
+m1,a1b2
+"
+            ),
+            (2, "expected 3 fields, found 2".to_string())
+        );
+        assert_eq!(
+            csv_error(
+                "id,pubkey,weight
+m1,a1b2,1
+m1,c3d4,1
+"
+            ),
+            (3, "duplicate id `m1`".to_string())
+        );
+        assert!(matches!(
+            parse_participants("{\"pubkey\":\"a1b2\"}\n{\"key\":\"c3d4\"}\n", InputFormat::Jsonl),
+            Err(IngestError::Line { line: 2, reason }) if reason.starts_with("unknown field `key`")
+        ));
+        assert_eq!(
+            ParticipantRegistry::load(
+                "a1b2
+ a1b2
+",
+                InputFormat::List
+            ),
+            Err(IngestError::Line {
+                line: 2,
+                reason: "duplicate key, first on line 1".to_string()
+            })
+        );
+    }
+}
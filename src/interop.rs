@@ -0,0 +1,3 @@
+//! Integration with the block structures of nodes running the lottery.
+
+pub mod zenotta;
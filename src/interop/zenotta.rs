@@ -0,0 +1,235 @@
+//! Conversions between the Zenotta node's block structures and the lottery's inputs.
+//!
+//! The node's block header, block and PoW entry are mirrored here with only the fields the
+//! lottery reads. Other fields are ignored when deserializing, so that a structure
+//! serialized by the node converts straight into a contribution, a participant registry or
+//! the seed inputs of a round, and a published round converts into the payload the node
+//! records alongside its block.
+//!
+//! The winning PoW hashes bound into a seed are the mining transaction hashes of the blocks
+//! from 2 blocks ago, in block order.
+
+use crate::participants::{ParticipantRegistry, RegistryError};
+use crate::rounds::{Contribution, Round, RoundPhase};
+use crate::unicorn::{construct_seed_with_mode, SeedMode};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Header of a node block
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct BlockHeader {
+    /// Block number
+    pub b_num: u64,
+    /// Hash of the previous block, if any
+    #[serde(default)]
+    pub previous_hash: Option<String>,
+    /// Nonce of the winning PoW and the hash of its mining transaction
+    pub nonce_and_mining_tx_hash: (Vec<u8>, String),
+}
+
+/// Node block, of which the lottery only reads the header
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Block {
+    pub header: BlockHeader,
+    /// Hashes of the block's transactions
+    #[serde(default)]
+    pub transactions: Vec<String>,
+}
+
+impl Block {
+    /// Hash of the block's winning PoW, as bound into the seed 2 blocks later
+    pub fn winning_hash(&self) -> &str {
+        &self.header.nonce_and_mining_tx_hash.1
+    }
+}
+
+/// PoW entry with which a miner takes part in the lottery for a block
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PowEntry {
+    /// Number of the block the miner takes part in
+    pub b_num: u64,
+    /// Public key of the miner
+    pub public_key: String,
+    /// Time at which the entry was received, in unix seconds
+    #[serde(default)]
+    pub timestamp: u64,
+}
+
+impl From<&PowEntry> for Contribution {
+    fn from(entry: &PowEntry) -> Self {
+        Self {
+            round_id: entry.b_num,
+            identity: entry.public_key.clone(),
+            timestamp: entry.timestamp,
+            nonce: None,
+        }
+    }
+}
+
+impl TryFrom<&[PowEntry]> for ParticipantRegistry {
+    type Error = RegistryError;
+
+    fn try_from(entries: &[PowEntry]) -> Result<Self, RegistryError> {
+        ParticipantRegistry::from_identities(entries.iter().map(|e| &e.public_key))
+    }
+}
+
+/// Inputs of a seed, taken from the node's PoW entries and winning blocks
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SeedInputs {
+    /// Normalized public keys of the miners, in canonical order
+    pub public_key_inputs: Vec<String>,
+    /// Hashes of the winning PoWs from 2 blocks ago
+    pub last_winning_hashes: Vec<String>,
+}
+
+impl SeedInputs {
+    /// Collects the seed inputs of a block's lottery, failing on the first invalid or
+    /// duplicate public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `entries`        - PoW entries of the miners taking part
+    /// * `winning_blocks` - Blocks from 2 blocks ago, in block order
+    pub fn new(entries: &[PowEntry], winning_blocks: &[Block]) -> Result<Self, RegistryError> {
+        let registry = ParticipantRegistry::try_from(entries)?;
+
+        Ok(Self {
+            public_key_inputs: registry.seed_inputs(),
+            last_winning_hashes: winning_blocks
+                .iter()
+                .map(|b| b.winning_hash().to_string())
+                .collect(),
+        })
+    }
+
+    /// Constructs the seed from the inputs
+    ///
+    /// ### Arguments
+    ///
+    /// * `mode` - How the seed is built from the inputs
+    pub fn seed(&self, mode: SeedMode) -> Integer {
+        construct_seed_with_mode(mode, &self.public_key_inputs, &self.last_winning_hashes)
+    }
+}
+
+/// Result of a published round, as recorded by the node alongside its block
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RoundPayload {
+    /// Block number the round was run for
+    pub b_num: u64,
+    /// Hex-encoded seed
+    pub seed: String,
+    /// Hex-encoded witness
+    pub witness: String,
+    pub g_value: String,
+    /// Public key of the winning miner
+    pub winner: String,
+    /// Index of the winner among the participants, in canonical order
+    pub winner_index: usize,
+}
+
+/// A round was converted into a payload before it was published
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NotPublished {
+    pub phase: RoundPhase,
+}
+
+impl fmt::Display for NotPublished {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Round is {:?} rather than published", self.phase)
+    }
+}
+
+impl Error for NotPublished {}
+
+impl TryFrom<&Round> for RoundPayload {
+    type Error = NotPublished;
+
+    fn try_from(round: &Round) -> Result<Self, NotPublished> {
+        let transcript = round
+            .transcript
+            .as_ref()
+            .filter(|_| round.phase == RoundPhase::Published)
+            .ok_or(NotPublished { phase: round.phase })?;
+
+        Ok(Self {
+            b_num: round.id,
+            seed: transcript.seed.to_string_radix(16),
+            witness: transcript.witness.to_string_radix(16),
+            g_value: transcript.g_value.clone(),
+            winner: transcript.winner.clone(),
+            winner_index: transcript.winner_index,
+        })
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod zenotta_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::unicorn::UnicornFixedParam;
+
+    #[test]
+    /// Checks that node entries and blocks seed a round the same way as the seed inputs, and
+    /// that only a published round converts into a payload
+    fn node_round_conversions() {
+        let entries: Vec<PowEntry> = ["AB01", "cd02", "ef03"]
+            .iter()
+            .map(|key| {
+                serde_json::from_value(serde_json::json!({
+                    "b_num": 4,
+                    "public_key": key,
+                    "timestamp": 10,
+                    "signature": "ignored",
+                }))
+                .unwrap()
+            })
+            .collect();
+        let winning: Block = serde_json::from_value(serde_json::json!({
+            "header": {
+                "version": 2,
+                "b_num": 2,
+                "nonce_and_mining_tx_hash": [[1, 2], "g0a1b2"],
+                "seed_value": [],
+            },
+            "transactions": ["g00"],
+        }))
+        .unwrap();
+
+        let fixed_params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 60,
+        };
+        let mut round = Round::new(4, fixed_params, 1, timeouts, 0)
+            .with_last_winning_hashes(vec![winning.winning_hash().to_string()]);
+        for entry in &entries {
+            round.submit(entry.into(), 10).unwrap();
+        }
+        assert_eq!(
+            RoundPayload::try_from(&round),
+            Err(NotPublished {
+                phase: RoundPhase::Collecting
+            })
+        );
+
+        let inputs = SeedInputs::new(&entries, &[winning]).unwrap();
+        assert_eq!(round.seal().unwrap(), &inputs.seed(SeedMode::Serialized));
+        round.evaluate(20).unwrap();
+
+        let payload = RoundPayload::try_from(&round).unwrap();
+        assert_eq!(payload.b_num, 4);
+        assert_eq!(
+            payload.winner,
+            inputs.public_key_inputs[payload.winner_index]
+        );
+        assert!(
+            ParticipantRegistry::try_from(&[entries[0].clone(), entries[0].clone()][..]).is_err()
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! JSON-RPC 2.0 interface to the HTTP server, and a client for it.
+//!
+//! Requests are posted to `/rpc`, one at a time or as a batch. Parameters are passed by name
+//! as an object; the methods are:
+//!
+//! - `lottery_eval`: evaluates a UNICORN for an `EvalRequest`, returning a `UnicornInfo`
+//! - `lottery_verify`: audits a `VerifyRequest`, returning an `AuditReport`
+//! - `lottery_select`: draws winners from a UNICORN for `SelectParams`, returning a
+//!   `SelectResult`
+//! - `lottery_getRound`: the `SelectionTranscript` of a published round, for `{round_id}`
+//!
+//! The same limits apply as on the REST routes. Notifications (requests without an `id`)
+//! are run but not answered.
+
+use crate::audit::AuditReport;
+use crate::selection::{prove_many_from, WinnersProof};
+use crate::server::{audit_blocking, eval_blocking, EvalRequest, ServerState, VerifyRequest};
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::UnicornInfo;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Version string carried by every request and response
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Invalid JSON was received
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid request object
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal JSON-RPC error
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The requested round has not been published
+pub const ROUND_NOT_FOUND: i64 = -32001;
+
+/// JSON-RPC request object
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+    /// Request id; `None` for notifications, which are not answered
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Value>,
+}
+
+/// JSON-RPC response object
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub id: Value,
+}
+
+/// Result or error of a call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Result(Value),
+    Error(RpcError),
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl Error for RpcError {}
+
+/// Parameters of `lottery_select`
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelectParams {
+    pub unicorn: UnicornInfo,
+    /// Identities of the participants, in input order
+    pub participants: Vec<String>,
+    /// Usage number of the draw; the configured one if absent
+    #[serde(default)]
+    pub usage_number: Option<u128>,
+    /// Number of winners; the configured number if absent
+    #[serde(default)]
+    pub winners: Option<usize>,
+}
+
+/// Result of `lottery_select`
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelectResult {
+    /// Identities of the winners, in draw order
+    pub winners: Vec<String>,
+    pub proof: WinnersProof,
+}
+
+/// Parameters of `lottery_getRound`
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct GetRoundParams {
+    pub round_id: u64,
+}
+
+/// Handler for `POST /rpc`
+///
+/// ### Arguments
+///
+/// * `state` - Shared server state
+/// * `body`  - Request body
+pub async fn rpc(State(state): State<ServerState>, body: String) -> axum::response::Response {
+    match handle(&state, &body).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+/// Runs a single or batch request, returning the reply. Returns `None` if nothing is to be
+/// answered, as for notifications
+///
+/// ### Arguments
+///
+/// * `state` - Shared server state
+/// * `body`  - Request body
+pub async fn handle(state: &ServerState, body: &str) -> Option<Value> {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return Some(failure(
+            Value::Null,
+            RpcError::new(PARSE_ERROR, "Parse error"),
+        ));
+    };
+
+    match value {
+        Value::Array(batch) if batch.is_empty() => Some(failure(
+            Value::Null,
+            RpcError::new(INVALID_REQUEST, "Empty batch"),
+        )),
+        Value::Array(batch) => {
+            let mut replies = Vec::new();
+            for call in batch {
+                replies.extend(handle_call(state, call).await);
+            }
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        call => handle_call(state, call).await,
+    }
+}
+
+async fn handle_call(state: &ServerState, call: Value) -> Option<Value> {
+    let request = match serde_json::from_value::<Request>(call) {
+        Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+        _ => {
+            let error = RpcError::new(INVALID_REQUEST, "Invalid request");
+            return Some(failure(Value::Null, error));
+        }
+    };
+
+    let outcome = match dispatch(state, &request.method, request.params).await {
+        Ok(result) => Outcome::Result(result),
+        Err(error) => Outcome::Error(error),
+    };
+
+    let response = Response {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        outcome,
+        id: request.id?,
+    };
+    Some(serde_json::to_value(response).unwrap())
+}
+
+async fn dispatch(state: &ServerState, method: &str, params: Value) -> Result<Value, RpcError> {
+    let config = state.config();
+
+    match method {
+        "lottery_eval" => {
+            let request: EvalRequest = params_of(params)?;
+            let unicorn = request
+                .unicorn(config.unicorn.iterations)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            let info = eval_blocking(unicorn)
+                .await
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Invalid UNICORN modulus"))?;
+            result_of(info)
+        }
+        "lottery_verify" => {
+            let request: VerifyRequest = params_of(params)?;
+            request
+                .check_iterations(config.unicorn.iterations)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            let report = audit_blocking(request)
+                .await
+                .ok_or_else(|| RpcError::new(INTERNAL_ERROR, "Audit failed"))?;
+            result_of(report)
+        }
+        "lottery_select" => {
+            let params: SelectParams = params_of(params)?;
+            let usage = params
+                .usage_number
+                .unwrap_or(config.selection.usage_number as u128);
+            let k = params.winners.unwrap_or(config.selection.winners);
+            let proof = prove_many_from(&params.unicorn, usage, &params.participants, k)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+            let winners = proof
+                .winners
+                .iter()
+                .map(|i| params.participants[*i].clone())
+                .collect();
+            result_of(SelectResult { winners, proof })
+        }
+        "lottery_getRound" => {
+            let GetRoundParams { round_id } = params_of(params)?;
+            let transcript = state
+                .published(round_id)
+                .and_then(|round| round.transcript)
+                .ok_or_else(|| RpcError::new(ROUND_NOT_FOUND, "Round has not been published"))?;
+            result_of(transcript)
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, "Method not found")),
+    }
+}
+
+fn params_of<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn result_of<T: Serialize>(result: T) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(result).unwrap())
+}
+
+fn failure(id: Value, error: RpcError) -> Value {
+    let response = Response {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        outcome: Outcome::Error(error),
+        id,
+    };
+    serde_json::to_value(response).unwrap()
+}
+
+/// Distinguishes `"id": null` from an absent id
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/*---- CLIENT ----*/
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent or the reply could not be read.
+    Http(reqwest::Error),
+    /// The server answered with an error.
+    Rpc(RpcError),
+    /// The reply does not match the request.
+    Malformed(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "JSON-RPC request failed: {}", e),
+            ClientError::Rpc(e) => write!(f, "JSON-RPC error: {}", e),
+            ClientError::Malformed(e) => write!(f, "Malformed JSON-RPC reply: {}", e),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+/// Client for a server's JSON-RPC endpoint
+#[derive(Debug)]
+pub struct RpcClient {
+    url: String,
+    http: reqwest::Client,
+    next_id: AtomicU64,
+}
+
+impl RpcClient {
+    /// Creates a client posting to an endpoint
+    ///
+    /// ### Arguments
+    ///
+    /// * `url` - URL of the endpoint, such as `http://127.0.0.1:8080/rpc`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            http: reqwest::Client::new(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Calls a method, decoding its result
+    ///
+    /// ### Arguments
+    ///
+    /// * `method` - Method name
+    /// * `params` - Named parameters
+    pub async fn call<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<R, ClientError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = Request {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params: serde_json::to_value(params)
+                .map_err(|e| ClientError::Malformed(e.to_string()))?,
+            id: Some(id.into()),
+        };
+
+        let response: Response = self
+            .http
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if response.id != id {
+            return Err(ClientError::Malformed(
+                "Reply id does not match".to_string(),
+            ));
+        }
+
+        match response.outcome {
+            Outcome::Result(result) => {
+                serde_json::from_value(result).map_err(|e| ClientError::Malformed(e.to_string()))
+            }
+            Outcome::Error(error) => Err(ClientError::Rpc(error)),
+        }
+    }
+
+    /// Calls `lottery_eval`
+    ///
+    /// ### Arguments
+    ///
+    /// * `request` - Seed and parameters to evaluate
+    pub async fn eval(&self, request: &EvalRequest) -> Result<UnicornInfo, ClientError> {
+        self.call("lottery_eval", request).await
+    }
+
+    /// Calls `lottery_verify`
+    ///
+    /// ### Arguments
+    ///
+    /// * `request` - Transcript or UNICORN to audit
+    pub async fn verify(&self, request: &VerifyRequest) -> Result<AuditReport, ClientError> {
+        self.call("lottery_verify", request).await
+    }
+
+    /// Calls `lottery_select`
+    ///
+    /// ### Arguments
+    ///
+    /// * `params` - UNICORN and participants to draw from
+    pub async fn select(&self, params: &SelectParams) -> Result<SelectResult, ClientError> {
+        self.call("lottery_select", params).await
+    }
+
+    /// Calls `lottery_getRound`
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round
+    pub async fn get_round(&self, round_id: u64) -> Result<SelectionTranscript, ClientError> {
+        self.call("lottery_getRound", GetRoundParams { round_id })
+            .await
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod jsonrpc_tests {
+    use super::*;
+    use crate::config::LotteryConfig;
+    use crate::server::router;
+    use crate::unicorn::UnicornFixedParam;
+    use serde_json::json;
+
+    fn eval_request() -> EvalRequest {
+        EvalRequest {
+            seed: "1eeb30c7".to_string(),
+            params: UnicornFixedParam::test(),
+        }
+    }
+
+    #[tokio::test]
+    /// Checks error codes, notifications and batches
+    async fn protocol_errors() {
+        let state = ServerState::new(LotteryConfig::default(), 0);
+        let code = |reply: Option<Value>| reply.unwrap()["error"]["code"].as_i64();
+
+        assert_eq!(code(handle(&state, "{").await), Some(PARSE_ERROR));
+        assert_eq!(code(handle(&state, "[]").await), Some(INVALID_REQUEST));
+        assert_eq!(
+            code(handle(&state, r#"{"jsonrpc":"1.0","method":"x","id":1}"#).await),
+            Some(INVALID_REQUEST)
+        );
+        assert_eq!(
+            code(handle(&state, r#"{"jsonrpc":"2.0","method":"x","id":null}"#).await),
+            Some(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            code(
+                handle(
+                    &state,
+                    r#"{"jsonrpc":"2.0","method":"lottery_eval","id":1}"#
+                )
+                .await
+            ),
+            Some(INVALID_PARAMS)
+        );
+
+        let batch = json!([
+            { "jsonrpc": "2.0", "method": "lottery_getRound", "params": { "round_id": 0 }, "id": "a" },
+            { "jsonrpc": "2.0", "method": "lottery_getRound", "params": { "round_id": 0 } },
+        ]);
+        let reply = handle(&state, &batch.to_string()).await.unwrap();
+        assert_eq!(reply.as_array().map(Vec::len), Some(1));
+        assert_eq!(reply[0]["id"], "a");
+        assert_eq!(reply[0]["error"]["code"], ROUND_NOT_FOUND);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"lottery_getRound"}"#;
+        assert_eq!(handle(&state, notification).await, None);
+    }
+
+    #[tokio::test]
+    /// Checks that the client evaluates, verifies and selects through a running server
+    async fn client_round_trip() {
+        let state = ServerState::new(LotteryConfig::default(), 0);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/rpc", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        let client = RpcClient::new(url);
+
+        let info = client.eval(&eval_request()).await.unwrap();
+        let report = client
+            .verify(&VerifyRequest::Unicorn(info.clone()))
+            .await
+            .unwrap();
+        assert!(report.passed());
+
+        let mut slow = info.clone();
+        slow.unicorn.iterations = u64::MAX;
+        assert!(matches!(
+            client.verify(&VerifyRequest::Unicorn(slow)).await,
+            Err(ClientError::Rpc(RpcError {
+                code: INVALID_PARAMS,
+                ..
+            }))
+        ));
+
+        let params = SelectParams {
+            unicorn: info.clone(),
+            participants: vec!["miner_a".to_string(), "miner_b".to_string()],
+            usage_number: Some(1),
+            winners: Some(2),
+        };
+        let selected = client.select(&params).await.unwrap();
+        assert!(selected.proof.verify(&info));
+        assert_eq!(selected.winners.len(), 2);
+
+        assert!(matches!(
+            client.get_round(0).await,
+            Err(ClientError::Rpc(RpcError {
+                code: ROUND_NOT_FOUND,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+//! Registry of coordinator signing keys and their rotation.
+//!
+//! A coordinator signs published rounds with its Ed25519 key (see `signing`), but keys are
+//! rotated over time, so a verifier checking a historical round needs to know which key was
+//! authorized when that round was signed. The registry starts from a genesis key that
+//! verifiers trust out of band. Each rotation names the next key and the round from which it
+//! takes over, and is signed by the key it replaces, so the whole chain of keys can be
+//! checked from the genesis key alone.
+//!
+//! Keys are identified by the first 8 bytes of the SHA-256 of their public key, hex-encoded.
+//! A key is authorized from its activation round up to the activation round of the next key.
+
+use crate::rounds::Round;
+use crate::signing::{Signed, SigningError};
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum KeyError {
+    /// A public key could not be decoded.
+    Malformed,
+    /// A rotation was not signed by the key it replaces.
+    BadRotation(SigningError),
+    /// A rotation does not activate after the key it replaces.
+    NotAfterLatest { active_from: u64, latest: u64 },
+    /// No key was authorized for the round.
+    NoKeyAt { round_id: u64 },
+    /// The round was not signed by the key authorized for it.
+    Unauthorized(SigningError),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Malformed => write!(f, "Malformed public key"),
+            KeyError::BadRotation(e) => write!(f, "Rotation not signed by the replaced key: {}", e),
+            KeyError::NotAfterLatest {
+                active_from,
+                latest,
+            } => write!(
+                f,
+                "Rotation activates at round {}, not after the current key's round {}",
+                active_from, latest
+            ),
+            KeyError::NoKeyAt { round_id } => {
+                write!(f, "No key was authorized at round {}", round_id)
+            }
+            KeyError::Unauthorized(e) => {
+                write!(f, "Round not signed by the key authorized for it: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for KeyError {}
+
+/// A coordinator key and the round from which it is authorized
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct CoordinatorKey {
+    /// Identifier of the key
+    pub key_id: String,
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+    /// First round the key is authorized for
+    pub active_from: u64,
+}
+
+impl CoordinatorKey {
+    /// Describes a key authorized from a given round
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key`  - Public key of the coordinator
+    /// * `active_from` - First round the key is authorized for
+    pub fn new(public_key: &PublicKey, active_from: u64) -> Self {
+        Self {
+            key_id: key_id(public_key),
+            public_key: hex::encode(public_key.as_bytes()),
+            active_from,
+        }
+    }
+
+    /// The public key
+    pub fn public_key(&self) -> Result<PublicKey, KeyError> {
+        hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or(KeyError::Malformed)
+    }
+}
+
+/// Handover to the next coordinator key, signed by the key it replaces
+pub type KeyRotation = Signed<CoordinatorKey>;
+
+/// Chain of coordinator keys, from the genesis key through each rotation
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct KeyRegistry {
+    pub genesis: CoordinatorKey,
+    /// Rotations in the order they were made
+    pub rotations: Vec<KeyRotation>,
+}
+
+impl KeyRegistry {
+    /// Creates a registry from the genesis key
+    ///
+    /// ### Arguments
+    ///
+    /// * `genesis`     - Public key trusted out of band
+    /// * `active_from` - First round the genesis key is authorized for
+    pub fn new(genesis: &PublicKey, active_from: u64) -> Self {
+        Self {
+            genesis: CoordinatorKey::new(genesis, active_from),
+            rotations: Vec::new(),
+        }
+    }
+
+    /// The most recently authorized key
+    pub fn latest(&self) -> &CoordinatorKey {
+        self.rotations
+            .last()
+            .map_or(&self.genesis, |rotation| &rotation.payload)
+    }
+
+    /// Every key in the chain, in activation order
+    pub fn keys(&self) -> impl Iterator<Item = &CoordinatorKey> {
+        std::iter::once(&self.genesis).chain(self.rotations.iter().map(|r| &r.payload))
+    }
+
+    /// Signs a handover from the latest key to the next one
+    ///
+    /// ### Arguments
+    ///
+    /// * `current`     - Key pair of the latest key
+    /// * `next`        - Public key taking over
+    /// * `active_from` - First round the next key is authorized for
+    pub fn sign_rotation(current: &Keypair, next: &PublicKey, active_from: u64) -> KeyRotation {
+        Signed::sign(CoordinatorKey::new(next, active_from), current)
+    }
+
+    /// Appends a rotation, checking that it was signed by the latest key and activates after it
+    ///
+    /// ### Arguments
+    ///
+    /// * `rotation` - Handover signed by the latest key
+    pub fn rotate(&mut self, rotation: KeyRotation) -> Result<&CoordinatorKey, KeyError> {
+        check_rotation(self.latest(), &rotation)?;
+        self.rotations.push(rotation);
+
+        Ok(self.latest())
+    }
+
+    /// Checks the whole chain from the genesis key, as after loading a registry
+    pub fn verify(&self) -> Result<(), KeyError> {
+        let mut current = &self.genesis;
+        for rotation in &self.rotations {
+            check_rotation(current, rotation)?;
+            current = &rotation.payload;
+        }
+
+        Ok(())
+    }
+
+    /// Key authorized for a round
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round identifier
+    pub fn authorized_at(&self, round_id: u64) -> Option<&CoordinatorKey> {
+        self.keys()
+            .take_while(|key| key.active_from <= round_id)
+            .last()
+    }
+
+    /// Checks that a round was signed by the key authorized for it, and then replays it
+    ///
+    /// ### Arguments
+    ///
+    /// * `signed` - Signed round
+    pub fn verify_round(&self, signed: &Signed<Round>) -> Result<&CoordinatorKey, KeyError> {
+        let round_id = signed.payload.id;
+        let key = self
+            .authorized_at(round_id)
+            .ok_or(KeyError::NoKeyAt { round_id })?;
+
+        signed
+            .verify(&key.public_key()?)
+            .map_err(KeyError::Unauthorized)?;
+
+        Ok(key)
+    }
+}
+
+/// Identifier of a public key: the first 8 bytes of its SHA-256, hex-encoded
+///
+/// ### Arguments
+///
+/// * `public_key` - Public key to identify
+pub fn key_id(public_key: &PublicKey) -> String {
+    hex::encode(&Sha256::digest(public_key.as_bytes())[..8])
+}
+
+fn check_rotation(current: &CoordinatorKey, rotation: &KeyRotation) -> Result<(), KeyError> {
+    rotation
+        .verify_signature(&current.public_key()?)
+        .map_err(KeyError::BadRotation)?;
+    rotation.payload.public_key()?;
+
+    if rotation.payload.active_from <= current.active_from {
+        return Err(KeyError::NotAfterLatest {
+            active_from: rotation.payload.active_from,
+            latest: current.active_from,
+        });
+    }
+
+    Ok(())
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod keys_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::signing::test_keypair;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn published_round(id: u64) -> Round {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 60,
+        };
+        let mut round = Round::new(id, params, 1, timeouts, 0);
+        round.contribute("miner_a", 10).unwrap();
+        round.seal().unwrap();
+        round.evaluate(70).unwrap();
+        round
+    }
+
+    #[test]
+    /// Checks that rotations chain from the genesis key and that historical rounds verify
+    /// only against the key authorized when they were signed
+    fn key_rotation() {
+        let (first, second, rogue) = (test_keypair(1), test_keypair(2), test_keypair(3));
+        let mut registry = KeyRegistry::new(&first.public, 1);
+
+        assert_eq!(
+            registry.rotate(KeyRegistry::sign_rotation(&rogue, &rogue.public, 10)),
+            Err(KeyError::BadRotation(SigningError::UntrustedSigner))
+        );
+        assert_eq!(
+            registry.rotate(KeyRegistry::sign_rotation(&first, &second.public, 1)),
+            Err(KeyError::NotAfterLatest {
+                active_from: 1,
+                latest: 1
+            })
+        );
+        let latest = registry
+            .rotate(KeyRegistry::sign_rotation(&first, &second.public, 10))
+            .unwrap();
+        assert_eq!(latest.key_id, key_id(&second.public));
+
+        let json = serde_json::to_string(&registry).unwrap();
+        let loaded: KeyRegistry = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.verify(), Ok(()));
+        assert_eq!(loaded.authorized_at(0), None);
+        assert_eq!(
+            loaded.authorized_at(9).unwrap().key_id,
+            key_id(&first.public)
+        );
+        assert_eq!(
+            loaded.authorized_at(10).unwrap().key_id,
+            key_id(&second.public)
+        );
+
+        let old = Signed::sign(published_round(5), &first);
+        assert_eq!(loaded.verify_round(&old).map(|k| k.active_from), Ok(1));
+        let resigned = Signed::sign(published_round(5), &second);
+        assert_eq!(
+            loaded.verify_round(&resigned),
+            Err(KeyError::Unauthorized(SigningError::UntrustedSigner))
+        );
+
+        let mut forged = loaded;
+        forged.rotations[0].payload.public_key = hex::encode(rogue.public.as_bytes());
+        assert_eq!(
+            forged.verify(),
+            Err(KeyError::BadRotation(SigningError::BadSignature))
+        );
+    }
+}
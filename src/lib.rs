@@ -1,3 +1,95 @@
 pub mod unicorn;
 pub mod utils;
-pub mod fortuna;
\ No newline at end of file
+pub mod fortuna;
+pub mod kdf;
+#[cfg(feature = "rand")]
+pub mod rng;
+pub mod transcript;
+pub mod timestamp;
+pub mod roughtime;
+pub mod participants;
+pub mod params;
+#[cfg(feature = "signing")]
+pub mod paramsfile;
+pub mod rounds;
+pub mod segments;
+pub mod tower;
+pub mod selftest;
+pub mod selection;
+pub mod sensitivity;
+pub mod sim;
+pub mod vdf;
+pub mod vrf;
+pub mod window;
+pub mod sortition;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "signing")]
+pub mod keys;
+pub mod disputes;
+pub mod consensus;
+pub mod finality;
+pub mod usage;
+pub mod payouts;
+pub mod merkle;
+pub mod manifest;
+pub mod pow;
+pub mod policy;
+pub mod config;
+pub mod calibrate;
+pub mod modulus;
+pub mod ingest;
+pub mod audit;
+pub mod schedule;
+pub mod daemon;
+pub mod beacon;
+pub mod feed;
+pub mod storage;
+pub mod auditlog;
+pub mod bundle;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "server")]
+pub mod ratelimit;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "jsonrpc")]
+pub mod jsonrpc;
+#[cfg(feature = "p2p")]
+pub mod p2p;
+#[cfg(feature = "signing")]
+pub mod ceremony;
+#[cfg(feature = "bls")]
+pub mod threshold;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "zenotta")]
+pub mod interop;
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+#[cfg(feature = "napi")]
+pub mod node;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(test)]
+mod golden;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
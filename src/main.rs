@@ -1,26 +1,1805 @@
-use miner_lottery::unicorn;
-use miner_lottery::utils::unicorn_selection::get_unicorn_prn;
-
-fn main() {
-    /*--- FOLLOWING IS A TEST ---*/
-
-    // The input public keys for the UNiCORN
-    let inputs = [
-        "AAAAC3NzaC1lZDI1NTE5AAAAIISBNp/6cz4by6FhlAtSI5Dg3agtFlOjoPayidNEDd78".to_string(),
-        "AAAAB3NzaC1yc2EAAAADAQABAAACAQDflRJbqp9Ru2f4oLeUjEjV7QxbtlM8DiuSmj6iWA7vv6Hb62cQeLRT3Un4yerjOOBrXd3s4psReCL4+oo3GmvOIRCPlpMqZZFPgHYyF8pGobwSZZHSKNPpIeNWM90hXenJ4zTym59W/+jU3dhe8AeaAZS0Qy09vsHr4K+7cAjsz1ebp0yKNK06Betsfis26tipf40QzWUwrn/UuUgdlpXG6H+bUNuZ2cWDVkuq4G00F7OCv3wEdtnAy8VKnpqVIWsjo7c1WWVPtlslcVv1gRbTNaZ9msyvaiQ+hUsJYo8VNmu9iONJGUa3PnkWMmy9Z4hIHPG/imtVrWr0UNCXPB1gahDUJrm22qOH0iwg7PB88X9W5ryihe7HN3Q1nVDpcLyUGoXessuFtbzugDkDkfiNkTz3AYRtikcL3F9gdpTZ0EtPuIXItplsdUi5Axng45HB3VwEcd9ehBMv0WmYzsF3pxyE5jQOscken91cdGFF0l6llhsXohZBkpvV2v+4XOM6NCsXATQVdNDpsrNIScczHKXT9J/aqO54BhrORiytPLBgJScEde65dYTbEIgvzxFJtNzHAveCN/A3L+C/TGC57lRRSsuG1bD/2S1Zy4XQHsbNWAdOaurO858ik13WC+Sn5frc81vMIZdqPU5/imgC9c2XYrcfSz82v9HnurO8nw==".to_string()
-    ];
-
-    // The fixed parameters for the UNiCORN
-    let fixed_params = unicorn::UnicornFixedParam {
-        modulus: "6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151".to_string(),
-        iterations: 1000,
-        security: 1,
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use miner_lottery::audit::{Artifact, AuditReport};
+use miner_lottery::auditlog::{verify_log, AuditEvent, AuditLog, LogHead};
+use miner_lottery::bundle::{export_round, import_round, BundleError};
+use miner_lottery::calibrate::{benchmark, calibrate, calibration_modulus};
+use miner_lottery::config::{validate_params, ConfigError, LotteryConfig, RoughtimeConfig};
+use miner_lottery::daemon::{Daemon, DaemonRequest, DaemonResponse, PublishedRound};
+use miner_lottery::ingest::{
+    parse_inputs, parse_participants, IngestError, InputEntry, InputFormat,
+};
+use miner_lottery::manifest::InputManifest;
+use miner_lottery::modulus::{
+    generate_modulus, generate_provable_modulus, ModulusCertificate, CERTIFICATE_ROUNDS,
+};
+use miner_lottery::params::check_recommended;
+use miner_lottery::rounds::{self, Round};
+use miner_lottery::segments::{eval_with_segments, first_divergence, SegmentTrace};
+use miner_lottery::selection::{prove_many_from, select};
+use miner_lottery::selftest::determinism_selftest;
+use miner_lottery::sim::{simulate, SimConfig};
+use miner_lottery::tower::{DelayTower, TowerLevel};
+use miner_lottery::unicorn::{
+    self, construct_seed_with_pow, g_matches, EvalMode, EvalState, UnicornFixedParam, UnicornInfo,
+};
+use miner_lottery::vdf::UnicornVdf;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const EXIT_CODES: &str = "Exit codes:
+  0  success
+  1  other failure
+  2  invalid command line
+  3  invalid parameters
+  4  verification failed
+  5  eval cancelled
+  6  I/O error
+  7  invalid input";
+
+/// Miner lottery driven by a UNICORN
+#[derive(Parser)]
+#[command(name = "miner-lottery", version, about, after_help = EXIT_CODES)]
+struct Cli {
+    /// Read and write JSON instead of text. Evaluated UNICORNs are read and written as
+    /// `{seed, witness, g}`, with the parameters given by flags
+    #[arg(long, global = true)]
+    json: bool,
+    /// TOML configuration file providing parameters, selection settings and entropy sources
+    /// not given by flags
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Format of errors written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Constructs the seed from the public key inputs
+    Seed {
+        #[command(flatten)]
+        inputs: InputArgs,
+    },
+    /// Evaluates the UNICORN for a seed
+    Eval {
+        #[command(flatten)]
+        inputs: InputArgs,
+        /// Hex-encoded seed, used instead of constructing one from the inputs
+        #[arg(long)]
+        seed: Option<String>,
+        #[command(flatten)]
+        params: ParamArgs,
+        /// File to write the evaluated UNICORN to, as JSON
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Cancel the eval if it has not completed after this long, such as `90s` or `15m`
+        #[arg(long, value_parser = parse_duration)]
+        timeout: Option<Duration>,
+        /// Evaluate in the slower mode hardened against timing side channels
+        #[arg(long)]
+        hardened: bool,
+        /// Audit sidecar to write a hash of the iterated value to every
+        /// `--segment-iterations` iterations, as JSON
+        #[arg(long)]
+        segments: Option<PathBuf>,
+        /// Iterations between hashes in the audit sidecar, rounded up to a multiple of 1024
+        #[arg(long, default_value_t = 1 << 20, requires = "segments")]
+        segment_iterations: u64,
+        /// File the eval is checkpointed to, and resumed from if it exists, so that a long
+        /// eval that is cancelled or interrupted does not start over
+        #[arg(long, conflicts_with = "segments")]
+        checkpoint: Option<PathBuf>,
+        /// Iterations between checkpoints [default: `daemon.checkpoint_iterations`]
+        #[arg(long, requires = "checkpoint")]
+        checkpoint_iterations: Option<u64>,
+        /// Report the eval's progress on stderr
+        #[arg(long)]
+        progress: bool,
+    },
+    /// Runs a delay tower from a seed, emitting a level every `--iterations` iterations
+    Tower {
+        #[command(flatten)]
+        inputs: InputArgs,
+        /// Hex-encoded seed, used instead of constructing one from the inputs
+        #[arg(long)]
+        seed: Option<String>,
+        #[command(flatten)]
+        params: ParamArgs,
+        /// File the tower and its levels are appended to as JSON lines, and the tower
+        /// resumed from if it already holds some
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Stop once the tower reaches this height, rather than running until interrupted
+        #[arg(long)]
+        height: Option<u64>,
+    },
+    /// Verifies an evaluated UNICORN, or audits a published round
+    Verify {
+        /// File holding the evaluated UNICORN, or `-` for stdin
+        #[arg(
+            short,
+            long,
+            required_unless_present = "round",
+            conflicts_with = "round"
+        )]
+        unicorn: Option<PathBuf>,
+        /// Published round, selection transcript or `UnicornInfo` to audit, as JSON, or `-`
+        /// for stdin. Every check is reported as passed or failed
+        #[arg(long)]
+        round: Option<PathBuf>,
+        #[command(flatten)]
+        params: ParamArgs,
+    },
+    /// Selects a winner among the inputs
+    Select {
+        #[command(flatten)]
+        inputs: InputArgs,
+        /// File holding the evaluated UNICORN, or `-` for stdin
+        #[arg(short, long)]
+        unicorn: PathBuf,
+        #[command(flatten)]
+        params: ParamArgs,
+        /// Usage number of the draw [default: from the configuration, or 1]
+        #[arg(long)]
+        usage: Option<u128>,
+        /// Number of distinct winners to draw with the unbiased sampler, printing the proof
+        /// of the draw [default: from the configuration, or 1]
+        #[arg(long)]
+        winners: Option<usize>,
+        /// File of `key,weight` lines setting the selection weights of the inputs
+        #[arg(long, value_name = "FILE")]
+        weights: Option<PathBuf>,
+    },
+    /// Benchmarks the eval and recommends an iteration count for a target eval time
+    Calibrate {
+        /// Target eval time, such as `90s`, `15m` or `1h`
+        #[arg(long, default_value = "60s", value_parser = parse_duration)]
+        target: Duration,
+        /// Size of the modulus to benchmark [default: size of the configured modulus]
+        #[arg(long)]
+        modulus_bits: Option<u32>,
+        /// Write the recommended iteration count into the configuration file
+        #[arg(long)]
+        write: bool,
+    },
+    /// Measures eval, verify and Fortuna throughput on this host against the configured round
+    /// timing
+    Bench {
+        /// Size of the modulus to benchmark [default: size of the configured modulus]
+        #[arg(long)]
+        modulus_bits: Option<u32>,
+    },
+    /// Runs simulated rounds with synthetic participants under the configured parameters,
+    /// reporting how evenly wins were spread and how long evals took
+    Simulate {
+        /// Number of rounds to run
+        #[arg(long, default_value_t = 1000)]
+        rounds: u64,
+        /// Number of synthetic participants in every round
+        #[arg(long, default_value_t = 10)]
+        participants: usize,
+        /// Seed from which participants and winning PoW hashes are derived
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Checks that this platform encodes digits, seeds and PRNs exactly as the reference
+    /// platform does
+    Selftest,
+    /// Checks the UNICORN parameters against the recommendations of the Random Zoo paper,
+    /// printing each shortfall
+    CheckParams {
+        #[command(flatten)]
+        params: ParamArgs,
+    },
+    /// Generates a fresh prime modulus congruent to 3 mod 4, with its primality certificate
+    Genmod {
+        /// Size of the modulus in bits
+        #[arg(long, default_value_t = 2048)]
+        bits: u32,
+        /// Number of Miller-Rabin rounds recorded in the certificate
+        #[arg(long, default_value_t = CERTIFICATE_ROUNDS)]
+        rounds: u32,
+        /// Also prove the modulus prime with a chain of Pocklington steps, so that it can be
+        /// configured as `primality.certificate`
+        #[arg(long)]
+        provable: bool,
+        /// File to write the certificate to, as JSON
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Runs rounds continuously as configured by the `[daemon]` table, accepting
+    /// contributions and reporting status over a local socket
+    Daemon {
+        /// Id of the first round
+        #[arg(long, default_value_t = 0)]
+        first_round: u64,
+        /// Path of the local socket [default: from the configuration]
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Directory published rounds are written to [default: from the configuration]
+        #[arg(long)]
+        publish_dir: Option<PathBuf>,
+    },
+    /// Serves the HTTP API, running rounds as configured by the `[daemon]` table
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Id of the first round
+        #[arg(long, default_value_t = 0)]
+        first_round: u64,
+        /// Address to serve the gRPC service on as well
+        #[cfg(feature = "grpc")]
+        #[arg(long)]
+        grpc_listen: Option<std::net::SocketAddr>,
+        /// JSON object mapping each API key to the miner identity it was issued to; only
+        /// contributions presenting their identity's key are accepted
+        #[arg(long)]
+        api_keys: Option<PathBuf>,
+        /// File of registered miner public keys, one hex key per line; only contributions
+        /// signed by their identity's key are accepted
+        #[cfg(feature = "signing")]
+        #[arg(long, conflicts_with = "api_keys")]
+        miner_keys: Option<PathBuf>,
+        /// Database directory published rounds are kept in, so that they survive restarts
+        #[cfg(feature = "sled")]
+        #[arg(long)]
+        store: Option<PathBuf>,
+        /// SQLite database file published rounds are kept in, so that they survive restarts
+        /// and can be queried
+        #[cfg(feature = "sqlite")]
+        #[arg(long)]
+        sqlite: Option<PathBuf>,
+    },
+    /// Packages a published round into a self-contained `.round` bundle that verifies
+    /// offline
+    ExportRound {
+        /// Published round, as JSON, or `-` for stdin
+        round: PathBuf,
+        /// File to write the bundle to [default: stdout]
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Checks a `.round` bundle and prints the round it holds
+    ImportRound {
+        /// Bundle file, or `-` for stdin
+        bundle: PathBuf,
+    },
+    /// Checks that an audit log is intact: every entry matches its hash and follows on from
+    /// the previous one
+    VerifyLog {
+        /// Audit log file
+        log: PathBuf,
+        /// Hash of an entry recorded earlier that the log must still contain
+        #[arg(long, requires = "entries")]
+        head: Option<String>,
+        /// Number of entries up to and including `--head`
+        #[arg(long, requires = "head")]
+        entries: Option<u64>,
+    },
+    /// Compares the audit sidecars of two evals of the same UNICORN, reporting the first
+    /// segment where they diverge
+    CompareSegments {
+        /// Audit sidecar of one eval
+        ours: PathBuf,
+        /// Audit sidecar of the other eval
+        theirs: PathBuf,
+    },
+    /// Compares two round records, such as conflicting results published by two nodes,
+    /// printing every value they disagree on
+    DiffRounds {
+        /// One round, as JSON
+        a: PathBuf,
+        /// The other round, as JSON
+        b: PathBuf,
+    },
+    /// Prints the JSON Schema of a published artifact, or of all of them keyed by name
+    #[cfg(feature = "schema")]
+    Schema {
+        /// Artifact to describe [default: all]
+        #[arg(value_parser = clap::builder::PossibleValuesParser::new(miner_lottery::schema::SCHEMA_NAMES))]
+        name: Option<String>,
+    },
+    /// Prints the OpenAPI document of the HTTP API
+    #[cfg(feature = "openapi")]
+    Openapi,
+    /// Follows a running daemon, printing the `g` value and winner of each round as it is
+    /// published. With `--json`, each round is written as one JSON object per line
+    Watch {
+        /// Socket of the daemon [default: from the configuration]
+        #[arg(long)]
+        endpoint: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
+/// Class of a failure, determining the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorKind {
+    Other,
+    Usage,
+    InvalidParams,
+    VerifyFailed,
+    EvalCancelled,
+    Io,
+    InvalidInput,
+}
+
+impl ErrorKind {
+    fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::InvalidParams => 3,
+            ErrorKind::VerifyFailed => 4,
+            ErrorKind::EvalCancelled => 5,
+            ErrorKind::Io => 6,
+            ErrorKind::InvalidInput => 7,
+        }
+    }
+}
+
+/// Failure with an explicit class
+#[derive(Debug)]
+struct CliError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CliError {}
+
+fn fail(kind: ErrorKind, message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(CliError {
+        kind,
+        message: message.into(),
+    })
+}
+
+/// Class of any failure, from its type if it was not raised as a `CliError`
+fn classify(e: &(dyn Error + 'static)) -> ErrorKind {
+    if let Some(e) = e.downcast_ref::<CliError>() {
+        e.kind
+    } else if e.is::<io::Error>() || matches!(e.downcast_ref(), Some(ConfigError::Io(_))) {
+        ErrorKind::Io
+    } else if e.is::<ConfigError>() {
+        ErrorKind::InvalidParams
+    } else if let Some(e) = e.downcast_ref::<serde_json::Error>() {
+        if e.is_io() {
+            ErrorKind::Io
+        } else {
+            ErrorKind::InvalidInput
+        }
+    } else if e.is::<IngestError>() || e.is::<rug::integer::ParseIntegerError>() {
+        ErrorKind::InvalidInput
+    } else {
+        ErrorKind::Other
+    }
+}
+
+/// Writes an error to stderr in the requested format and returns its exit code
+fn report(e: &(dyn Error + 'static), format: ErrorFormat) -> ExitCode {
+    let kind = classify(e);
+
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {}", e),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            json!({ "error": { "kind": kind, "code": kind.exit_code(), "message": e.to_string() } })
+        ),
+    }
+
+    ExitCode::from(kind.exit_code())
+}
+
+/// Evaluated UNICORN as read and written in JSON mode
+#[derive(Serialize, Deserialize)]
+struct EvalOutput {
+    /// Hex-encoded seed
+    seed: String,
+    /// Hex-encoded witness
+    witness: String,
+    g: String,
+}
+
+#[derive(Args)]
+struct InputArgs {
+    /// Public key of a participant, in seed order [default: from the configuration]
+    #[arg(short, long = "input", value_name = "KEY")]
+    inputs: Vec<String>,
+    /// File listing one public key per line, optionally followed by `,weight`, or `-` for
+    /// stdin. Keys are normalized and put in canonical order
+    #[arg(long = "inputs", value_name = "FILE", conflicts_with = "inputs")]
+    inputs_file: Option<PathBuf>,
+    /// Format of the inputs file: `list`, `csv` with an `id,pubkey,weight` header, or
+    /// `jsonl` [default: from the file extension, or list]
+    #[arg(long, value_name = "FORMAT", requires = "inputs_file")]
+    input_format: Option<InputFormat>,
+    /// Hash of a winning PoW from 2 blocks ago to bind into the seed [default: from the
+    /// configuration]
+    #[arg(long = "pow-hash", value_name = "HASH")]
+    pow_hashes: Vec<String>,
+}
+
+#[derive(Args)]
+struct ParamArgs {
+    /// File holding the UNICORN parameters, as JSON
+    #[arg(long, conflicts_with_all = ["modulus", "iterations", "security", "primality_rounds"])]
+    params: Option<PathBuf>,
+    /// UNICORN modulus, in decimal [default: from the configuration, or the example modulus]
+    #[arg(long)]
+    modulus: Option<String>,
+    /// UNICORN iterations [default: from the configuration, or 1000]
+    #[arg(long)]
+    iterations: Option<u64>,
+    /// UNICORN security level [default: from the configuration, or 1]
+    #[arg(long)]
+    security: Option<u32>,
+    /// Miller-Rabin rounds the modulus must pass [default: from the configuration, or 15]
+    #[arg(long)]
+    primality_rounds: Option<u32>,
+}
+
+/// Inputs of a draw, resolved from flags, files and the configuration
+struct Inputs {
+    entries: Vec<InputEntry>,
+    pow_hashes: Vec<String>,
+}
+
+impl InputArgs {
+    /// Inputs and PoW hashes, falling back to the configured ones when none are given.
+    /// Duplicates in an inputs file are reported on stderr
+    fn resolve(self, config: &LotteryConfig) -> Result<Inputs, Box<dyn Error>> {
+        let entries = match &self.inputs_file {
+            Some(path) => {
+                let format = self
+                    .input_format
+                    .unwrap_or_else(|| InputFormat::from_path(path));
+                let ingested = parse_participants(&read_source(path)?, format)?;
+                for duplicate in &ingested.duplicates {
+                    eprintln!(
+                        "Warning: duplicate input {} on line {} (first on line {})",
+                        duplicate.identity, duplicate.line, duplicate.first_line
+                    );
+                }
+                ingested.entries
+            }
+            None => {
+                let inputs = if self.inputs.is_empty() {
+                    &config.entropy.inputs
+                } else {
+                    &self.inputs
+                };
+                inputs
+                    .iter()
+                    .map(|identity| InputEntry {
+                        identity: identity.clone(),
+                        weight: None,
+                        id: None,
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(Inputs {
+            entries,
+            pow_hashes: if self.pow_hashes.is_empty() {
+                config.entropy.pow_hashes.clone()
+            } else {
+                self.pow_hashes
+            },
+        })
+    }
+}
+
+impl Inputs {
+    fn seed(&self) -> Result<Integer, Box<dyn Error>> {
+        if self.entries.is_empty() {
+            return Err(fail(
+                ErrorKind::InvalidInput,
+                "At least one input is required",
+            ));
+        }
+
+        let identities: Vec<String> = self.entries.iter().map(|e| e.identity.clone()).collect();
+        Ok(construct_seed_with_pow(&identities, &self.pow_hashes))
+    }
+}
+
+impl ParamArgs {
+    /// Parameters from the parameters file if given, or else from the flags, falling back to
+    /// the configured parameters
+    fn fixed_params(&self, config: &LotteryConfig) -> Result<UnicornFixedParam, Box<dyn Error>> {
+        let params = match &self.params {
+            Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+            None => UnicornFixedParam {
+                modulus: self
+                    .modulus
+                    .clone()
+                    .unwrap_or_else(|| config.unicorn.modulus.clone()),
+                iterations: self.iterations.unwrap_or(config.unicorn.iterations),
+                security: self.security.unwrap_or(config.unicorn.security),
+                primality_rounds: self
+                    .primality_rounds
+                    .unwrap_or(config.unicorn.primality_rounds),
+            },
+        };
+        validate_params(&params)?;
+
+        Ok(params)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) if requested_json_errors() => {
+            let message = e.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            let e = fail(ErrorKind::Usage, message.trim_start_matches("error: "));
+            return report(e.as_ref(), ErrorFormat::Json);
+        }
+        Err(e) => e.exit(),
+    };
+
+    let config = match cli.config.as_deref().map(LotteryConfig::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => return report(&e, cli.error_format),
+    };
+
+    match run(cli.command, cli.json, &config, cli.config.as_deref()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report(e.as_ref(), cli.error_format),
+    }
+}
+
+/// Whether JSON errors were requested on a command line that failed to parse
+fn requested_json_errors() -> bool {
+    let args: Vec<String> = std::env::args().collect();
+
+    args.iter().any(|a| a == "--error-format=json")
+        || args
+            .windows(2)
+            .any(|w| w[0] == "--error-format" && w[1] == "json")
+}
+
+fn run(
+    command: Command,
+    json: bool,
+    config: &LotteryConfig,
+    config_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Seed { inputs } => {
+            let seed = inputs.resolve(config)?.seed()?.to_string_radix(16);
+
+            if json {
+                println!("{}", json!({ "seed": seed }));
+            } else {
+                println!("{}", seed);
+            }
+        }
+        Command::Eval {
+            inputs,
+            seed,
+            params,
+            out,
+            timeout,
+            hardened,
+            segments,
+            segment_iterations,
+            checkpoint,
+            checkpoint_iterations,
+            progress,
+        } => {
+            let seed = match seed {
+                Some(seed) => Integer::from_str_radix(&seed, 16)?,
+                None => inputs.resolve(config)?.seed()?,
+            };
+            let fixed_params = params.fixed_params(config)?;
+            let modulus = Integer::from_str_radix(&fixed_params.modulus, 10)?;
+
+            let unicorn = unicorn::Unicorn {
+                seed,
+                modulus,
+                iterations: fixed_params.iterations,
+                security_level: fixed_params.security,
+                primality_rounds: fixed_params.primality_rounds,
+            };
+            let mode = match hardened {
+                true => EvalMode::Hardened,
+                false => config.daemon.eval_mode,
+            };
+            let interval = segments.as_ref().map(|_| segment_iterations);
+            let checkpoint = checkpoint.map(|path| EvalCheckpointing {
+                path,
+                interval: checkpoint_iterations.unwrap_or(config.daemon.checkpoint_iterations),
+            });
+            let reporter = EvalReporter::new(&unicorn, checkpoint, progress)?;
+            let (witness, g_value, trace) =
+                eval_with_timeout(&unicorn, mode, interval, reporter, timeout)?;
+            let info = UnicornInfo::new(unicorn, witness, g_value);
+
+            let output = EvalOutput {
+                seed: info.unicorn.seed.to_string_radix(16),
+                witness: info.witness.to_string_radix(16),
+                g: info.g_value.clone(),
+            };
+
+            if json {
+                println!("{}", serde_json::to_string(&output)?);
+            } else {
+                println!("seed:    {}", output.seed);
+                println!("witness: {}", output.witness);
+                println!("g:       {}", output.g);
+            }
+
+            if let Some(out) = out {
+                fs::write(out, serde_json::to_string_pretty(&info)?)?;
+            }
+            if let Some((path, trace)) = segments.zip(trace) {
+                fs::write(path, serde_json::to_string_pretty(&trace)?)?;
+            }
+        }
+        Command::Tower {
+            inputs,
+            seed,
+            params,
+            out,
+            height,
+        } => {
+            let seed = match seed {
+                Some(seed) => Integer::from_str_radix(&seed, 16)?,
+                None => inputs.resolve(config)?.seed()?,
+            };
+            let tower = DelayTower::new(seed, params.fixed_params(config)?);
+
+            // The file starts with the tower itself, followed by its levels. An existing file is
+            // resumed from its last level, which is checked against the one below it
+            let mut below = None;
+            let mut file = None;
+            if let Some(path) = &out {
+                let written = match path.exists() {
+                    true => fs::read_to_string(path)?,
+                    false => String::new(),
+                };
+                let mut lines = written.lines().filter(|l| !l.trim().is_empty());
+                let header = lines.next().map(serde_json::from_str::<DelayTower>);
+                if header
+                    .as_ref()
+                    .is_some_and(|h| h.as_ref().ok() != Some(&tower))
+                {
+                    return Err(fail(
+                        ErrorKind::VerifyFailed,
+                        "File holds the levels of another tower",
+                    ));
+                }
+
+                let mut levels = lines
+                    .map(serde_json::from_str::<TowerLevel>)
+                    .collect::<Result<Vec<_>, _>>()?;
+                below = levels.pop();
+                if let Some(level) = &below {
+                    if !tower.verify_level(levels.last(), level) {
+                        return Err(fail(
+                            ErrorKind::VerifyFailed,
+                            "Last level written does not verify",
+                        ));
+                    }
+                }
+
+                let mut handle = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                if header.is_none() {
+                    writeln!(handle, "{}", serde_json::to_string(&tower)?)?;
+                }
+                file = Some(handle);
+            }
+            if let (Some(below), Some(height)) = (&below, height) {
+                if below.height >= height {
+                    return Ok(());
+                }
+            }
+
+            let mut failed = None;
+            let last = tower.run(below, |level| {
+                let line = serde_json::to_string(level).unwrap();
+                let written = match &mut file {
+                    Some(file) => writeln!(file, "{}", line),
+                    None => writeln!(io::stdout(), "{}", line),
+                };
+                if let Err(e) = written {
+                    failed = Some(e);
+                    return false;
+                }
+                if !json && file.is_some() {
+                    eprintln!("Level {}: {}", level.height, level.output);
+                }
+                height.is_none_or(|h| level.height < h)
+            });
+
+            if let Some(e) = failed {
+                return Err(e.into());
+            }
+            if last.is_none() {
+                return Err(fail(ErrorKind::InvalidParams, "Invalid UNICORN parameters"));
+            }
+        }
+        Command::Verify {
+            round: Some(round), ..
+        } => {
+            let report = audit_artifact(&read_source(&round)?)?;
+
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                for result in &report.checks {
+                    let name = serde_json::to_value(result.check)?;
+                    match &result.detail {
+                        None => println!("PASS {}", name.as_str().unwrap_or_default()),
+                        Some(detail) => {
+                            println!("FAIL {}: {}", name.as_str().unwrap_or_default(), detail)
+                        }
+                    }
+                }
+            }
+
+            if !report.passed() {
+                return Err(fail(ErrorKind::VerifyFailed, "Round failed audit"));
+            }
+        }
+        Command::Verify {
+            unicorn, params, ..
+        } => {
+            let unicorn = unicorn.unwrap_or_default();
+            let info = read_unicorn(&unicorn, json.then_some(&params), config)?;
+            let uni = &info.unicorn;
+
+            let witness_ok = uni.verify_ref(&uni.seed, &info.witness);
+            let g_ok = g_matches(&info.witness, &info.g_value);
+
+            if json {
+                println!(
+                    "{}",
+                    json!({ "verified": witness_ok && g_ok, "witness": witness_ok, "g": g_ok })
+                );
+            }
+            if !witness_ok {
+                return Err(fail(
+                    ErrorKind::VerifyFailed,
+                    "Witness does not verify against the seed",
+                ));
+            }
+            if !g_ok {
+                return Err(fail(
+                    ErrorKind::VerifyFailed,
+                    "g value does not match the witness",
+                ));
+            }
+            if !json {
+                println!("Verified");
+            }
+        }
+        Command::Select {
+            inputs,
+            unicorn,
+            params,
+            usage,
+            winners,
+            weights,
+        } => {
+            let info = read_unicorn(&unicorn, json.then_some(&params), config)?;
+            let mut inputs = inputs.resolve(config)?;
+            let usage = usage.unwrap_or(config.selection.usage_number as u128);
+            if inputs.seed()? != info.unicorn.seed {
+                return Err(fail(
+                    ErrorKind::VerifyFailed,
+                    "Inputs do not match the UNICORN seed",
+                ));
+            }
+
+            if let Some(weights) = &weights {
+                apply_weights(&mut inputs.entries, &read_source(weights)?)?;
+            }
+
+            let multi = winners.is_some() || weights.is_some() || config.selection.winners > 1;
+            if multi {
+                let k = winners.unwrap_or(config.selection.winners);
+                let proof = prove_many_from(&info, usage, &inputs.entries, k)
+                    .map_err(|e| fail(ErrorKind::InvalidInput, e.to_string()))?;
+                let drawn: Vec<_> = proof
+                    .winners
+                    .iter()
+                    .map(|i| json!({ "winner": inputs.entries[*i].identity, "index": i }))
+                    .collect();
+
+                if json {
+                    let output = json!({ "winners": drawn, "usage_number": usage, "proof": proof });
+                    println!("{}", output);
+                } else {
+                    for i in &proof.winners {
+                        println!("Selected: {} (index {})", inputs.entries[*i].identity, i);
+                    }
+                    println!("Proof: {}", serde_json::to_string_pretty(&proof)?);
+                }
+                return Ok(());
+            }
+
+            let (index, winner) = select(&info, usage, &inputs.entries)
+                .ok_or_else(|| fail(ErrorKind::InvalidInput, "Inputs have no selection weight"))?;
+            let winner = &winner.identity;
+            if json {
+                println!(
+                    "{}",
+                    json!({ "winner": winner, "index": index, "usage_number": usage })
+                );
+            } else {
+                println!("Selected: {} (index {})", winner, index);
+            }
+        }
+        Command::Calibrate {
+            target,
+            modulus_bits,
+            write,
+        } => {
+            if write && config_path.is_none() {
+                return Err(fail(ErrorKind::Usage, "--write requires --config"));
+            }
+
+            let modulus = match modulus_bits {
+                Some(bits) if bits < 3 => {
+                    return Err(fail(
+                        ErrorKind::InvalidParams,
+                        "Modulus must be at least 3 bits",
+                    ))
+                }
+                Some(bits) => calibration_modulus(bits),
+                None => Integer::from_str_radix(&config.unicorn.modulus, 10)?,
+            };
+            let calibration = calibrate(&modulus, target)
+                .ok_or_else(|| fail(ErrorKind::InvalidParams, "Invalid UNICORN modulus"))?;
+
+            if json {
+                println!("{}", serde_json::to_string(&calibration)?);
+            } else {
+                println!("modulus bits:           {}", calibration.modulus_bits);
+                println!(
+                    "sample:                 {} iterations in {:.3}s",
+                    calibration.sample_iterations,
+                    calibration.sample_time.as_secs_f64()
+                );
+                println!(
+                    "iterations per second:  {:.1}",
+                    calibration.iterations_per_sec
+                );
+                println!(
+                    "recommended iterations: {} for {}s",
+                    calibration.recommended_iterations,
+                    target.as_secs_f64()
+                );
+            }
+
+            if let Some(path) = config_path.filter(|_| write) {
+                let mut config = config.clone();
+                config.unicorn.iterations = calibration.recommended_iterations;
+                fs::write(path, config.to_toml())?;
+            }
+        }
+        Command::Simulate {
+            rounds,
+            participants,
+            seed,
+        } => {
+            let sim_config = SimConfig {
+                rounds,
+                participants,
+                seed,
+                fixed_params: config.unicorn.clone(),
+                usage_number: config.selection.usage_number as u128,
+            };
+            let vdf = UnicornVdf {
+                mode: config.daemon.eval_mode,
+            };
+            let report = simulate(&sim_config, &vdf)
+                .map_err(|e| fail(ErrorKind::InvalidParams, e.to_string()))?;
+
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                println!(
+                    "{} rounds, {} participants, {:.1} expected wins each",
+                    report.rounds,
+                    participants,
+                    report.expected_wins()
+                );
+                println!(
+                    "chi-squared: {:.3} ({} degrees of freedom)",
+                    report.chi_squared(),
+                    participants.saturating_sub(1)
+                );
+                println!("max deviation: {:.2}%", report.max_deviation() * 100.0);
+                println!(
+                    "eval time: {:.3}ms mean, {:.3}ms max",
+                    report.mean_eval_time().as_secs_f64() * 1000.0,
+                    report.max_eval_time.as_secs_f64() * 1000.0
+                );
+                println!();
+                for (identity, wins) in report.identities.iter().zip(&report.wins) {
+                    println!("{}  {}", identity, wins);
+                }
+            }
+        }
+        Command::Selftest => {
+            determinism_selftest().map_err(|e| fail(ErrorKind::VerifyFailed, e.to_string()))?;
+
+            if json {
+                println!("{}", json!({ "passed": true }));
+            } else {
+                println!("Determinism self-test passed");
+            }
+        }
+        Command::Bench { modulus_bits } => {
+            let modulus = match modulus_bits {
+                Some(bits) if bits < 3 => {
+                    return Err(fail(
+                        ErrorKind::InvalidParams,
+                        "Modulus must be at least 3 bits",
+                    ))
+                }
+                Some(bits) => calibration_modulus(bits),
+                None => Integer::from_str_radix(&config.unicorn.modulus, 10)?,
+            };
+            let benchmark = benchmark(&modulus)
+                .ok_or_else(|| fail(ErrorKind::InvalidParams, "Invalid UNICORN modulus"))?;
+
+            let iterations = config.unicorn.iterations;
+            let eval_time = benchmark.eval_time(iterations);
+            let verify_time = benchmark.verify_time(iterations);
+            let eval_budget = Duration::from_secs(config.daemon.eval_secs);
+
+            if json {
+                let output = json!({
+                    "benchmark": benchmark,
+                    "iterations": iterations,
+                    "eval_secs": eval_time.as_secs_f64(),
+                    "verify_secs": verify_time.as_secs_f64(),
+                    "eval_budget_secs": eval_budget.as_secs_f64(),
+                    "within_budget": eval_time <= eval_budget,
+                });
+                println!("{}", output);
+            } else {
+                println!(
+                    "modulus bits: {}, configured iterations: {}",
+                    benchmark.modulus_bits, iterations
+                );
+                println!();
+                println!(
+                    "{:<10} {:>22} {:>14} {:>10}",
+                    "operation", "rate", "round time", "budget"
+                );
+                println!(
+                    "{:<10} {:>22} {:>13.3}s {:>9}s",
+                    "eval",
+                    format!("{:.1} it/s", benchmark.eval_iterations_per_sec),
+                    eval_time.as_secs_f64(),
+                    eval_budget.as_secs()
+                );
+                println!(
+                    "{:<10} {:>22} {:>13.3}s {:>10}",
+                    "verify",
+                    format!("{:.1} it/s", benchmark.verify_iterations_per_sec),
+                    verify_time.as_secs_f64(),
+                    "-"
+                );
+                println!(
+                    "{:<10} {:>22} {:>14} {:>10}",
+                    "fortuna",
+                    format!("{:.1} MiB/s", benchmark.fortuna_bytes_per_sec / 1048576.0),
+                    "-",
+                    "-"
+                );
+                println!();
+                if eval_time <= eval_budget {
+                    println!("The eval fits within the configured eval timeout");
+                } else {
+                    println!(
+                        "The eval exceeds the configured eval timeout; rounds on this host \
+                         will abort"
+                    );
+                }
+            }
+        }
+        Command::CheckParams { params } => {
+            let warnings = check_recommended(&params.fixed_params(config)?);
+
+            if json {
+                println!("{}", serde_json::to_string(&warnings)?);
+            } else if warnings.is_empty() {
+                println!("Parameters meet the recommendations");
+            } else {
+                for warning in &warnings {
+                    println!("WARN {}", warning);
+                }
+            }
+        }
+        Command::Genmod {
+            bits,
+            rounds,
+            provable,
+            out,
+        } => {
+            if bits < 3 {
+                return Err(fail(
+                    ErrorKind::InvalidParams,
+                    "Modulus must be at least 3 bits",
+                ));
+            }
+
+            let mut entropy = [0; 32];
+            getrandom::getrandom(&mut entropy).map_err(|e| e.to_string())?;
+            let (modulus, proof) = match provable {
+                true => {
+                    let (modulus, proof) = generate_provable_modulus(bits, &entropy);
+                    (modulus, Some(proof))
+                }
+                false => (generate_modulus(bits, &entropy), None),
+            };
+            let mut certificate = ModulusCertificate::new(&modulus, rounds);
+            if let Some(proof) = proof {
+                certificate = certificate.and_then(|c| c.with_proof(proof));
+            }
+            let certificate = certificate.ok_or("Generated modulus failed certification")?;
+
+            if json {
+                println!("{}", serde_json::to_string(&certificate)?);
+            } else {
+                println!("modulus: {}", certificate.modulus);
+                println!("bits:    {}", certificate.bits);
+                println!(
+                    "passed {} Miller-Rabin rounds with bases derived from the modulus; \
+                     error probability at most 2^{}",
+                    certificate.rounds, certificate.error_bound_log2
+                );
+                if let Some(proof) = &certificate.proof {
+                    println!(
+                        "proven prime by a chain of {} Pocklington steps",
+                        proof.steps.len()
+                    );
+                }
+            }
+
+            if let Some(out) = out {
+                fs::write(out, serde_json::to_string_pretty(&certificate)?)?;
+            }
+        }
+        Command::Daemon {
+            first_round,
+            socket,
+            publish_dir,
+        } => {
+            let mut config = config.clone();
+            config.daemon.socket = socket.unwrap_or(config.daemon.socket);
+            config.daemon.publish_dir = publish_dir.unwrap_or(config.daemon.publish_dir);
+
+            warn_recommendations(&config);
+            run_daemon(config, first_round)?;
+        }
+        #[cfg(feature = "server")]
+        Command::Serve {
+            listen,
+            first_round,
+            #[cfg(feature = "grpc")]
+            grpc_listen,
+            api_keys,
+            #[cfg(feature = "signing")]
+            miner_keys,
+            #[cfg(feature = "sled")]
+            store,
+            #[cfg(feature = "sqlite")]
+            sqlite,
+        } => {
+            use miner_lottery::auth::ApiKeyAuthorizer;
+            use miner_lottery::server::{router, run_rounds, ServerState};
+
+            warn_recommendations(config);
+
+            let mut state = ServerState::new(config.clone(), first_round);
+            if let Some(path) = &config.daemon.audit_log {
+                state = state.with_audit_log(AuditLog::open(path)?);
+            }
+            if let Some(path) = api_keys {
+                let keys = serde_json::from_str(&read_source(&path)?)
+                    .map_err(|e| fail(ErrorKind::InvalidInput, e.to_string()))?;
+                state = state.with_authorizer(ApiKeyAuthorizer::new(keys));
+            }
+            #[cfg(feature = "signing")]
+            if let Some(path) = miner_keys {
+                let keys = read_source(&path)?
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                state = state.with_authorizer(miner_lottery::auth::SignatureAuthorizer::new(keys));
+            }
+            #[cfg(feature = "sled")]
+            if let Some(path) = store {
+                let store = miner_lottery::storage::SledStore::open(path)?;
+                state = state.with_store(store)?;
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(path) = sqlite {
+                let store = miner_lottery::storage::SqliteStore::open(path)?;
+                state = state.with_store(store)?;
+            }
+            let runtime = tokio::runtime::Runtime::new()?;
+            runtime.block_on(async {
+                let listener = tokio::net::TcpListener::bind(&listen).await?;
+                eprintln!("Listening on {}", listen);
+
+                tokio::spawn(run_rounds(state.clone()));
+                #[cfg(feature = "grpc")]
+                if let Some(addr) = grpc_listen {
+                    eprintln!("Serving gRPC on {}", addr);
+                    tokio::spawn(miner_lottery::grpc::serve(addr, state.clone()));
+                }
+                axum::serve(listener, router(state)).await
+            })?;
+        }
+        Command::ExportRound { round, out } => {
+            let round = Round::from_json(&read_source(&round)?)?;
+            let bundle =
+                export_round(&round).map_err(|e| fail(ErrorKind::InvalidInput, e.to_string()))?;
+
+            match out {
+                Some(out) => fs::write(out, bundle)?,
+                None => print!("{}", bundle),
+            }
+        }
+        Command::ImportRound { bundle } => {
+            let round = import_round(&read_source(&bundle)?).map_err(|e| match e {
+                BundleError::Malformed(_) | BundleError::UnsupportedVersion(_) => {
+                    fail(ErrorKind::InvalidInput, e.to_string())
+                }
+                _ => fail(ErrorKind::VerifyFailed, e.to_string()),
+            })?;
+
+            if json {
+                println!("{}", serde_json::to_string(&round)?);
+            } else {
+                let transcript = round.transcript.as_ref();
+                println!("Verified round {}", round.id);
+                println!("g:      {}", transcript.map_or("", |t| &t.g_value));
+                println!("winner: {}", transcript.map_or("", |t| &t.winner));
+            }
+        }
+        Command::VerifyLog { log, head, entries } => {
+            let expected = head
+                .zip(entries)
+                .map(|(hash, entries)| LogHead { entries, hash });
+            let head = verify_log(&read_source(&log)?, expected.as_ref())
+                .map_err(|e| fail(ErrorKind::VerifyFailed, e.to_string()))?;
+
+            if json {
+                println!("{}", serde_json::to_string(&head)?);
+            } else {
+                println!("Intact: {} entries, head {}", head.entries, head.hash);
+            }
+        }
+        Command::CompareSegments { ours, theirs } => {
+            let ours: SegmentTrace = serde_json::from_str(&read_source(&ours)?)?;
+            let theirs: SegmentTrace = serde_json::from_str(&read_source(&theirs)?)?;
+            let divergence = first_divergence(&ours, &theirs);
+
+            if json {
+                println!("{}", json!({ "divergence": divergence }));
+            } else if divergence.is_none() {
+                println!("Traces agree on all {} segments", ours.segments.len());
+            }
+            if let Some(divergence) = divergence {
+                return Err(fail(ErrorKind::VerifyFailed, divergence.to_string()));
+            }
+        }
+        Command::DiffRounds { a, b } => {
+            let a = Round::from_json(&read_source(&a)?)?;
+            let b = Round::from_json(&read_source(&b)?)?;
+            let diff = rounds::diff(&a, &b);
+
+            if json {
+                println!("{}", serde_json::to_string(&diff)?);
+            } else if diff.is_empty() {
+                println!("Rounds agree");
+            } else {
+                print!("{}", diff);
+            }
+            if !diff.is_empty() {
+                return Err(fail(ErrorKind::VerifyFailed, "Rounds differ"));
+            }
+        }
+        #[cfg(feature = "schema")]
+        Command::Schema { name } => {
+            use miner_lottery::schema::{schema, schemas};
+
+            let value = match name {
+                Some(name) => serde_json::to_value(schema(&name))?,
+                None => serde_json::to_value(schemas())?,
+            };
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        #[cfg(feature = "openapi")]
+        Command::Openapi => {
+            let document = miner_lottery::openapi::document();
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
+        Command::Watch { endpoint } => {
+            let endpoint = endpoint.unwrap_or_else(|| config.daemon.socket.clone());
+            watch(&endpoint, json)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes each parameter recommendation the configuration falls short of to stderr
+fn warn_recommendations(config: &LotteryConfig) {
+    for warning in config.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// Runs rounds until the process is stopped, serving the socket on its own thread. A round
+/// whose eval fails is aborted in favour of the next, and a round that cannot be written out
+/// is still published to subscribers
+fn run_daemon(config: LotteryConfig, first_round: u64) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&config.daemon.publish_dir)?;
+    let publish_dir = config.daemon.publish_dir.clone();
+    let socket = config.daemon.socket.clone();
+    let roughtime = config.daemon.roughtime.clone();
+    let audit_log: SharedLog = match &config.daemon.audit_log {
+        Some(path) => Some(Arc::new(Mutex::new(AuditLog::open(path)?))),
+        None => None,
     };
+    let daemon = Arc::new(Mutex::new(Daemon::new(config, first_round, unix_now())));
+    let subscribers = Subscribers::default();
+
+    serve_socket(
+        &socket,
+        Arc::clone(&daemon),
+        Arc::clone(&subscribers),
+        audit_log.clone(),
+    )?;
+    eprintln!("Listening on {}", socket.display());
+
+    loop {
+        let Some(unicorn) = poll_daemon(&daemon, roughtime.as_ref()) else {
+            thread::sleep(Duration::from_secs(1));
+            continue;
+        };
+        record(
+            &audit_log,
+            AuditEvent::sealed(daemon.lock().unwrap().round()),
+        );
+
+        let round_id = daemon.lock().unwrap().round().id;
+        let Some(info) = unicorn
+            .eval()
+            .map(|(witness, g_value)| UnicornInfo::new(unicorn, witness, g_value))
+        else {
+            daemon.lock().unwrap().abort(unix_now());
+            eprintln!("Eval of round {} failed; aborting it", round_id);
+            continue;
+        };
+
+        match daemon.lock().unwrap().finish(info, unix_now()) {
+            Ok(round) => {
+                for event in AuditEvent::published(&round) {
+                    record(&audit_log, event);
+                }
+                match write_published(&publish_dir, &round) {
+                    Ok(path) => eprintln!("Published round {} to {}", round.id, path.display()),
+                    Err(e) => eprintln!("Could not publish round {}: {}", round.id, e),
+                }
+
+                let line = PublishedRound::from_round(&round)
+                    .and_then(|p| serde_json::to_string(&DaemonResponse::Published(p)).ok());
+                if let Some(line) = line {
+                    broadcast(&subscribers, &line);
+                }
+            }
+            Err(e) => eprintln!("Round failed: {}", e),
+        }
+    }
+}
+
+/// Writes a published round and its input manifest to the publish directory, returning the
+/// path of the round
+///
+/// ### Arguments
+///
+/// * `publish_dir` - Directory published rounds are written to
+/// * `round`       - Published round
+fn write_published(publish_dir: &Path, round: &Round) -> Result<PathBuf, Box<dyn Error>> {
+    let path = publish_dir.join(format!("round-{}.json", round.id));
+    fs::write(&path, round.to_json_pretty()?)?;
+    let manifest = InputManifest::from_round(round);
+    let manifest_path = publish_dir.join(format!("manifest-{}.json", round.id));
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+
+    Ok(path)
+}
+
+/// Advances the daemon's current round, having its cutoff attested by the Roughtime server
+/// if one is configured
+#[cfg_attr(not(feature = "roughtime"), allow(unused_variables))]
+fn poll_daemon(
+    daemon: &Mutex<Daemon>,
+    roughtime: Option<&RoughtimeConfig>,
+) -> Option<unicorn::Unicorn> {
+    #[cfg(feature = "roughtime")]
+    if let Some(server) = roughtime {
+        let nonce = daemon.lock().unwrap().cutoff_nonce(unix_now());
+        if let Some(nonce) = nonce {
+            let timeout = Duration::from_secs(5);
+            return match miner_lottery::roughtime::query(
+                &server.address,
+                &server.public_key,
+                &nonce,
+                timeout,
+            ) {
+                Ok(attestation) => daemon.lock().unwrap().poll_attested(attestation),
+                Err(e) => {
+                    eprintln!("Could not attest round cutoff: {}", e);
+                    None
+                }
+            };
+        }
+    }
+
+    daemon.lock().unwrap().poll(unix_now())
+}
+
+/// Audit log shared by the daemon's threads, if one is configured
+type SharedLog = Option<Arc<Mutex<AuditLog>>>;
+
+/// Appends an event to the audit log, if one is configured
+fn record(log: &SharedLog, event: AuditEvent) {
+    if let Some(log) = log {
+        if let Err(e) = log.lock().unwrap().append(event, unix_now()) {
+            eprintln!("Could not append to audit log: {}", e);
+        }
+    }
+}
+
+/// Connections subscribed to published rounds
+#[cfg(unix)]
+type Subscribers = Arc<Mutex<Vec<std::os::unix::net::UnixStream>>>;
+#[cfg(not(unix))]
+type Subscribers = Arc<Mutex<Vec<()>>>;
+
+/// Sends a line to every subscriber, dropping those that have disconnected
+#[cfg(unix)]
+fn broadcast(subscribers: &Subscribers, line: &str) {
+    use std::io::Write;
+
+    subscribers
+        .lock()
+        .unwrap()
+        .retain_mut(|stream| writeln!(stream, "{}", line).is_ok());
+}
+
+#[cfg(not(unix))]
+fn broadcast(_subscribers: &Subscribers, _line: &str) {}
+
+/// Answers requests on the daemon's local socket, one JSON object per line
+#[cfg(unix)]
+fn serve_socket(
+    path: &Path,
+    daemon: Arc<Mutex<Daemon>>,
+    subscribers: Subscribers,
+    audit_log: SharedLog,
+) -> Result<(), Box<dyn Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous run would otherwise fail the bind
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let daemon = Arc::clone(&daemon);
+            let subscribers = Arc::clone(&subscribers);
+            let audit_log = audit_log.clone();
+            thread::spawn(move || {
+                let mut writer = &stream;
+                for line in BufReader::new(&stream).lines() {
+                    let Ok(line) = line else { break };
+                    let response = match serde_json::from_str::<DaemonRequest>(&line) {
+                        Ok(request) => {
+                            let subscribe = request == DaemonRequest::Subscribe;
+                            let contribution = match &request {
+                                DaemonRequest::Contribute(c) => Some(c.clone()),
+                                _ => None,
+                            };
+                            let response = daemon.lock().unwrap().handle(request, unix_now());
+                            if let (Some(c), DaemonResponse::Accepted { fingerprint }) =
+                                (contribution, &response)
+                            {
+                                let event = AuditEvent::ContributionAccepted {
+                                    round_id: c.round_id,
+                                    identity: c.identity,
+                                    fingerprint: fingerprint.clone(),
+                                };
+                                record(&audit_log, event);
+                            }
+                            if subscribe {
+                                if let Ok(stream) = stream.try_clone() {
+                                    subscribers.lock().unwrap().push(stream);
+                                }
+                            }
+                            response
+                        }
+                        Err(e) => DaemonResponse::Error {
+                            message: e.to_string(),
+                        },
+                    };
+                    let response = serde_json::to_string(&response).unwrap();
+                    if writeln!(writer, "{}", response).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(
+    _path: &Path,
+    _daemon: Arc<Mutex<Daemon>>,
+    _subscribers: Subscribers,
+    _audit_log: SharedLog,
+) -> Result<(), Box<dyn Error>> {
+    Err(fail(
+        ErrorKind::Other,
+        "The daemon socket is only supported on unix",
+    ))
+}
+
+/// Subscribes to a daemon and prints each published round until the daemon goes away
+#[cfg(unix)]
+fn watch(endpoint: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(endpoint)?;
+    writeln!(
+        stream,
+        "{}",
+        serde_json::to_string(&DaemonRequest::Subscribe)?
+    )?;
+
+    for line in BufReader::new(&stream).lines() {
+        let round = match serde_json::from_str::<DaemonResponse>(&line?)? {
+            DaemonResponse::Subscribed {
+                latest: Some(round),
+            }
+            | DaemonResponse::Published(round) => round,
+            DaemonResponse::Error { message } => return Err(message.into()),
+            _ => continue,
+        };
+
+        if json {
+            println!("{}", serde_json::to_string(&round)?);
+        } else {
+            println!(
+                "round {}: g {} winner {} (index {})",
+                round.round_id, round.g_value, round.winner, round.winner_index
+            );
+        }
+    }
+
+    Ok(())
+}
 
-    let seed = unicorn::construct_seed(&inputs);
-    let unicorn_info = unicorn::construct_unicorn(seed, &fixed_params);
-    let prn = get_unicorn_prn(&unicorn_info, 0);
-    let selection = prn as usize % inputs.len();
+#[cfg(not(unix))]
+fn watch(_endpoint: &Path, _json: bool) -> Result<(), Box<dyn Error>> {
+    Err(fail(
+        ErrorKind::Other,
+        "The daemon socket is only supported on unix",
+    ))
+}
+
+/// Current unix time in seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Sets the weights of the inputs from a `key,weight` list. Every key in the list must be
+/// one of the inputs
+fn apply_weights(entries: &mut [InputEntry], text: &str) -> Result<(), Box<dyn Error>> {
+    for weighted in parse_inputs(text)?.entries {
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.identity == weighted.identity)
+            .ok_or_else(|| {
+                fail(
+                    ErrorKind::InvalidInput,
+                    format!("Weighted key `{}` is not an input", weighted.identity),
+                )
+            })?;
+        entry.weight = weighted.weight;
+    }
+
+    Ok(())
+}
+
+/// Audits a published artifact: a round, a selection transcript or a bare `UnicornInfo`
+fn audit_artifact(contents: &str) -> Result<AuditReport, Box<dyn Error>> {
+    Artifact::from_json(contents)?
+        .audit()
+        .ok_or_else(|| fail(ErrorKind::InvalidInput, "Round has not been published"))
+}
+
+/// Witness, `g` value and audit sidecar, if requested, of an eval
+type EvalResult = (Integer, String, Option<SegmentTrace>);
+
+/// Checkpoint file of an eval and the iterations between writes to it
+struct EvalCheckpointing {
+    path: PathBuf,
+    interval: u64,
+}
+
+/// Eval state written to a checkpoint file, with the commitment of the UNICORN it belongs to
+#[derive(Serialize, Deserialize)]
+struct EvalCheckpoint {
+    commitment: String,
+    state: EvalState,
+}
+
+/// Minimum time between progress lines on stderr
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Follows a running eval, reporting its progress and flushing checkpoints
+struct EvalReporter {
+    /// Iterations of the eval
+    iterations: u64,
+    /// Commitment of the UNICORN, to tell its checkpoints from those of other evals
+    commitment: String,
+    /// State to resume the eval from
+    resume: Option<EvalState>,
+    checkpoint: Option<EvalCheckpointing>,
+    /// Iterations done when the last checkpoint was written
+    checkpointed: u64,
+    progress: bool,
+    last_report: Option<Instant>,
+}
+
+impl EvalReporter {
+    /// Creates the reporter of an eval, reading the state to resume from if the checkpoint
+    /// file exists
+    fn new(
+        unicorn: &unicorn::Unicorn,
+        checkpoint: Option<EvalCheckpointing>,
+        progress: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let commitment = unicorn.commitment();
+        let resume = match &checkpoint {
+            Some(c) if c.path.exists() => {
+                let stored: EvalCheckpoint = serde_json::from_str(&fs::read_to_string(&c.path)?)?;
+                if stored.commitment != commitment {
+                    return Err(fail(
+                        ErrorKind::InvalidInput,
+                        "Checkpoint is of the eval of another UNICORN",
+                    ));
+                }
+                if stored.state.iterations_done > unicorn.iterations {
+                    return Err(fail(
+                        ErrorKind::InvalidInput,
+                        "Checkpoint is past the last UNICORN iteration",
+                    ));
+                }
+                Some(stored.state)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            iterations: unicorn.iterations,
+            commitment,
+            checkpointed: resume.as_ref().map_or(0, |s| s.iterations_done),
+            resume,
+            checkpoint,
+            progress,
+            last_report: None,
+        })
+    }
+
+    /// Called with the eval's state at each progress report
+    fn report(&mut self, state: &EvalState) {
+        if self.progress
+            && (state.iterations_done == self.iterations
+                || self
+                    .last_report
+                    .is_none_or(|t| t.elapsed() >= PROGRESS_REPORT_INTERVAL))
+        {
+            self.last_report = Some(Instant::now());
+            eprintln!(
+                "eval: {}/{} iterations ({:.1}%)",
+                state.iterations_done,
+                self.iterations,
+                state.iterations_done as f64 * 100.0 / self.iterations.max(1) as f64
+            );
+        }
+
+        let Some(checkpoint) = &self.checkpoint else {
+            return;
+        };
+        let due = checkpoint.interval > 0
+            && state.iterations_done >= self.checkpointed.saturating_add(checkpoint.interval);
+        if due && state.iterations_done < self.iterations {
+            self.checkpointed = state.iterations_done;
+            let stored = EvalCheckpoint {
+                commitment: self.commitment.clone(),
+                state: state.clone(),
+            };
+            if let Err(e) = write_checkpoint(&checkpoint.path, &stored) {
+                eprintln!("Could not write checkpoint: {}", e);
+            }
+        }
+    }
+
+    /// Removes the checkpoint file once the eval has completed
+    fn finish(&self) {
+        if let Some(checkpoint) = &self.checkpoint {
+            let _ = fs::remove_file(&checkpoint.path);
+        }
+    }
+}
+
+/// Writes a checkpoint next to its file and renames it into place, so that an interrupted
+/// write never leaves a truncated checkpoint behind
+fn write_checkpoint(path: &Path, checkpoint: &EvalCheckpoint) -> Result<(), Box<dyn Error>> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, serde_json::to_string(checkpoint)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Runs the eval in the given mode, recording segment hashes every `segment_interval` iterations
+/// if given, and cancelling it if it has not completed by the timeout. Without segment hashes,
+/// the eval resumes from and reports to `reporter`
+fn eval_with_timeout(
+    unicorn: &unicorn::Unicorn,
+    mode: EvalMode,
+    segment_interval: Option<u64>,
+    mut reporter: EvalReporter,
+    timeout: Option<Duration>,
+) -> Result<EvalResult, Box<dyn Error>> {
+    let invalid = || fail(ErrorKind::InvalidParams, "Invalid UNICORN modulus");
+    let mut eval = move |unicorn: &unicorn::Unicorn| match segment_interval {
+        None => {
+            let state = reporter
+                .resume
+                .take()
+                .unwrap_or_else(|| unicorn.start_state());
+            let result = unicorn.eval_from_with_mode(state, mode, |s| reporter.report(s));
+            if result.is_some() {
+                reporter.finish();
+            }
+            result.map(|(w, g)| (w, g, None))
+        }
+        Some(interval) => {
+            eval_with_segments(unicorn, mode, interval).map(|(w, g, t)| (w, g, Some(t)))
+        }
+    };
+    let Some(timeout) = timeout else {
+        return eval(unicorn).ok_or_else(invalid);
+    };
+
+    // The eval cannot be interrupted, so it is left running on its own thread and the
+    // process exits without waiting for it
+    let (tx, rx) = mpsc::channel();
+    let unicorn = unicorn.clone();
+    thread::spawn(move || tx.send(eval(&unicorn)));
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result.ok_or_else(invalid),
+        Err(_) => Err(fail(
+            ErrorKind::EvalCancelled,
+            format!("Eval cancelled after {}s", timeout.as_secs_f64()),
+        )),
+    }
+}
+
+/// Reads a file, or stdin for `-`
+fn read_source(path: &Path) -> Result<String, Box<dyn Error>> {
+    if path == Path::new("-") {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        return Ok(contents);
+    }
+
+    Ok(fs::read_to_string(path)?)
+}
+
+/// Parses a duration given as a number followed by `ms`, `s`, `m` or `h`
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration `{}`", value))?;
+
+    match unit {
+        "ms" => Some(Duration::from_millis(amount)),
+        "s" | "" => Some(Duration::from_secs(amount)),
+        "m" => amount.checked_mul(60).map(Duration::from_secs),
+        "h" => amount.checked_mul(3600).map(Duration::from_secs),
+        _ => return Err(format!("Unknown duration unit `{}`", unit)),
+    }
+    .ok_or_else(|| format!("Duration `{}` is too long", value))
+}
+
+/// Reads an evaluated UNICORN from a file, or stdin for `-`. With parameters, the file is
+/// read in the JSON mode schema and combined with them; otherwise it holds a `UnicornInfo`
+fn read_unicorn(
+    path: &Path,
+    params: Option<&ParamArgs>,
+    config: &LotteryConfig,
+) -> Result<UnicornInfo, Box<dyn Error>> {
+    let contents = read_source(path)?;
+
+    let Some(params) = params else {
+        return Ok(serde_json::from_str(&contents)?);
+    };
+
+    let output: EvalOutput = serde_json::from_str(&contents)?;
+    let fixed_params = params.fixed_params(config)?;
+
+    let unicorn = unicorn::Unicorn {
+        seed: Integer::from_str_radix(&output.seed, 16)?,
+        modulus: Integer::from_str_radix(&fixed_params.modulus, 10)?,
+        iterations: fixed_params.iterations,
+        security_level: fixed_params.security,
+        primality_rounds: fixed_params.primality_rounds,
+    };
+    let witness = Integer::from_str_radix(&output.witness, 16)?;
 
-    println!("Selected: {}", inputs[selection]);
+    Ok(UnicornInfo::new(unicorn, witness, output.g))
 }
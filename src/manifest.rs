@@ -0,0 +1,270 @@
+//! Manifest of the inputs a seed was constructed from.
+//!
+//! Each entry records the normalized bytes of one input, where it came from and its digest,
+//! `SHA-256(0x00 || bytes)` as used for tree-hashed seeds and Merkle leaves. Participants are
+//! listed in the registry's canonical order, followed by the winning PoW hashes in the order
+//! they were bound into the seed. The manifest rebuilds the exact pre-image of the seed, so
+//! its hash is the seed itself, and a contributor holding only the manifest can check that
+//! their input was included unmodified.
+
+use crate::merkle::{root_preimage, MerkleTree};
+use crate::participants::{normalize_identity, ParticipantRegistry};
+use crate::rounds::Round;
+use crate::unicorn::{input_digest, seed_preimage, SeedMode};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ManifestError {
+    /// Entry bytes are not hex-encoded UTF-8.
+    MalformedEntry { index: usize },
+    /// Entry hash is not the digest of its bytes.
+    HashMismatch { index: usize },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::MalformedEntry { index } => {
+                write!(f, "Manifest entry {} is not hex-encoded UTF-8", index)
+            }
+            ManifestError::HashMismatch { index } => {
+                write!(f, "Manifest entry {} does not match its hash", index)
+            }
+        }
+    }
+}
+
+impl Error for ManifestError {}
+
+/// Where a seed input came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// Identity of a registered participant
+    Participant,
+    /// Hash of a winning PoW from 2 blocks ago
+    PowHash,
+}
+
+/// A single input of the seed
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ManifestEntry {
+    pub source: InputSource,
+    /// Hex-encoded normalized bytes of the input
+    pub bytes: String,
+    /// Hex-encoded digest of the bytes
+    pub hash: String,
+}
+
+impl ManifestEntry {
+    /// Records an input that is already normalized
+    ///
+    /// ### Arguments
+    ///
+    /// * `source` - Where the input came from
+    /// * `input`  - Normalized input
+    pub fn new(source: InputSource, input: &str) -> Self {
+        Self {
+            source,
+            bytes: hex::encode(input.as_bytes()),
+            hash: hex::encode(input_digest(input)),
+        }
+    }
+
+    /// Decodes the normalized input
+    fn input(&self) -> Option<String> {
+        String::from_utf8(hex::decode(&self.bytes).ok()?).ok()
+    }
+}
+
+/// Every input of a seed along with how they were combined
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InputManifest {
+    /// How the seed is built from the participants
+    pub seed_mode: SeedMode,
+    /// Whether the seed commits to the Merkle root of the participants
+    pub commit_merkle_root: bool,
+    /// Participants in canonical order, then the winning PoW hashes
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl InputManifest {
+    /// Records the inputs of a seed built from the registered participants
+    ///
+    /// ### Arguments
+    ///
+    /// * `registry`            - Registered participants
+    /// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+    /// * `seed_mode`           - How the seed is built from the participants
+    /// * `commit_merkle_root`  - Whether the seed commits to the Merkle root instead
+    pub fn new(
+        registry: &ParticipantRegistry,
+        last_winning_hashes: &[String],
+        seed_mode: SeedMode,
+        commit_merkle_root: bool,
+    ) -> Self {
+        let participants = registry
+            .seed_inputs()
+            .into_iter()
+            .map(|i| ManifestEntry::new(InputSource::Participant, &i));
+        let pow_hashes = last_winning_hashes
+            .iter()
+            .map(|h| ManifestEntry::new(InputSource::PowHash, h));
+
+        Self {
+            seed_mode,
+            commit_merkle_root,
+            entries: participants.chain(pow_hashes).collect(),
+        }
+    }
+
+    /// Records the inputs of a round's seed
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round whose seed is recorded
+    pub fn from_round(round: &Round) -> Self {
+        Self::new(
+            &round.registry,
+            &round.last_winning_hashes,
+            round.seed_mode,
+            round.commit_merkle_root,
+        )
+    }
+
+    /// Checks that every entry decodes and matches its hash
+    pub fn verify(&self) -> Result<(), ManifestError> {
+        for (index, entry) in self.entries.iter().enumerate() {
+            let input = entry
+                .input()
+                .ok_or(ManifestError::MalformedEntry { index })?;
+            if hex::encode(input_digest(&input)) != entry.hash {
+                return Err(ManifestError::HashMismatch { index });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the exact bytes hashed into the seed, once the entries are verified
+    pub fn preimage(&self) -> Result<Vec<u8>, ManifestError> {
+        self.verify()?;
+        let inputs = |source: InputSource| -> Vec<String> {
+            self.entries
+                .iter()
+                .filter(|e| e.source == source)
+                .filter_map(ManifestEntry::input)
+                .collect()
+        };
+        let participants = inputs(InputSource::Participant);
+        let pow_hashes = inputs(InputSource::PowHash);
+
+        Ok(match (self.commit_merkle_root, self.seed_mode) {
+            (true, _) => root_preimage(&MerkleTree::new(&participants).root(), &pow_hashes),
+            (false, mode) => seed_preimage(mode, &participants, &pow_hashes),
+        })
+    }
+
+    /// Hex-encoded hash of the manifest, which is the seed it records
+    pub fn hash(&self) -> Result<String, ManifestError> {
+        Ok(hex::encode(Sha256::digest(self.preimage()?)))
+    }
+
+    /// Seed recorded by the manifest
+    pub fn seed(&self) -> Result<Integer, ManifestError> {
+        Ok(Integer::from_str_radix(&self.hash()?, 16).unwrap())
+    }
+
+    /// Finds the entry of a participant, checking that it holds their identity unmodified
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity as submitted; normalized before lookup
+    pub fn find_participant(&self, identity: &str) -> Option<usize> {
+        let expected = ManifestEntry::new(InputSource::Participant, &normalize_identity(identity));
+        self.entries.iter().position(|e| *e == expected)
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod manifest_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn sealed_round(seed_mode: SeedMode, commit_merkle_root: bool) -> Round {
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 600,
+        };
+        let mut round = Round::new(3, UnicornFixedParam::default(), 1, timeouts, 0)
+            .with_seed_mode(seed_mode)
+            .with_last_winning_hashes(vec!["0a0b".to_string(), "0c0d".to_string()]);
+        if commit_merkle_root {
+            round = round.with_merkle_root();
+        }
+        for identity in ["miner_a", " a1b2 ", "miner_c"] {
+            round.contribute(identity, 0).unwrap();
+        }
+        round.seal().unwrap();
+        round
+    }
+
+    #[test]
+    /// Checks that the manifest hashes to the seed in every mode
+    fn hash_is_seed() {
+        for (mode, root) in [
+            (SeedMode::Serialized, false),
+            (SeedMode::TreeHash, false),
+            (SeedMode::Serialized, true),
+        ] {
+            let round = sealed_round(mode, root);
+            let manifest = InputManifest::from_round(&round);
+
+            assert_eq!(manifest.seed(), Ok(round.seed.clone()));
+            assert_eq!(manifest.entries.len(), 5);
+        }
+
+        let round = sealed_round(SeedMode::Serialized, false);
+        let manifest = InputManifest::from_round(&round);
+        assert_eq!(
+            manifest.preimage().unwrap(),
+            seed_preimage(
+                SeedMode::Serialized,
+                &round.registry.seed_inputs(),
+                &round.last_winning_hashes
+            )
+        );
+    }
+
+    #[test]
+    /// Checks that contributors find their normalized input, and that modified entries are
+    /// rejected
+    fn contributor_inclusion() {
+        let manifest = InputManifest::from_round(&sealed_round(SeedMode::TreeHash, false));
+
+        let index = manifest.find_participant("a1b2").unwrap();
+        assert_eq!(manifest.entries[index].bytes, hex::encode("a1b2"));
+        assert!(manifest.find_participant("miner_a").is_some());
+        assert_eq!(manifest.find_participant("miner_d"), None);
+        assert_eq!(manifest.find_participant("0a0b"), None);
+
+        let mut tampered = manifest.clone();
+        tampered.entries[index].bytes = hex::encode("a1b3");
+        assert_eq!(tampered.seed(), Err(ManifestError::HashMismatch { index }));
+        tampered.entries[index].bytes = "zz".to_string();
+        assert_eq!(
+            tampered.verify(),
+            Err(ManifestError::MalformedEntry { index })
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Merkle tree over the canonical participant list.
+//!
+//! Leaves are the SHA-256 of the identity bytes prefixed with `0x00`, and inner nodes the
+//! SHA-256 of both children prefixed with `0x01`, so that a leaf can never be passed off as an
+//! inner node. A node without a sibling is promoted to the next level unchanged rather than
+//! paired with itself, so no two participant lists share a root.
+//!
+//! When the root is committed into the seed, a verifier holding only the root can check a
+//! winner's inclusion proof without downloading the full participant list. The same goes for
+//! any other participant: `prove_inclusion` gives a miner the proof for their own key, which
+//! they check with `verify_inclusion` against the root bound into the seed.
+
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+type Hash = [u8; 32];
+
+/// Merkle tree over a list of identities
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct MerkleTree {
+    /// Levels of the tree, from the leaves up to the root
+    levels: Vec<Vec<Hash>>,
+}
+
+/// Proof that an identity is a leaf of a Merkle tree
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct InclusionProof {
+    /// Identity the proof is for
+    #[serde(default)]
+    pub input: String,
+    /// Index of the leaf in the canonical list
+    pub index: usize,
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Hex-encoded sibling hashes from the leaf level up; promoted levels have no sibling
+    pub siblings: Vec<String>,
+}
+
+impl MerkleTree {
+    /// Builds the tree over identities in canonical order
+    ///
+    /// ### Arguments
+    ///
+    /// * `identities` - Identities in canonical order
+    pub fn new(identities: &[String]) -> Self {
+        let mut levels = vec![identities.iter().map(|i| leaf_hash(i)).collect::<Vec<_>>()];
+
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Self { levels }
+    }
+
+    /// Hex-encoded root of the tree. The root of an empty tree is the hash of no bytes
+    pub fn root(&self) -> String {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => hex::encode(root),
+            None => hex::encode(Sha256::digest([])),
+        }
+    }
+
+    /// Number of leaves in the tree
+    pub fn leaf_count(&self) -> usize {
+        self.levels.first().map_or(0, |leaves| leaves.len())
+    }
+
+    /// Creates the inclusion proof for an identity, or `None` if it is not a leaf
+    ///
+    /// ### Arguments
+    ///
+    /// * `input` - Identity, as it was given when building the tree
+    pub fn prove_inclusion(&self, input: &str) -> Option<InclusionProof> {
+        let leaf = leaf_hash(input);
+        let index = self.levels.first()?.iter().position(|l| *l == leaf)?;
+        Some(self.proof(index, input))
+    }
+
+    /// Creates the inclusion proof for the leaf at an index
+    fn proof(&self, index: usize, input: &str) -> InclusionProof {
+        let mut siblings = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(hex::encode(sibling));
+            }
+            position /= 2;
+        }
+
+        InclusionProof {
+            input: input.to_string(),
+            index,
+            leaf_count: self.leaf_count(),
+            siblings,
+        }
+    }
+}
+
+impl InclusionProof {
+    /// Checks that an identity is included under a root
+    ///
+    /// ### Arguments
+    ///
+    /// * `root`     - Hex-encoded Merkle root
+    /// * `identity` - Identity claimed to be included
+    pub fn verify(&self, root: &str, identity: &str) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+
+        let mut hash = leaf_hash(identity);
+        let mut siblings = self.siblings.iter();
+        let mut position = self.index;
+        let mut width = self.leaf_count;
+
+        while width > 1 {
+            // The last node of an odd-width level has no sibling and is promoted
+            if position ^ 1 < width {
+                let sibling: Hash = match siblings.next().map(hex::decode) {
+                    Some(Ok(bytes)) => match bytes.try_into() {
+                        Ok(sibling) => sibling,
+                        Err(_) => return false,
+                    },
+                    _ => return false,
+                };
+
+                hash = if position.is_multiple_of(2) {
+                    node_hash(&hash, &sibling)
+                } else {
+                    node_hash(&sibling, &hash)
+                };
+            }
+
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+
+        siblings.next().is_none() && hex::encode(hash) == root
+    }
+}
+
+/// Checks that the identity an inclusion proof is for is a leaf under a Merkle root
+///
+/// ### Arguments
+///
+/// * `proof`     - Inclusion proof from `prove_inclusion`
+/// * `seed_root` - Hex-encoded Merkle root the seed commits to
+pub fn verify_inclusion(proof: &InclusionProof, seed_root: &str) -> bool {
+    proof.verify(seed_root, &proof.input)
+}
+
+/// Constructs a seed committing to a Merkle root of the participants rather than the full
+/// list, binding the hashes of the winning PoWs from 2 blocks ago alongside it
+///
+/// ### Arguments
+///
+/// * `merkle_root`         - Hex-encoded Merkle root of the participants
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn construct_seed_with_root(merkle_root: &str, last_winning_hashes: &[String]) -> Integer {
+    let seed = hex::encode(Sha256::digest(root_preimage(
+        merkle_root,
+        last_winning_hashes,
+    )));
+    Integer::from_str_radix(&seed, 16).unwrap()
+}
+
+/// Exact bytes hashed into a seed committing to a Merkle root
+///
+/// ### Arguments
+///
+/// * `merkle_root`         - Hex-encoded Merkle root of the participants
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn root_preimage(merkle_root: &str, last_winning_hashes: &[String]) -> Vec<u8> {
+    serialize(&(merkle_root, last_winning_hashes)).unwrap()
+}
+
+fn leaf_hash(identity: &str) -> Hash {
+    Sha256::new()
+        .chain_update([0])
+        .chain_update(identity.as_bytes())
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod merkle_tests {
+    use super::*;
+
+    fn identities(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("miner_{}", i)).collect()
+    }
+
+    #[test]
+    /// Checks that every leaf of trees of various sizes has a valid proof
+    fn proofs_verify() {
+        for n in 1..=9 {
+            let ids = identities(n);
+            let tree = MerkleTree::new(&ids);
+
+            for (i, id) in ids.iter().enumerate() {
+                let proof = tree.prove_inclusion(id).unwrap();
+                assert_eq!(proof.index, i);
+                assert!(verify_inclusion(&proof, &tree.root()));
+                assert!(!proof.verify(&tree.root(), "miner_x"));
+            }
+            assert_eq!(tree.prove_inclusion("miner_x"), None);
+        }
+    }
+
+    #[test]
+    /// Checks that proofs for another position or tree size are refused
+    fn tampered_proofs() {
+        let ids = identities(5);
+        let tree = MerkleTree::new(&ids);
+        let proof = tree.prove_inclusion(&ids[4]).unwrap();
+
+        let mut swapped = proof.clone();
+        swapped.input = ids[3].clone();
+        let mut moved = proof.clone();
+        moved.index = 3;
+        let mut resized = proof.clone();
+        resized.leaf_count = 6;
+
+        assert!(proof.verify(&tree.root(), &ids[4]));
+        assert!(!verify_inclusion(&swapped, &tree.root()));
+        assert!(!moved.verify(&tree.root(), &ids[4]));
+        assert!(!resized.verify(&tree.root(), &ids[4]));
+        assert_ne!(MerkleTree::new(&identities(4)).root(), tree.root());
+    }
+}
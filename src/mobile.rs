@@ -0,0 +1,137 @@
+//! Kotlin and Swift bindings for mobile wallets through UniFFI, built with the `uniffi`
+//! feature.
+//!
+//! Only verification is exposed: a wallet passes in a published round, selection transcript
+//! or `UnicornInfo` as served by the HTTP API and gets back whether the lottery verified,
+//! without trusting the node that served it. The bindings are generated from the built
+//! library with the `uniffi-bindgen` binary, as configured in `uniffi.toml`:
+//!
+//! ```text
+//! cargo build --release --features uniffi
+//! cargo run --features uniffi --bin uniffi-bindgen -- generate \
+//!     --library target/release/libminer_lottery.so --language kotlin --out-dir out
+//! ```
+
+use crate::audit::{Artifact, AuditReport};
+use std::error::Error;
+use std::fmt;
+
+/// Outcome of verifying a published artifact
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Verification {
+    /// Whether every check passed
+    pub passed: bool,
+    /// Kind of the verified artifact: `round`, `transcript` or `unicorn`
+    pub kind: String,
+    /// The `g` value of the UNICORN the outcome was drawn from
+    pub g_value: Option<String>,
+    /// The winner, if the artifact records a draw. Only meaningful if `passed`
+    pub winner: Option<String>,
+    /// Descriptions of the failed checks
+    pub failures: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Error)]
+pub enum VerifyError {
+    /// The JSON is not a round, transcript or UNICORN.
+    InvalidArtifact { reason: String },
+    /// The round has not been published, so there is nothing to verify.
+    Unpublished,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidArtifact { reason } => {
+                write!(f, "Not a round, transcript or UNICORN: {}", reason)
+            }
+            VerifyError::Unpublished => write!(f, "Round has not been published"),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Verifies a published round, selection transcript or `UnicornInfo` given as JSON
+///
+/// ### Arguments
+///
+/// * `json` - JSON of the artifact
+#[uniffi::export]
+pub fn verify_artifact(json: String) -> Result<Verification, VerifyError> {
+    let artifact = Artifact::from_json(&json).map_err(|e| VerifyError::InvalidArtifact {
+        reason: e.to_string(),
+    })?;
+    let report = artifact.audit().ok_or(VerifyError::Unpublished)?;
+
+    Ok(verification(&artifact, &report))
+}
+
+fn verification(artifact: &Artifact, report: &AuditReport) -> Verification {
+    let transcript = artifact.transcript();
+    let (kind, g_value) = match artifact {
+        Artifact::Round(_) => ("round", transcript.map(|t| t.g_value.clone())),
+        Artifact::Transcript(t) => ("transcript", Some(t.g_value.clone())),
+        Artifact::Unicorn(info) => ("unicorn", Some(info.g_value.clone())),
+    };
+
+    Verification {
+        passed: report.passed(),
+        kind: kind.to_string(),
+        g_value,
+        winner: transcript.map(|t| t.winner.clone()),
+        failures: report
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| match &c.detail {
+                Some(detail) => format!("{:?}: {}", c.check, detail),
+                None => format!("{:?}", c.check),
+            })
+            .collect(),
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod mobile_tests {
+    use super::*;
+    use crate::rounds::{Round, RoundTimeouts};
+    use crate::unicorn::UnicornFixedParam;
+
+    #[test]
+    /// Checks that a published round verifies, a tampered one reports its failures and an
+    /// unpublished one is refused
+    fn verifies_artifacts() {
+        let params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 600,
+        };
+        let mut round = Round::new(7, params, 1, timeouts, 0);
+        assert_eq!(
+            verify_artifact(round.to_json_pretty().unwrap()),
+            Err(VerifyError::Unpublished)
+        );
+
+        round.contribute("miner_a", 0).unwrap();
+        round.seal().unwrap();
+        let transcript = round.evaluate(0).unwrap().clone();
+        let verification = verify_artifact(round.to_json_pretty().unwrap()).unwrap();
+        assert!(verification.passed);
+        assert_eq!(verification.kind, "round");
+        assert_eq!(verification.winner, Some("miner_a".to_string()));
+        assert_eq!(verification.g_value, Some(transcript.g_value.clone()));
+
+        let mut tampered = transcript;
+        tampered.g_value = "00".to_string();
+        let verification = verify_artifact(serde_json::to_string(&tampered).unwrap()).unwrap();
+        assert!(!verification.passed);
+        assert!(!verification.failures.is_empty());
+        assert!(matches!(
+            verify_artifact("{}".to_string()),
+            Err(VerifyError::InvalidArtifact { .. })
+        ));
+    }
+}
@@ -0,0 +1,495 @@
+//! Generation and certification of UNICORN moduli.
+//!
+//! A UNICORN modulus must be a large prime congruent to 3 mod 4. Generation expands
+//! caller-supplied entropy with SHA-256 into a random candidate of the requested size and
+//! steps through those congruent to 3 mod 4 until one is prime.
+//!
+//! A `ModulusCertificate` records the Miller-Rabin rounds a modulus passed. The bases are
+//! derived by hashing the modulus rather than chosen by the generator, so anyone can rerun
+//! exactly the same rounds, and a composite modulus passes `rounds` independent rounds with
+//! probability at most `4^-rounds`.
+//!
+//! For moduli baked into production configurations, a certificate can also carry a
+//! `PrimalityProof`, which makes it deterministic. `generate_provable_modulus` builds the
+//! modulus `p` as `2qR + 1` around a prime `q` larger than `sqrt(p)`, so that Pocklington's
+//! theorem proves `p` prime given a proof for `q`. The chain of such steps ends at a prime
+//! of at most 64 bits, which is proven by BPSW, as it has no counterexamples below `2^64`.
+
+use crate::unicorn::MR_PRIME_ITERS;
+use rug::integer::{IsPrime, Order};
+use rug::{Complete, Integer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Default number of Miller-Rabin rounds recorded in a certificate
+pub const CERTIFICATE_ROUNDS: u32 = 64;
+
+/// Size in bits up to which a prime is proven by BPSW rather than a further Pocklington step
+pub const PROOF_BASE_BITS: u32 = 64;
+
+/// Primes below which BPSW checks by trial division
+const SMALL_PRIMES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Record of the primality checks passed by a modulus
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ModulusCertificate {
+    /// Modulus, in decimal
+    pub modulus: String,
+    /// Size of the modulus in bits
+    pub bits: u32,
+    /// Number of Miller-Rabin rounds passed
+    pub rounds: u32,
+    /// Hex-encoded Miller-Rabin bases, derived from the modulus
+    pub bases: Vec<String>,
+    /// Upper bound on the probability that a composite passes every round, as a power of 2
+    pub error_bound_log2: i64,
+    /// Deterministic proof that the modulus is prime, if one was made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<PrimalityProof>,
+}
+
+/// Step of a primality proof, proving `prime` from the prime `q` of the next step
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PocklingtonStep {
+    /// Prime proven by the step, in decimal
+    pub prime: String,
+    /// Base `a` with `a^(n-1) = 1` and `gcd(a^((n-1)/q) - 1, n) = 1` mod `n = prime`; 0 on
+    /// the last step, which is proven by BPSW
+    pub witness: u32,
+}
+
+/// Deterministic proof that a modulus is prime, as a chain of Pocklington steps
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PrimalityProof {
+    /// Steps from the modulus down to a prime of at most `PROOF_BASE_BITS` bits
+    pub steps: Vec<PocklingtonStep>,
+}
+
+impl PrimalityProof {
+    /// Checks that the proof proves a modulus prime
+    ///
+    /// ### Arguments
+    ///
+    /// * `modulus` - Modulus the proof must start from
+    pub fn verify(&self, modulus: &Integer) -> bool {
+        let primes: Option<Vec<Integer>> = self
+            .steps
+            .iter()
+            .map(|step| Integer::from_str_radix(&step.prime, 10).ok())
+            .collect();
+        let Some(primes) = primes else {
+            return false;
+        };
+        let (Some(first), Some(last)) = (primes.first(), primes.last()) else {
+            return false;
+        };
+
+        first == modulus
+            && last.significant_bits() <= PROOF_BASE_BITS
+            && bpsw(last)
+            && self
+                .steps
+                .iter()
+                .zip(primes.windows(2))
+                .all(|(step, pair)| pocklington(&pair[0], &pair[1], step.witness))
+    }
+}
+
+impl ModulusCertificate {
+    /// Certifies a modulus. Returns `None` if it is not congruent to 3 mod 4 or fails a
+    /// Miller-Rabin round
+    ///
+    /// ### Arguments
+    ///
+    /// * `modulus` - Modulus to certify
+    /// * `rounds`  - Number of Miller-Rabin rounds
+    pub fn new(modulus: &Integer, rounds: u32) -> Option<Self> {
+        let certificate = Self {
+            modulus: modulus.to_string_radix(10),
+            bits: modulus.significant_bits(),
+            rounds,
+            bases: derive_bases(modulus, rounds)
+                .iter()
+                .map(|b| b.to_string_radix(16))
+                .collect(),
+            error_bound_log2: -2 * rounds as i64,
+            proof: None,
+        };
+
+        certificate.verify().then_some(certificate)
+    }
+
+    /// Attaches a primality proof to the certificate. Returns `None` if it does not prove the
+    /// modulus prime
+    ///
+    /// ### Arguments
+    ///
+    /// * `proof` - Proof of the modulus
+    pub fn with_proof(mut self, proof: PrimalityProof) -> Option<Self> {
+        self.proof = Some(proof);
+        self.verify().then_some(self)
+    }
+
+    /// Reruns every check recorded in the certificate
+    pub fn verify(&self) -> bool {
+        let Ok(modulus) = Integer::from_str_radix(&self.modulus, 10) else {
+            return false;
+        };
+        let bases = derive_bases(&modulus, self.rounds);
+
+        modulus > 3
+            && modulus.mod_u(4) == 3
+            && modulus.significant_bits() == self.bits
+            && self.error_bound_log2 >= -2 * self.rounds as i64
+            && bases.len() == self.bases.len()
+            && bases
+                .iter()
+                .zip(&self.bases)
+                .all(|(base, recorded)| base.to_string_radix(16) == *recorded)
+            && bases.iter().all(|base| miller_rabin_round(&modulus, base))
+            && self.proof.as_ref().is_none_or(|p| p.verify(&modulus))
+    }
+}
+
+/// Checks whether a number is probably prime with the given number of Miller-Rabin rounds,
+/// on top of the BPSW test GMP always runs
+///
+/// ### Arguments
+///
+/// * `n`      - Number to check
+/// * `rounds` - Number of Miller-Rabin rounds
+pub fn is_probable_prime(n: &Integer, rounds: u32) -> bool {
+    !matches!(n.is_probably_prime(rounds), IsPrime::No)
+}
+
+/// Baillie-PSW test: a strong probable prime test to base 2 followed by a strong Lucas test
+/// with Selfridge's parameters. No composite below `2^64` passes it
+///
+/// ### Arguments
+///
+/// * `n` - Number to check
+pub fn bpsw(n: &Integer) -> bool {
+    if *n < 2 {
+        return false;
+    }
+    for p in SMALL_PRIMES {
+        if *n == p {
+            return true;
+        }
+        if n.is_divisible_u(p) {
+            return false;
+        }
+    }
+
+    miller_rabin_round(n, &Integer::from(2)) && strong_lucas(n)
+}
+
+/// Generates a random prime modulus congruent to 3 mod 4 with exactly `bits` bits
+///
+/// ### Arguments
+///
+/// * `bits`    - Size of the modulus in bits; at least 3
+/// * `entropy` - Random bytes seeding the candidate search
+pub fn generate_modulus(bits: u32, entropy: &[u8]) -> Integer {
+    let len = bits.div_ceil(8) as usize;
+
+    for attempt in 0u32.. {
+        let bytes = expand(&[entropy, &attempt.to_be_bytes()], len);
+        let mut candidate = Integer::from_digits(&bytes, Order::MsfBe);
+        candidate.keep_bits_mut(bits);
+        candidate.set_bit(bits - 1, true);
+        candidate |= 3;
+
+        while candidate.significant_bits() == bits {
+            if !matches!(candidate.is_probably_prime(MR_PRIME_ITERS), IsPrime::No) {
+                return candidate;
+            }
+            candidate += 4;
+        }
+    }
+
+    unreachable!("candidate search exhausted")
+}
+
+/// Generates a prime modulus congruent to 3 mod 4 with exactly `bits` bits, along with a
+/// deterministic proof that it is prime
+///
+/// ### Arguments
+///
+/// * `bits`    - Size of the modulus in bits; at least 3
+/// * `entropy` - Random bytes seeding the candidate search
+pub fn generate_provable_modulus(bits: u32, entropy: &[u8]) -> (Integer, PrimalityProof) {
+    let steps = provable_prime(bits, entropy, true);
+    let modulus = Integer::from_str_radix(&steps[0].prime, 10).unwrap();
+
+    (modulus, PrimalityProof { steps })
+}
+
+/// Proof steps of a prime with exactly `bits` bits, congruent to 3 mod 4 if `blum`
+fn provable_prime(bits: u32, entropy: &[u8], blum: bool) -> Vec<PocklingtonStep> {
+    let len = bits.div_ceil(8) as usize;
+
+    if bits <= PROOF_BASE_BITS {
+        for attempt in 0u32.. {
+            let bytes = expand(&[entropy, &attempt.to_be_bytes()], len);
+            let mut candidate = Integer::from_digits(&bytes, Order::MsfBe);
+            candidate.keep_bits_mut(bits);
+            candidate.set_bit(bits - 1, true);
+            candidate |= if blum { 3 } else { 1 };
+
+            if bpsw(&candidate) {
+                let prime = candidate.to_string_radix(10);
+                return vec![PocklingtonStep { prime, witness: 0 }];
+            }
+        }
+    }
+
+    // `q` of `bits / 2 + 2` bits has `q^2 > 2^bits > p`
+    let mut steps = provable_prime(bits / 2 + 2, &expand(&[entropy, b"factor"], 32), false);
+    let q = Integer::from_str_radix(&steps[0].prime, 10).unwrap();
+    let two_q = Integer::from(&q * 2);
+    let lo = (Integer::from(1) << (bits - 1)) / &two_q + 1;
+    let hi = ((Integer::from(1) << bits) - 1u32) / &two_q;
+    let range = Integer::from(&hi - &lo) + 1;
+
+    for attempt in 0u32.. {
+        // 16 extra bytes keep the bias of the reduction negligible
+        let bytes = expand(&[entropy, &attempt.to_be_bytes()], len + 16);
+        let r: Integer = Integer::from_digits(&bytes, Order::MsfBe) % &range + &lo;
+        // With `q` odd, `2qR + 1` is 3 mod 4 exactly when `R` is odd
+        if blum && r.is_even() {
+            continue;
+        }
+
+        let candidate = r * &two_q + 1;
+        if !is_probable_prime(&candidate, 0) || !bpsw(&candidate) {
+            continue;
+        }
+        if let Some(witness) = (2..1_000).find(|&a| pocklington(&candidate, &q, a)) {
+            let prime = candidate.to_string_radix(10);
+            steps.insert(0, PocklingtonStep { prime, witness });
+            return steps;
+        }
+    }
+
+    unreachable!("candidate search exhausted")
+}
+
+/// Pocklington's criterion for a prime factor `q > sqrt(n)` of `n - 1`: `n` is prime if
+/// `a^(n-1) = 1 mod n` and `gcd(a^((n-1)/q) - 1, n) = 1`
+fn pocklington(n: &Integer, q: &Integer, a: u32) -> bool {
+    let n_minus_one = Integer::from(n - 1);
+    if *q < 2 || !n_minus_one.is_divisible(q) || Integer::from(q * q) <= *n {
+        return false;
+    }
+
+    let a = Integer::from(a);
+    if Integer::from(a.pow_mod_ref(&n_minus_one, n).unwrap()) != 1 {
+        return false;
+    }
+    let cofactor = Integer::from(&n_minus_one / q);
+    let t: Integer = Integer::from(a.pow_mod_ref(&cofactor, n).unwrap()) - 1;
+
+    t.gcd_ref(n).complete() == 1
+}
+
+/// Strong Lucas probable prime test with Selfridge's parameters, for an odd `n` with no
+/// small factors
+fn strong_lucas(n: &Integer) -> bool {
+    if n.is_perfect_square() {
+        return false;
+    }
+
+    // First of 5, -7, 9, -11, ... with Jacobi symbol -1
+    let mut d = 5i64;
+    loop {
+        match Integer::from(d).jacobi(n) {
+            -1 => break,
+            0 => return false,
+            _ => d = if d > 0 { -(d + 2) } else { 2 - d },
+        }
+    }
+    let q = Integer::from((1 - d) / 4);
+    let d = Integer::from(d);
+
+    let reduce = |x: Integer| -> Integer {
+        let r = x % n;
+        if r < 0 {
+            r + n
+        } else {
+            r
+        }
+    };
+    let halve = |x: Integer| -> Integer {
+        let x = if x.is_odd() { x + n } else { x };
+        x >> 1
+    };
+
+    // n + 1 = odd * 2^s, and U_k, V_k and Q^k are built up over the bits of odd with P = 1
+    let n_plus_one = Integer::from(n + 1);
+    let s = n_plus_one.find_one(0).unwrap_or(0);
+    let odd = Integer::from(&n_plus_one >> s);
+    let mut u = Integer::from(1);
+    let mut v = Integer::from(1);
+    let mut qk = reduce(q.clone());
+
+    for bit in (0..odd.significant_bits() - 1).rev() {
+        u = reduce(u * &v);
+        v = reduce(Integer::from(&v * &v) - Integer::from(&qk * 2));
+        qk = reduce(Integer::from(&qk * &qk));
+
+        if odd.get_bit(bit) {
+            let next_u = halve(reduce(Integer::from(&u + &v)));
+            let next_v = halve(reduce(Integer::from(&d * &u) + &v));
+            u = next_u;
+            v = next_v;
+            qk = reduce(qk * &q);
+        }
+    }
+
+    if u == 0 || v == 0 {
+        return true;
+    }
+    for _ in 1..s {
+        v = reduce(Integer::from(&v * &v) - Integer::from(&qk * 2));
+        if v == 0 {
+            return true;
+        }
+        qk = reduce(Integer::from(&qk * &qk));
+    }
+
+    false
+}
+
+/// Miller-Rabin bases in `[2, n - 2]`, derived from the SHA-256 of the modulus and the
+/// round number
+fn derive_bases(modulus: &Integer, rounds: u32) -> Vec<Integer> {
+    if *modulus <= 4 {
+        return Vec::new();
+    }
+
+    let digits = modulus.to_digits::<u8>(Order::MsfBe);
+    let range = Integer::from(modulus - 3);
+
+    (0..rounds)
+        .map(|round| {
+            // 16 extra bytes keep the bias of the reduction negligible
+            let bytes = expand(&[&digits, &round.to_be_bytes()], digits.len() + 16);
+            Integer::from_digits(&bytes, Order::MsfBe) % &range + 2
+        })
+        .collect()
+}
+
+/// Expands the hash of some parts into `len` bytes, hashing them with a block counter
+fn expand(parts: &[&[u8]], len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len + 32);
+
+    for block in 0u32.. {
+        if bytes.len() >= len {
+            break;
+        }
+
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        hasher.update(block.to_be_bytes());
+        bytes.extend(hasher.finalize());
+    }
+
+    bytes.truncate(len);
+    bytes
+}
+
+/// Single Miller-Rabin round for an odd modulus
+fn miller_rabin_round(n: &Integer, base: &Integer) -> bool {
+    let n_minus_one = Integer::from(n - 1);
+    let s = n_minus_one.find_one(0).unwrap_or(0);
+    let d = Integer::from(&n_minus_one >> s);
+
+    let mut x = base.clone().pow_mod(&d, n).unwrap();
+    if x == 1 || x == n_minus_one {
+        return true;
+    }
+
+    for _ in 1..s {
+        x = x.pow_mod(&Integer::from(2), n).unwrap();
+        if x == n_minus_one {
+            return true;
+        }
+    }
+
+    false
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod modulus_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that generated moduli have the requested form and certify
+    fn generate_and_certify() {
+        let modulus = generate_modulus(256, &[7; 32]);
+        let certificate = ModulusCertificate::new(&modulus, 16).unwrap();
+
+        assert_eq!(modulus.significant_bits(), 256);
+        assert_eq!(modulus.mod_u(4), 3);
+        assert_eq!(modulus, generate_modulus(256, &[7; 32]));
+        assert!(certificate.verify());
+    }
+
+    #[test]
+    /// Checks that composites and tampered certificates are refused
+    fn refuses_bad_moduli() {
+        let example = Integer::from_str_radix(crate::config::DEFAULT_MODULUS, 10).unwrap();
+        let mut certificate = ModulusCertificate::new(&example, 8).unwrap();
+
+        assert_eq!(ModulusCertificate::new(&Integer::from(3 * 7 * 11), 8), None);
+        assert_eq!(ModulusCertificate::new(&Integer::from(13), 8), None);
+
+        certificate.bases[0] = "2".to_string();
+        assert!(!certificate.verify());
+    }
+
+    #[test]
+    /// Checks that BPSW agrees with GMP on small numbers and refuses known pseudoprimes
+    fn bpsw_matches_gmp() {
+        for n in 0..5_000u32 {
+            let n = Integer::from(n);
+            assert_eq!(bpsw(&n), is_probable_prime(&n, 30), "{}", n);
+        }
+
+        // Strong pseudoprimes to base 2, strong Lucas pseudoprimes and a Carmichael number
+        for n in [2047u32, 3277, 4033, 5459, 5777, 10877, 561] {
+            assert!(!bpsw(&Integer::from(n)), "{}", n);
+        }
+        let example = Integer::from_str_radix(crate::config::DEFAULT_MODULUS, 10).unwrap();
+        assert!(bpsw(&example));
+    }
+
+    #[test]
+    /// Checks that provable moduli have the requested form and a proof that checks, and that
+    /// tampered proofs are refused
+    fn provable_modulus() {
+        let (modulus, proof) = generate_provable_modulus(256, &[7; 32]);
+
+        assert_eq!(modulus.significant_bits(), 256);
+        assert_eq!(modulus.mod_u(4), 3);
+        assert!(proof.verify(&modulus));
+        assert!(proof.steps.len() > 1);
+
+        let certificate = ModulusCertificate::new(&modulus, 8).unwrap();
+        let certificate = certificate.with_proof(proof.clone()).unwrap();
+        assert!(certificate.verify());
+
+        let mut tampered = proof.clone();
+        tampered.steps[0].witness = 0;
+        assert!(!tampered.verify(&modulus));
+        let mut truncated = proof.clone();
+        truncated.steps.truncate(1);
+        assert!(!truncated.verify(&modulus));
+        assert!(!proof.verify(&(modulus + 4)));
+    }
+}
@@ -0,0 +1,214 @@
+//! Node.js bindings, built with the `napi` feature.
+//!
+//! The addon runs the same eval, verification and selection code as the node, so that the
+//! JavaScript tooling around the Zenotta node can drive the lottery natively instead of
+//! shelling out to the CLI. Seeds are hex strings and evaluated UNICORNs are plain objects in
+//! the same shape as the CLI and HTTP API write them. Eval and verification run on the libuv
+//! thread pool and return promises. The addon is built with the napi CLI, as configured in
+//! `package.json`:
+//!
+//! ```js
+//! const ml = require("miner-lottery");
+//!
+//! const inputs = ["miner_a", "miner_b"];
+//! const info = await ml.eval(ml.constructSeed(inputs), { iterations: 1000 });
+//! assert(await ml.verify(info));
+//! const winners = ml.select(info, inputs, 1n, 1);
+//! ```
+
+use crate::audit::audit_unicorn;
+use crate::config::{validate_params, DEFAULT_ITERATIONS, DEFAULT_MODULUS, DEFAULT_SECURITY};
+use crate::selection::prove_many_from;
+use crate::unicorn::{construct_seed as seed_from_inputs, Unicorn, UnicornFixedParam, UnicornInfo};
+use napi::bindgen_prelude::{AsyncTask, BigInt};
+use napi::{Env, Error, JsUnknown, Result, Status, Task};
+use napi_derive::napi;
+use rug::Integer;
+use serde_json::Value;
+
+/// UNICORN parameters, defaulting to those of the configured round
+#[napi(object)]
+pub struct EvalParams {
+    /// Decimal modulus
+    pub modulus: Option<String>,
+    pub iterations: Option<i64>,
+    pub security: Option<u32>,
+}
+
+/// Eval of a UNICORN on the libuv thread pool
+pub struct EvalTask {
+    unicorn: Unicorn,
+}
+
+impl Task for EvalTask {
+    type Output = UnicornInfo;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<UnicornInfo> {
+        let (witness, g_value) = self
+            .unicorn
+            .eval()
+            .ok_or_else(|| invalid("Modulus for UNICORN eval invalid"))?;
+
+        Ok(UnicornInfo::new(self.unicorn.clone(), witness, g_value))
+    }
+
+    fn resolve(&mut self, env: Env, info: UnicornInfo) -> Result<JsUnknown> {
+        env.to_js_value(&info)
+    }
+}
+
+/// Verification of an evaluated UNICORN on the libuv thread pool
+pub struct VerifyTask {
+    info: UnicornInfo,
+}
+
+impl Task for VerifyTask {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> Result<bool> {
+        Ok(audit_unicorn(&self.info).passed())
+    }
+
+    fn resolve(&mut self, _env: Env, passed: bool) -> Result<bool> {
+        Ok(passed)
+    }
+}
+
+/// Seed constructed from the public key inputs of a round, as a hex string
+#[napi]
+pub fn construct_seed(inputs: Vec<String>) -> String {
+    seed_from_inputs(&inputs).to_string_radix(16)
+}
+
+/// Evaluates the UNICORN for a hex seed, resolving to the evaluated `UnicornInfo`
+#[napi(ts_return_type = "Promise<object>")]
+pub fn eval(seed: String, params: Option<EvalParams>) -> Result<AsyncTask<EvalTask>> {
+    Ok(AsyncTask::new(EvalTask {
+        unicorn: unicorn(&seed, params)?,
+    }))
+}
+
+/// Whether the modulus, witness and `g` value of an evaluated UNICORN all check out
+#[napi(ts_args_type = "unicorn: object", ts_return_type = "Promise<boolean>")]
+pub fn verify(unicorn: Value) -> Result<AsyncTask<VerifyTask>> {
+    Ok(AsyncTask::new(VerifyTask {
+        info: unicorn_info(unicorn)?,
+    }))
+}
+
+/// Draws distinct winners from an evaluated UNICORN, in draw order
+#[napi(
+    ts_args_type = "unicorn: object, participants: string[], usageNumber?: bigint, winners?: number"
+)]
+pub fn select(
+    unicorn: Value,
+    participants: Vec<String>,
+    usage_number: Option<BigInt>,
+    winners: Option<u32>,
+) -> Result<Vec<String>> {
+    let info = unicorn_info(unicorn)?;
+    let usage_number = match usage_number {
+        Some(usage) => match usage.get_u128() {
+            (false, usage, true) => usage,
+            _ => {
+                return Err(invalid(
+                    "usageNumber must be a non-negative 128-bit integer",
+                ))
+            }
+        },
+        None => 1,
+    };
+
+    let proof = prove_many_from(
+        &info,
+        usage_number,
+        &participants,
+        winners.unwrap_or(1) as usize,
+    )
+    .map_err(|e| invalid(&e.to_string()))?;
+
+    Ok(proof
+        .winners
+        .iter()
+        .map(|i| participants[*i].clone())
+        .collect())
+}
+
+/// UNICORN for a hex seed, with the given parameters over the defaults
+fn unicorn(seed: &str, params: Option<EvalParams>) -> Result<Unicorn> {
+    let params = params.unwrap_or(EvalParams {
+        modulus: None,
+        iterations: None,
+        security: None,
+    });
+    let iterations = match params.iterations {
+        Some(iterations) => {
+            u64::try_from(iterations).map_err(|_| invalid("iterations must not be negative"))?
+        }
+        None => DEFAULT_ITERATIONS,
+    };
+    let params = UnicornFixedParam {
+        modulus: params
+            .modulus
+            .unwrap_or_else(|| DEFAULT_MODULUS.to_string()),
+        iterations,
+        security: params.security.unwrap_or(DEFAULT_SECURITY),
+        ..Default::default()
+    };
+    validate_params(&params).map_err(|e| invalid(&e.to_string()))?;
+
+    Ok(Unicorn {
+        seed: Integer::from_str_radix(seed.trim_start_matches("0x"), 16)
+            .ok()
+            .filter(|n| *n >= 0)
+            .ok_or_else(|| invalid("seed must be a non-negative hex string"))?,
+        modulus: Integer::from_str_radix(&params.modulus, 10).unwrap(),
+        iterations: params.iterations,
+        security_level: params.security,
+        primality_rounds: params.primality_rounds,
+    })
+}
+
+fn unicorn_info(value: Value) -> Result<UnicornInfo> {
+    serde_json::from_value(value).map_err(|e| invalid(&e.to_string()))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::new(Status::InvalidArg, reason.to_string())
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod node_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that the addon evaluates, verifies and selects as the node does
+    fn addon_functions() {
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let params = EvalParams {
+            modulus: None,
+            iterations: Some(1_000),
+            security: Some(1),
+        };
+        let mut eval = EvalTask {
+            unicorn: unicorn(&construct_seed(inputs.clone()), Some(params)).unwrap(),
+        };
+        let info = eval.compute().unwrap();
+        let value = serde_json::to_value(&info).unwrap();
+        let mut verify = VerifyTask {
+            info: unicorn_info(value.clone()).unwrap(),
+        };
+
+        assert_eq!(info.unicorn.seed, seed_from_inputs(&inputs));
+        assert!(verify.compute().unwrap());
+        assert_eq!(
+            select(value, inputs.clone(), None, Some(2)).unwrap().len(),
+            2
+        );
+        assert!(unicorn("not hex", None).is_err());
+    }
+}
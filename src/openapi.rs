@@ -0,0 +1,94 @@
+//! OpenAPI description of the HTTP API, built with the `openapi` feature.
+//!
+//! The document is generated from the extractors and responses of the route handlers in
+//! `server`, through the JSON Schema of the types they take and return, so it follows the
+//! handlers as they change. The server serves it at `GET /openapi.json`, along with Swagger UI
+//! at `GET /docs`. `miner-lottery openapi` prints it without running a server.
+
+use crate::server::{api_routes, ServerState};
+use aide::axum::ApiRouter;
+use aide::openapi::{Info, OpenApi};
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+
+/// Swagger UI page, loading the document served at `/openapi.json`
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>miner-lottery API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+/// The OpenAPI document of the HTTP API
+pub fn document() -> OpenApi {
+    let mut api = base();
+    let _ = api_routes().finish_api(&mut api);
+    api
+}
+
+/// Finishes the documented routes, adding the routes serving their document and Swagger UI
+///
+/// ### Arguments
+///
+/// * `routes` - Documented routes of the API
+pub(crate) fn serve(routes: ApiRouter<ServerState>) -> Router<ServerState> {
+    let mut api = base();
+    let router = routes.finish_api(&mut api);
+    let document = serde_json::to_value(&api).unwrap();
+
+    router
+        .route("/openapi.json", get(|| async move { Json(document) }))
+        .route("/docs", get(|| async { Html(SWAGGER_UI) }))
+}
+
+/// Document without any paths
+fn base() -> OpenApi {
+    OpenApi {
+        info: Info {
+            title: "miner-lottery".to_string(),
+            description: Some("HTTP API of the miner lottery daemon".to_string()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Info::default()
+        },
+        ..OpenApi::default()
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod openapi_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that every route is documented with the schemas of its bodies
+    fn documents_routes() {
+        let api = document();
+        let paths = api.paths.as_ref().unwrap();
+        let schemas = &api.components.as_ref().unwrap().schemas;
+
+        for path in [
+            "/rounds/{id}/contributions",
+            "/rounds/{id}/result",
+            "/rounds/{id}/feed",
+            "/eval",
+            "/verify",
+            "/events",
+            "/status",
+        ] {
+            assert!(paths.paths.contains_key(path), "{} is not documented", path);
+        }
+        for schema in ["EvalRequest", "UnicornInfo", "AuditReport", "ServerStatus"] {
+            assert!(schemas.contains_key(schema), "{} has no schema", schema);
+        }
+    }
+}
@@ -0,0 +1,663 @@
+//! Gossip of seed contributions and published UNICORNs between peers.
+//!
+//! Without a coordinator, every peer relays what it hears. To stop a single bad peer from
+//! flooding the network, a message is only re-broadcast after it has been validated:
+//!
+//! - A contribution must be signed by the key it registers, so that nobody can contribute on
+//!   another participant's behalf.
+//! - A UNICORN announcement must be signed by one of the configured announcers, the
+//!   coordinator and verifiers, and be for a round whose seed this peer already knows. Its
+//!   seed must be that seed, its commitment the commitment of that seed, and its parameters
+//!   the locally configured ones, so that a peer never runs more iterations than it is
+//!   configured for. Only then is it verified: the witness must lead back to the seed and `g`
+//!   must be its hash.
+//!
+//! Messages are bincode-encoded and published on one topic per kind. Duplicates are ignored
+//! by message id, mirroring the accept/ignore/reject outcomes of pubsub validation.
+//!
+//! The node is written against the `GossipTransport` trait rather than a particular pubsub
+//! implementation. `gossip_swarm` builds a libp2p gossipsub swarm for it, over TCP with Noise
+//! and yamux, in which messages are only forwarded once the node has validated them.
+
+use crate::config::LotteryConfig;
+use crate::rounds::Contribution;
+use crate::signing::{Signed, SigningError};
+use crate::unicorn::{g_matches, Unicorn, UnicornFixedParam, UnicornInfo};
+use bincode::{deserialize, serialize};
+use ed25519_dalek::PublicKey;
+use libp2p::futures::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance};
+use libp2p::identity::Keypair;
+use libp2p::swarm::SwarmEvent;
+use libp2p::{noise, tcp, yamux, Swarm, SwarmBuilder};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Topic carrying signed contributions
+pub const CONTRIBUTIONS_TOPIC: &str = "miner-lottery/contributions/v1";
+
+/// Topic carrying signed UNICORN announcements
+pub const UNICORNS_TOPIC: &str = "miner-lottery/unicorns/v1";
+
+/// Number of message ids remembered for duplicate suppression
+pub const SEEN_CAPACITY: usize = 4_096;
+
+/// Time a connection without open streams is kept for, in seconds
+pub const IDLE_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GossipError {
+    /// The message could not be decoded, or arrived on the wrong topic.
+    Malformed,
+    /// The signature did not verify.
+    Signature(SigningError),
+    /// A contribution was not signed by the key it registers.
+    NotSelfSigned,
+    /// A UNICORN was not announced by a configured announcer.
+    UnknownAnnouncer,
+    /// The seed of the announced round is not known to this peer.
+    UnknownRound,
+    /// The announced seed is not the one this peer expects for the round.
+    SeedMismatch,
+    /// The announced commitment is not the commitment of the seed.
+    CommitmentMismatch,
+    /// The announced parameters are not the locally configured ones.
+    ParamsMismatch,
+    /// The witness does not lead back to the seed.
+    BadWitness,
+    /// The `g` value is not the hash of the witness.
+    GValueMismatch,
+    /// The transport failed to publish.
+    Transport(String),
+}
+
+impl fmt::Display for GossipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GossipError::Malformed => write!(f, "Malformed gossip message"),
+            GossipError::Signature(e) => write!(f, "Gossip signature invalid: {}", e),
+            GossipError::NotSelfSigned => {
+                write!(f, "Contribution not signed by the key it registers")
+            }
+            GossipError::UnknownAnnouncer => write!(f, "UNICORN announcer is not trusted"),
+            GossipError::UnknownRound => write!(f, "Seed of the announced round is unknown"),
+            GossipError::SeedMismatch => write!(f, "UNICORN seed mismatch"),
+            GossipError::CommitmentMismatch => write!(f, "UNICORN commitment mismatch"),
+            GossipError::ParamsMismatch => {
+                write!(f, "UNICORN parameters differ from the local ones")
+            }
+            GossipError::BadWitness => write!(f, "UNICORN witness does not verify"),
+            GossipError::GValueMismatch => write!(f, "UNICORN g value does not match witness"),
+            GossipError::Transport(e) => write!(f, "Gossip transport error: {}", e),
+        }
+    }
+}
+
+impl Error for GossipError {}
+
+/// What UNICORN announcements are checked against: the keys allowed to announce them and the
+/// locally configured parameters
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GossipPolicy {
+    announcers: HashSet<String>,
+    modulus: Integer,
+    iterations: u64,
+    security: u32,
+}
+
+impl GossipPolicy {
+    /// Creates a policy. Returns `None` if the modulus is not a decimal integer
+    ///
+    /// ### Arguments
+    ///
+    /// * `announcers` - Hex-encoded Ed25519 public keys of the coordinator and verifiers
+    /// * `params`     - Locally configured UNICORN parameters
+    pub fn new(announcers: &[String], params: &UnicornFixedParam) -> Option<Self> {
+        Some(Self {
+            announcers: announcers
+                .iter()
+                .map(|key| key.trim().to_ascii_lowercase())
+                .collect(),
+            modulus: Integer::from_str_radix(&params.modulus, 10).ok()?,
+            iterations: params.iterations,
+            security: params.security,
+        })
+    }
+
+    /// Policy for the announcers and UNICORN parameters of a configuration. Returns `None` if
+    /// the modulus is not a decimal integer
+    ///
+    /// ### Arguments
+    ///
+    /// * `config` - Lottery configuration
+    pub fn from_config(config: &LotteryConfig) -> Option<Self> {
+        Self::new(&config.p2p.announcers, &config.unicorn)
+    }
+
+    /// Whether a UNICORN was evaluated with the local parameters
+    fn allows(&self, unicorn: &Unicorn) -> bool {
+        unicorn.iterations == self.iterations
+            && unicorn.security_level == self.security
+            && unicorn.modulus == self.modulus
+    }
+}
+
+/// A published UNICORN together with the round it was evaluated for
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UnicornAnnouncement {
+    pub round_id: u64,
+    /// Commitment value `c` of the seed
+    pub commitment: String,
+    pub info: UnicornInfo,
+}
+
+/// Message gossiped between peers
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum GossipMessage {
+    Contribution(Signed<Contribution>),
+    Unicorn(Signed<UnicornAnnouncement>),
+}
+
+impl GossipMessage {
+    /// Topic the message is published on
+    pub fn topic(&self) -> &'static str {
+        match self {
+            GossipMessage::Contribution(_) => CONTRIBUTIONS_TOPIC,
+            GossipMessage::Unicorn(_) => UNICORNS_TOPIC,
+        }
+    }
+
+    /// Encodes the message for publishing
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize(self).unwrap()
+    }
+
+    /// Decodes a received message, checking that it arrived on its own topic
+    ///
+    /// ### Arguments
+    ///
+    /// * `topic` - Topic the message arrived on
+    /// * `data`  - Encoded message
+    pub fn from_bytes(topic: &str, data: &[u8]) -> Result<Self, GossipError> {
+        let message: Self = deserialize(data).map_err(|_| GossipError::Malformed)?;
+        if message.topic() != topic {
+            return Err(GossipError::Malformed);
+        }
+
+        Ok(message)
+    }
+
+    /// Checks the message's signature and contents. UNICORN announcements are checked
+    /// against the policy and the expected seed before their witness is verified
+    ///
+    /// ### Arguments
+    ///
+    /// * `policy`        - Trusted announcers and local parameters
+    /// * `expected_seed` - Seed this peer expects for the announced round, if known
+    pub fn validate(
+        &self,
+        policy: &GossipPolicy,
+        expected_seed: Option<&Integer>,
+    ) -> Result<(), GossipError> {
+        match self {
+            GossipMessage::Contribution(signed) => {
+                let signer = signer_of(signed)?;
+                if signed.public_key != signed.payload.identity.trim().to_ascii_lowercase() {
+                    return Err(GossipError::NotSelfSigned);
+                }
+                signed
+                    .verify_signature(&signer)
+                    .map_err(GossipError::Signature)
+            }
+            GossipMessage::Unicorn(signed) => {
+                if !policy
+                    .announcers
+                    .contains(&signed.public_key.to_ascii_lowercase())
+                {
+                    return Err(GossipError::UnknownAnnouncer);
+                }
+                signed
+                    .verify_signature(&signer_of(signed)?)
+                    .map_err(GossipError::Signature)?;
+
+                let announcement = &signed.payload;
+                let unicorn = &announcement.info.unicorn;
+                if unicorn.seed != *expected_seed.ok_or(GossipError::UnknownRound)? {
+                    return Err(GossipError::SeedMismatch);
+                }
+                if announcement.commitment != unicorn.commitment() {
+                    return Err(GossipError::CommitmentMismatch);
+                }
+                if !policy.allows(unicorn) {
+                    return Err(GossipError::ParamsMismatch);
+                }
+                if !unicorn.verify_ref(&unicorn.seed, &announcement.info.witness) {
+                    return Err(GossipError::BadWitness);
+                }
+                if !g_matches(&announcement.info.witness, &announcement.info.g_value) {
+                    return Err(GossipError::GValueMismatch);
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Broadcast layer the gossip node publishes through
+pub trait GossipTransport {
+    /// Publishes encoded data on a topic
+    fn publish(&mut self, topic: &str, data: Vec<u8>) -> Result<(), String>;
+}
+
+/// Peer validating and relaying gossip
+#[derive(Debug)]
+pub struct GossipNode<T> {
+    transport: T,
+    policy: GossipPolicy,
+    seen: HashSet<[u8; 32]>,
+    seen_order: VecDeque<[u8; 32]>,
+    seeds: BTreeMap<u64, Integer>,
+}
+
+impl<T: GossipTransport> GossipNode<T> {
+    /// Creates a node publishing through a transport
+    ///
+    /// ### Arguments
+    ///
+    /// * `transport` - Broadcast layer
+    /// * `policy`    - Trusted announcers and local parameters
+    pub fn new(transport: T, policy: GossipPolicy) -> Self {
+        Self {
+            transport,
+            policy,
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            seeds: BTreeMap::new(),
+        }
+    }
+
+    /// The underlying transport
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The underlying transport, mutably, such as to listen on or dial addresses
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Records the seed this peer expects for a round, as built from the contributions it
+    /// accepted, so that announcements for the round can be checked. Announcements for
+    /// rounds without an expected seed are rejected
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round identifier
+    /// * `seed`     - Seed of the round
+    pub fn expect_seed(&mut self, round_id: u64, seed: Integer) {
+        self.seeds.insert(round_id, seed);
+    }
+
+    /// Validates and publishes a message originating at this peer
+    ///
+    /// ### Arguments
+    ///
+    /// * `message` - Message to publish
+    pub fn publish(&mut self, message: &GossipMessage) -> Result<(), GossipError> {
+        message.validate(&self.policy, self.expected_seed(message))?;
+        let data = message.to_bytes();
+        self.remember(&data);
+
+        self.transport
+            .publish(message.topic(), data)
+            .map_err(GossipError::Transport)
+    }
+
+    /// Handles a message received from a peer. Valid messages not seen before are
+    /// re-broadcast and returned; duplicates are ignored with `None`
+    ///
+    /// ### Arguments
+    ///
+    /// * `topic` - Topic the message arrived on
+    /// * `data`  - Encoded message
+    pub fn receive(
+        &mut self,
+        topic: &str,
+        data: &[u8],
+    ) -> Result<Option<GossipMessage>, GossipError> {
+        let Some(message) = self.check(topic, data)? else {
+            return Ok(None);
+        };
+
+        self.transport
+            .publish(topic, data.to_vec())
+            .map_err(GossipError::Transport)?;
+
+        Ok(Some(message))
+    }
+
+    /// Validates a message received from a peer without re-broadcasting it, for transports
+    /// that forward messages themselves once validated. Duplicates are ignored with `None`
+    ///
+    /// ### Arguments
+    ///
+    /// * `topic` - Topic the message arrived on
+    /// * `data`  - Encoded message
+    pub fn check(
+        &mut self,
+        topic: &str,
+        data: &[u8],
+    ) -> Result<Option<GossipMessage>, GossipError> {
+        if self.seen.contains(&message_id(data)) {
+            return Ok(None);
+        }
+
+        let message = GossipMessage::from_bytes(topic, data)?;
+        message.validate(&self.policy, self.expected_seed(&message))?;
+        self.remember(data);
+
+        Ok(Some(message))
+    }
+
+    fn expected_seed(&self, message: &GossipMessage) -> Option<&Integer> {
+        match message {
+            GossipMessage::Unicorn(signed) => self.seeds.get(&signed.payload.round_id),
+            GossipMessage::Contribution(_) => None,
+        }
+    }
+
+    fn remember(&mut self, data: &[u8]) {
+        let id = message_id(data);
+        if self.seen.insert(id) {
+            self.seen_order.push_back(id);
+        }
+        if self.seen_order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl GossipTransport for Swarm<gossipsub::Behaviour> {
+    fn publish(&mut self, topic: &str, data: Vec<u8>) -> Result<(), String> {
+        self.behaviour_mut()
+            .publish(IdentTopic::new(topic), data)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl GossipNode<Swarm<gossipsub::Behaviour>> {
+    /// Drives the swarm until a peer sends a valid message not seen before. The outcome of
+    /// every received message is reported to gossipsub, which forwards accepted messages,
+    /// drops ignored ones and penalises the peers that sent rejected ones
+    pub async fn next_message(&mut self) -> GossipMessage {
+        loop {
+            let SwarmEvent::Behaviour(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            }) = self.transport.select_next_some().await
+            else {
+                continue;
+            };
+
+            let (acceptance, accepted) = match self.check(message.topic.as_str(), &message.data) {
+                Ok(Some(accepted)) => (MessageAcceptance::Accept, Some(accepted)),
+                Ok(None) => (MessageAcceptance::Ignore, None),
+                Err(e) => {
+                    tracing::debug!("Rejected gossip from {}: {}", propagation_source, e);
+                    (MessageAcceptance::Reject, None)
+                }
+            };
+            // Fails only if the message has already left the cache, so nothing is forwarded
+            let _ = self
+                .transport
+                .behaviour_mut()
+                .report_message_validation_result(&message_id, &propagation_source, acceptance);
+
+            if let Some(accepted) = accepted {
+                return accepted;
+            }
+        }
+    }
+}
+
+/// Gossipsub behaviour subscribed to the lottery topics. Messages are signed by the peer's
+/// identity, identified by the hash `GossipNode` suppresses duplicates by, and only forwarded
+/// once the node has validated them
+///
+/// ### Arguments
+///
+/// * `keypair` - Identity of the peer
+pub fn gossip_behaviour(keypair: &Keypair) -> Result<gossipsub::Behaviour, GossipError> {
+    let config = gossipsub::ConfigBuilder::default()
+        .validation_mode(gossipsub::ValidationMode::Strict)
+        .validate_messages()
+        .message_id_fn(|message| gossipsub::MessageId::new(&message_id(&message.data)))
+        .build()
+        .map_err(transport_error)?;
+    let mut behaviour = gossipsub::Behaviour::new(
+        gossipsub::MessageAuthenticity::Signed(keypair.clone()),
+        config,
+    )
+    .map_err(transport_error)?;
+
+    for topic in [CONTRIBUTIONS_TOPIC, UNICORNS_TOPIC] {
+        behaviour
+            .subscribe(&IdentTopic::new(topic))
+            .map_err(transport_error)?;
+    }
+
+    Ok(behaviour)
+}
+
+/// Swarm running the gossip behaviour over TCP, secured with Noise and multiplexed with
+/// yamux. The swarm must be driven from within a Tokio runtime
+///
+/// ### Arguments
+///
+/// * `keypair` - Identity of the peer
+pub fn gossip_swarm(keypair: Keypair) -> Result<Swarm<gossipsub::Behaviour>, GossipError> {
+    let swarm = SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default,
+        )
+        .map_err(transport_error)?
+        .with_behaviour(|key| gossip_behaviour(key).map_err(Into::into))
+        .map_err(transport_error)?
+        .with_swarm_config(|config| {
+            config.with_idle_connection_timeout(Duration::from_secs(IDLE_TIMEOUT_SECS))
+        })
+        .build();
+
+    Ok(swarm)
+}
+
+/// Id of an encoded message, for duplicate suppression
+fn message_id(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn transport_error(e: impl fmt::Display) -> GossipError {
+    GossipError::Transport(e.to_string())
+}
+
+fn signer_of<P>(signed: &Signed<P>) -> Result<PublicKey, GossipError> {
+    hex::decode(&signed.public_key)
+        .ok()
+        .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+        .ok_or(GossipError::Signature(SigningError::Malformed))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod p2p_tests {
+    use super::*;
+    use crate::signing::test_keypair;
+    use crate::unicorn::{construct_seed, construct_unicorn};
+    use ed25519_dalek::Keypair;
+
+    #[derive(Default)]
+    struct Outbox(Vec<(String, Vec<u8>)>);
+
+    impl GossipTransport for Outbox {
+        fn publish(&mut self, topic: &str, data: Vec<u8>) -> Result<(), String> {
+            self.0.push((topic.to_string(), data));
+            Ok(())
+        }
+    }
+
+    fn contribution(keypair: &Keypair) -> GossipMessage {
+        let contribution = Contribution {
+            round_id: 1,
+            identity: hex::encode(keypair.public.as_bytes()),
+            timestamp: 10,
+            nonce: None,
+        };
+        GossipMessage::Contribution(Signed::sign(contribution, keypair))
+    }
+
+    fn policy(announcer: &Keypair) -> GossipPolicy {
+        GossipPolicy::new(
+            &[hex::encode(announcer.public.as_bytes())],
+            &UnicornFixedParam::test(),
+        )
+        .unwrap()
+    }
+
+    fn announcement(keypair: &Keypair) -> Signed<UnicornAnnouncement> {
+        let info = construct_unicorn(
+            construct_seed(&["miner_a".to_string()]),
+            &UnicornFixedParam::test(),
+        );
+        let announcement = UnicornAnnouncement {
+            round_id: 1,
+            commitment: info.unicorn.commitment(),
+            info,
+        };
+        Signed::sign(announcement, keypair)
+    }
+
+    #[test]
+    /// Checks that valid messages are relayed once and invalid ones are rejected
+    fn relay_after_validation() {
+        let mut node = GossipNode::new(Outbox::default(), policy(&test_keypair(5)));
+        let message = contribution(&test_keypair(1));
+        let data = message.to_bytes();
+
+        assert_eq!(node.receive(CONTRIBUTIONS_TOPIC, &data), Ok(Some(message)));
+        assert_eq!(node.receive(CONTRIBUTIONS_TOPIC, &data), Ok(None));
+        assert_eq!(
+            node.receive(UNICORNS_TOPIC, &data),
+            Ok(None),
+            "duplicates are ignored before decoding"
+        );
+        assert_eq!(node.transport().0.len(), 1);
+
+        // Signed by one key but registering another
+        let GossipMessage::Contribution(mut forged) = contribution(&test_keypair(2)) else {
+            unreachable!()
+        };
+        forged.payload.identity = hex::encode(test_keypair(3).public.as_bytes());
+        let forged = GossipMessage::Contribution(forged).to_bytes();
+        assert_eq!(
+            node.receive(CONTRIBUTIONS_TOPIC, &forged),
+            Err(GossipError::NotSelfSigned)
+        );
+        assert_eq!(
+            node.receive(UNICORNS_TOPIC, &contribution(&test_keypair(4)).to_bytes()),
+            Err(GossipError::Malformed)
+        );
+    }
+
+    #[test]
+    /// Checks that UNICORN announcements are checked against the trusted announcers, the
+    /// expected seed and the local parameters before their witness
+    fn unicorn_checks() {
+        let signer = test_keypair(5);
+        let policy = policy(&signer);
+        let valid = announcement(&signer);
+        let seed = valid.payload.info.unicorn.seed.clone();
+
+        let mut other_seed = valid.payload.clone();
+        other_seed.info.unicorn.seed += 2;
+        other_seed.commitment = other_seed.info.unicorn.commitment();
+        let mut self_committed = valid.payload.clone();
+        self_committed.commitment = "00".to_string();
+        let mut more_iterations = valid.payload.clone();
+        more_iterations.info.unicorn.iterations = u64::MAX;
+        let mut bad_witness = valid.payload.clone();
+        bad_witness.info.witness += 1;
+        let mut wrong_g = valid.payload.clone();
+        wrong_g.info.g_value = "00".to_string();
+
+        let validate = |announcement: UnicornAnnouncement, expected: Option<&Integer>| {
+            GossipMessage::Unicorn(Signed::sign(announcement, &signer)).validate(&policy, expected)
+        };
+
+        assert_eq!(validate(valid.payload.clone(), Some(&seed)), Ok(()));
+        assert_eq!(
+            GossipMessage::Unicorn(announcement(&test_keypair(6))).validate(&policy, Some(&seed)),
+            Err(GossipError::UnknownAnnouncer)
+        );
+        assert_eq!(
+            validate(valid.payload.clone(), None),
+            Err(GossipError::UnknownRound)
+        );
+        assert_eq!(
+            validate(other_seed, Some(&seed)),
+            Err(GossipError::SeedMismatch)
+        );
+        assert_eq!(
+            validate(self_committed, Some(&seed)),
+            Err(GossipError::CommitmentMismatch)
+        );
+        assert_eq!(
+            validate(more_iterations, Some(&seed)),
+            Err(GossipError::ParamsMismatch)
+        );
+        assert_eq!(
+            validate(bad_witness, Some(&seed)),
+            Err(GossipError::BadWitness)
+        );
+        assert_eq!(
+            validate(wrong_g, Some(&seed)),
+            Err(GossipError::GValueMismatch)
+        );
+
+        let mut node = GossipNode::new(Outbox::default(), policy.clone());
+        let data = GossipMessage::Unicorn(valid).to_bytes();
+        assert_eq!(
+            node.receive(UNICORNS_TOPIC, &data),
+            Err(GossipError::UnknownRound)
+        );
+        node.expect_seed(1, seed);
+        assert!(node.receive(UNICORNS_TOPIC, &data).unwrap().is_some());
+    }
+
+    #[test]
+    /// Checks that the gossipsub swarm is subscribed to both topics
+    fn gossipsub_swarm() {
+        let swarm = gossip_swarm(libp2p::identity::Keypair::generate_ed25519()).unwrap();
+        let mut topics: Vec<String> = swarm
+            .behaviour()
+            .topics()
+            .map(|topic| topic.to_string())
+            .collect();
+        topics.sort();
+
+        assert_eq!(topics, [CONTRIBUTIONS_TOPIC, UNICORNS_TOPIC]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Recommendations on UNICORN parameters, after Lenstra and Wesolowski's "Random Zoo".
+//!
+//! `config::validate_params` rejects parameters the eval cannot work with. Parameters that
+//! pass it can still fall short of the paper's guidance: sloth over a `2k`-bit prime gives
+//! `k` bits of security, so the modulus has to grow with the claimed security level, and the
+//! delay is only meaningful if there are enough iterations that the eval cannot be ground
+//! through cheaply. `check_recommended` reports each shortfall as a warning, leaving the
+//! decision to the operator.
+
+use crate::unicorn::UnicornFixedParam;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Lowest security level, in bits, recommended for production rounds
+pub const RECOMMENDED_SECURITY: u32 = 128;
+
+/// Fewest iterations recommended for the eval to impose a meaningful delay
+pub const RECOMMENDED_MIN_ITERATIONS: u64 = 1 << 16;
+
+/// A way in which parameters fall short of the recommendations
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "warning", rename_all = "snake_case")]
+pub enum Warning {
+    /// The modulus has fewer than the `2k` bits needed for the claimed security level `k`.
+    ModulusTooSmall { bits: u32, required: u32 },
+    /// The claimed security level is below `RECOMMENDED_SECURITY`.
+    SecurityTooLow { security: u32 },
+    /// The modulus is more than twice the size the claimed security level needs, so the
+    /// level likely was not updated along with the modulus.
+    SecurityInconsistent { security: u32, bits: u32 },
+    /// There are fewer than `RECOMMENDED_MIN_ITERATIONS` iterations.
+    IterationsTooLow { iterations: u64, recommended: u64 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ModulusTooSmall { bits, required } => write!(
+                f,
+                "Modulus of {} bits is too small for the claimed security; {} bits are required",
+                bits, required
+            ),
+            Warning::SecurityTooLow { security } => write!(
+                f,
+                "Security level {} is below the recommended {}",
+                security, RECOMMENDED_SECURITY
+            ),
+            Warning::SecurityInconsistent { security, bits } => write!(
+                f,
+                "Security level {} is inconsistent with a modulus of {} bits, which gives {}",
+                security,
+                bits,
+                bits / 2
+            ),
+            Warning::IterationsTooLow {
+                iterations,
+                recommended,
+            } => write!(
+                f,
+                "{} iterations are too few for a meaningful delay; at least {} are recommended",
+                iterations, recommended
+            ),
+        }
+    }
+}
+
+/// Checks parameters against the recommendations, returning every shortfall. A modulus that
+/// does not parse is left to `config::validate_params` to report
+///
+/// ### Arguments
+///
+/// * `params` - Parameters to check
+pub fn check_recommended(params: &UnicornFixedParam) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    if let Ok(modulus) = Integer::from_str_radix(&params.modulus, 10) {
+        let bits = modulus.significant_bits();
+        let required = params.security.saturating_mul(2);
+        if bits < required {
+            warnings.push(Warning::ModulusTooSmall { bits, required });
+        } else if bits / 4 > params.security {
+            warnings.push(Warning::SecurityInconsistent {
+                security: params.security,
+                bits,
+            });
+        }
+    }
+    if params.security < RECOMMENDED_SECURITY {
+        warnings.push(Warning::SecurityTooLow {
+            security: params.security,
+        });
+    }
+    if params.iterations < RECOMMENDED_MIN_ITERATIONS {
+        warnings.push(Warning::IterationsTooLow {
+            iterations: params.iterations,
+            recommended: RECOMMENDED_MIN_ITERATIONS,
+        });
+    }
+
+    warnings
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod params_tests {
+    use super::*;
+    use crate::config::DEFAULT_MODULUS;
+
+    #[test]
+    /// Checks that each shortfall is flagged, and that recommended parameters pass
+    fn flags_shortfalls() {
+        let mut params = UnicornFixedParam {
+            modulus: DEFAULT_MODULUS.to_string(),
+            iterations: RECOMMENDED_MIN_ITERATIONS,
+            security: 256,
+            ..Default::default()
+        };
+        assert_eq!(check_recommended(&params), vec![]);
+
+        params.security = 1;
+        params.iterations = 1_000;
+        assert_eq!(
+            check_recommended(&params),
+            vec![
+                Warning::SecurityInconsistent {
+                    security: 1,
+                    bits: 521
+                },
+                Warning::SecurityTooLow { security: 1 },
+                Warning::IterationsTooLow {
+                    iterations: 1_000,
+                    recommended: RECOMMENDED_MIN_ITERATIONS
+                },
+            ]
+        );
+
+        params.security = 512;
+        params.iterations = RECOMMENDED_MIN_ITERATIONS;
+        assert_eq!(
+            check_recommended(&params),
+            vec![Warning::ModulusTooSmall {
+                bits: 521,
+                required: 1024
+            }]
+        );
+    }
+}
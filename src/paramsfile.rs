@@ -0,0 +1,228 @@
+//! Signed distribution files for lottery parameters.
+//!
+//! Governance blesses a set of fixed UNICORN parameters by signing them, together with the
+//! window of time during which they may be used, with its Ed25519 key. The result is written
+//! as a JSON `ParamsFile` and handed to nodes, which load it with `load_verified` and only
+//! accept the parameters if the file was signed by the trusted governance key, is within its
+//! validity window and holds valid parameters.
+//!
+//! Signatures are made as in `signing`, so a params file is a `Signed` payload like any other
+//! signed result.
+
+use crate::config::{validate_params, ConfigError};
+use crate::signing::{Signed, SigningError};
+use crate::unicorn::UnicornFixedParam;
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parameters blessed by governance for a window of time
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct BlessedParams {
+    pub params: UnicornFixedParam,
+    /// Time from which the parameters may be used, in unix seconds
+    pub valid_from: u64,
+    /// Time after which the parameters may no longer be used, in unix seconds
+    pub valid_until: u64,
+}
+
+/// Parameter distribution file: blessed parameters signed by governance
+pub type ParamsFile = Signed<BlessedParams>;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParamsFileError {
+    /// The file could not be read.
+    Io(String),
+    /// The file is not a valid params file.
+    Parse(String),
+    /// The signature is malformed, untrusted or does not match.
+    Signing(SigningError),
+    /// The validity window has not started yet.
+    NotYetValid { valid_from: u64 },
+    /// The validity window has ended.
+    Expired { valid_until: u64 },
+    /// The signed parameters are themselves invalid.
+    Invalid(ConfigError),
+}
+
+impl fmt::Display for ParamsFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsFileError::Io(e) => write!(f, "Could not read params file: {}", e),
+            ParamsFileError::Parse(e) => write!(f, "Invalid params file: {}", e),
+            ParamsFileError::Signing(e) => write!(f, "Params file not trusted: {}", e),
+            ParamsFileError::NotYetValid { valid_from } => {
+                write!(f, "Params are not valid before {}", valid_from)
+            }
+            ParamsFileError::Expired { valid_until } => {
+                write!(f, "Params expired at {}", valid_until)
+            }
+            ParamsFileError::Invalid(e) => write!(f, "Signed params are invalid: {}", e),
+        }
+    }
+}
+
+impl Error for ParamsFileError {}
+
+impl From<SigningError> for ParamsFileError {
+    fn from(e: SigningError) -> Self {
+        ParamsFileError::Signing(e)
+    }
+}
+
+impl Signed<BlessedParams> {
+    /// Blesses parameters for a window of time with the governance key
+    ///
+    /// ### Arguments
+    ///
+    /// * `params`      - Parameters to bless
+    /// * `valid_from`  - Time from which they may be used, in unix seconds
+    /// * `valid_until` - Time after which they may no longer be used, in unix seconds
+    /// * `keypair`     - Governance key pair
+    pub fn issue(
+        params: UnicornFixedParam,
+        valid_from: u64,
+        valid_until: u64,
+        keypair: &Keypair,
+    ) -> Self {
+        let blessed = BlessedParams {
+            params,
+            valid_from,
+            valid_until,
+        };
+
+        Signed::sign(blessed, keypair)
+    }
+
+    /// Checks that the file was signed by the trusted key, is valid at the given time and
+    /// holds valid parameters, returning the parameters
+    ///
+    /// ### Arguments
+    ///
+    /// * `trusted` - Trusted governance public key
+    /// * `now`     - Current unix time in seconds
+    pub fn verify_at(
+        &self,
+        trusted: &PublicKey,
+        now: u64,
+    ) -> Result<&UnicornFixedParam, ParamsFileError> {
+        self.verify_signature(trusted)?;
+
+        let blessed = &self.payload;
+        if now < blessed.valid_from {
+            return Err(ParamsFileError::NotYetValid {
+                valid_from: blessed.valid_from,
+            });
+        }
+        if now > blessed.valid_until {
+            return Err(ParamsFileError::Expired {
+                valid_until: blessed.valid_until,
+            });
+        }
+        validate_params(&blessed.params).map_err(ParamsFileError::Invalid)?;
+
+        Ok(&blessed.params)
+    }
+}
+
+/// Loads a params file and checks it against the trusted governance key at the given time,
+/// returning the blessed parameters
+///
+/// ### Arguments
+///
+/// * `path`    - Path of the JSON params file
+/// * `trusted` - Trusted governance public key
+/// * `now`     - Current unix time in seconds
+pub fn load_verified_at(
+    path: &Path,
+    trusted: &PublicKey,
+    now: u64,
+) -> Result<UnicornFixedParam, ParamsFileError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ParamsFileError::Io(format!("{}: {}", path.display(), e)))?;
+    let file: ParamsFile =
+        serde_json::from_str(&contents).map_err(|e| ParamsFileError::Parse(e.to_string()))?;
+
+    file.verify_at(trusted, now).cloned()
+}
+
+/// Loads a params file and checks it against the trusted governance key now, returning the
+/// blessed parameters
+///
+/// ### Arguments
+///
+/// * `path`    - Path of the JSON params file
+/// * `trusted` - Trusted governance public key
+pub fn load_verified(
+    path: &Path,
+    trusted: &PublicKey,
+) -> Result<UnicornFixedParam, ParamsFileError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    load_verified_at(path, trusted, now)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod paramsfile_tests {
+    use super::*;
+    use crate::signing::test_keypair;
+
+    #[test]
+    /// Checks that params files are only accepted when signed by the trusted key, within
+    /// their window and for valid parameters
+    fn load_signed_params() {
+        let governance = test_keypair(1);
+        let params = UnicornFixedParam::test();
+        let file = ParamsFile::issue(params.clone(), 100, 200, &governance);
+
+        let path =
+            std::env::temp_dir().join(format!("miner-lottery-params-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_string_pretty(&file).unwrap()).unwrap();
+        let loaded = load_verified_at(&path, &governance.public, 150);
+        let untrusted = load_verified_at(&path, &test_keypair(2).public, 150);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, Ok(params.clone()));
+        assert_eq!(
+            untrusted,
+            Err(ParamsFileError::Signing(SigningError::UntrustedSigner))
+        );
+        assert_eq!(
+            file.verify_at(&governance.public, 99),
+            Err(ParamsFileError::NotYetValid { valid_from: 100 })
+        );
+        assert_eq!(
+            file.verify_at(&governance.public, 201),
+            Err(ParamsFileError::Expired { valid_until: 200 })
+        );
+
+        let mut extended = file.clone();
+        extended.payload.valid_until = 1_000;
+        assert_eq!(
+            extended.verify_at(&governance.public, 150),
+            Err(ParamsFileError::Signing(SigningError::BadSignature))
+        );
+
+        let bad = UnicornFixedParam {
+            modulus: "15".to_string(),
+            ..params
+        };
+        assert!(matches!(
+            ParamsFile::issue(bad, 100, 200, &governance).verify_at(&governance.public, 150),
+            Err(ParamsFileError::Invalid(_))
+        ));
+        assert!(matches!(
+            load_verified(&path, &governance.public),
+            Err(ParamsFileError::Io(_))
+        ));
+    }
+}
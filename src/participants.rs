@@ -0,0 +1,349 @@
+//! Registry of lottery participants with canonical identity handling.
+//!
+//! Identities are normalized before registration: surrounding whitespace is trimmed and
+//! hex-encoded 32-byte keys are lowercased (other identities, including shorter hex strings
+//! and encodings such as base64, are case-sensitive and are kept as-is). Each normalized
+//! identity is fingerprinted with SHA-256, and duplicates are rejected by fingerprint.
+//!
+//! Participants are kept sorted by fingerprint, so a participant's index depends only on the
+//! set of registered identities and not on the order in which they were submitted. The same
+//! canonical order is used for the bytes fed into the seed.
+//!
+//! A registry can require a proof-of-work ticket with every registration, making it costly to
+//! register many fake identities.
+
+use crate::merkle::MerkleTree;
+use crate::pow::PowRequirement;
+use crate::unicorn::construct_seed;
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+/// Length of a hex-encoded key, whose case is not significant
+pub const KEY_HEX_LEN: usize = 64;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RegistryError {
+    /// Identity was empty after normalization.
+    EmptyIdentity,
+    /// Identity normalizes to one that is already registered.
+    Duplicate { fingerprint: String },
+    /// Proof-of-work ticket is required but was not given.
+    MissingTicket,
+    /// Proof-of-work ticket does not meet the requirement.
+    InsufficientWork,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::EmptyIdentity => write!(f, "Identity is empty"),
+            RegistryError::Duplicate { fingerprint } => {
+                write!(f, "Duplicate identity with fingerprint {}", fingerprint)
+            }
+            RegistryError::MissingTicket => write!(f, "Proof-of-work ticket is required"),
+            RegistryError::InsufficientWork => {
+                write!(f, "Proof-of-work ticket does not meet the requirement")
+            }
+        }
+    }
+}
+
+impl Error for RegistryError {}
+
+/// A registered participant
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Participant {
+    /// Normalized identity
+    pub identity: String,
+    /// Hex-encoded SHA-256 of the normalized identity
+    pub fingerprint: String,
+}
+
+/// Registry of unique participants in canonical order
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ParticipantRegistry {
+    participants: Vec<Participant>,
+    /// Proof-of-work required with every registration, if any
+    #[serde(default)]
+    pow_requirement: Option<PowRequirement>,
+}
+
+impl ParticipantRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry from a list of identities, failing on the first invalid
+    /// or duplicate identity
+    ///
+    /// ### Arguments
+    ///
+    /// * `identities` - Identities to register
+    pub fn from_identities<I, S>(identities: I) -> Result<Self, RegistryError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut registry = Self::new();
+        for identity in identities {
+            registry.register(identity.as_ref())?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Requires a proof-of-work ticket with every registration
+    ///
+    /// ### Arguments
+    ///
+    /// * `requirement` - Proof-of-work to require
+    pub fn with_pow_requirement(mut self, requirement: PowRequirement) -> Self {
+        self.pow_requirement = Some(requirement);
+        self
+    }
+
+    /// Proof-of-work required with every registration, if any
+    pub fn pow_requirement(&self) -> Option<&PowRequirement> {
+        self.pow_requirement.as_ref()
+    }
+
+    /// Registers an identity, returning its fingerprint. Fails if the registry requires
+    /// proof-of-work tickets
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity to register
+    pub fn register(&mut self, identity: &str) -> Result<String, RegistryError> {
+        self.register_with_ticket(identity, None)
+    }
+
+    /// Registers an identity with a proof-of-work ticket nonce, returning its fingerprint.
+    /// The ticket is ignored if the registry does not require one
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity to register
+    /// * `nonce`    - Ticket nonce for the normalized identity
+    pub fn register_with_ticket(
+        &mut self,
+        identity: &str,
+        nonce: Option<u64>,
+    ) -> Result<String, RegistryError> {
+        let round_id = self.pow_requirement.map(|r| r.round_id);
+        self.register_bound(identity, nonce, round_id)
+    }
+
+    /// Registers an identity carried over from an earlier round, with a proof-of-work ticket
+    /// bound to that round. The ticket must still meet the required difficulty
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity to register
+    /// * `nonce`    - Ticket nonce for the normalized identity
+    /// * `round_id` - Round the ticket is bound to
+    pub(crate) fn register_carried(
+        &mut self,
+        identity: &str,
+        nonce: Option<u64>,
+        round_id: u64,
+    ) -> Result<String, RegistryError> {
+        self.register_bound(identity, nonce, Some(round_id))
+    }
+
+    /// Registers an identity with a ticket bound to the given round, if work is required
+    fn register_bound(
+        &mut self,
+        identity: &str,
+        nonce: Option<u64>,
+        round_id: Option<u64>,
+    ) -> Result<String, RegistryError> {
+        let identity = normalize_identity(identity);
+        if identity.is_empty() {
+            return Err(RegistryError::EmptyIdentity);
+        }
+
+        if let (Some(requirement), Some(round_id)) = (&self.pow_requirement, round_id) {
+            let requirement = PowRequirement {
+                round_id,
+                ..*requirement
+            };
+            let nonce = nonce.ok_or(RegistryError::MissingTicket)?;
+            if !requirement.check(&identity, nonce) {
+                return Err(RegistryError::InsufficientWork);
+            }
+        }
+
+        let fingerprint = fingerprint(&identity);
+        match self.position(&fingerprint) {
+            Ok(_) => Err(RegistryError::Duplicate { fingerprint }),
+            Err(pos) => {
+                self.participants.insert(
+                    pos,
+                    Participant {
+                        identity,
+                        fingerprint: fingerprint.clone(),
+                    },
+                );
+                Ok(fingerprint)
+            }
+        }
+    }
+
+    /// Number of registered participants
+    pub fn len(&self) -> usize {
+        self.participants.len()
+    }
+
+    /// Whether no participants are registered
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    /// Registered participants in canonical order
+    pub fn participants(&self) -> &[Participant] {
+        &self.participants
+    }
+
+    /// Gets the participant at a canonical index
+    ///
+    /// ### Arguments
+    ///
+    /// * `index` - Canonical index of the participant
+    pub fn get(&self, index: usize) -> Option<&Participant> {
+        self.participants.get(index)
+    }
+
+    /// Gets the canonical index of an identity, if registered
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity to look up; normalized before lookup
+    pub fn index_of(&self, identity: &str) -> Option<usize> {
+        let fingerprint = fingerprint(&normalize_identity(identity));
+        self.position(&fingerprint).ok()
+    }
+
+    /// Normalized identities in canonical order, as passed to `construct_seed`
+    pub fn seed_inputs(&self) -> Vec<String> {
+        self.participants
+            .iter()
+            .map(|p| p.identity.clone())
+            .collect()
+    }
+
+    /// Exact bytes hashed into the seed
+    pub fn seed_bytes(&self) -> Vec<u8> {
+        serialize(&self.seed_inputs()).unwrap()
+    }
+
+    /// Constructs the seed from the registered participants
+    pub fn construct_seed(&self) -> Integer {
+        construct_seed(&self.seed_inputs())
+    }
+
+    /// Merkle tree over the normalized identities in canonical order
+    pub fn merkle_tree(&self) -> MerkleTree {
+        MerkleTree::new(&self.seed_inputs())
+    }
+
+    fn position(&self, fingerprint: &str) -> Result<usize, usize> {
+        self.participants
+            .binary_search_by(|p| p.fingerprint.as_str().cmp(fingerprint))
+    }
+}
+
+/// Normalizes an identity: trims surrounding whitespace and lowercases hex-encoded keys
+///
+/// ### Arguments
+///
+/// * `identity` - Identity to normalize
+pub fn normalize_identity(identity: &str) -> String {
+    let identity = identity.trim();
+
+    if identity.len() == KEY_HEX_LEN && identity.chars().all(|c| c.is_ascii_hexdigit()) {
+        identity.to_ascii_lowercase()
+    } else {
+        identity.to_string()
+    }
+}
+
+/// Computes the hex-encoded SHA-256 fingerprint of a normalized identity
+///
+/// ### Arguments
+///
+/// * `identity` - Normalized identity
+pub fn fingerprint(identity: &str) -> String {
+    hex::encode(Sha256::digest(identity.as_bytes()))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod participants_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that canonical order does not depend on registration order
+    fn order_independent() {
+        let a = ParticipantRegistry::from_identities(["a1b2", "c3d4", "AAAAC3Nza"]).unwrap();
+        let b = ParticipantRegistry::from_identities(["AAAAC3Nza", "c3d4", "a1b2"]).unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a.seed_bytes(), b.seed_bytes());
+        assert_eq!(a.construct_seed(), construct_seed(&a.seed_inputs()));
+    }
+
+    #[test]
+    /// Checks that identities differing only by whitespace or key hex case are duplicates
+    fn rejects_duplicates() {
+        let key = "a1b2".repeat(KEY_HEX_LEN / 4);
+        let mut registry = ParticipantRegistry::new();
+        let fingerprint = registry.register(&key).unwrap();
+
+        assert_eq!(
+            registry.register(&format!("  {}\n", key.to_ascii_uppercase())),
+            Err(RegistryError::Duplicate { fingerprint })
+        );
+        assert!(registry.register("A1B2").is_ok());
+        assert!(registry.register(" a1b2 ").is_ok());
+        assert_eq!(registry.register("   "), Err(RegistryError::EmptyIdentity));
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    /// Checks that a registry requiring proof-of-work only accepts valid tickets
+    fn pow_tickets() {
+        let requirement = PowRequirement {
+            round_id: 1,
+            difficulty: 8,
+        };
+        let mut registry = ParticipantRegistry::new().with_pow_requirement(requirement);
+        let nonce = requirement.solve("a1b2");
+
+        assert_eq!(registry.register("a1b2"), Err(RegistryError::MissingTicket));
+        assert_eq!(
+            registry.register_with_ticket("c3d4", Some(nonce)),
+            Err(RegistryError::InsufficientWork)
+        );
+        assert!(registry.register_with_ticket(" a1b2 ", Some(nonce)).is_ok());
+    }
+
+    #[test]
+    /// Checks that base64 identities keep their case
+    fn base64_case_preserved() {
+        let registry = ParticipantRegistry::from_identities(["AAAAC3Nza", "aaaac3nza"]).unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.index_of(" AAAAC3Nza "),
+            registry.index_of("AAAAC3Nza")
+        );
+    }
+}
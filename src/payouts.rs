@@ -0,0 +1,216 @@
+//! Prize payout splitting among multiple winners.
+//!
+//! Payouts are in indivisible units, so a split usually leaves a few units over that cannot
+//! be shared evenly. Those units are handed out one each to winners ranked from the UNICORN's
+//! Fortuna stream under the payouts usage number, so a payout can be reproduced by any
+//! verifier holding the `UnicornInfo`, just like the draw itself.
+
+use crate::selection::select_many;
+use crate::unicorn::UnicornInfo;
+use crate::usage::PAYOUTS_NAMESPACE;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Usage number of the payout tiebreak draw
+pub const PAYOUT_USAGE_NUMBER: u128 = PAYOUTS_NAMESPACE as u128;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PayoutError {
+    /// The scheme has no winners to pay.
+    NoWinners,
+    /// Fixed shares add up to more than the total.
+    ExceedsTotal { requested: u128, total: u64 },
+    /// Proportional weights add up to zero.
+    ZeroWeight,
+    /// The UNICORN's `g` value is too short to draw the tiebreak from.
+    InvalidGValue,
+}
+
+impl fmt::Display for PayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayoutError::NoWinners => write!(f, "Payout has no winners"),
+            PayoutError::ExceedsTotal { requested, total } => write!(
+                f,
+                "Fixed shares of {} exceed the total of {}",
+                requested, total
+            ),
+            PayoutError::ZeroWeight => write!(f, "Payout weights add up to zero"),
+            PayoutError::InvalidGValue => write!(f, "UNICORN g value is too short"),
+        }
+    }
+}
+
+impl Error for PayoutError {}
+
+/// How a total is split among winners, in winner order
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum PayoutScheme {
+    /// Each winner is paid a fixed amount; anything left over is not paid out
+    Fixed(Vec<u64>),
+    /// Winners are paid in proportion to their weights. Units left over go to the winners
+    /// with the largest fractional shares, with ties broken by the PRN stream
+    Proportional(Vec<u64>),
+    /// The total is split equally among this many winners. Units left over go to winners
+    /// drawn from the PRN stream
+    RandomTiebreak(usize),
+}
+
+/// Result of a payout split
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PayoutSplit {
+    /// Amount paid to each winner, in winner order
+    pub amounts: Vec<u64>,
+    /// Part of the total that is not paid out
+    pub unallocated: u64,
+}
+
+/// Splits a total among winners according to a scheme
+///
+/// ### Arguments
+///
+/// * `total`   - Total to split, in indivisible units
+/// * `scheme`  - Scheme to split by
+/// * `unicorn` - UNICORN the winners were drawn from
+pub fn split(
+    total: u64,
+    scheme: &PayoutScheme,
+    unicorn: &UnicornInfo,
+) -> Result<PayoutSplit, PayoutError> {
+    match scheme {
+        PayoutScheme::Fixed(shares) => {
+            if shares.is_empty() {
+                return Err(PayoutError::NoWinners);
+            }
+
+            let requested: u128 = shares.iter().map(|s| *s as u128).sum();
+            if requested > total as u128 {
+                return Err(PayoutError::ExceedsTotal { requested, total });
+            }
+
+            Ok(PayoutSplit {
+                amounts: shares.clone(),
+                unallocated: total - requested as u64,
+            })
+        }
+        PayoutScheme::Proportional(weights) => split_weighted(total, weights, unicorn),
+        PayoutScheme::RandomTiebreak(winners) => split_weighted(total, &vec![1; *winners], unicorn),
+    }
+}
+
+/// Splits a total in proportion to weights using the largest remainder method
+fn split_weighted(
+    total: u64,
+    weights: &[u64],
+    unicorn: &UnicornInfo,
+) -> Result<PayoutSplit, PayoutError> {
+    if weights.is_empty() {
+        return Err(PayoutError::NoWinners);
+    }
+
+    let total_weight: u128 = weights.iter().map(|w| *w as u128).sum();
+    if total_weight == 0 {
+        return Err(PayoutError::ZeroWeight);
+    }
+
+    // Shares and fractional parts are exact: total * weight < 2^128 for 64-bit values
+    let shares: Vec<(u64, u128)> = weights
+        .iter()
+        .map(|w| {
+            let scaled = total as u128 * *w as u128;
+            ((scaled / total_weight) as u64, scaled % total_weight)
+        })
+        .collect();
+    let paid: u64 = shares.iter().map(|(share, _)| share).sum();
+
+    let n = weights.len();
+    let mut tiebreak = vec![0; n];
+    for (rank, index) in select_many(unicorn, PAYOUT_USAGE_NUMBER, n, n)
+        .map_err(|_| PayoutError::InvalidGValue)?
+        .into_iter()
+        .enumerate()
+    {
+        tiebreak[index] = rank;
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|i| (std::cmp::Reverse(shares[*i].1), tiebreak[*i]));
+
+    let mut amounts: Vec<u64> = shares.iter().map(|(share, _)| *share).collect();
+    for i in order.into_iter().take((total - paid) as usize) {
+        amounts[i] += 1;
+    }
+
+    Ok(PayoutSplit {
+        amounts,
+        unallocated: 0,
+    })
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod payouts_tests {
+    use super::*;
+
+    fn test_unicorn() -> UnicornInfo {
+        UnicornInfo {
+            g_value: "0106834db40e90d1cafaa9e4c1981873186ebf019629852059aaf8e4ca35da01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that fixed shares are paid as given and refused beyond the total
+    fn fixed_split() {
+        let unicorn = test_unicorn();
+        let payout = split(100, &PayoutScheme::Fixed(vec![50, 30]), &unicorn).unwrap();
+
+        assert_eq!(payout.amounts, vec![50, 30]);
+        assert_eq!(payout.unallocated, 20);
+        assert_eq!(
+            split(10, &PayoutScheme::Fixed(vec![6, 6]), &unicorn),
+            Err(PayoutError::ExceedsTotal {
+                requested: 12,
+                total: 10
+            })
+        );
+    }
+
+    #[test]
+    /// Checks that proportional splits pay the full total and leftovers go by largest remainder
+    fn proportional_split() {
+        let unicorn = test_unicorn();
+        let payout = split(10, &PayoutScheme::Proportional(vec![1, 2, 3]), &unicorn).unwrap();
+
+        // Exact shares are 1.67, 3.33 and 5
+        assert_eq!(payout.amounts, vec![2, 3, 5]);
+        assert_eq!(
+            split(10, &PayoutScheme::Proportional(vec![0, 0]), &unicorn),
+            Err(PayoutError::ZeroWeight)
+        );
+    }
+
+    #[test]
+    /// Checks that equal splits hand leftover units to the first winners of the PRN ranking
+    fn random_tiebreak_split() {
+        let unicorn = test_unicorn();
+        let payout = split(11, &PayoutScheme::RandomTiebreak(4), &unicorn).unwrap();
+        let ranking = select_many(&unicorn, PAYOUT_USAGE_NUMBER, 4, 3).unwrap();
+
+        assert_eq!(payout.amounts.iter().sum::<u64>(), 11);
+        for (i, amount) in payout.amounts.iter().enumerate() {
+            assert_eq!(*amount, if ranking.contains(&i) { 3 } else { 2 });
+        }
+
+        let short_g = UnicornInfo {
+            g_value: "0106".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            split(11, &PayoutScheme::RandomTiebreak(4), &short_g),
+            Err(PayoutError::InvalidGValue)
+        );
+    }
+}
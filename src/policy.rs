@@ -0,0 +1,161 @@
+//! Minimum-delay policy for published rounds.
+//!
+//! A UNICORN is only uncontestable if nobody can evaluate it before the contributions are
+//! sealed. A node configured with too few iterations for its modulus would publish "instant"
+//! UNICORNs that defeat that argument, while still passing every other check. The policy
+//! uses a calibration of the eval speed to estimate the delay implied by a set of
+//! parameters, and rejects them if it falls below a configured floor.
+//!
+//! Eval speed depends on the size of the modulus, so a calibration only applies to moduli of
+//! the size it was measured with.
+
+use crate::calibrate::{time_at_rate, Calibration};
+use crate::unicorn::UnicornFixedParam;
+use rug::Integer;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyError {
+    /// The modulus is not a decimal integer.
+    InvalidModulus,
+    /// The calibration was measured with a modulus of a different size.
+    Uncalibrated {
+        calibrated_bits: u32,
+        modulus_bits: u32,
+    },
+    /// The parameters imply a delay below the floor.
+    DelayTooShort { implied: Duration, floor: Duration },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::InvalidModulus => write!(f, "Modulus is not a decimal integer"),
+            PolicyError::Uncalibrated {
+                calibrated_bits,
+                modulus_bits,
+            } => write!(
+                f,
+                "Calibration is for a {}-bit modulus, not {} bits",
+                calibrated_bits, modulus_bits
+            ),
+            PolicyError::DelayTooShort { implied, floor } => write!(
+                f,
+                "Parameters imply a delay of {:.3}s, below the minimum of {}s",
+                implied.as_secs_f64(),
+                floor.as_secs_f64()
+            ),
+        }
+    }
+}
+
+impl Error for PolicyError {}
+
+/// Floor on the delay implied by UNICORN parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelayPolicy {
+    /// Shortest delay a round may imply
+    pub min_delay: Duration,
+    /// Eval speed the delay is estimated from
+    pub calibration: Calibration,
+}
+
+impl DelayPolicy {
+    /// Creates a policy
+    ///
+    /// ### Arguments
+    ///
+    /// * `min_delay`   - Shortest delay a round may imply
+    /// * `calibration` - Eval speed the delay is estimated from
+    pub fn new(min_delay: Duration, calibration: Calibration) -> Self {
+        Self {
+            min_delay,
+            calibration,
+        }
+    }
+
+    /// Delay implied by an iteration count at the calibrated eval speed
+    ///
+    /// ### Arguments
+    ///
+    /// * `iterations` - UNICORN iterations
+    pub fn implied_delay(&self, iterations: u64) -> Duration {
+        time_at_rate(iterations, self.calibration.iterations_per_sec)
+    }
+
+    /// Checks parameters against the policy, returning the delay they imply
+    ///
+    /// ### Arguments
+    ///
+    /// * `params` - Parameters to check
+    pub fn check(&self, params: &UnicornFixedParam) -> Result<Duration, PolicyError> {
+        let modulus = Integer::from_str_radix(&params.modulus, 10)
+            .map_err(|_| PolicyError::InvalidModulus)?;
+        if modulus.significant_bits() != self.calibration.modulus_bits {
+            return Err(PolicyError::Uncalibrated {
+                calibrated_bits: self.calibration.modulus_bits,
+                modulus_bits: modulus.significant_bits(),
+            });
+        }
+
+        let implied = self.implied_delay(params.iterations);
+        if implied < self.min_delay {
+            return Err(PolicyError::DelayTooShort {
+                implied,
+                floor: self.min_delay,
+            });
+        }
+
+        Ok(implied)
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod policy_tests {
+    use super::*;
+
+    fn create_policy() -> DelayPolicy {
+        let calibration = Calibration {
+            modulus_bits: 521,
+            sample_iterations: 1_000,
+            sample_time: Duration::from_millis(500),
+            iterations_per_sec: 2_000.0,
+            recommended_iterations: 120_000,
+        };
+        DelayPolicy::new(Duration::from_secs(60), calibration)
+    }
+
+    #[test]
+    /// Checks that parameters are held to the floor at the calibrated speed
+    fn enforces_floor() {
+        let policy = create_policy();
+        let mut params = UnicornFixedParam {
+            iterations: 120_000,
+            ..UnicornFixedParam::test()
+        };
+
+        assert_eq!(policy.check(&params), Ok(Duration::from_secs(60)));
+
+        params.iterations = 1_000;
+        assert_eq!(
+            policy.check(&params),
+            Err(PolicyError::DelayTooShort {
+                implied: Duration::from_millis(500),
+                floor: Duration::from_secs(60),
+            })
+        );
+
+        params.modulus = "2147483647".to_string();
+        assert!(matches!(
+            policy.check(&params),
+            Err(PolicyError::Uncalibrated {
+                calibrated_bits: 521,
+                modulus_bits: 31
+            })
+        ));
+    }
+}
@@ -0,0 +1,92 @@
+//! Proof-of-work tickets for Sybil resistance.
+//!
+//! A round can require every participant to attach a ticket: a nonce such that the SHA-256 of
+//! the round id, the participant's normalized identity and the nonce has a minimum number of
+//! leading zero bits. The work is bound to both the round and the identity, so tickets cannot
+//! be reused across rounds or identities, and registering many fake identities costs work for
+//! each one.
+
+use bincode::serialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Proof-of-work required of each participant in a round
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PowRequirement {
+    /// Round the work must be bound to
+    pub round_id: u64,
+    /// Minimum number of leading zero bits of the ticket hash
+    pub difficulty: u32,
+}
+
+impl PowRequirement {
+    /// Checks a ticket nonce for a normalized identity
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Normalized identity of the participant
+    /// * `nonce`    - Ticket nonce
+    pub fn check(&self, identity: &str, nonce: u64) -> bool {
+        leading_zero_bits(&ticket_hash(self.round_id, identity, nonce)) >= self.difficulty
+    }
+
+    /// Finds the first ticket nonce satisfying the requirement for a normalized identity.
+    /// Takes `2^difficulty` hashes on average
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Normalized identity of the participant
+    pub fn solve(&self, identity: &str) -> u64 {
+        (0..).find(|nonce| self.check(identity, *nonce)).unwrap()
+    }
+}
+
+/// Hash of a ticket: SHA-256 of the bincode encoding of the round id, identity and nonce
+///
+/// ### Arguments
+///
+/// * `round_id` - Round the ticket is for
+/// * `identity` - Normalized identity of the participant
+/// * `nonce`    - Ticket nonce
+pub fn ticket_hash(round_id: u64, identity: &str, nonce: u64) -> [u8; 32] {
+    Sha256::digest(serialize(&(round_id, identity, nonce)).unwrap()).into()
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    bits
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod pow_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that solved tickets are bound to their round and identity
+    fn tickets_are_bound() {
+        let requirement = PowRequirement {
+            round_id: 1,
+            difficulty: 8,
+        };
+        let other_round = PowRequirement {
+            round_id: 2,
+            ..requirement
+        };
+        let nonce = requirement.solve("miner_a");
+
+        assert!(requirement.check("miner_a", nonce));
+        assert!(leading_zero_bits(&ticket_hash(1, "miner_a", nonce)) >= 8);
+        assert!(!other_round.check("miner_a", nonce));
+        assert!(!requirement.check("miner_b", nonce));
+    }
+}
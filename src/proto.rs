@@ -0,0 +1,340 @@
+//! Protobuf messages for round data, matching `proto/types.proto`.
+//!
+//! Non-Rust services can exchange UNICORN parameters, evaluated UNICORNs and selection proofs
+//! in these messages, whether over the gRPC service or on their own, e.g. as Kafka records.
+//! As with the gRPC service, the messages are written by hand so that building does not
+//! require `protoc`. Big integers are carried as strings, hex for seeds and witnesses and
+//! decimal for moduli as in `UnicornFixedParam`, and usage numbers as their namespace and
+//! sequence.
+
+use crate::rounds::GracePolicy;
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{
+    SeedMode, Unicorn as UnicornValue, UnicornFixedParam, UnicornInfo as UnicornInfoValue,
+    MR_PRIME_ITERS,
+};
+use crate::usage::{usage_number, UsageAllocation};
+use rug::Integer;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FixedParams {
+    #[prost(string, tag = "1")]
+    pub modulus: String,
+    #[prost(uint64, tag = "2")]
+    pub iterations: u64,
+    #[prost(uint32, tag = "3")]
+    pub security: u32,
+    #[prost(uint32, tag = "4")]
+    pub primality_rounds: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Unicorn {
+    #[prost(uint64, tag = "1")]
+    pub iterations: u64,
+    #[prost(uint32, tag = "2")]
+    pub security_level: u32,
+    #[prost(string, tag = "3")]
+    pub seed: String,
+    #[prost(string, tag = "4")]
+    pub modulus: String,
+    #[prost(uint32, tag = "5")]
+    pub primality_rounds: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UnicornInfo {
+    #[prost(message, optional, tag = "1")]
+    pub unicorn: Option<Unicorn>,
+    #[prost(string, tag = "2")]
+    pub g_value: String,
+    #[prost(string, tag = "3")]
+    pub witness: String,
+    #[prost(string, optional, tag = "4")]
+    pub input_digest: Option<String>,
+    #[prost(message, optional, tag = "5")]
+    pub fixed_params: Option<FixedParams>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UsageNumber {
+    #[prost(uint64, tag = "1")]
+    pub namespace: u64,
+    #[prost(uint64, tag = "2")]
+    pub sequence: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SelectionProof {
+    #[prost(string, repeated, tag = "1")]
+    pub inputs: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub last_winning_hashes: Vec<String>,
+    #[prost(message, optional, tag = "3")]
+    pub fixed_params: Option<FixedParams>,
+    #[prost(string, tag = "4")]
+    pub seed: String,
+    #[prost(string, tag = "5")]
+    pub commitment: String,
+    #[prost(string, tag = "6")]
+    pub witness: String,
+    #[prost(string, tag = "7")]
+    pub g_value: String,
+    #[prost(message, optional, tag = "8")]
+    pub usage_number: Option<UsageNumber>,
+    #[prost(string, tag = "9")]
+    pub prn_bytes: String,
+    #[prost(uint64, tag = "10")]
+    pub winner_index: u64,
+    #[prost(string, tag = "11")]
+    pub winner: String,
+    #[prost(message, repeated, tag = "12")]
+    pub usage_allocations: Vec<UsageNumber>,
+    #[prost(string, optional, tag = "13")]
+    pub merkle_root: Option<String>,
+    #[prost(bool, tag = "14")]
+    pub tree_hash: bool,
+    #[prost(uint64, tag = "15")]
+    pub cutoff: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProtoError {
+    /// A required message field was not set.
+    MissingField(&'static str),
+    /// A big integer field could not be parsed.
+    InvalidInteger(&'static str),
+}
+
+impl fmt::Display for ProtoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoError::MissingField(field) => write!(f, "`{}` is required", field),
+            ProtoError::InvalidInteger(field) => write!(f, "`{}` is not a valid integer", field),
+        }
+    }
+}
+
+impl Error for ProtoError {}
+
+/// Parses a big integer field of the given radix
+///
+/// ### Arguments
+///
+/// * `field` - Name of the field, for the error
+/// * `value` - Value of the field
+/// * `radix` - Radix the value is written in
+fn integer(field: &'static str, value: &str, radix: i32) -> Result<Integer, ProtoError> {
+    Integer::from_str_radix(value, radix).map_err(|_| ProtoError::InvalidInteger(field))
+}
+
+/// Primality rounds from a message, where 0 means the field was not set
+fn primality_rounds(rounds: u32) -> u32 {
+    match rounds {
+        0 => MR_PRIME_ITERS,
+        rounds => rounds,
+    }
+}
+
+impl From<&UnicornFixedParam> for FixedParams {
+    fn from(params: &UnicornFixedParam) -> Self {
+        Self {
+            modulus: params.modulus.clone(),
+            iterations: params.iterations,
+            security: params.security,
+            primality_rounds: params.primality_rounds,
+        }
+    }
+}
+
+impl TryFrom<FixedParams> for UnicornFixedParam {
+    type Error = ProtoError;
+
+    fn try_from(p: FixedParams) -> Result<Self, ProtoError> {
+        integer("modulus", &p.modulus, 10)?;
+        Ok(Self {
+            modulus: p.modulus,
+            iterations: p.iterations,
+            security: p.security,
+            primality_rounds: primality_rounds(p.primality_rounds),
+        })
+    }
+}
+
+impl From<&UnicornValue> for Unicorn {
+    fn from(u: &UnicornValue) -> Self {
+        Self {
+            iterations: u.iterations,
+            security_level: u.security_level,
+            seed: u.seed.to_string_radix(16),
+            modulus: u.modulus.to_string(),
+            primality_rounds: u.primality_rounds,
+        }
+    }
+}
+
+impl TryFrom<Unicorn> for UnicornValue {
+    type Error = ProtoError;
+
+    fn try_from(u: Unicorn) -> Result<Self, ProtoError> {
+        Ok(Self {
+            iterations: u.iterations,
+            security_level: u.security_level,
+            seed: integer("seed", &u.seed, 16)?,
+            modulus: integer("modulus", &u.modulus, 10)?,
+            primality_rounds: primality_rounds(u.primality_rounds),
+        })
+    }
+}
+
+impl From<&UnicornInfoValue> for UnicornInfo {
+    fn from(info: &UnicornInfoValue) -> Self {
+        Self {
+            unicorn: Some((&info.unicorn).into()),
+            g_value: info.g_value.clone(),
+            witness: info.witness.to_string_radix(16),
+            input_digest: info.input_digest.clone(),
+            fixed_params: info.fixed_params.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<UnicornInfo> for UnicornInfoValue {
+    type Error = ProtoError;
+
+    fn try_from(info: UnicornInfo) -> Result<Self, ProtoError> {
+        let unicorn = info.unicorn.ok_or(ProtoError::MissingField("unicorn"))?;
+        Ok(Self {
+            unicorn: unicorn.try_into()?,
+            g_value: info.g_value,
+            witness: integer("witness", &info.witness, 16)?,
+            input_digest: info.input_digest,
+            fixed_params: info.fixed_params.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+impl From<u128> for UsageNumber {
+    fn from(usage: u128) -> Self {
+        let allocation = UsageAllocation::from_usage_number(usage);
+        Self {
+            namespace: allocation.namespace,
+            sequence: allocation.sequence,
+        }
+    }
+}
+
+impl From<&UsageNumber> for u128 {
+    fn from(u: &UsageNumber) -> Self {
+        usage_number(u.namespace, u.sequence)
+    }
+}
+
+impl From<&SelectionTranscript> for SelectionProof {
+    fn from(t: &SelectionTranscript) -> Self {
+        Self {
+            inputs: t.inputs.clone(),
+            last_winning_hashes: t.last_winning_hashes.clone(),
+            fixed_params: Some((&t.fixed_params).into()),
+            seed: t.seed.to_string_radix(16),
+            commitment: t.commitment.clone(),
+            witness: t.witness.to_string_radix(16),
+            g_value: t.g_value.clone(),
+            usage_number: Some(t.usage_number.into()),
+            prn_bytes: t.prn_bytes.clone(),
+            winner_index: t.winner_index as u64,
+            winner: t.winner.clone(),
+            usage_allocations: t
+                .usage_allocations
+                .iter()
+                .map(|a| a.usage_number.into())
+                .collect(),
+            merkle_root: t.merkle_root.clone(),
+            tree_hash: t.seed_mode == SeedMode::TreeHash,
+            cutoff: t.cutoff,
+        }
+    }
+}
+
+impl TryFrom<SelectionProof> for SelectionTranscript {
+    type Error = ProtoError;
+
+    fn try_from(p: SelectionProof) -> Result<Self, ProtoError> {
+        let params = p
+            .fixed_params
+            .ok_or(ProtoError::MissingField("fixed_params"))?;
+
+        Ok(Self {
+            inputs: p.inputs,
+            last_winning_hashes: p.last_winning_hashes,
+            fixed_params: params.try_into()?,
+            seed: integer("seed", &p.seed, 16)?,
+            commitment: p.commitment,
+            witness: integer("witness", &p.witness, 16)?,
+            g_value: p.g_value,
+            usage_number: p
+                .usage_number
+                .as_ref()
+                .map(u128::from)
+                .ok_or(ProtoError::MissingField("usage_number"))?,
+            prn_bytes: p.prn_bytes,
+            winner_index: p.winner_index as usize,
+            winner: p.winner,
+            usage_allocations: p
+                .usage_allocations
+                .iter()
+                .map(|u| UsageAllocation::from_usage_number(u.into()))
+                .collect(),
+            merkle_root: p.merkle_root,
+            seed_mode: if p.tree_hash {
+                SeedMode::TreeHash
+            } else {
+                SeedMode::Serialized
+            },
+            timestamp: None,
+            cutoff_attestation: None,
+            cutoff: p.cutoff,
+            grace: GracePolicy::default(),
+            late_contributions: Vec::new(),
+        })
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod proto_tests {
+    use super::*;
+    use crate::unicorn::construct_unicorn;
+    use prost::Message;
+
+    #[test]
+    /// Checks that parameters, UNICORNs and proofs survive encoding and conversion
+    fn round_trips() {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let transcript = SelectionTranscript::record(&inputs, &params, 1).unwrap();
+        let info = construct_unicorn(transcript.seed.clone(), &params)
+            .with_input_digest(Some("ab".repeat(32)));
+
+        let decoded = |bytes: Vec<u8>| SelectionProof::decode(bytes.as_slice()).unwrap();
+        let proof = decoded(SelectionProof::from(&transcript).encode_to_vec());
+        let decoded_info = UnicornInfo::decode(UnicornInfo::from(&info).encode_to_vec().as_slice());
+
+        assert_eq!(SelectionTranscript::try_from(proof).unwrap(), transcript);
+        assert_eq!(
+            UnicornInfoValue::try_from(decoded_info.unwrap()).unwrap(),
+            info
+        );
+        assert_eq!(
+            UnicornFixedParam::try_from(FixedParams::from(&params)).unwrap(),
+            params
+        );
+        assert_eq!(
+            SelectionTranscript::try_from(SelectionProof::default()),
+            Err(ProtoError::MissingField("fixed_params"))
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Python bindings, built with the `python` feature.
+//!
+//! The extension module runs the same eval, verification, Fortuna and selection code as the
+//! node, so that fairness audits and operations scripts written in Python check exactly what
+//! the node did. Seeds and witnesses are Python `int`s, identities are `str` and random bytes
+//! are `bytes`. Slow calls release the GIL. The module is built with maturin, as configured
+//! in `pyproject.toml`:
+//!
+//! ```python
+//! import miner_lottery as ml
+//!
+//! inputs = ["miner_a", "miner_b"]
+//! info = ml.Unicorn(ml.construct_seed(inputs), iterations=1000).eval()
+//! assert info.verify()
+//! winners = ml.select(info, inputs, usage_number=1)
+//! ```
+
+// The pyo3 macros convert the error of every returned `PyResult` into a `PyErr`
+#![allow(clippy::useless_conversion)]
+
+use crate::audit::audit_unicorn;
+use crate::config::{validate_params, DEFAULT_ITERATIONS, DEFAULT_MODULUS, DEFAULT_SECURITY};
+use crate::fortuna::Fortuna;
+use crate::selection::prove_many_from;
+use crate::unicorn::{construct_seed as seed_from_inputs, Unicorn, UnicornFixedParam, UnicornInfo};
+use crate::utils::unicorn_selection::get_unicorn_csprng;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyInt};
+use rug::Integer;
+
+/// UNICORN with its parameters, ready to be evaluated
+#[pyclass(name = "Unicorn", module = "miner_lottery")]
+#[derive(Clone)]
+pub struct PyUnicorn {
+    inner: Unicorn,
+}
+
+#[pymethods]
+impl PyUnicorn {
+    #[new]
+    #[pyo3(signature = (seed, modulus = None, iterations = DEFAULT_ITERATIONS, security = DEFAULT_SECURITY))]
+    fn new(
+        seed: &Bound<'_, PyAny>,
+        modulus: Option<&Bound<'_, PyAny>>,
+        iterations: u64,
+        security: u32,
+    ) -> PyResult<Self> {
+        let modulus = match modulus {
+            Some(modulus) => integer_from_py(modulus)?,
+            None => Integer::from_str_radix(DEFAULT_MODULUS, 10).unwrap(),
+        };
+        let params = UnicornFixedParam {
+            modulus: modulus.to_string_radix(10),
+            iterations,
+            security,
+            ..Default::default()
+        };
+        validate_params(&params).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        Ok(Self {
+            inner: Unicorn {
+                seed: integer_from_py(seed)?,
+                modulus,
+                iterations,
+                security_level: security,
+                primality_rounds: params.primality_rounds,
+            },
+        })
+    }
+
+    #[getter]
+    fn seed(&self, py: Python<'_>) -> PyResult<PyObject> {
+        integer_to_py(py, &self.inner.seed)
+    }
+
+    #[getter]
+    fn modulus(&self, py: Python<'_>) -> PyResult<PyObject> {
+        integer_to_py(py, &self.inner.modulus)
+    }
+
+    #[getter]
+    fn iterations(&self) -> u64 {
+        self.inner.iterations
+    }
+
+    #[getter]
+    fn security(&self) -> u32 {
+        self.inner.security_level
+    }
+
+    /// Commitment value `c` for the seed
+    fn commitment(&self) -> String {
+        self.inner.commitment()
+    }
+
+    /// Runs the eval, returning the evaluated `UnicornInfo`
+    fn eval(&self, py: Python<'_>) -> PyResult<PyUnicornInfo> {
+        let unicorn = self.inner.clone();
+        let (witness, g_value) = py
+            .allow_threads(|| unicorn.eval())
+            .ok_or_else(|| PyValueError::new_err("Modulus for UNICORN eval invalid"))?;
+
+        Ok(PyUnicornInfo {
+            inner: UnicornInfo::new(unicorn, witness, g_value),
+        })
+    }
+
+    /// Checks a witness against the seed
+    fn verify(&self, py: Python<'_>, witness: &Bound<'_, PyAny>) -> PyResult<bool> {
+        let witness = integer_from_py(witness)?;
+        let seed = self.inner.seed.clone();
+
+        Ok(py.allow_threads(|| self.inner.verify(seed, witness)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Unicorn(seed=0x{}, iterations={}, security={})",
+            self.inner.seed.to_string_radix(16),
+            self.inner.iterations,
+            self.inner.security_level
+        )
+    }
+}
+
+/// Evaluated UNICORN
+#[pyclass(name = "UnicornInfo", module = "miner_lottery")]
+#[derive(Clone)]
+pub struct PyUnicornInfo {
+    inner: UnicornInfo,
+}
+
+#[pymethods]
+impl PyUnicornInfo {
+    #[getter]
+    fn unicorn(&self) -> PyUnicorn {
+        PyUnicorn {
+            inner: self.inner.unicorn.clone(),
+        }
+    }
+
+    #[getter]
+    fn witness(&self, py: Python<'_>) -> PyResult<PyObject> {
+        integer_to_py(py, &self.inner.witness)
+    }
+
+    #[getter]
+    fn g_value(&self) -> String {
+        self.inner.g_value.clone()
+    }
+
+    /// Whether the modulus, witness and `g` value all check out
+    fn verify(&self, py: Python<'_>) -> bool {
+        py.allow_threads(|| audit_unicorn(&self.inner).passed())
+    }
+
+    /// JSON as written by the CLI and HTTP API
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.inner).unwrap()
+    }
+
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let inner = serde_json::from_str(json).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("UnicornInfo(g_value='{}')", self.inner.g_value)
+    }
+}
+
+/// Fortuna CSPRNG, as used to draw winners
+#[pyclass(name = "Fortuna", module = "miner_lottery")]
+pub struct PyFortuna {
+    inner: Fortuna,
+}
+
+#[pymethods]
+impl PyFortuna {
+    #[new]
+    fn new(key: [u8; 32], usage_number: u128) -> PyResult<Self> {
+        let inner =
+            Fortuna::new(&key, usage_number).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    /// The CSPRNG draws with a usage number are made from
+    #[staticmethod]
+    fn from_unicorn(unicorn: &PyUnicornInfo, usage_number: u128) -> PyResult<Self> {
+        let inner = get_unicorn_csprng(&unicorn.inner, usage_number)
+            .ok_or_else(|| PyValueError::new_err("UNICORN g value is too short"))?;
+        Ok(Self { inner })
+    }
+
+    /// Next `n` pseudorandom bytes
+    fn get_bytes<'py>(&mut self, py: Python<'py>, n: usize) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self
+            .inner
+            .get_bytes(n)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyBytes::new_bound(py, &bytes))
+    }
+}
+
+/// Seed constructed from the public key inputs of a round
+#[pyfunction]
+fn construct_seed(py: Python<'_>, inputs: Vec<String>) -> PyResult<PyObject> {
+    integer_to_py(py, &seed_from_inputs(&inputs))
+}
+
+/// Draws distinct winners from an evaluated UNICORN, in draw order
+#[pyfunction]
+#[pyo3(signature = (unicorn, participants, usage_number = 1, winners = 1))]
+fn select(
+    unicorn: &PyUnicornInfo,
+    participants: Vec<String>,
+    usage_number: u128,
+    winners: usize,
+) -> PyResult<Vec<String>> {
+    let proof = prove_many_from(&unicorn.inner, usage_number, &participants, winners)
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+    Ok(proof
+        .winners
+        .iter()
+        .map(|i| participants[*i].clone())
+        .collect())
+}
+
+/// The `miner_lottery` extension module
+#[pymodule]
+pub fn miner_lottery(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyUnicorn>()?;
+    m.add_class::<PyUnicornInfo>()?;
+    m.add_class::<PyFortuna>()?;
+    m.add_function(wrap_pyfunction!(construct_seed, m)?)?;
+    m.add_function(wrap_pyfunction!(select, m)?)?;
+    m.add("DEFAULT_MODULUS", DEFAULT_MODULUS)?;
+    Ok(())
+}
+
+/// Big integer from a Python `int`, or from a hex `str`
+fn integer_from_py(value: &Bound<'_, PyAny>) -> PyResult<Integer> {
+    let (digits, radix) = match value.downcast::<PyInt>() {
+        Ok(int) => (int.str()?.to_string(), 10),
+        Err(_) => (value.extract::<String>()?, 16),
+    };
+
+    Integer::from_str_radix(digits.trim_start_matches("0x"), radix)
+        .ok()
+        .filter(|n| *n >= 0)
+        .ok_or_else(|| PyValueError::new_err("expected a non-negative int or hex str"))
+}
+
+fn integer_to_py(py: Python<'_>, value: &Integer) -> PyResult<PyObject> {
+    let int = py
+        .get_type_bound::<PyInt>()
+        .call1((value.to_string_radix(16), 16))?;
+    Ok(int.unbind())
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod python_tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    /// Checks that the module evaluates, verifies and selects from Python as it does in Rust
+    fn python_module() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "miner_lottery").unwrap();
+            miner_lottery(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("ml", module).unwrap();
+
+            py.run_bound(
+                r#"
+inputs = ["miner_a", "miner_b", "miner_c"]
+seed = ml.construct_seed(inputs)
+info = ml.Unicorn(seed, iterations=1000).eval()
+assert info.verify()
+assert info.unicorn.verify(info.witness)
+assert not info.unicorn.verify(info.witness + 1)
+assert ml.UnicornInfo.from_json(info.to_json()).g_value == info.g_value
+winners = ml.select(info, inputs, usage_number=1, winners=2)
+assert len(set(winners)) == 2
+assert len(ml.Fortuna.from_unicorn(info, 1).get_bytes(8)) == 8
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+
+            let seed: String = py
+                .eval_bound("hex(ml.construct_seed(['miner_a']))", Some(&globals), None)
+                .unwrap()
+                .extract()
+                .unwrap();
+            let expected = seed_from_inputs(&["miner_a".to_string()]);
+            assert_eq!(seed, format!("0x{}", expected.to_string_radix(16)));
+        });
+    }
+}
@@ -0,0 +1,223 @@
+//! Per-identity rate limiting of contributions.
+//!
+//! Each identity has a token bucket holding up to `burst` tokens and refilled with one token
+//! every `refill_secs`; every contribution attempt takes a token. Independently, each identity
+//! may make at most `round_quota` attempts per round. Attempts count whether or not the
+//! contribution is then accepted, so that one participant cannot keep the seed collector busy
+//! with a flood of submissions.
+//!
+//! Identities are normalized as in the participant registry before they are counted, so
+//! case variants of one hex key share its bucket and quota. The round that quotas are counted
+//! for is the one the caller is collecting for, never one named by the contribution itself.
+//!
+//! All times are unix timestamps in seconds, passed in by the caller.
+
+use crate::config::LimitsConfig;
+use crate::participants::normalize_identity;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LimitError {
+    /// The identity has run out of tokens.
+    RateLimited { retry_after: u64 },
+    /// The identity has used up its attempts for the round.
+    QuotaExceeded { round_id: u64, quota: u32 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::RateLimited { retry_after } => {
+                write!(f, "Rate limited; retry in {}s", retry_after)
+            }
+            LimitError::QuotaExceeded { round_id, quota } => write!(
+                f,
+                "At most {} contributions per identity are accepted for round {}",
+                quota, round_id
+            ),
+        }
+    }
+}
+
+impl Error for LimitError {}
+
+/// Token bucket of one identity
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: u32,
+    /// Time up to which refills have been credited
+    updated: u64,
+}
+
+/// Rate limiter keyed by identity
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limits: LimitsConfig,
+    buckets: HashMap<String, Bucket>,
+    /// Round the attempts are counted for
+    round_id: u64,
+    attempts: HashMap<String, u32>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter with full buckets
+    ///
+    /// ### Arguments
+    ///
+    /// * `limits` - Configured limits
+    pub fn new(limits: LimitsConfig) -> Self {
+        Self {
+            limits,
+            buckets: HashMap::new(),
+            round_id: 0,
+            attempts: HashMap::new(),
+        }
+    }
+
+    /// Records a contribution attempt, refusing it if the identity is over its rate or its
+    /// quota for the round. Quotas are reset when the collecting round changes
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity of the contributor
+    /// * `round_id` - Round currently collecting contributions
+    /// * `now`      - Current unix time in seconds
+    pub fn check(&mut self, identity: &str, round_id: u64, now: u64) -> Result<(), LimitError> {
+        if round_id != self.round_id {
+            self.start_round(round_id, now);
+        }
+
+        let identity = normalize_identity(identity);
+        let identity = identity.as_str();
+        let attempts = self.attempts.get(identity).copied().unwrap_or_default();
+        if self.limits.round_quota > 0 && attempts >= self.limits.round_quota {
+            return Err(LimitError::QuotaExceeded {
+                round_id,
+                quota: self.limits.round_quota,
+            });
+        }
+
+        let bucket = self.refilled(identity, now);
+        if bucket.tokens == 0 {
+            return Err(LimitError::RateLimited {
+                retry_after: (bucket.updated + self.limits.refill_secs).saturating_sub(now),
+            });
+        }
+
+        self.buckets.insert(
+            identity.to_string(),
+            Bucket {
+                tokens: bucket.tokens - 1,
+                ..bucket
+            },
+        );
+        self.attempts.insert(identity.to_string(), attempts + 1);
+
+        Ok(())
+    }
+
+    /// The identity's bucket with refills credited up to `now`
+    fn refilled(&self, identity: &str, now: u64) -> Bucket {
+        let Some(bucket) = self.buckets.get(identity).copied() else {
+            return Bucket {
+                tokens: self.limits.burst,
+                updated: now,
+            };
+        };
+
+        let refills = now.saturating_sub(bucket.updated) / self.limits.refill_secs;
+        let tokens = (bucket.tokens as u64 + refills).min(self.limits.burst as u64) as u32;
+
+        Bucket {
+            tokens,
+            updated: match tokens == self.limits.burst {
+                true => now,
+                false => bucket.updated + refills * self.limits.refill_secs,
+            },
+        }
+    }
+
+    /// Resets the quotas for a new round and forgets buckets that have refilled
+    fn start_round(&mut self, round_id: u64, now: u64) {
+        self.round_id = round_id;
+        self.attempts.clear();
+
+        let full: Vec<String> = self
+            .buckets
+            .keys()
+            .filter(|identity| self.refilled(identity, now).tokens == self.limits.burst)
+            .cloned()
+            .collect();
+        for identity in full {
+            self.buckets.remove(&identity);
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod ratelimit_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that the bucket empties at the burst size and refills over time
+    fn token_bucket() {
+        let limits = LimitsConfig {
+            burst: 2,
+            refill_secs: 5,
+            round_quota: 0,
+        };
+        let mut limiter = RateLimiter::new(limits);
+
+        assert_eq!(limiter.check("miner_a", 1, 100), Ok(()));
+        assert_eq!(limiter.check("miner_a", 1, 100), Ok(()));
+        assert_eq!(
+            limiter.check("miner_a", 1, 102),
+            Err(LimitError::RateLimited { retry_after: 3 })
+        );
+        assert_eq!(limiter.check("miner_b", 1, 102), Ok(()));
+        assert_eq!(limiter.check("miner_a", 1, 105), Ok(()));
+        assert!(limiter.check("miner_a", 1, 105).is_err());
+    }
+
+    #[test]
+    /// Checks that quotas apply per round
+    fn round_quota() {
+        let limits = LimitsConfig {
+            burst: 10,
+            refill_secs: 1,
+            round_quota: 2,
+        };
+        let mut limiter = RateLimiter::new(limits);
+
+        assert_eq!(limiter.check("miner_a", 1, 0), Ok(()));
+        assert_eq!(limiter.check("miner_a", 1, 0), Ok(()));
+        assert_eq!(
+            limiter.check("miner_a", 1, 0),
+            Err(LimitError::QuotaExceeded {
+                round_id: 1,
+                quota: 2
+            })
+        );
+        assert_eq!(limiter.check("miner_a", 2, 0), Ok(()));
+    }
+
+    #[test]
+    /// Checks that case variants of a hex key share one bucket
+    fn normalized_identities() {
+        let limits = LimitsConfig {
+            burst: 1,
+            refill_secs: 60,
+            round_quota: 0,
+        };
+        let mut limiter = RateLimiter::new(limits);
+        let key = "ab".repeat(32);
+
+        assert_eq!(limiter.check(&key, 1, 0), Ok(()));
+        assert!(limiter.check(&key.to_uppercase(), 1, 0).is_err());
+        assert!(limiter.check(&format!(" {}", key), 1, 0).is_err());
+    }
+}
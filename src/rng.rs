@@ -0,0 +1,165 @@
+//! A `rand` RNG driven by a UNICORN.
+//!
+//! `UnicornRng` draws from the same Fortuna CSPRNG as the lottery's own PRNs, so that code
+//! written against the `rand` traits can be driven by uncontestable randomness: anyone
+//! holding the evaluated UNICORN and the usage number can reproduce every value it produced.
+//!
+//! Its seed is the Fortuna key taken from the `g` value followed by the 96-bit usage number,
+//! big-endian. `fill_bytes` yields the CSPRNG's byte stream unchanged, so its first
+//! `PRN_LEN` bytes are the PRN bytes drawn by `get_unicorn_prn_bytes` for the same usage
+//! number.
+
+use crate::fortuna::Fortuna;
+use crate::unicorn::{g_key, UnicornInfo, G_KEY_LEN};
+use rand_core::{impls, CryptoRng, Error, RngCore, SeedableRng};
+
+/// Length in bytes of the usage number within a seed
+const USAGE_LEN: usize = 12;
+
+/// Seed of a `UnicornRng`: the Fortuna key followed by the big-endian usage number
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnicornSeed(pub [u8; G_KEY_LEN + USAGE_LEN]);
+
+impl Default for UnicornSeed {
+    fn default() -> Self {
+        Self([0; G_KEY_LEN + USAGE_LEN])
+    }
+}
+
+impl AsRef<[u8]> for UnicornSeed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsMut<[u8]> for UnicornSeed {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl UnicornSeed {
+    /// Seed for a given usage number of a UNICORN. Returns `None` if the `g` value is too
+    /// short to key the CSPRNG or the usage number is wider than 96 bits
+    ///
+    /// ### Arguments
+    ///
+    /// * `info`         - Evaluated UNICORN
+    /// * `usage_number` - Usage number of the draw
+    pub fn new(info: &UnicornInfo, usage_number: u128) -> Option<Self> {
+        let key = g_key(&info.witness)?;
+        let usage = usage_number.to_be_bytes();
+        if usage[..16 - USAGE_LEN].iter().any(|b| *b != 0) {
+            return None;
+        }
+
+        let mut seed = Self::default();
+        seed.0[..G_KEY_LEN].copy_from_slice(&key);
+        seed.0[G_KEY_LEN..].copy_from_slice(&usage[16 - USAGE_LEN..]);
+        Some(seed)
+    }
+
+    fn key(&self) -> &[u8; G_KEY_LEN] {
+        self.0[..G_KEY_LEN].try_into().unwrap()
+    }
+
+    fn usage_number(&self) -> u128 {
+        let mut usage = [0; 16];
+        usage[16 - USAGE_LEN..].copy_from_slice(&self.0[G_KEY_LEN..]);
+        u128::from_be_bytes(usage)
+    }
+}
+
+/// `rand` RNG drawing from a UNICORN's Fortuna CSPRNG
+pub struct UnicornRng {
+    csprng: Fortuna,
+}
+
+impl UnicornRng {
+    /// Creates the RNG for a given usage number of a UNICORN. Returns `None` if the `g`
+    /// value is too short to key the CSPRNG or the usage number is wider than 96 bits
+    ///
+    /// ### Arguments
+    ///
+    /// * `info`         - Evaluated UNICORN
+    /// * `usage_number` - Usage number of the draw
+    pub fn new(info: &UnicornInfo, usage_number: u128) -> Option<Self> {
+        UnicornSeed::new(info, usage_number).map(Self::from_seed)
+    }
+}
+
+impl SeedableRng for UnicornRng {
+    type Seed = UnicornSeed;
+
+    fn from_seed(seed: UnicornSeed) -> Self {
+        Self {
+            csprng: Fortuna::new(seed.key(), seed.usage_number())
+                .expect("usage numbers in a seed are at most 96 bits"),
+        }
+    }
+}
+
+impl RngCore for UnicornRng {
+    fn next_u32(&mut self) -> u32 {
+        impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("Fortuna failed to generate bytes")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.csprng.fill_bytes(dest).map_err(Error::new)
+    }
+}
+
+impl CryptoRng for UnicornRng {}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod rng_tests {
+    use super::*;
+    use crate::unicorn::{construct_unicorn, UnicornFixedParam};
+    use crate::utils::unicorn_selection::{get_unicorn_csprng, get_unicorn_prn_bytes, PRN_LEN};
+    use rug::Integer;
+
+    #[test]
+    /// Checks that the RNG yields the same stream as the UNICORN's CSPRNG, starting with its
+    /// PRN bytes, and that its seed round-trips
+    fn unicorn_rng_stream() {
+        let info = construct_unicorn(
+            Integer::from(42),
+            &UnicornFixedParam {
+                iterations: 100,
+                ..UnicornFixedParam::test()
+            },
+        );
+
+        let mut rng = UnicornRng::new(&info, 3).unwrap();
+        let mut bytes = [0; 64];
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(bytes[..PRN_LEN], get_unicorn_prn_bytes(&info, 3).unwrap());
+        assert_eq!(
+            bytes.to_vec(),
+            get_unicorn_csprng(&info, 3).unwrap().get_bytes(64).unwrap()
+        );
+
+        let seed = UnicornSeed::new(&info, 3).unwrap();
+        let mut reseeded = UnicornRng::from_seed(seed);
+        assert_eq!(
+            reseeded.next_u64(),
+            u64::from_le_bytes(bytes[..8].try_into().unwrap())
+        );
+        assert_ne!(
+            UnicornRng::new(&info, 4).unwrap().next_u64(),
+            rng.next_u64()
+        );
+        assert!(UnicornSeed::new(&info, 1 << 96).is_none());
+    }
+}
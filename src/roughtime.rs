@@ -0,0 +1,455 @@
+//! Roughtime clock attestation of round cutoffs.
+//!
+//! Rather than trusting the local system clock, the daemon can anchor the cutoff of a round to
+//! a clock reading attested by a Roughtime server. The request's nonce commits to the round's
+//! inputs, so the signed response proves that the participant set was already fixed when the
+//! server read its clock, and the reading proves that the cutoff had passed. The attestation
+//! is recorded in the round and its transcript, from which anyone holding the server's
+//! long-term key can check it.
+//!
+//! Messages follow the original Roughtime protocol: a request carries a 64-byte `NONC` and is
+//! padded to 1024 bytes, and the response's `SREP` is signed by an online key that the server's
+//! long-term key delegates to in `CERT`. The nonce is a leaf of the Merkle tree whose `ROOT`
+//! is signed, and `PATH` and `INDX` lead from the leaf to the root.
+//!
+//! Attestations are recorded without the `roughtime` feature, which adds checking their
+//! signatures and querying servers.
+
+use bincode::serialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::error::Error;
+use std::fmt;
+
+/// Size every request is padded to
+pub const REQUEST_LEN: usize = 1024;
+
+/// Context prepended to the delegation signed by the long-term key
+pub const DELEGATION_CONTEXT: &[u8] = b"RoughTime v1 delegation signature--\x00";
+
+/// Context prepended to the signed response
+pub const RESPONSE_CONTEXT: &[u8] = b"RoughTime v1 response signature\x00";
+
+/// Context of the nonce committing to a round's inputs
+const CUTOFF_CONTEXT: &[u8] = b"miner-lottery/cutoff/v1";
+
+const NONC: u32 = u32::from_le_bytes(*b"NONC");
+const PAD: u32 = u32::from_le_bytes(*b"PAD\xff");
+#[cfg(feature = "roughtime")]
+const SIG: u32 = u32::from_le_bytes(*b"SIG\x00");
+#[cfg(feature = "roughtime")]
+const PATH: u32 = u32::from_le_bytes(*b"PATH");
+#[cfg(feature = "roughtime")]
+const SREP: u32 = u32::from_le_bytes(*b"SREP");
+#[cfg(feature = "roughtime")]
+const CERT: u32 = u32::from_le_bytes(*b"CERT");
+#[cfg(feature = "roughtime")]
+const INDX: u32 = u32::from_le_bytes(*b"INDX");
+#[cfg(feature = "roughtime")]
+const ROOT: u32 = u32::from_le_bytes(*b"ROOT");
+#[cfg(feature = "roughtime")]
+const MIDP: u32 = u32::from_le_bytes(*b"MIDP");
+#[cfg(feature = "roughtime")]
+const RADI: u32 = u32::from_le_bytes(*b"RADI");
+#[cfg(feature = "roughtime")]
+const DELE: u32 = u32::from_le_bytes(*b"DELE");
+#[cfg(feature = "roughtime")]
+const PUBK: u32 = u32::from_le_bytes(*b"PUBK");
+#[cfg(feature = "roughtime")]
+const MINT: u32 = u32::from_le_bytes(*b"MINT");
+#[cfg(feature = "roughtime")]
+const MAXT: u32 = u32::from_le_bytes(*b"MAXT");
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RoughtimeError {
+    /// The response is not a valid Roughtime message.
+    Malformed,
+    /// A signature does not match, or the delegation is not from the trusted key.
+    BadSignature,
+    /// The nonce is not a leaf under the signed root.
+    NotIncluded,
+    /// The reading is outside the delegation's validity window.
+    OutsideDelegation,
+    /// The attestation's recorded fields do not match its response.
+    Mismatch,
+    /// The server could not be reached.
+    Io(String),
+}
+
+impl fmt::Display for RoughtimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoughtimeError::Malformed => write!(f, "Malformed Roughtime response"),
+            RoughtimeError::BadSignature => write!(f, "Invalid Roughtime signature"),
+            RoughtimeError::NotIncluded => write!(f, "Nonce is not under the signed root"),
+            RoughtimeError::OutsideDelegation => {
+                write!(f, "Reading is outside the delegation's validity window")
+            }
+            RoughtimeError::Mismatch => write!(f, "Attestation does not match its response"),
+            RoughtimeError::Io(e) => write!(f, "Could not reach the Roughtime server: {}", e),
+        }
+    }
+}
+
+impl Error for RoughtimeError {}
+
+/// Clock reading attested by a Roughtime server for a nonce
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClockAttestation {
+    /// Address of the server
+    pub server: String,
+    /// Hex-encoded long-term Ed25519 public key of the server
+    pub public_key: String,
+    /// Hex-encoded nonce of the request
+    pub nonce: String,
+    /// Attested time, in microseconds since the unix epoch
+    pub midpoint: u64,
+    /// Uncertainty of the attested time, in microseconds
+    pub radius: u32,
+    /// Hex-encoded response, as sent by the server
+    pub response: String,
+}
+
+impl ClockAttestation {
+    /// Attested time in unix seconds
+    pub fn unix_secs(&self) -> u64 {
+        self.midpoint / 1_000_000
+    }
+
+    /// Earliest time the reading allows for, in unix seconds
+    pub fn earliest_secs(&self) -> u64 {
+        self.midpoint.saturating_sub(self.radius as u64) / 1_000_000
+    }
+}
+
+/// Nonce committing to the inputs of a round: the SHA-512 of a fixed context followed by the
+/// bincode encoding of the inputs and the last winning PoW hashes
+///
+/// ### Arguments
+///
+/// * `inputs`              - Inputs of the round, in seed order
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn cutoff_nonce(inputs: &[String], last_winning_hashes: &[String]) -> [u8; 64] {
+    Sha512::new()
+        .chain_update(CUTOFF_CONTEXT)
+        .chain_update(serialize(&(inputs, last_winning_hashes)).unwrap())
+        .finalize()
+        .into()
+}
+
+/// Encodes a request for a nonce, padded to `REQUEST_LEN` bytes
+///
+/// ### Arguments
+///
+/// * `nonce` - Nonce the response must sign
+pub fn encode_request(nonce: &[u8; 64]) -> Vec<u8> {
+    let header = 4 + 4 + 2 * 4;
+    let padding = vec![0; REQUEST_LEN - header - nonce.len()];
+    encode_message(&[(NONC, nonce), (PAD, &padding)])
+}
+
+/// Fields of a response, unverified
+#[cfg(feature = "roughtime")]
+struct Response<'a> {
+    sig: &'a [u8],
+    srep: &'a [u8],
+    path: &'a [u8],
+    index: u32,
+    root: &'a [u8],
+    midpoint: u64,
+    radius: u32,
+    cert_sig: &'a [u8],
+    dele: &'a [u8],
+    online_key: &'a [u8],
+    min_time: u64,
+    max_time: u64,
+}
+
+#[cfg(feature = "roughtime")]
+impl<'a> Response<'a> {
+    fn parse(response: &'a [u8]) -> Option<Self> {
+        let message = decode_message(response)?;
+        let srep = get(&message, SREP)?;
+        let signed = decode_message(srep)?;
+        let cert = decode_message(get(&message, CERT)?)?;
+        let dele = get(&cert, DELE)?;
+        let delegation = decode_message(dele)?;
+
+        Some(Self {
+            sig: get(&message, SIG)?,
+            srep,
+            path: get(&message, PATH)?,
+            index: u32::from_le_bytes(get(&message, INDX)?.try_into().ok()?),
+            root: get(&signed, ROOT)?,
+            midpoint: u64::from_le_bytes(get(&signed, MIDP)?.try_into().ok()?),
+            radius: u32::from_le_bytes(get(&signed, RADI)?.try_into().ok()?),
+            cert_sig: get(&cert, SIG)?,
+            dele,
+            online_key: get(&delegation, PUBK)?,
+            min_time: u64::from_le_bytes(get(&delegation, MINT)?.try_into().ok()?),
+            max_time: u64::from_le_bytes(get(&delegation, MAXT)?.try_into().ok()?),
+        })
+        .filter(|r| r.path.len() % 64 == 0 && r.root.len() == 64)
+    }
+
+    /// Whether the nonce is the leaf at `index` under the signed root
+    fn includes(&self, nonce: &[u8]) -> bool {
+        let mut hash: [u8; 64] = Sha512::new()
+            .chain_update([0])
+            .chain_update(nonce)
+            .finalize()
+            .into();
+        let mut index = self.index;
+        for sibling in self.path.chunks(64) {
+            let (left, right) = match index & 1 {
+                0 => (&hash[..], sibling),
+                _ => (sibling, &hash[..]),
+            };
+            hash = Sha512::new()
+                .chain_update([1])
+                .chain_update(left)
+                .chain_update(right)
+                .finalize()
+                .into();
+            index >>= 1;
+        }
+
+        hash[..] == *self.root
+    }
+}
+
+#[cfg(feature = "roughtime")]
+impl ClockAttestation {
+    /// Checks the attestation against the server's long-term key: the delegation, the
+    /// response signature, the nonce's inclusion under the signed root, the validity window
+    /// and that the recorded reading is the signed one
+    pub fn verify(&self) -> Result<(), RoughtimeError> {
+        use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+        let key =
+            |bytes: &[u8]| PublicKey::from_bytes(bytes).map_err(|_| RoughtimeError::Malformed);
+        let signature =
+            |bytes: &[u8]| Signature::try_from(bytes).map_err(|_| RoughtimeError::Malformed);
+        let decode = |hex_str: &str| hex::decode(hex_str).map_err(|_| RoughtimeError::Malformed);
+
+        let long_term = key(&decode(&self.public_key)?)?;
+        let nonce = decode(&self.nonce)?;
+        let bytes = decode(&self.response)?;
+        let response = Response::parse(&bytes).ok_or(RoughtimeError::Malformed)?;
+
+        long_term
+            .verify(
+                &[DELEGATION_CONTEXT, response.dele].concat(),
+                &signature(response.cert_sig)?,
+            )
+            .map_err(|_| RoughtimeError::BadSignature)?;
+        key(response.online_key)?
+            .verify(
+                &[RESPONSE_CONTEXT, response.srep].concat(),
+                &signature(response.sig)?,
+            )
+            .map_err(|_| RoughtimeError::BadSignature)?;
+
+        if !response.includes(&nonce) {
+            return Err(RoughtimeError::NotIncluded);
+        }
+        if response.midpoint < response.min_time || response.midpoint > response.max_time {
+            return Err(RoughtimeError::OutsideDelegation);
+        }
+        if response.midpoint != self.midpoint || response.radius != self.radius {
+            return Err(RoughtimeError::Mismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Queries a Roughtime server for a reading over a nonce and checks the response against the
+/// server's long-term key
+///
+/// ### Arguments
+///
+/// * `server`     - Address of the server, as `host:port`
+/// * `public_key` - Hex-encoded long-term public key of the server
+/// * `nonce`      - Nonce the response must sign
+/// * `timeout`    - How long to wait for the response
+#[cfg(feature = "roughtime")]
+pub fn query(
+    server: &str,
+    public_key: &str,
+    nonce: &[u8; 64],
+    timeout: std::time::Duration,
+) -> Result<ClockAttestation, RoughtimeError> {
+    use std::net::UdpSocket;
+
+    let io = |e: std::io::Error| RoughtimeError::Io(e.to_string());
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(io)?;
+    socket.set_read_timeout(Some(timeout)).map_err(io)?;
+    socket.connect(server).map_err(io)?;
+    socket.send(&encode_request(nonce)).map_err(io)?;
+
+    let mut buffer = vec![0; 4096];
+    let len = socket.recv(&mut buffer).map_err(io)?;
+    let response = Response::parse(&buffer[..len]).ok_or(RoughtimeError::Malformed)?;
+
+    let attestation = ClockAttestation {
+        server: server.to_string(),
+        public_key: public_key.to_string(),
+        nonce: hex::encode(nonce),
+        midpoint: response.midpoint,
+        radius: response.radius,
+        response: hex::encode(&buffer[..len]),
+    };
+    attestation.verify()?;
+
+    Ok(attestation)
+}
+
+/// Encodes a message from tags in ascending order and values whose lengths are multiples of 4
+fn encode_message(fields: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut message = (fields.len() as u32).to_le_bytes().to_vec();
+    let mut offset = 0;
+    for (_, value) in &fields[..fields.len() - 1] {
+        offset += value.len() as u32;
+        message.extend(offset.to_le_bytes());
+    }
+    for (tag, _) in fields {
+        message.extend(tag.to_le_bytes());
+    }
+    for (_, value) in fields {
+        message.extend(*value);
+    }
+    message
+}
+
+/// Decodes a message into its tags and values
+#[cfg(feature = "roughtime")]
+fn decode_message(message: &[u8]) -> Option<Vec<(u32, &[u8])>> {
+    let word = |i: usize| {
+        message
+            .get(4 * i..4 * i + 4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+    };
+    let count = word(0)? as usize;
+    if count == 0 || count > 64 {
+        return None;
+    }
+
+    let values = message.get(4 * (2 * count)..)?;
+    let mut fields = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = if i == 0 { 0 } else { word(i)? as usize };
+        let end = if i + 1 == count {
+            values.len()
+        } else {
+            word(i + 1)? as usize
+        };
+        let tag = word(count + i)?;
+        if start > end || fields.last().is_some_and(|(last, _)| *last >= tag) {
+            return None;
+        }
+        fields.push((tag, values.get(start..end)?));
+    }
+
+    Some(fields)
+}
+
+#[cfg(feature = "roughtime")]
+fn get<'a>(fields: &[(u32, &'a [u8])], tag: u32) -> Option<&'a [u8]> {
+    fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| *v)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(all(test, feature = "roughtime"))]
+mod roughtime_tests {
+    use super::*;
+    use crate::signing::test_keypair;
+    use ed25519_dalek::{Keypair, Signer};
+
+    /// Builds a response as a server would, for a batch of two nonces
+    fn respond(long_term: &Keypair, nonce: &[u8; 64], midpoint: u64) -> Vec<u8> {
+        let online = test_keypair(9);
+        let leaf = |n: &[u8]| -> [u8; 64] {
+            Sha512::new()
+                .chain_update([0])
+                .chain_update(n)
+                .finalize()
+                .into()
+        };
+        let (ours, other) = (leaf(nonce), leaf(&[0; 64]));
+        let root: [u8; 64] = Sha512::new()
+            .chain_update([1])
+            .chain_update(other)
+            .chain_update(ours)
+            .finalize()
+            .into();
+
+        let dele = encode_message(&[
+            (PUBK, online.public.as_bytes()),
+            (MINT, &0u64.to_le_bytes()),
+            (MAXT, &u64::MAX.to_le_bytes()),
+        ]);
+        let cert_sig = long_term.sign(&[DELEGATION_CONTEXT, &dele].concat());
+        let cert = encode_message(&[(SIG, &cert_sig.to_bytes()), (DELE, &dele)]);
+
+        let srep = encode_message(&[
+            (RADI, &1_000_000u32.to_le_bytes()),
+            (MIDP, &midpoint.to_le_bytes()),
+            (ROOT, &root),
+        ]);
+        let sig = online.sign(&[RESPONSE_CONTEXT, &srep].concat());
+
+        encode_message(&[
+            (SIG, &sig.to_bytes()),
+            (PATH, &other),
+            (SREP, &srep),
+            (CERT, &cert),
+            (INDX, &1u32.to_le_bytes()),
+        ])
+    }
+
+    #[test]
+    /// Checks that requests are padded, and that attestations only verify for the trusted
+    /// key, the committed nonce and the signed reading
+    fn attested_reading() {
+        let server = test_keypair(1);
+        let nonce = cutoff_nonce(&["miner_a".to_string()], &[]);
+        let request = encode_request(&nonce);
+        assert_eq!(request.len(), REQUEST_LEN);
+        assert_eq!(
+            get(&decode_message(&request).unwrap(), NONC),
+            Some(&nonce[..])
+        );
+
+        let response = respond(&server, &nonce, 1_700_000_000_500_000);
+        let attestation = ClockAttestation {
+            server: "roughtime.example:2002".to_string(),
+            public_key: hex::encode(server.public.as_bytes()),
+            nonce: hex::encode(nonce),
+            midpoint: 1_700_000_000_500_000,
+            radius: 1_000_000,
+            response: hex::encode(&response),
+        };
+        assert_eq!(attestation.verify(), Ok(()));
+        assert_eq!(attestation.unix_secs(), 1_700_000_000);
+        assert_eq!(attestation.earliest_secs(), 1_699_999_999);
+
+        let untrusted = ClockAttestation {
+            public_key: hex::encode(test_keypair(2).public.as_bytes()),
+            ..attestation.clone()
+        };
+        let other_nonce = ClockAttestation {
+            nonce: hex::encode(cutoff_nonce(&["miner_b".to_string()], &[])),
+            ..attestation.clone()
+        };
+        let later = ClockAttestation {
+            midpoint: 1_800_000_000_000_000,
+            ..attestation
+        };
+        assert_eq!(untrusted.verify(), Err(RoughtimeError::BadSignature));
+        assert_eq!(other_nonce.verify(), Err(RoughtimeError::NotIncluded));
+        assert_eq!(later.verify(), Err(RoughtimeError::Mismatch));
+    }
+}
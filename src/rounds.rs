@@ -0,0 +1,1402 @@
+//! Lottery round state machine.
+//!
+//! A round moves through the following phases:
+//!
+//! - `Collecting`: participants register their identities
+//! - `Sealed`: the participant set is frozen and the seed is fixed
+//! - `Evaluating`: the UNICORN eval is running
+//! - `Published`: the UNICORN and the selection transcript are available
+//! - `Verified`: the published result has been checked and confirmed
+//!
+//! A published round is final once a quorum of designated verifiers have signed its result;
+//! see `finality`.
+//!
+//! Collection and evaluation are bounded by timeouts. Contributions arriving after the
+//! collection deadline are refused, and an eval that completes after its deadline aborts the
+//! round.
+//!
+//! To prevent grinding, where a late contributor picks their input after seeing everybody
+//! else's, each contribution carries its round id and timestamp and is refused once the
+//! collection cutoff has passed, either by its own timestamp or by the local clock. Sealing
+//! commits to a digest of the sealed inputs, which is checked again before the eval starts.
+//! Rounds can also require a proof-of-work ticket bound to the round id with each contribution.
+//!
+//! A `GracePolicy` can instead keep a round collecting for a grace period after its cutoff.
+//! Contributions timestamped within it are either accepted and flagged as late, or carried
+//! over into the next round. Either way a contribution is classified by its own timestamp
+//! alone, and the late ones are recorded in the transcript, so every node converges on the
+//! same sealed inputs.
+//!
+//! A round can commit to the Merkle root of its participants instead of the full list, so that
+//! the winner's registration can be proven to holders of the root alone.
+//!
+//! `replay` re-derives a stored round from its raw inputs and reports every stored value it
+//! does not reproduce, for operators auditing their own history. `diff` compares two round
+//! records value by value, for when two nodes publish conflicting results.
+//!
+//! All times are unix timestamps in seconds, passed in by the caller so that every node
+//! applying the same events reaches the same state. Rounds are serde-serializable so that node
+//! software can persist them between transitions.
+
+use crate::finality::Finality;
+use crate::merkle::{construct_seed_with_root, InclusionProof};
+use crate::participants::{fingerprint, normalize_identity, ParticipantRegistry, RegistryError};
+use crate::pow::PowRequirement;
+use crate::roughtime::ClockAttestation;
+use crate::storage::{RoundStore, StorageError};
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{
+    construct_seed_with_mode, g_from_witness, SeedMode, Unicorn, UnicornFixedParam, UnicornInfo,
+};
+use crate::utils::rug_integer;
+use crate::vdf::{UnicornVdf, Vdf};
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+/// Phase of a lottery round
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RoundPhase {
+    Collecting,
+    Sealed,
+    Evaluating,
+    Published,
+    Verified,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RoundError {
+    /// Operation is not valid in the current phase.
+    InvalidTransition { from: RoundPhase, to: RoundPhase },
+    /// The deadline for the current phase has passed.
+    TimedOut { phase: RoundPhase },
+    /// Participant registration failure.
+    Registry(RegistryError),
+    /// Contribution is for another round.
+    WrongRound { expected: u64, actual: u64 },
+    /// Contribution is timestamped after the collection cutoff.
+    LateContribution { timestamp: u64, cutoff: u64 },
+    /// The sealed inputs no longer match the committed digest.
+    SealedDigestMismatch,
+    /// The round was sealed without participants.
+    NoParticipants,
+    /// The UNICORN parameters or eval result are invalid.
+    InvalidUnicorn,
+    /// Replay of the published result failed.
+    VerificationFailed,
+}
+
+impl From<RegistryError> for RoundError {
+    fn from(e: RegistryError) -> Self {
+        Self::Registry(e)
+    }
+}
+
+impl fmt::Display for RoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundError::InvalidTransition { from, to } => {
+                write!(f, "Invalid round transition from {:?} to {:?}", from, to)
+            }
+            RoundError::TimedOut { phase } => write!(f, "Round timed out in {:?}", phase),
+            RoundError::Registry(e) => write!(f, "Registry error: {}", e),
+            RoundError::WrongRound { expected, actual } => write!(
+                f,
+                "Contribution for round {} submitted to round {}",
+                actual, expected
+            ),
+            RoundError::LateContribution { timestamp, cutoff } => write!(
+                f,
+                "Contribution at {} is after the cutoff at {}",
+                timestamp, cutoff
+            ),
+            RoundError::SealedDigestMismatch => write!(f, "Sealed inputs digest mismatch"),
+            RoundError::NoParticipants => write!(f, "Round has no participants"),
+            RoundError::InvalidUnicorn => write!(f, "Invalid UNICORN for round"),
+            RoundError::VerificationFailed => write!(f, "Round verification failed"),
+        }
+    }
+}
+
+impl Error for RoundError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RoundError::Registry(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Timeouts for the bounded phases of a round, in seconds
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoundTimeouts {
+    /// Time after round creation during which contributions are accepted
+    pub collect_secs: u64,
+    /// Time after eval start within which the eval must complete
+    pub eval_secs: u64,
+}
+
+/// What a round does with contributions timestamped in its grace period
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LateMode {
+    /// Late contributions are refused.
+    #[default]
+    Reject,
+    /// Late contributions are accepted into the round and flagged as late.
+    Flag,
+    /// Late contributions are carried over into the next round.
+    NextRound,
+}
+
+/// How a round treats contributions timestamped after its cutoff
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default, deny_unknown_fields)]
+pub struct GracePolicy {
+    /// What is done with contributions timestamped in the grace period
+    pub mode: LateMode,
+    /// Time after the cutoff during which late contributions are taken, in seconds. Ignored
+    /// when late contributions are refused
+    pub grace_secs: u64,
+}
+
+impl GracePolicy {
+    /// Time after the cutoff during which late contributions are taken, in seconds
+    pub fn grace_secs(&self) -> u64 {
+        match self.mode {
+            LateMode::Reject => 0,
+            LateMode::Flag | LateMode::NextRound => self.grace_secs,
+        }
+    }
+}
+
+/// A participant's contribution to a round
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Contribution {
+    pub round_id: u64,
+    pub identity: String,
+    /// Time at which the contribution was made
+    pub timestamp: u64,
+    /// Proof-of-work ticket nonce, if the round requires one
+    #[serde(default)]
+    pub nonce: Option<u64>,
+}
+
+/// A single lottery round
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Round {
+    pub id: u64,
+    pub phase: RoundPhase,
+    pub fixed_params: UnicornFixedParam,
+    pub usage_number: u128,
+    pub timeouts: RoundTimeouts,
+    pub registry: ParticipantRegistry,
+    /// Hashes of the winning PoWs from 2 blocks ago, bound into the seed at sealing
+    #[serde(default)]
+    pub last_winning_hashes: Vec<String>,
+    /// Accepted contributions, in arrival order
+    #[serde(default)]
+    pub contributions: Vec<Contribution>,
+    /// Time at which the round was created
+    pub created_at: u64,
+    /// Time after which contributions are late. Rounds stored before cutoffs were recorded
+    /// load without one
+    #[serde(default = "no_cutoff")]
+    pub cutoff: u64,
+    /// How contributions timestamped after the cutoff are treated
+    #[serde(default)]
+    pub grace: GracePolicy,
+    /// Contributions timestamped in the grace period, to be carried over into the next round
+    #[serde(default)]
+    pub carried_over: Vec<Contribution>,
+    /// Whether the seed commits to the Merkle root of the participants
+    #[serde(default)]
+    pub commit_merkle_root: bool,
+    /// How the seed is built from the participants, when it does not commit to a Merkle root
+    #[serde(default)]
+    pub seed_mode: SeedMode,
+    /// Digest of the sealed inputs, committed when the round is sealed
+    #[serde(default)]
+    pub sealed_digest: Option<String>,
+    /// Deadline for the current phase, if it is bounded
+    pub deadline: Option<u64>,
+    /// Attested clock reading the round was sealed at, if the cutoff was anchored to one
+    #[serde(default)]
+    pub cutoff_attestation: Option<ClockAttestation>,
+    /// Signatures of the quorum of verifiers that finalized the round, once it is final
+    #[serde(default)]
+    pub finality: Option<Finality>,
+    #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub seed: Integer,
+    pub transcript: Option<SelectionTranscript>,
+}
+
+impl Round {
+    /// Creates a new round in the `Collecting` phase
+    ///
+    /// ### Arguments
+    ///
+    /// * `id`           - Round identifier
+    /// * `fixed_params` - UNICORN parameters for the round
+    /// * `usage_number` - Usage number of the winner draw
+    /// * `timeouts`     - Phase timeouts
+    /// * `now`          - Current unix time in seconds
+    pub fn new(
+        id: u64,
+        fixed_params: UnicornFixedParam,
+        usage_number: u128,
+        timeouts: RoundTimeouts,
+        now: u64,
+    ) -> Self {
+        Self {
+            id,
+            phase: RoundPhase::Collecting,
+            fixed_params,
+            usage_number,
+            timeouts,
+            registry: ParticipantRegistry::new(),
+            last_winning_hashes: Vec::new(),
+            contributions: Vec::new(),
+            created_at: now,
+            cutoff: now.saturating_add(timeouts.collect_secs),
+            grace: GracePolicy::default(),
+            carried_over: Vec::new(),
+            commit_merkle_root: false,
+            seed_mode: SeedMode::Serialized,
+            sealed_digest: None,
+            deadline: Some(now.saturating_add(timeouts.collect_secs)),
+            cutoff_attestation: None,
+            finality: None,
+            seed: Integer::new(),
+            transcript: None,
+        }
+    }
+
+    /// Sets the hashes of the winning PoWs from 2 blocks ago to bind into the seed
+    ///
+    /// ### Arguments
+    ///
+    /// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+    pub fn with_last_winning_hashes(mut self, last_winning_hashes: Vec<String>) -> Self {
+        self.last_winning_hashes = last_winning_hashes;
+        self
+    }
+
+    /// Requires a proof-of-work ticket bound to the round with every contribution
+    ///
+    /// ### Arguments
+    ///
+    /// * `difficulty` - Minimum number of leading zero bits of each ticket hash
+    pub fn with_pow_difficulty(mut self, difficulty: u32) -> Self {
+        self.registry = self.registry.with_pow_requirement(PowRequirement {
+            round_id: self.id,
+            difficulty,
+        });
+        self
+    }
+
+    /// Commits the Merkle root of the participants into the seed instead of the full list
+    pub fn with_merkle_root(mut self) -> Self {
+        self.commit_merkle_root = true;
+        self
+    }
+
+    /// Builds the seed from the participants in the given mode
+    ///
+    /// ### Arguments
+    ///
+    /// * `seed_mode` - How the seed is built from the participants
+    pub fn with_seed_mode(mut self, seed_mode: SeedMode) -> Self {
+        self.seed_mode = seed_mode;
+        self
+    }
+
+    /// Sets the time after which contributions are refused, in place of the collection
+    /// timeout. Without a cutoff, the round collects until one is set with `set_cutoff`
+    ///
+    /// ### Arguments
+    ///
+    /// * `cutoff` - Time after which contributions are refused, if already known
+    pub fn with_cutoff(mut self, cutoff: Option<u64>) -> Self {
+        self.cutoff = cutoff.unwrap_or_else(no_cutoff);
+        self.deadline = cutoff.map(|_| self.collect_deadline());
+        self
+    }
+
+    /// Treats contributions timestamped after the cutoff by the given policy. Collection is
+    /// extended by the grace period
+    ///
+    /// ### Arguments
+    ///
+    /// * `grace` - Policy for late contributions
+    pub fn with_grace(mut self, grace: GracePolicy) -> Self {
+        self.grace = grace;
+        self.deadline = self.deadline.map(|_| self.collect_deadline());
+        self
+    }
+
+    /// Sets the time after which contributions are refused. Only valid while collecting
+    ///
+    /// ### Arguments
+    ///
+    /// * `cutoff` - Time after which contributions are refused
+    pub fn set_cutoff(&mut self, cutoff: u64) -> Result<(), RoundError> {
+        self.expect_phase(RoundPhase::Collecting, RoundPhase::Collecting)?;
+        self.cutoff = cutoff;
+        self.deadline = Some(self.collect_deadline());
+        Ok(())
+    }
+
+    /// Registers contributions carried over from the previous round, with proof-of-work
+    /// tickets bound to that round. Contributions made for any other round, and those that
+    /// fail to register, such as duplicates of ones already made to this round, are dropped.
+    /// Only valid while collecting
+    ///
+    /// ### Arguments
+    ///
+    /// * `contributions` - Contributions carried over from the previous round
+    pub fn carry_in(&mut self, contributions: &[Contribution]) -> Result<usize, RoundError> {
+        self.expect_phase(RoundPhase::Collecting, RoundPhase::Collecting)?;
+
+        let mut carried = 0;
+        for contribution in contributions {
+            if Some(contribution.round_id) != self.id.checked_sub(1) {
+                continue;
+            }
+            let registered = self.registry.register_carried(
+                &contribution.identity,
+                contribution.nonce,
+                contribution.round_id,
+            );
+            if registered.is_ok() {
+                self.contributions.push(contribution.clone());
+                carried += 1;
+            }
+        }
+
+        Ok(carried)
+    }
+
+    /// Contributions accepted after the cutoff of the round they were made for: those flagged
+    /// as late in this round, and those carried over from the previous round
+    pub fn late_contributions(&self) -> Vec<Contribution> {
+        self.contributions
+            .iter()
+            .filter(|c| c.round_id != self.id || c.timestamp > self.cutoff)
+            .cloned()
+            .collect()
+    }
+
+    /// End of collection: the cutoff, extended by the grace period
+    fn collect_deadline(&self) -> u64 {
+        self.cutoff.saturating_add(self.grace.grace_secs())
+    }
+
+    /// Whether the deadline of the current phase has passed
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn is_timed_out(&self, now: u64) -> bool {
+        matches!(self.deadline, Some(deadline) if now > deadline)
+    }
+
+    /// Registers a participant with a contribution timestamped `now`
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity of the participant
+    /// * `now`      - Current unix time in seconds
+    pub fn contribute(&mut self, identity: &str, now: u64) -> Result<String, RoundError> {
+        self.submit(
+            Contribution {
+                round_id: self.id,
+                identity: identity.to_string(),
+                timestamp: now,
+                nonce: None,
+            },
+            now,
+        )
+    }
+
+    /// Submits a contribution, returning the fingerprint of the registered identity. Only
+    /// valid while collecting, and refused if either the contribution's timestamp or the
+    /// current time is past the cutoff and its grace period. Contributions timestamped in the
+    /// grace period are flagged or carried over as the grace policy says
+    ///
+    /// ### Arguments
+    ///
+    /// * `contribution` - Contribution to submit
+    /// * `now`          - Current unix time in seconds
+    pub fn submit(&mut self, contribution: Contribution, now: u64) -> Result<String, RoundError> {
+        self.expect_phase(RoundPhase::Collecting, RoundPhase::Collecting)?;
+        if contribution.round_id != self.id {
+            return Err(RoundError::WrongRound {
+                expected: self.id,
+                actual: contribution.round_id,
+            });
+        }
+        if self.is_timed_out(now) {
+            return Err(RoundError::TimedOut { phase: self.phase });
+        }
+        if contribution.timestamp > self.cutoff {
+            if contribution.timestamp > self.collect_deadline() {
+                return Err(RoundError::LateContribution {
+                    timestamp: contribution.timestamp,
+                    cutoff: self.cutoff,
+                });
+            }
+            if self.grace.mode == LateMode::NextRound {
+                return self.carry_over(contribution);
+            }
+        }
+
+        let fingerprint = self
+            .registry
+            .register_with_ticket(&contribution.identity, contribution.nonce)?;
+        self.contributions.push(contribution);
+
+        Ok(fingerprint)
+    }
+
+    /// Holds a contribution timestamped in the grace period for the next round, checking its
+    /// proof-of-work ticket against this round
+    fn carry_over(&mut self, contribution: Contribution) -> Result<String, RoundError> {
+        let identity = normalize_identity(&contribution.identity);
+        if identity.is_empty() {
+            return Err(RegistryError::EmptyIdentity.into());
+        }
+        if let Some(requirement) = self.registry.pow_requirement() {
+            let nonce = contribution.nonce.ok_or(RegistryError::MissingTicket)?;
+            if !requirement.check(&identity, nonce) {
+                return Err(RegistryError::InsufficientWork.into());
+            }
+        }
+
+        let fingerprint = fingerprint(&identity);
+        let carried = self.carried_over.iter();
+        if carried
+            .map(|c| normalize_identity(&c.identity))
+            .any(|c| c == identity)
+        {
+            return Err(RegistryError::Duplicate { fingerprint }.into());
+        }
+        self.carried_over.push(contribution);
+
+        Ok(fingerprint)
+    }
+
+    /// Seals the participant set, fixes the seed and commits to the sealed inputs digest
+    pub fn seal(&mut self) -> Result<&Integer, RoundError> {
+        self.expect_phase(RoundPhase::Collecting, RoundPhase::Sealed)?;
+        if self.registry.is_empty() {
+            return Err(RoundError::NoParticipants);
+        }
+
+        self.seed = match self.merkle_root() {
+            Some(root) => construct_seed_with_root(&root, &self.last_winning_hashes),
+            None => construct_seed_with_mode(
+                self.seed_mode,
+                &self.registry.seed_inputs(),
+                &self.last_winning_hashes,
+            ),
+        };
+        self.sealed_digest = Some(self.inputs_digest());
+        self.phase = RoundPhase::Sealed;
+        self.deadline = None;
+
+        Ok(&self.seed)
+    }
+
+    /// Merkle root of the participants, if the seed commits to it
+    pub fn merkle_root(&self) -> Option<String> {
+        self.commit_merkle_root
+            .then(|| self.registry.merkle_tree().root())
+    }
+
+    /// Creates the proof that a participant is included under the Merkle root. Returns `None`
+    /// if the seed does not commit to a Merkle root or the identity is not registered
+    ///
+    /// ### Arguments
+    ///
+    /// * `identity` - Identity as submitted; normalized before lookup
+    pub fn prove_inclusion(&self, identity: &str) -> Option<InclusionProof> {
+        if !self.commit_merkle_root {
+            return None;
+        }
+        self.registry
+            .merkle_tree()
+            .prove_inclusion(&normalize_identity(identity))
+    }
+
+    /// Digest of the round's inputs: the round id, the exact seed bytes of the registered
+    /// participants, the last winning PoW hashes and the timestamp of every contribution,
+    /// sorted by identity so that arrival order does not matter
+    pub fn inputs_digest(&self) -> String {
+        let mut timestamps: Vec<(String, u64)> = self
+            .contributions
+            .iter()
+            .map(|c| (normalize_identity(&c.identity), c.timestamp))
+            .collect();
+        timestamps.sort();
+
+        let inputs = (
+            self.id,
+            self.registry.seed_bytes(),
+            &self.last_winning_hashes,
+            timestamps,
+        );
+        hex::encode(Sha256::digest(serialize(&inputs).unwrap()))
+    }
+
+    /// Starts the eval, returning the UNICORN to evaluate. The eval itself may run
+    /// elsewhere; its result is handed back through `complete_eval`
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn start_eval(&mut self, now: u64) -> Result<Unicorn, RoundError> {
+        self.expect_phase(RoundPhase::Sealed, RoundPhase::Evaluating)?;
+        if self.sealed_digest.as_deref() != Some(self.inputs_digest().as_str()) {
+            return Err(RoundError::SealedDigestMismatch);
+        }
+
+        let unicorn = self.unicorn()?;
+
+        self.phase = RoundPhase::Evaluating;
+        self.deadline = Some(now.saturating_add(self.timeouts.eval_secs));
+
+        Ok(unicorn)
+    }
+
+    /// The UNICORN evaluated for the round, from its sealed seed
+    pub fn unicorn(&self) -> Result<Unicorn, RoundError> {
+        Ok(Unicorn {
+            seed: self.seed.clone(),
+            modulus: Integer::from_str_radix(&self.fixed_params.modulus, 10)
+                .map_err(|_| RoundError::InvalidUnicorn)?,
+            iterations: self.fixed_params.iterations,
+            security_level: self.fixed_params.security,
+            primality_rounds: self.fixed_params.primality_rounds,
+        })
+    }
+
+    /// Completes the eval and publishes the selection. Aborts the round if the eval
+    /// deadline has passed. The UNICORN must have the round's seed and parameters, and its
+    /// witness must verify
+    ///
+    /// ### Arguments
+    ///
+    /// * `info` - Evaluated UNICORN
+    /// * `now`  - Current unix time in seconds
+    pub fn complete_eval(
+        &mut self,
+        info: UnicornInfo,
+        now: u64,
+    ) -> Result<&SelectionTranscript, RoundError> {
+        self.complete_eval_with(&UnicornVdf::default(), info, now)
+    }
+
+    /// Completes the eval, checking the witness with the given VDF
+    ///
+    /// ### Arguments
+    ///
+    /// * `vdf`  - VDF the UNICORN was evaluated with
+    /// * `info` - Evaluated UNICORN
+    /// * `now`  - Current unix time in seconds
+    pub fn complete_eval_with(
+        &mut self,
+        vdf: &impl Vdf,
+        info: UnicornInfo,
+        now: u64,
+    ) -> Result<&SelectionTranscript, RoundError> {
+        self.expect_phase(RoundPhase::Evaluating, RoundPhase::Published)?;
+        if self.is_timed_out(now) {
+            self.phase = RoundPhase::Aborted;
+            self.deadline = None;
+            return Err(RoundError::TimedOut {
+                phase: RoundPhase::Evaluating,
+            });
+        }
+
+        let expected = self.unicorn()?;
+        let params_match = info.unicorn.seed == expected.seed
+            && info.unicorn.modulus == expected.modulus
+            && info.unicorn.iterations == expected.iterations
+            && info.unicorn.security_level == expected.security_level;
+        if !params_match
+            || info.g_value != g_from_witness(&info.witness)
+            || !vdf.verify(&expected, &info.witness)
+        {
+            return Err(RoundError::InvalidUnicorn);
+        }
+
+        let mut transcript = SelectionTranscript::from_unicorn(
+            &self.registry.seed_inputs(),
+            &self.last_winning_hashes,
+            &self.fixed_params,
+            &info,
+            self.usage_number,
+        )
+        .ok_or(RoundError::NoParticipants)?;
+        transcript.merkle_root = self.merkle_root();
+        transcript.seed_mode = self.seed_mode;
+        transcript.cutoff_attestation = self.cutoff_attestation.clone();
+        transcript.cutoff = self.cutoff;
+        transcript.grace = self.grace;
+        transcript.late_contributions = self.late_contributions();
+
+        self.phase = RoundPhase::Published;
+        self.deadline = None;
+
+        Ok(self.transcript.insert(transcript))
+    }
+
+    /// Runs the eval in place, moving the round from `Sealed` to `Published`
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn evaluate(&mut self, now: u64) -> Result<&SelectionTranscript, RoundError> {
+        self.evaluate_with(&UnicornVdf::default(), now)
+    }
+
+    /// Runs the eval in place with the given VDF, moving the round from `Sealed` to
+    /// `Published`
+    ///
+    /// ### Arguments
+    ///
+    /// * `vdf` - VDF evaluating the round's UNICORN
+    /// * `now` - Current unix time in seconds
+    pub fn evaluate_with(
+        &mut self,
+        vdf: &impl Vdf,
+        now: u64,
+    ) -> Result<&SelectionTranscript, RoundError> {
+        let unicorn = self.start_eval(now)?;
+        let (witness, g_value) = vdf.eval(&unicorn).ok_or(RoundError::InvalidUnicorn)?;
+
+        self.complete_eval_with(vdf, UnicornInfo::new(unicorn, witness, g_value), now)
+    }
+
+    /// Checks the published selection with the fast witness verify, without re-running the
+    /// eval, and marks the round as verified
+    pub fn verify(&mut self) -> Result<(), RoundError> {
+        self.expect_phase(RoundPhase::Published, RoundPhase::Verified)?;
+
+        let transcript = self
+            .transcript
+            .as_ref()
+            .ok_or(RoundError::VerificationFailed)?;
+        if transcript.seed != self.seed || transcript.verify().is_err() {
+            return Err(RoundError::VerificationFailed);
+        }
+
+        self.phase = RoundPhase::Verified;
+
+        Ok(())
+    }
+
+    /// Whether a quorum of verifiers has finalized the round
+    pub fn is_final(&self) -> bool {
+        self.finality.is_some()
+    }
+
+    /// Serializes the round to pretty JSON
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Deserializes a round from JSON
+    ///
+    /// ### Arguments
+    ///
+    /// * `json` - JSON produced by `to_json_pretty`
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    fn expect_phase(&self, expected: RoundPhase, to: RoundPhase) -> Result<(), RoundError> {
+        if self.phase != expected {
+            return Err(RoundError::InvalidTransition {
+                from: self.phase,
+                to,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A value of a stored round that differs from the value replayed from its inputs
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Divergence {
+    /// Name of the diverging value
+    pub field: String,
+    pub stored: String,
+    pub replayed: String,
+}
+
+/// Outcome of replaying a stored round from its raw inputs
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ReplayReport {
+    pub round_id: u64,
+    /// Values of the stored round that the replay did not reproduce, in the order they were
+    /// checked
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    /// Whether the replay reproduced the stored round
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    fn compare(&mut self, field: &str, stored: impl ToString, replayed: impl ToString) {
+        let (stored, replayed) = (stored.to_string(), replayed.to_string());
+        if stored != replayed {
+            self.divergences.push(Divergence {
+                field: field.to_string(),
+                stored,
+                replayed,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReplayError {
+    /// The store failed to read the round.
+    Storage(StorageError),
+    /// No round is stored under the id.
+    NotFound { round_id: u64 },
+    /// The stored round has no published result to replay.
+    NotPublished { phase: RoundPhase },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Storage(e) => write!(f, "Could not read round: {}", e),
+            ReplayError::NotFound { round_id } => write!(f, "Round {} is not stored", round_id),
+            ReplayError::NotPublished { phase } => {
+                write!(f, "Round has no published result in {:?}", phase)
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplayError::Storage(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Replays a stored round from its raw inputs and diffs the result against the stored one.
+/// The participants are registered again from the stored contributions, the seed and sealed
+/// inputs digest rebuilt from them, the stored witness checked against the rebuilt seed and
+/// the winner derived again from it. Each value the replay does not reproduce is reported as a
+/// divergence, rather than failing the replay
+///
+/// ### Arguments
+///
+/// * `round_id` - Id of the round to replay
+/// * `store`    - Store holding the round
+pub fn replay(round_id: u64, store: &dyn RoundStore) -> Result<ReplayReport, ReplayError> {
+    let stored = store
+        .get_round(round_id)
+        .map_err(ReplayError::Storage)?
+        .ok_or(ReplayError::NotFound { round_id })?;
+    let transcript = stored
+        .transcript
+        .as_ref()
+        .ok_or(ReplayError::NotPublished {
+            phase: stored.phase,
+        })?;
+    let mut report = ReplayReport {
+        round_id,
+        divergences: Vec::new(),
+    };
+
+    let mut registry = ParticipantRegistry::new();
+    if let Some(requirement) = stored.registry.pow_requirement() {
+        registry = registry.with_pow_requirement(*requirement);
+    }
+    for contribution in &stored.contributions {
+        let deadline = stored.cutoff.saturating_add(stored.grace.grace_secs());
+        if contribution.round_id == stored.id && contribution.timestamp > deadline {
+            report.compare("contribution", &contribution.identity, "refused as late");
+        }
+        if contribution.round_id != stored.id
+            && Some(contribution.round_id) != stored.id.checked_sub(1)
+        {
+            report.compare(
+                "contribution",
+                &contribution.identity,
+                "made for another round",
+            );
+            continue;
+        }
+        let registered = registry.register_carried(
+            &contribution.identity,
+            contribution.nonce,
+            contribution.round_id,
+        );
+        if let Err(e) = registered {
+            report.compare("contribution", &contribution.identity, e);
+        }
+    }
+
+    let mut rebuilt = stored.clone();
+    rebuilt.registry = registry;
+    rebuilt.phase = RoundPhase::Collecting;
+    if rebuilt.seal().is_err() {
+        report.compare("participants", stored.registry.len(), 0);
+        return Ok(report);
+    }
+
+    let inputs = rebuilt.registry.seed_inputs();
+    report.compare("inputs", transcript.inputs.join(","), inputs.join(","));
+    report.compare("seed", &stored.seed, &rebuilt.seed);
+    report.compare(
+        "sealed_digest",
+        stored.sealed_digest.as_deref().unwrap_or_default(),
+        rebuilt.sealed_digest.as_deref().unwrap_or_default(),
+    );
+
+    let Ok(unicorn) = rebuilt.unicorn() else {
+        report.compare(
+            "fixed_params",
+            &stored.fixed_params.modulus,
+            "invalid modulus",
+        );
+        return Ok(report);
+    };
+    if !unicorn.verify_ref(&rebuilt.seed, &transcript.witness) {
+        report.compare("witness", &transcript.witness, "fails verification");
+        return Ok(report);
+    }
+
+    let info = UnicornInfo::new(
+        unicorn,
+        transcript.witness.clone(),
+        g_from_witness(&transcript.witness),
+    );
+    let Some(derived) = SelectionTranscript::from_unicorn(
+        &inputs,
+        &rebuilt.last_winning_hashes,
+        &rebuilt.fixed_params,
+        &info,
+        rebuilt.usage_number,
+    ) else {
+        report.compare("participants", stored.registry.len(), 0);
+        return Ok(report);
+    };
+    report.compare("commitment", &transcript.commitment, &derived.commitment);
+    report.compare("g_value", &transcript.g_value, &derived.g_value);
+    report.compare("prn_bytes", &transcript.prn_bytes, &derived.prn_bytes);
+    report.compare(
+        "winner_index",
+        transcript.winner_index,
+        derived.winner_index,
+    );
+    report.compare("winner", &transcript.winner, &derived.winner);
+
+    Ok(report)
+}
+
+/// Values that differ between two round records, each paired as `(a, b)`. Values that agree
+/// are left out
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct RoundDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<(u64, u64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<(RoundPhase, RoundPhase)>,
+    /// Seed inputs registered in `a` only
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inputs_only_in_a: Vec<String>,
+    /// Seed inputs registered in `b` only
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inputs_only_in_b: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_winning_hashes: Option<(Vec<String>, Vec<String>)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_params: Option<(UnicornFixedParam, UnicornFixedParam)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_number: Option<(u128, u128)>,
+    /// Decimal seeds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sealed_digest: Option<(Option<String>, Option<String>)>,
+    /// Decimal witnesses, if published
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub witness: Option<(Option<String>, Option<String>)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub g_value: Option<(Option<String>, Option<String>)>,
+    /// Winning inputs, if published
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winner: Option<(Option<String>, Option<String>)>,
+}
+
+impl RoundDiff {
+    /// Whether the two records agree on every compared value
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for RoundDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn line(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            a: impl fmt::Debug,
+            b: impl fmt::Debug,
+        ) -> fmt::Result {
+            writeln!(f, "{}:\n  a: {:?}\n  b: {:?}", name, a, b)
+        }
+
+        if let Some((a, b)) = &self.id {
+            line(f, "id", a, b)?;
+        }
+        if let Some((a, b)) = &self.phase {
+            line(f, "phase", a, b)?;
+        }
+        for input in &self.inputs_only_in_a {
+            writeln!(f, "input only in a: {}", input)?;
+        }
+        for input in &self.inputs_only_in_b {
+            writeln!(f, "input only in b: {}", input)?;
+        }
+        if let Some((a, b)) = &self.last_winning_hashes {
+            line(f, "last_winning_hashes", a, b)?;
+        }
+        if let Some((a, b)) = &self.fixed_params {
+            line(f, "fixed_params", a, b)?;
+        }
+        if let Some((a, b)) = &self.usage_number {
+            line(f, "usage_number", a, b)?;
+        }
+        if let Some((a, b)) = &self.seed {
+            line(f, "seed", a, b)?;
+        }
+        if let Some((a, b)) = &self.sealed_digest {
+            line(f, "sealed_digest", a, b)?;
+        }
+        if let Some((a, b)) = &self.witness {
+            line(f, "witness", a, b)?;
+        }
+        if let Some((a, b)) = &self.g_value {
+            line(f, "g_value", a, b)?;
+        }
+        if let Some((a, b)) = &self.winner {
+            line(f, "winner", a, b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares two round records, such as conflicting results published by two nodes for the
+/// same round
+///
+/// ### Arguments
+///
+/// * `a` - One round record
+/// * `b` - The other round record
+pub fn diff(a: &Round, b: &Round) -> RoundDiff {
+    fn differ<T: PartialEq>(a: T, b: T) -> Option<(T, T)> {
+        (a != b).then_some((a, b))
+    }
+    let published = |round: &Round, value: fn(&SelectionTranscript) -> String| {
+        round.transcript.as_ref().map(value)
+    };
+
+    let (inputs_a, inputs_b) = (a.registry.seed_inputs(), b.registry.seed_inputs());
+    let only_in = |these: &[String], those: &[String]| -> Vec<String> {
+        these
+            .iter()
+            .filter(|i| !those.contains(i))
+            .cloned()
+            .collect()
+    };
+
+    RoundDiff {
+        id: differ(a.id, b.id),
+        phase: differ(a.phase, b.phase),
+        inputs_only_in_a: only_in(&inputs_a, &inputs_b),
+        inputs_only_in_b: only_in(&inputs_b, &inputs_a),
+        last_winning_hashes: differ(a.last_winning_hashes.clone(), b.last_winning_hashes.clone()),
+        fixed_params: differ(a.fixed_params.clone(), b.fixed_params.clone()),
+        usage_number: differ(a.usage_number, b.usage_number),
+        seed: differ(a.seed.to_string(), b.seed.to_string()),
+        sealed_digest: differ(a.sealed_digest.clone(), b.sealed_digest.clone()),
+        witness: differ(
+            published(a, |t| t.witness.to_string()),
+            published(b, |t| t.witness.to_string()),
+        ),
+        g_value: differ(
+            published(a, |t| t.g_value.clone()),
+            published(b, |t| t.g_value.clone()),
+        ),
+        winner: differ(
+            published(a, |t| t.winner.clone()),
+            published(b, |t| t.winner.clone()),
+        ),
+    }
+}
+
+/// Cutoff of a round that has none
+fn no_cutoff() -> u64 {
+    u64::MAX
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod rounds_tests {
+    use super::*;
+    use crate::merkle::verify_inclusion;
+    use crate::storage::MemoryStore;
+    use crate::vdf::MockVdf;
+
+    fn test_round(now: u64) -> Round {
+        let fixed_params = UnicornFixedParam::test();
+        let timeouts = RoundTimeouts {
+            collect_secs: 60,
+            eval_secs: 60,
+        };
+
+        Round::new(1, fixed_params, 1, timeouts, now)
+    }
+
+    #[test]
+    /// Checks that a round goes through every phase and survives persistence midway
+    fn full_lifecycle() {
+        let mut round = test_round(0).with_last_winning_hashes(vec!["00ab".to_string()]);
+        round.contribute("miner_a", 10).unwrap();
+        round.contribute("miner_b", 20).unwrap();
+        round.seal().unwrap();
+
+        let mut round = Round::from_json(&round.to_json_pretty().unwrap()).unwrap();
+        round.evaluate(70).unwrap();
+        round.verify().unwrap();
+
+        assert_eq!(round.phase, RoundPhase::Verified);
+    }
+
+    #[test]
+    /// Checks that a round committing to the Merkle root verifies and proves its winner
+    fn merkle_root_lifecycle() {
+        let mut round = test_round(0).with_merkle_root();
+        for i in 0..5 {
+            round.contribute(&format!("miner_{}", i), 10).unwrap();
+        }
+        round.seal().unwrap();
+        round.evaluate(70).unwrap();
+        round.verify().unwrap();
+
+        let transcript = round.transcript.as_ref().unwrap();
+        let root = round.merkle_root().unwrap();
+        let proof = transcript.winner_proof().unwrap();
+
+        assert_eq!(transcript.merkle_root.as_ref(), Some(&root));
+        assert_ne!(round.seed, round.registry.construct_seed());
+        assert!(proof.verify(&root, &transcript.winner));
+
+        let proof = round.prove_inclusion(" miner_3 ").unwrap();
+        assert_eq!(proof.input, "miner_3");
+        assert!(verify_inclusion(&proof, &root));
+        assert_eq!(round.prove_inclusion("miner_9"), None);
+        assert_eq!(
+            construct_seed_with_root(&root, &round.last_winning_hashes),
+            round.seed
+        );
+    }
+
+    #[test]
+    /// Checks that a tree-hashed seed is recorded in the transcript and replays
+    fn tree_hash_lifecycle() {
+        let mut round = test_round(0).with_seed_mode(SeedMode::TreeHash);
+        for i in 0..5 {
+            round.contribute(&format!("miner_{}", i), 10).unwrap();
+        }
+        round.seal().unwrap();
+        round.evaluate(70).unwrap();
+        round.verify().unwrap();
+
+        let transcript = round.transcript.as_ref().unwrap();
+        assert_eq!(transcript.seed_mode, SeedMode::TreeHash);
+        assert_ne!(round.seed, round.registry.construct_seed());
+        assert!(transcript.replay().is_ok());
+    }
+
+    #[test]
+    /// Checks that out-of-order transitions are refused
+    fn invalid_transitions() {
+        let mut round = test_round(0);
+
+        assert_eq!(
+            round.verify(),
+            Err(RoundError::InvalidTransition {
+                from: RoundPhase::Collecting,
+                to: RoundPhase::Verified
+            })
+        );
+        assert_eq!(round.seal(), Err(RoundError::NoParticipants));
+    }
+
+    #[test]
+    /// Checks that late contributions are refused and late evals abort the round
+    fn timeouts() {
+        let mut round = test_round(0);
+        round.contribute("miner_a", 60).unwrap();
+
+        assert_eq!(
+            round.contribute("miner_b", 61),
+            Err(RoundError::TimedOut {
+                phase: RoundPhase::Collecting
+            })
+        );
+
+        round.seal().unwrap();
+        let unicorn = round.start_eval(100).unwrap();
+        let (witness, g_value) = unicorn.eval().unwrap();
+        let info = UnicornInfo::new(unicorn, witness, g_value);
+
+        assert!(round.complete_eval(info, 161).is_err());
+        assert_eq!(round.phase, RoundPhase::Aborted);
+    }
+
+    #[test]
+    /// Checks that evals with other parameters or a wrong witness are not published
+    fn complete_eval_checks_unicorn() {
+        let mut round = test_round(0);
+        round.contribute("miner_a", 10).unwrap();
+        round.seal().unwrap();
+        let unicorn = round.start_eval(100).unwrap();
+
+        let mut short = unicorn.clone();
+        short.iterations = 1;
+        let (witness, g_value) = short.eval().unwrap();
+        let info = UnicornInfo::new(short, witness, g_value);
+        assert_eq!(
+            round.complete_eval(info, 100),
+            Err(RoundError::InvalidUnicorn)
+        );
+
+        let (witness, _) = unicorn.eval().unwrap();
+        let wrong = Integer::from(&witness + 1);
+        let info = UnicornInfo::new(unicorn.clone(), wrong.clone(), g_from_witness(&wrong));
+        assert_eq!(
+            round.complete_eval(info, 100),
+            Err(RoundError::InvalidUnicorn)
+        );
+
+        let info = UnicornInfo::new(unicorn, witness.clone(), g_from_witness(&witness));
+        assert!(round.complete_eval(info, 100).is_ok());
+    }
+
+    #[test]
+    /// Checks that contributions are bound to their round and cutoff, and that the sealed
+    /// inputs cannot change before eval
+    fn contribution_cutoff() {
+        let mut round = test_round(0);
+        let contribution = |round_id, timestamp| Contribution {
+            round_id,
+            identity: format!("miner_{}_{}", round_id, timestamp),
+            timestamp,
+            nonce: None,
+        };
+
+        assert_eq!(
+            round.submit(contribution(2, 10), 10),
+            Err(RoundError::WrongRound {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            round.submit(contribution(1, 61), 10),
+            Err(RoundError::LateContribution {
+                timestamp: 61,
+                cutoff: 60
+            })
+        );
+        round.submit(contribution(1, 30), 30).unwrap();
+        round.seal().unwrap();
+
+        assert!(matches!(
+            round.submit(contribution(1, 40), 40),
+            Err(RoundError::InvalidTransition { .. })
+        ));
+
+        let mut backdated = round.clone();
+        backdated.contributions[0].timestamp = 20;
+        assert_eq!(
+            backdated.start_eval(70),
+            Err(RoundError::SealedDigestMismatch)
+        );
+
+        round.registry.register("miner_late").unwrap();
+
+        assert_eq!(round.start_eval(70), Err(RoundError::SealedDigestMismatch));
+    }
+
+    #[test]
+    /// Checks that rounds stored before cutoffs were recorded still load
+    fn loads_without_cutoff() {
+        let mut json = serde_json::to_value(test_round(0)).unwrap();
+        json.as_object_mut().unwrap().remove("cutoff");
+        let round: Round = serde_json::from_value(json).unwrap();
+
+        assert_eq!(round.cutoff, u64::MAX);
+    }
+
+    #[test]
+    /// Checks that contributions in the grace period are flagged or carried over by their
+    /// timestamps alone
+    fn grace_policies() {
+        let contribution = |round_id, identity: &str, timestamp| Contribution {
+            round_id,
+            identity: identity.to_string(),
+            timestamp,
+            nonce: None,
+        };
+
+        let mut flagging = test_round(0).with_grace(GracePolicy {
+            mode: LateMode::Flag,
+            grace_secs: 5,
+        });
+        flagging.submit(contribution(1, "miner_a", 60), 60).unwrap();
+        flagging.submit(contribution(1, "miner_b", 65), 65).unwrap();
+        assert!(flagging.submit(contribution(1, "miner_c", 66), 65).is_err());
+        assert_eq!(
+            flagging.late_contributions(),
+            vec![contribution(1, "miner_b", 65)]
+        );
+        assert!(!flagging.is_timed_out(65));
+        flagging.seal().unwrap();
+        flagging.evaluate(66).unwrap();
+        assert_eq!(
+            flagging.transcript.as_ref().unwrap().late_contributions,
+            vec![contribution(1, "miner_b", 65)]
+        );
+
+        let mut deferring = test_round(0).with_grace(GracePolicy {
+            mode: LateMode::NextRound,
+            grace_secs: 5,
+        });
+        deferring
+            .submit(contribution(1, "miner_a", 60), 60)
+            .unwrap();
+        deferring
+            .submit(contribution(1, "miner_b", 62), 62)
+            .unwrap();
+        assert_eq!(deferring.registry.len(), 1);
+        assert_eq!(deferring.carried_over, vec![contribution(1, "miner_b", 62)]);
+
+        let mut next = Round::new(2, deferring.fixed_params.clone(), 1, deferring.timeouts, 66);
+        assert_eq!(next.carry_in(&deferring.carried_over), Ok(1));
+        assert_eq!(next.late_contributions(), deferring.carried_over);
+
+        let mut later = Round::new(3, deferring.fixed_params.clone(), 1, deferring.timeouts, 66);
+        assert_eq!(later.carry_in(&deferring.carried_over), Ok(0));
+        assert!(later.registry.is_empty());
+    }
+
+    #[test]
+    /// Checks that a stored round replays cleanly and that tampering with it is reported
+    fn replay_stored_round() {
+        let mut round = test_round(0);
+        round.contribute("miner_a", 1).unwrap();
+        round.contribute("miner_b", 2).unwrap();
+        round.seal().unwrap();
+        round.evaluate(3).unwrap();
+
+        let mut store = MemoryStore::default();
+        store.put_round(&round).unwrap();
+        assert!(replay(1, &store).unwrap().is_consistent());
+        assert_eq!(
+            replay(2, &store),
+            Err(ReplayError::NotFound { round_id: 2 })
+        );
+
+        let fields = |store: &MemoryStore| -> Vec<String> {
+            let report = replay(1, store).unwrap();
+            report.divergences.into_iter().map(|d| d.field).collect()
+        };
+
+        let transcript = round.transcript.as_mut().unwrap();
+        transcript.winner_index = 1 - transcript.winner_index;
+        store.put_round(&round).unwrap();
+        assert_eq!(fields(&store), ["winner_index"]);
+
+        round.contributions.pop();
+        store.put_round(&round).unwrap();
+        assert_eq!(
+            fields(&store),
+            ["inputs", "seed", "sealed_digest", "witness"]
+        );
+    }
+
+    #[test]
+    /// Checks that a diff lists exactly the values two round records disagree on
+    fn diff_rounds() {
+        let publish = |identities: &[&str]| {
+            let mut round = test_round(0);
+            for identity in identities {
+                round.contribute(identity, 1).unwrap();
+            }
+            round.seal().unwrap();
+            round.evaluate(2).unwrap();
+            round
+        };
+        let a = publish(&["miner_a", "miner_b"]);
+        let b = publish(&["miner_a", "miner_c"]);
+
+        assert!(diff(&a, &a.clone()).is_empty());
+
+        let d = diff(&a, &b);
+        assert_eq!(d.inputs_only_in_a, ["miner_b"]);
+        assert_eq!(d.inputs_only_in_b, ["miner_c"]);
+        assert!(d.seed.is_some() && d.witness.is_some() && d.g_value.is_some());
+        assert!(d.to_string().contains("input only in b: miner_c"));
+        assert_eq!(
+            (d.id, d.phase, d.fixed_params, d.usage_number),
+            (None, None, None, None)
+        );
+    }
+
+    #[test]
+    /// Checks that a round evaluated with the mock VDF publishes at once
+    fn evaluate_with_mock_vdf() {
+        let mut round = test_round(0);
+        round.fixed_params.iterations = u64::MAX;
+        round.contribute("miner_a", 1).unwrap();
+        round.contribute("miner_b", 2).unwrap();
+        round.seal().unwrap();
+
+        let (witness, _) = MockVdf.eval(&round.unicorn().unwrap()).unwrap();
+        let transcript = round.evaluate_with(&MockVdf, 3).unwrap();
+
+        assert_eq!(transcript.witness, witness);
+        assert_eq!(round.phase, RoundPhase::Published);
+    }
+}
@@ -0,0 +1,144 @@
+//! Round scheduling policies.
+//!
+//! A schedule decides when the collecting round is cut off. It is read from the
+//! `[daemon.schedule]` table, so that every node running with the same configuration computes
+//! the same round boundaries:
+//!
+//! - `rolling`: each round is cut off `daemon.collect_secs` after it is opened
+//! - `interval`: rounds are cut off on a fixed grid of `interval_secs` slots counted from the
+//!   unix epoch and shifted by `cutoff_offset_secs`. A round is cut off at the first boundary
+//!   after it is opened, so a round opened late in a slot collects for the rest of it
+//! - `block_height`: round `id` is cut off once the chain reaches height
+//!   `id + cutoff_offset_blocks`, as reported to the daemon
+//! - `manual`: rounds are cut off `cutoff_offset_secs` after an operator triggers them
+//!
+//! Rounds under the last two policies collect without a deadline until they are triggered.
+
+use serde::{Deserialize, Serialize};
+
+/// Policy deciding when rounds are cut off
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(tag = "policy", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Schedule {
+    /// Rounds collect for `daemon.collect_secs` from when they are opened.
+    #[default]
+    Rolling,
+    /// Rounds are cut off on a fixed grid of slots from the unix epoch.
+    Interval {
+        interval_secs: u64,
+        #[serde(default)]
+        cutoff_offset_secs: u64,
+    },
+    /// Rounds are cut off once the chain reaches a height past their id.
+    BlockHeight {
+        #[serde(default)]
+        cutoff_offset_blocks: u64,
+    },
+    /// Rounds are cut off after an operator triggers them.
+    Manual {
+        #[serde(default)]
+        cutoff_offset_secs: u64,
+    },
+}
+
+impl Schedule {
+    /// Cutoff of a round opened at the given time, or `None` if the round waits for a trigger
+    ///
+    /// ### Arguments
+    ///
+    /// * `collect_secs` - Collection window of rolling rounds
+    /// * `now`          - Current unix time in seconds
+    pub fn cutoff(&self, collect_secs: u64, now: u64) -> Option<u64> {
+        match *self {
+            Schedule::Rolling => Some(now.saturating_add(collect_secs)),
+            Schedule::Interval {
+                interval_secs,
+                cutoff_offset_secs,
+            } => Some(next_boundary(interval_secs, cutoff_offset_secs, now)),
+            Schedule::BlockHeight { .. } | Schedule::Manual { .. } => None,
+        }
+    }
+
+    /// Cutoff of a round once the chain reaches a height, or `None` if rounds are not cut off
+    /// by block height or the height is not yet reached. The round is cut off at once
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round
+    /// * `height`   - Height the chain has reached
+    /// * `now`      - Current unix time in seconds
+    pub fn height_cutoff(&self, round_id: u64, height: u64, now: u64) -> Option<u64> {
+        match *self {
+            Schedule::BlockHeight {
+                cutoff_offset_blocks,
+            } if height >= round_id.saturating_add(cutoff_offset_blocks) => Some(now),
+            _ => None,
+        }
+    }
+
+    /// Cutoff of a round triggered by an operator, or `None` if rounds are not triggered
+    /// manually
+    ///
+    /// ### Arguments
+    ///
+    /// * `now` - Current unix time in seconds
+    pub fn manual_cutoff(&self, now: u64) -> Option<u64> {
+        match *self {
+            Schedule::Manual { cutoff_offset_secs } => Some(now.saturating_add(cutoff_offset_secs)),
+            _ => None,
+        }
+    }
+}
+
+/// First boundary of a grid of `interval`-second slots shifted by `offset` after `now`
+fn next_boundary(interval: u64, offset: u64, now: u64) -> u64 {
+    let interval = interval.max(1);
+    let phase = offset % interval;
+    if now < phase {
+        return phase;
+    }
+
+    (now - (now - phase) % interval).saturating_add(interval)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod schedule_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that interval rounds are cut off on the shifted grid whenever they are opened
+    fn interval_boundaries() {
+        let schedule = Schedule::Interval {
+            interval_secs: 60,
+            cutoff_offset_secs: 15,
+        };
+
+        assert_eq!(schedule.cutoff(10, 0), Some(15));
+        assert_eq!(schedule.cutoff(10, 15), Some(75));
+        assert_eq!(schedule.cutoff(10, 74), Some(75));
+        assert_eq!(schedule.cutoff(10, 1_000), Some(1_035));
+        assert_eq!(Schedule::Rolling.cutoff(10, 1_000), Some(1_010));
+    }
+
+    #[test]
+    /// Checks that triggered rounds only get a cutoff from their own trigger
+    fn triggered_cutoffs() {
+        let by_height = Schedule::BlockHeight {
+            cutoff_offset_blocks: 2,
+        };
+        let manual = Schedule::Manual {
+            cutoff_offset_secs: 5,
+        };
+
+        assert_eq!(by_height.cutoff(10, 100), None);
+        assert_eq!(by_height.height_cutoff(7, 8, 100), None);
+        assert_eq!(by_height.height_cutoff(7, 9, 100), Some(100));
+        assert_eq!(by_height.manual_cutoff(100), None);
+
+        assert_eq!(manual.cutoff(10, 100), None);
+        assert_eq!(manual.height_cutoff(7, 9, 100), None);
+        assert_eq!(manual.manual_cutoff(100), Some(105));
+    }
+}
@@ -0,0 +1,82 @@
+//! JSON Schemas of the published lottery artifacts.
+//!
+//! Schemas are generated from the same types that serialize the artifacts, so they cannot
+//! drift from what is actually published. Big integers (seeds, witnesses and moduli) are
+//! described as the hex strings they are serialized as.
+//!
+//! The selection proof is the `WinnersProof` written by `select` and the JSON-RPC `select`
+//! method; a round transcript is the `SelectionTranscript` recorded when a round is evaluated.
+
+use crate::manifest::InputManifest;
+use crate::rounds::Round;
+use crate::selection::WinnersProof;
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{UnicornFixedParam, UnicornInfo};
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use std::collections::BTreeMap;
+
+/// Names of the available schemas
+pub const SCHEMA_NAMES: [&str; 6] = [
+    "unicorn-info",
+    "unicorn-fixed-param",
+    "selection-proof",
+    "transcript",
+    "round",
+    "input-manifest",
+];
+
+/// Returns the schema of an artifact by name, or `None` if there is no such schema
+///
+/// ### Arguments
+///
+/// * `name` - One of `SCHEMA_NAMES`
+pub fn schema(name: &str) -> Option<RootSchema> {
+    Some(match name {
+        "unicorn-info" => schema_for!(UnicornInfo),
+        "unicorn-fixed-param" => schema_for!(UnicornFixedParam),
+        "selection-proof" => schema_for!(WinnersProof),
+        "transcript" => schema_for!(SelectionTranscript),
+        "round" => schema_for!(Round),
+        "input-manifest" => schema_for!(InputManifest),
+        _ => return None,
+    })
+}
+
+/// Returns the schemas of all artifacts, keyed by name
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    SCHEMA_NAMES
+        .iter()
+        .map(|name| (*name, schema(name).unwrap()))
+        .collect()
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    /// Checks that every named schema exists and that big integers are described as strings
+    fn schemas_describe_artifacts() {
+        assert_eq!(schemas().len(), SCHEMA_NAMES.len());
+        assert!(schema("unknown").is_none());
+
+        let schema = serde_json::to_value(schema("transcript").unwrap()).unwrap();
+        assert_eq!(schema["properties"]["seed"]["type"], "string");
+        assert_eq!(schema["properties"]["witness"]["type"], "string");
+
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let transcript = SelectionTranscript::record(&inputs, &params, 1).unwrap();
+        let value = serde_json::to_value(&transcript).unwrap();
+
+        let required = schema["required"].as_array().unwrap();
+        assert!(required
+            .iter()
+            .all(|field| value.get(field.as_str().unwrap()).is_some()));
+        assert!(matches!(value["seed"], Value::String(_)));
+    }
+}
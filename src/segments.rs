@@ -0,0 +1,233 @@
+//! Per-segment state hashes of an eval, for forensic comparison of evaluators.
+//!
+//! An eval only publishes its final witness, so two evaluators who disagree on a result can
+//! only tell that they diverged somewhere in the whole delay. Recording a SHA-256 of the
+//! iterated value every `interval` iterations into a sidecar narrows that down: comparing two
+//! sidecars gives the first segment where the evaluators part ways, which can then be
+//! re-evaluated from the last agreeing segment on its own.
+//!
+//! Hashes are taken at progress reports, so the interval is rounded up to a multiple of
+//! `unicorn::PROGRESS_INTERVAL`. The final state is always recorded.
+
+use crate::unicorn::{EvalMode, EvalState, Unicorn, PROGRESS_INTERVAL};
+use rug::integer::Order;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Hash of the iterated value at the end of a segment
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SegmentHash {
+    /// Number of iterations completed at the end of the segment
+    pub iterations_done: u64,
+    /// Hex-encoded SHA-256 of the big-endian bytes of the iterated value
+    pub hash: String,
+}
+
+/// Audit sidecar of an eval, holding the hash of every segment
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SegmentTrace {
+    /// Hex-encoded seed the eval started from
+    pub seed: String,
+    /// UNICORN iterations
+    pub iterations: u64,
+    /// Iterations between recorded hashes
+    pub interval: u64,
+    /// Segment hashes, in eval order
+    pub segments: Vec<SegmentHash>,
+}
+
+/// Where two segment traces first disagree
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Divergence {
+    /// The traces are not of the same eval, or were recorded at different intervals.
+    Incomparable,
+    /// The traces disagree at the end of a segment. `last_agreed` is the iteration count of
+    /// the last segment both agree on, or 0 if they disagree from the first one.
+    Segment {
+        last_agreed: u64,
+        iterations_done: u64,
+    },
+    /// The traces agree on every segment both hold, but one has more than the other.
+    Length { last_agreed: u64 },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Incomparable => write!(f, "Traces are not of the same eval"),
+            Divergence::Segment {
+                last_agreed,
+                iterations_done,
+            } => write!(
+                f,
+                "Traces diverge between iterations {} and {}",
+                last_agreed, iterations_done
+            ),
+            Divergence::Length { last_agreed } => write!(
+                f,
+                "Traces agree up to iteration {} but one ends there",
+                last_agreed
+            ),
+        }
+    }
+}
+
+/// Hashes an iterated value
+///
+/// ### Arguments
+///
+/// * `w` - Iterated value to hash
+pub fn hash_state(w: &Integer) -> String {
+    hex::encode(Sha256::digest(w.to_digits::<u8>(Order::MsfBe)))
+}
+
+/// Evaluates a UNICORN, recording a segment hash every `interval` iterations
+///
+/// ### Arguments
+///
+/// * `unicorn` - UNICORN to evaluate
+/// * `mode`    - Eval mode
+/// * `interval` - Iterations between recorded hashes, rounded up to a multiple of
+///   `PROGRESS_INTERVAL`
+pub fn eval_with_segments(
+    unicorn: &Unicorn,
+    mode: EvalMode,
+    interval: u64,
+) -> Option<(Integer, String, SegmentTrace)> {
+    let interval = interval
+        .max(1)
+        .div_ceil(PROGRESS_INTERVAL)
+        .saturating_mul(PROGRESS_INTERVAL);
+    let mut segments: Vec<SegmentHash> = Vec::new();
+
+    let (witness, g_value) =
+        unicorn.eval_from_with_mode(unicorn.start_state(), mode, |state: &EvalState| {
+            let last = segments.last().map(|s| s.iterations_done);
+            let due = state.iterations_done.is_multiple_of(interval)
+                || state.iterations_done == unicorn.iterations;
+            if due && last != Some(state.iterations_done) {
+                segments.push(SegmentHash {
+                    iterations_done: state.iterations_done,
+                    hash: hash_state(&state.w),
+                });
+            }
+        })?;
+
+    let trace = SegmentTrace {
+        seed: unicorn.seed.to_string_radix(16),
+        iterations: unicorn.iterations,
+        interval,
+        segments,
+    };
+    Some((witness, g_value, trace))
+}
+
+/// Finds where two segment traces first disagree, or `None` if they agree throughout
+///
+/// ### Arguments
+///
+/// * `ours`   - Trace of one evaluator
+/// * `theirs` - Trace of the other evaluator
+pub fn first_divergence(ours: &SegmentTrace, theirs: &SegmentTrace) -> Option<Divergence> {
+    if ours.seed != theirs.seed
+        || ours.iterations != theirs.iterations
+        || ours.interval != theirs.interval
+    {
+        return Some(Divergence::Incomparable);
+    }
+
+    let mut last_agreed = 0;
+    for (a, b) in ours.segments.iter().zip(&theirs.segments) {
+        if a.iterations_done != b.iterations_done {
+            return Some(Divergence::Incomparable);
+        }
+        if a.hash != b.hash {
+            return Some(Divergence::Segment {
+                last_agreed,
+                iterations_done: a.iterations_done,
+            });
+        }
+        last_agreed = a.iterations_done;
+    }
+
+    match ours.segments.len() == theirs.segments.len() {
+        true => None,
+        false => Some(Divergence::Length { last_agreed }),
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod segments_tests {
+    use super::*;
+    use crate::config::DEFAULT_MODULUS;
+    use crate::unicorn::construct_seed;
+
+    fn create_unicorn() -> Unicorn {
+        Unicorn {
+            seed: construct_seed(&["miner_0".to_string()]),
+            modulus: Integer::from_str_radix(DEFAULT_MODULUS, 10).unwrap(),
+            iterations: 2 * PROGRESS_INTERVAL + 10,
+            security_level: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that segments are recorded at each interval and at the end of the eval, with the
+    /// final hash matching the witness
+    fn records_segments() {
+        let uni = create_unicorn();
+
+        let (witness, g_value, trace) = eval_with_segments(&uni, EvalMode::Standard, 1).unwrap();
+
+        assert_eq!(Some((witness.clone(), g_value)), uni.eval());
+        assert_eq!(trace.interval, PROGRESS_INTERVAL);
+        let done: Vec<u64> = trace.segments.iter().map(|s| s.iterations_done).collect();
+        assert_eq!(
+            done,
+            vec![PROGRESS_INTERVAL, 2 * PROGRESS_INTERVAL, uni.iterations]
+        );
+        assert_eq!(trace.segments[2].hash, hash_state(&witness));
+    }
+
+    #[test]
+    /// Checks that the first disagreeing segment is found
+    fn finds_divergence() {
+        let uni = create_unicorn();
+        let (_, _, ours) = eval_with_segments(&uni, EvalMode::Standard, 1).unwrap();
+        let (_, _, hardened) = eval_with_segments(&uni, EvalMode::Hardened, 1).unwrap();
+        assert_eq!(first_divergence(&ours, &hardened), None);
+
+        let mut theirs = ours.clone();
+        theirs.segments[1].hash = hash_state(&Integer::from(2));
+        theirs.segments[2].hash = hash_state(&Integer::from(3));
+        assert_eq!(
+            first_divergence(&ours, &theirs),
+            Some(Divergence::Segment {
+                last_agreed: PROGRESS_INTERVAL,
+                iterations_done: 2 * PROGRESS_INTERVAL,
+            })
+        );
+
+        let mut short = ours.clone();
+        short.segments.pop();
+        assert_eq!(
+            first_divergence(&ours, &short),
+            Some(Divergence::Length {
+                last_agreed: 2 * PROGRESS_INTERVAL
+            })
+        );
+
+        let mut other = ours.clone();
+        other.interval *= 2;
+        assert_eq!(
+            first_divergence(&ours, &other),
+            Some(Divergence::Incomparable)
+        );
+    }
+}
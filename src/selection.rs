@@ -0,0 +1,865 @@
+//! Winner selection from an evaluated UNICORN.
+//!
+//! Every draw reads from the Fortuna stream for its usage number, so the first draw of any
+//! selection is the PRN returned by `get_unicorn_prn` and later draws continue the same stream.
+//! This makes selections reproducible by any verifier holding the `UnicornInfo`.
+//!
+//! Participants can be of any type implementing `Selectable`, which provides the canonical
+//! bytes fed into the seed and an optional selection weight, so that callers can select over
+//! their own miner structs directly.
+
+use crate::fortuna::Fortuna;
+use crate::participants::Participant;
+use crate::unicorn::UnicornInfo;
+use crate::usage::WINNER_NAMESPACE;
+use crate::utils::unicorn_selection::{get_unicorn_csprng, next_prn};
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+/// Maximum number of re-draws before falling back to a draw among the eligible entries only
+pub const MAX_REDRAWS: usize = 64;
+
+/// Usage number of the first category in `select_by_quota`; category `i` uses
+/// `QUOTA_USAGE_BASE + i`
+pub const QUOTA_USAGE_BASE: u128 = 1 << 16;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SelectionError {
+    /// More winners were requested than there are participants.
+    NotEnoughParticipants { requested: usize, available: usize },
+    /// The same usage number was given for two draws.
+    UsageNumberReused(u128),
+    /// The UNICORN's `g` value is too short to key the CSPRNG.
+    InvalidGValue,
+    /// The participants' weights sum to more than `u64::MAX`.
+    WeightOverflow,
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionError::NotEnoughParticipants {
+                requested,
+                available,
+            } => write!(
+                f,
+                "Requested {} winners from {} participants",
+                requested, available
+            ),
+            SelectionError::UsageNumberReused(usage) => {
+                write!(f, "Usage number {} used for more than one draw", usage)
+            }
+            SelectionError::InvalidGValue => write!(f, "UNICORN g value is too short"),
+            SelectionError::WeightOverflow => write!(f, "Participant weights overflow u64"),
+        }
+    }
+}
+
+impl Error for SelectionError {}
+
+/// A participant that can take part in a selection
+pub trait Selectable {
+    /// Canonical bytes of the participant, as fed into the seed
+    fn canonical_bytes(&self) -> Vec<u8>;
+
+    /// Selection weight of the participant. Unweighted participants count as weight 1
+    fn weight(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl Selectable for String {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl Selectable for &str {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl Selectable for Participant {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.identity.as_bytes().to_vec()
+    }
+}
+
+/// Winners drawn for a single category by `select_by_quota`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QuotaSelection<'a, C, T> {
+    pub category: C,
+    /// Usage number of the category's draw
+    pub usage_number: u128,
+    /// Indices of the winners within the category's participants, in draw order
+    pub indices: Vec<usize>,
+    pub winners: Vec<&'a T>,
+}
+
+/// Selects a winner among `num_participants` entries, skipping excluded indices.
+///
+/// Excluded participants still contribute to the seed; they are only barred from winning. The
+/// first draw is the regular selection, so exclusions only change the outcome when an excluded
+/// participant is drawn. In that case the next index is drawn from the same stream, up to
+/// `MAX_REDRAWS` times, after which a last draw is made among the eligible entries directly.
+/// Every draw rejects PRNs in the biased tail as `select` does. Returns `None` if every
+/// participant is excluded or the `g` value is too short to key the CSPRNG.
+///
+/// ### Arguments
+///
+/// * `unicorn`          - UNICORN to draw from
+/// * `usage_number`     - Usage number of the draw
+/// * `num_participants` - Number of participants in the draw
+/// * `excluded`         - Indices of participants barred from winning
+pub fn select_excluding(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    num_participants: usize,
+    excluded: &BTreeSet<usize>,
+) -> Option<usize> {
+    let eligible: Vec<usize> = (0..num_participants)
+        .filter(|i| !excluded.contains(i))
+        .collect();
+    if eligible.is_empty() {
+        return None;
+    }
+
+    let mut csprng = get_unicorn_csprng(unicorn, usage_number)?;
+
+    for _ in 0..MAX_REDRAWS {
+        let index = uniform_index(&mut csprng, num_participants);
+
+        if !excluded.contains(&index) {
+            return Some(index);
+        }
+    }
+
+    Some(eligible[uniform_index(&mut csprng, eligible.len())])
+}
+
+/// Selects `k` distinct winners among `num_participants` entries, in draw order, using a
+/// partial Fisher-Yates shuffle driven by the Fortuna stream
+///
+/// ### Arguments
+///
+/// * `unicorn`          - UNICORN to draw from
+/// * `usage_number`     - Usage number of the draw
+/// * `num_participants` - Number of participants in the draw
+/// * `k`                - Number of winners
+pub fn select_many(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    num_participants: usize,
+    k: usize,
+) -> Result<Vec<usize>, SelectionError> {
+    if k > num_participants {
+        return Err(SelectionError::NotEnoughParticipants {
+            requested: k,
+            available: num_participants,
+        });
+    }
+
+    let mut csprng =
+        get_unicorn_csprng(unicorn, usage_number).ok_or(SelectionError::InvalidGValue)?;
+    let mut indices: Vec<usize> = (0..num_participants).collect();
+
+    for i in 0..k {
+        let j = i + uniform_index(&mut csprng, num_participants - i);
+        indices.swap(i, j);
+    }
+
+    indices.truncate(k);
+    Ok(indices)
+}
+
+/// Ranks all participants with an unbiased Fisher-Yates shuffle driven by the Fortuna
+/// stream. The ranking starts with the same winners as `select_many` for the same usage
+/// number, so it can serve as an ordered list of backups or a proposer schedule. Returns
+/// `None` if the `g` value is too short to key the CSPRNG
+///
+/// ### Arguments
+///
+/// * `unicorn`      - UNICORN to draw from
+/// * `participants` - Participants to rank
+/// * `usage_number` - Usage number of the draw
+pub fn rank_participants<T>(
+    unicorn: &UnicornInfo,
+    participants: &[T],
+    usage_number: u128,
+) -> Option<Vec<usize>> {
+    let n = participants.len();
+    select_many(unicorn, usage_number, n, n).ok()
+}
+
+/// Selects a fixed number of winners per category from a single UNICORN. Each category is
+/// drawn from its own usage number, so the winners of one category do not depend on the
+/// size or contents of any other
+///
+/// ### Arguments
+///
+/// * `unicorn` - UNICORN to draw from
+/// * `groups`  - Categories with their participants and number of winners
+pub fn select_by_quota<'a, C: Clone, T>(
+    unicorn: &UnicornInfo,
+    groups: &[(C, &'a [T], usize)],
+) -> Result<Vec<QuotaSelection<'a, C, T>>, SelectionError> {
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, (category, participants, k))| {
+            let usage_number = QUOTA_USAGE_BASE + i as u128;
+            let indices = select_many(unicorn, usage_number, participants.len(), *k)?;
+
+            Ok(QuotaSelection {
+                category: category.clone(),
+                usage_number,
+                winners: indices.iter().map(|i| &participants[*i]).collect(),
+                indices,
+            })
+        })
+        .collect()
+}
+
+/// Combined proof of a two-stage draw
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TwoStageProof {
+    /// `g` value of the UNICORN both stages were drawn from
+    pub g_value: String,
+    pub num_participants: usize,
+    /// Usage number of the pool draw
+    pub pool_usage_number: u128,
+    /// Indices of the participants in the pool, in draw order
+    pub pool: Vec<usize>,
+    /// Usage number of the final draw
+    pub final_usage_number: u128,
+    /// Position of the winner within `pool`
+    pub pool_position: usize,
+    /// Index of the winner among all participants
+    pub winner: usize,
+}
+
+impl TwoStageProof {
+    /// Checks both stages of the draw against the UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn` - UNICORN the draw claims to come from
+    pub fn verify(&self, unicorn: &UnicornInfo) -> bool {
+        self.g_value == unicorn.g_value
+            && select_two_stage(
+                unicorn,
+                self.num_participants,
+                self.pool.len(),
+                self.pool_usage_number,
+                self.final_usage_number,
+            )
+            .is_ok_and(|proof| proof == *self)
+    }
+}
+
+/// Selects a pool of `pool_size` candidates and then a single winner from that pool. Both
+/// stages are drawn from the same UNICORN using distinct usage numbers
+///
+/// ### Arguments
+///
+/// * `unicorn`            - UNICORN to draw from
+/// * `num_participants`   - Number of participants in the draw
+/// * `pool_size`          - Number of candidates in the pool
+/// * `pool_usage_number`  - Usage number of the pool draw
+/// * `final_usage_number` - Usage number of the final draw
+pub fn select_two_stage(
+    unicorn: &UnicornInfo,
+    num_participants: usize,
+    pool_size: usize,
+    pool_usage_number: u128,
+    final_usage_number: u128,
+) -> Result<TwoStageProof, SelectionError> {
+    if pool_usage_number == final_usage_number {
+        return Err(SelectionError::UsageNumberReused(pool_usage_number));
+    }
+
+    let pool = select_many(unicorn, pool_usage_number, num_participants, pool_size)?;
+    let pool_position = select_many(unicorn, final_usage_number, pool.len(), 1)?[0];
+
+    Ok(TwoStageProof {
+        g_value: unicorn.g_value.clone(),
+        num_participants,
+        pool_usage_number,
+        winner: pool[pool_position],
+        pool,
+        final_usage_number,
+        pool_position,
+    })
+}
+
+/// Constructs the seed from any selectable participants. For strings this is the same seed
+/// as the one from `construct_seed`
+///
+/// ### Arguments
+///
+/// * `participants` - Participants in seed order
+pub fn construct_seed_from<T: Selectable>(participants: &[T]) -> Integer {
+    let inputs: Vec<Vec<u8>> = participants.iter().map(|p| p.canonical_bytes()).collect();
+    let pki = hex::encode(Sha256::digest(serialize(&inputs).unwrap()));
+    Integer::from_str_radix(&pki, 16).unwrap()
+}
+
+/// Winner of a draw along with ordered backups, for use when the winner turns out to be
+/// offline or ineligible
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BackupSelection<'a, T> {
+    pub winner: (usize, &'a T),
+    /// Backups in the order they take over from the winner
+    pub backups: Vec<(usize, &'a T)>,
+}
+
+impl<'a, T> BackupSelection<'a, T> {
+    /// First of the winner and backups, in order, that passes an eligibility check
+    ///
+    /// ### Arguments
+    ///
+    /// * `eligible` - Eligibility check
+    pub fn first_eligible(&self, eligible: impl Fn(&T) -> bool) -> Option<(usize, &'a T)> {
+        std::iter::once(self.winner)
+            .chain(self.backups.iter().copied())
+            .find(|(_, p)| eligible(p))
+    }
+}
+
+/// Selects the winner of the winner draw followed by `n_backups` distinct backups drawn from
+/// the same Fortuna stream, with probability proportional to weight. The winner is the same
+/// as the one from `select` with the winner usage number, so backups can be drawn after the
+/// fact without changing the outcome of the draw
+///
+/// ### Arguments
+///
+/// * `unicorn`      - UNICORN to draw from
+/// * `participants` - Participants in the draw
+/// * `n_backups`    - Number of backups
+pub fn select_with_backups<'a, T: Selectable>(
+    unicorn: &UnicornInfo,
+    participants: &'a [T],
+    n_backups: usize,
+) -> Result<BackupSelection<'a, T>, SelectionError> {
+    let mut remaining = weights_of(participants);
+    let available = remaining.iter().filter(|w| **w > 0).count();
+    let mut total = remaining
+        .iter()
+        .try_fold(0u64, |acc, w| acc.checked_add(*w))
+        .ok_or(SelectionError::WeightOverflow)?;
+    if n_backups >= available {
+        return Err(SelectionError::NotEnoughParticipants {
+            requested: n_backups.saturating_add(1),
+            available,
+        });
+    }
+
+    let mut csprng = get_unicorn_csprng(unicorn, WINNER_NAMESPACE as u128)
+        .ok_or(SelectionError::InvalidGValue)?;
+    let mut target = uniform_below(&mut csprng, total);
+    let mut indices = Vec::with_capacity(n_backups + 1);
+
+    for _ in 0..=n_backups {
+        let index = weighted_index(&remaining, target);
+        total -= remaining[index];
+        remaining[index] = 0;
+        indices.push(index);
+
+        if total > 0 {
+            target = uniform_below(&mut csprng, total);
+        }
+    }
+
+    let mut selected = indices.into_iter().map(|i| (i, &participants[i]));
+
+    Ok(BackupSelection {
+        winner: selected.next().unwrap(),
+        backups: selected.collect(),
+    })
+}
+
+/// Selects a single winner with probability proportional to weight. PRNs in the biased tail
+/// above the largest multiple of the total weight are rejected and redrawn, so for unweighted
+/// participants this is the same winner as `get_unicorn_prn` modulo the number of
+/// participants unless that PRN was rejected. Returns `None` if the total weight is zero or
+/// the `g` value is too short to key the CSPRNG
+///
+/// ### Arguments
+///
+/// * `unicorn`      - UNICORN to draw from
+/// * `usage_number` - Usage number of the draw
+/// * `participants` - Participants in the draw
+pub fn select<'a, T: Selectable>(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    participants: &'a [T],
+) -> Option<(usize, &'a T)> {
+    let weights = weights_of(participants);
+    let total = weights
+        .iter()
+        .try_fold(0u64, |acc, w| acc.checked_add(*w))?;
+    if total == 0 {
+        return None;
+    }
+
+    let mut csprng = get_unicorn_csprng(unicorn, usage_number)?;
+    let target = uniform_below(&mut csprng, total);
+    let index = weighted_index(&weights, target);
+
+    Some((index, &participants[index]))
+}
+
+/// Selects `k` distinct winners in draw order. Unweighted participants are drawn with
+/// `select_many`; otherwise each draw picks among the remaining participants with
+/// probability proportional to weight, and zero-weight participants are never drawn
+///
+/// ### Arguments
+///
+/// * `unicorn`      - UNICORN to draw from
+/// * `usage_number` - Usage number of the draw
+/// * `participants` - Participants in the draw
+/// * `k`            - Number of winners
+pub fn select_many_from<'a, T: Selectable>(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    participants: &'a [T],
+    k: usize,
+) -> Result<Vec<(usize, &'a T)>, SelectionError> {
+    let indices = if participants.iter().all(|p| p.weight().is_none()) {
+        select_many(unicorn, usage_number, participants.len(), k)?
+    } else {
+        select_many_weighted(unicorn, usage_number, &weights_of(participants), k)?
+    };
+
+    Ok(indices.into_iter().map(|i| (i, &participants[i])).collect())
+}
+
+/// Proof of a draw by `select_many_from`, replayable from the UNICORN and the weights alone
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WinnersProof {
+    /// `g` value of the UNICORN the winners were drawn from
+    pub g_value: String,
+    pub usage_number: u128,
+    pub num_participants: usize,
+    /// Selection weights of all participants, if any participant is weighted
+    pub weights: Option<Vec<u64>>,
+    /// Indices of the winners, in draw order
+    pub winners: Vec<usize>,
+}
+
+impl WinnersProof {
+    /// Replays the draw against the UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn` - UNICORN the draw claims to come from
+    pub fn verify(&self, unicorn: &UnicornInfo) -> bool {
+        let k = self.winners.len();
+        let winners = match &self.weights {
+            None => select_many(unicorn, self.usage_number, self.num_participants, k),
+            Some(weights) if weights.len() == self.num_participants => {
+                select_many_weighted(unicorn, self.usage_number, weights, k)
+            }
+            Some(_) => return false,
+        };
+
+        self.g_value == unicorn.g_value && winners.is_ok_and(|w| w == self.winners)
+    }
+}
+
+/// Selects `k` distinct winners as in `select_many_from`, returning the proof of the draw
+///
+/// ### Arguments
+///
+/// * `unicorn`      - UNICORN to draw from
+/// * `usage_number` - Usage number of the draw
+/// * `participants` - Participants in the draw
+/// * `k`            - Number of winners
+pub fn prove_many_from<T: Selectable>(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    participants: &[T],
+    k: usize,
+) -> Result<WinnersProof, SelectionError> {
+    let winners = select_many_from(unicorn, usage_number, participants, k)?;
+    let weights = participants
+        .iter()
+        .any(|p| p.weight().is_some())
+        .then(|| weights_of(participants));
+
+    Ok(WinnersProof {
+        g_value: unicorn.g_value.clone(),
+        usage_number,
+        num_participants: participants.len(),
+        weights,
+        winners: winners.into_iter().map(|(i, _)| i).collect(),
+    })
+}
+
+/// Weighted draws without replacement
+fn select_many_weighted(
+    unicorn: &UnicornInfo,
+    usage_number: u128,
+    weights: &[u64],
+    k: usize,
+) -> Result<Vec<usize>, SelectionError> {
+    let available = weights.iter().filter(|w| **w > 0).count();
+    let mut total = weights
+        .iter()
+        .try_fold(0u64, |acc, w| acc.checked_add(*w))
+        .ok_or(SelectionError::WeightOverflow)?;
+    if k > available {
+        return Err(SelectionError::NotEnoughParticipants {
+            requested: k,
+            available,
+        });
+    }
+
+    let mut csprng =
+        get_unicorn_csprng(unicorn, usage_number).ok_or(SelectionError::InvalidGValue)?;
+    let mut remaining = weights.to_vec();
+    let mut winners = Vec::with_capacity(k);
+
+    for _ in 0..k {
+        let index = weighted_index(&remaining, uniform_below(&mut csprng, total));
+        total -= remaining[index];
+        remaining[index] = 0;
+        winners.push(index);
+    }
+
+    Ok(winners)
+}
+
+/// Weights of the participants, with unweighted participants counting as 1
+fn weights_of<T: Selectable>(participants: &[T]) -> Vec<u64> {
+    participants
+        .iter()
+        .map(|p| p.weight().unwrap_or(1))
+        .collect()
+}
+
+/// Index of the entry whose cumulative weight range contains `target`
+fn weighted_index(weights: &[u64], mut target: u64) -> usize {
+    for (i, w) in weights.iter().enumerate() {
+        if target < *w {
+            return i;
+        }
+        target -= w;
+    }
+
+    unreachable!("target exceeds total weight")
+}
+
+/// Draws an index uniformly below `n` from the CSPRNG, rejecting PRNs in the biased tail
+///
+/// ### Arguments
+///
+/// * `csprng` - CSPRNG to draw from
+/// * `n`      - Exclusive upper bound; must be non-zero
+pub fn uniform_index(csprng: &mut Fortuna, n: usize) -> usize {
+    uniform_below(csprng, n as u64) as usize
+}
+
+/// Draws a value uniformly below `n` from the CSPRNG, rejecting PRNs in the biased tail
+fn uniform_below(csprng: &mut Fortuna, n: u64) -> u64 {
+    let zone = u64::MAX - (u64::MAX % n);
+
+    loop {
+        let prn = next_prn(csprng);
+        if prn < zone {
+            return prn % n;
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod selection_tests {
+    use super::*;
+    use crate::unicorn::construct_seed;
+    use crate::utils::unicorn_selection::get_unicorn_prn;
+
+    fn test_unicorn() -> UnicornInfo {
+        UnicornInfo {
+            g_value: "0106834db40e90d1cafaa9e4c1981873186ebf019629852059aaf8e4ca35da01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that exclusions leave the regular winner untouched unless it is excluded
+    fn exclusion_redraws_only_when_needed() {
+        let unicorn = test_unicorn();
+        let winner = get_unicorn_prn(&unicorn, 1).unwrap() as usize % 10;
+
+        let other = BTreeSet::from([(winner + 1) % 10]);
+        let excluded = BTreeSet::from([winner]);
+        let fallback = select_excluding(&unicorn, 1, 10, &excluded).unwrap();
+
+        assert_eq!(
+            select_excluding(&unicorn, 1, 10, &BTreeSet::new()),
+            Some(winner)
+        );
+        assert_eq!(select_excluding(&unicorn, 1, 10, &other), Some(winner));
+        let participants: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        assert_eq!(
+            select(&unicorn, 1, &participants).map(|(i, _)| i),
+            Some(winner)
+        );
+        assert_ne!(fallback, winner);
+        assert_eq!(select_excluding(&unicorn, 1, 10, &excluded), Some(fallback));
+    }
+
+    #[test]
+    /// Checks fallback behaviour when few or no participants are eligible
+    fn exclusion_fallback() {
+        let unicorn = test_unicorn();
+        let all: BTreeSet<usize> = (0..10).collect();
+        let all_but_one: BTreeSet<usize> = (0..10).filter(|i| *i != 7).collect();
+
+        assert_eq!(select_excluding(&unicorn, 1, 10, &all), None);
+        assert_eq!(select_excluding(&unicorn, 1, 10, &all_but_one), Some(7));
+        assert_eq!(select_excluding(&unicorn, 1, 0, &BTreeSet::new()), None);
+    }
+
+    #[test]
+    /// Checks that draws from a `g` value too short to key the CSPRNG fail instead of panicking
+    fn short_g_value() {
+        let unicorn = UnicornInfo {
+            g_value: "0106834d".to_string(),
+            ..Default::default()
+        };
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+
+        assert_eq!(select(&unicorn, 1, &inputs), None);
+        assert_eq!(select_excluding(&unicorn, 1, 2, &BTreeSet::new()), None);
+        assert_eq!(
+            prove_many_from(&unicorn, 1, &inputs, 1),
+            Err(SelectionError::InvalidGValue)
+        );
+        assert!(select_with_backups(&unicorn, &inputs, 1).is_err());
+    }
+
+    #[test]
+    /// Checks that a two-stage draw picks its winner from the pool and verifies
+    fn two_stage_selection() {
+        let unicorn = test_unicorn();
+        let proof = select_two_stage(&unicorn, 1_000, 100, 2, 3).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.winner = proof.pool[(proof.pool_position + 1) % 100];
+
+        assert_eq!(proof.pool.len(), 100);
+        assert_eq!(proof.winner, proof.pool[proof.pool_position]);
+        assert!(proof.verify(&unicorn));
+        assert!(!tampered.verify(&unicorn));
+        assert_eq!(
+            select_two_stage(&unicorn, 1_000, 100, 2, 2),
+            Err(SelectionError::UsageNumberReused(2))
+        );
+        assert!(select_two_stage(&unicorn, 1_000, 0, 2, 3).is_err());
+    }
+
+    #[test]
+    /// Checks that a ranking is a permutation extending the regular multi-winner draw
+    fn ranking() {
+        let unicorn = test_unicorn();
+        let participants: Vec<usize> = (0..50).collect();
+
+        let ranking = rank_participants(&unicorn, &participants, 4).unwrap();
+        let mut sorted = ranking.clone();
+        sorted.sort();
+
+        assert_eq!(sorted, participants);
+        assert_eq!(ranking[..5], select_many(&unicorn, 4, 50, 5).unwrap());
+        assert_ne!(
+            ranking,
+            rank_participants(&unicorn, &participants, 5).unwrap()
+        );
+        assert_eq!(rank_participants::<u8>(&unicorn, &[], 4), Some(vec![]));
+    }
+
+    struct Miner {
+        key: &'static str,
+        stake: u64,
+    }
+
+    impl Selectable for Miner {
+        fn canonical_bytes(&self) -> Vec<u8> {
+            self.key.as_bytes().to_vec()
+        }
+
+        fn weight(&self) -> Option<u64> {
+            Some(self.stake)
+        }
+    }
+
+    #[test]
+    /// Checks that generic selection over strings matches the string-based functions
+    fn generic_selection_compatible() {
+        let unicorn = test_unicorn();
+        let inputs = vec![
+            "miner_a".to_string(),
+            "miner_b".to_string(),
+            "miner_c".to_string(),
+        ];
+        let winner = get_unicorn_prn(&unicorn, 1).unwrap() as usize % inputs.len();
+
+        assert_eq!(construct_seed_from(&inputs), construct_seed(&inputs));
+        assert_eq!(
+            select(&unicorn, 1, &inputs),
+            Some((winner, &inputs[winner]))
+        );
+        assert_eq!(
+            select_many_from(&unicorn, 1, &inputs, 2)
+                .unwrap()
+                .iter()
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>(),
+            select_many(&unicorn, 1, inputs.len(), 2).unwrap()
+        );
+    }
+
+    #[test]
+    /// Checks that weighted selection never picks zero-weight participants, and that weights
+    /// summing past `u64::MAX` are reported as such
+    fn weighted_selection() {
+        let unicorn = test_unicorn();
+        let miners = [
+            Miner { key: "a", stake: 0 },
+            Miner { key: "b", stake: 5 },
+            Miner { key: "c", stake: 0 },
+            Miner { key: "d", stake: 1 },
+        ];
+
+        let winners = select_many_from(&unicorn, 1, &miners, 2).unwrap();
+        let mut indices: Vec<usize> = winners.iter().map(|(i, _)| *i).collect();
+        indices.sort();
+
+        assert_eq!(indices, vec![1, 3]);
+        assert!(matches!(select(&unicorn, 1, &miners), Some((1 | 3, _))));
+        assert!(select_many_from(&unicorn, 1, &miners, 3).is_err());
+        assert_eq!(select(&unicorn, 1, &miners[..1]).map(|(i, _)| i), None);
+
+        let whales = [
+            Miner {
+                key: "a",
+                stake: u64::MAX,
+            },
+            Miner { key: "b", stake: 1 },
+        ];
+        assert_eq!(
+            select_many_from(&unicorn, 1, &whales, 1).err(),
+            Some(SelectionError::WeightOverflow)
+        );
+    }
+
+    #[test]
+    /// Checks that multi-winner proofs replay for weighted and unweighted draws only
+    fn winners_proof() {
+        let unicorn = test_unicorn();
+        let miners = [
+            Miner { key: "a", stake: 3 },
+            Miner { key: "b", stake: 5 },
+            Miner { key: "c", stake: 0 },
+            Miner { key: "d", stake: 1 },
+        ];
+        let keys = ["a", "b", "c", "d"];
+
+        let weighted = prove_many_from(&unicorn, 3, &miners, 2).unwrap();
+        let unweighted = prove_many_from(&unicorn, 3, &keys, 2).unwrap();
+        let mut tampered = weighted.clone();
+        tampered.winners.reverse();
+
+        assert_eq!(weighted.weights, Some(vec![3, 5, 0, 1]));
+        assert_eq!(unweighted.weights, None);
+        assert!(weighted.verify(&unicorn));
+        assert!(unweighted.verify(&unicorn));
+        assert!(!tampered.verify(&unicorn));
+        tampered = weighted.clone();
+        tampered.g_value = "00".to_string();
+        assert!(!tampered.verify(&unicorn));
+    }
+
+    #[test]
+    /// Checks that quota selection draws distinct winners per category
+    fn quota_selection() {
+        let unicorn = test_unicorn();
+        let large = ["l0", "l1", "l2", "l3"];
+        let small = ["s0", "s1", "s2", "s3", "s4", "s5"];
+
+        let selection = select_by_quota(
+            &unicorn,
+            &[("large", &large[..], 2), ("small", &small[..], 3)],
+        )
+        .unwrap();
+        let small_winners: BTreeSet<usize> = selection[1].indices.iter().copied().collect();
+
+        assert_eq!(selection[0].winners.len(), 2);
+        assert_eq!(small_winners.len(), 3);
+        assert_eq!(
+            selection[1].indices,
+            select_many(&unicorn, QUOTA_USAGE_BASE + 1, small.len(), 3).unwrap()
+        );
+        assert_eq!(
+            select_by_quota(&unicorn, &[("large", &large[..], 5)]),
+            Err(SelectionError::NotEnoughParticipants {
+                requested: 5,
+                available: 4
+            })
+        );
+    }
+
+    #[test]
+    /// Checks that backups follow the regular winner and are distinct
+    fn backups_follow_winner() {
+        let unicorn = test_unicorn();
+        let participants = ["p0", "p1", "p2", "p3", "p4"];
+
+        let selection = select_with_backups(&unicorn, &participants, 4).unwrap();
+        let drawn: BTreeSet<usize> = std::iter::once(selection.winner.0)
+            .chain(selection.backups.iter().map(|(i, _)| *i))
+            .collect();
+
+        assert_eq!(
+            Some(selection.winner),
+            select(&unicorn, WINNER_NAMESPACE as u128, &participants)
+        );
+        assert_eq!(drawn.len(), 5);
+        assert_eq!(
+            selection.first_eligible(|p| p != selection.winner.1),
+            Some(selection.backups[0])
+        );
+        assert!(select_with_backups(&unicorn, &participants, 5).is_err());
+        assert_eq!(
+            select_with_backups(&unicorn, &participants, usize::MAX).err(),
+            Some(SelectionError::NotEnoughParticipants {
+                requested: usize::MAX,
+                available: 5
+            })
+        );
+
+        let whales = [
+            Miner {
+                key: "whale_a",
+                stake: u64::MAX,
+            },
+            Miner {
+                key: "whale_b",
+                stake: 1,
+            },
+        ];
+        assert_eq!(
+            select_with_backups(&unicorn, &whales, 0).err(),
+            Some(SelectionError::WeightOverflow)
+        );
+    }
+}
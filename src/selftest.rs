@@ -0,0 +1,205 @@
+//! Known-answer checks for the encodings that consensus depends on.
+//!
+//! Every node must turn the same witness into the same `g` value and PRNs, and the same
+//! inputs into the same seed bytes, whatever its endianness or GMP limb size. Those paths
+//! go through `Integer::to_digits`, limb-by-limb reads of the witness and bincode, so
+//! `determinism_selftest` runs each of them on fixed values and compares the results
+//! against answers recorded on a reference platform. It needs no network or test harness,
+//! so a node can run it with `miner-lottery selftest` before joining.
+
+use crate::fortuna::Fortuna;
+use crate::unicorn::{construct_seed, g_from_witness, g_key, UnicornInfo};
+use crate::utils::rug_integer;
+use crate::utils::unicorn_selection::get_unicorn_prn_bytes;
+use rug::integer::Order;
+use rug::Integer;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+
+/// Witness the checks draw from, spanning several limbs of either size with a zero limb
+/// in the middle
+const WITNESS: &str =
+    "f00dfacecafebeef0123456789abcdef0000000000000000fedcba98765432100000000100000002";
+
+/// Inputs the seed check constructs a seed from
+const SEED_INPUTS: [&str; 3] = ["miner_a", "miner_b", "miner_c"];
+
+/// Usage number the PRN check draws with
+const USAGE_NUMBER: u128 = 1;
+
+/// Expected big-endian digits of `WITNESS`
+const EXPECTED_DIGITS: &str = WITNESS;
+
+/// Expected little-endian digits of `WITNESS`, least significant byte first
+const EXPECTED_DIGITS_LSF: &str = concat!(
+    "02000000010000001032547698badcfe0000000000000000efcdab8967452301",
+    "efbefecacefa0df0",
+);
+
+/// Expected seed constructed from `SEED_INPUTS`, in hex
+const EXPECTED_SEED: &str = "a145e767d7457062365d91a366d0a223b4552b669e0557f9ca0cb016cba7a564";
+
+/// Expected bincode encoding of the seed and usage number, in hex
+const EXPECTED_BINCODE: &str = concat!(
+    "4000000000000000613134356537363764373435373036323336356439316133",
+    "3636643061323233623435353262363639653035353766396361306362303136",
+    "636261376135363401000000000000000000000000000000",
+);
+
+/// Expected first 32 bytes of Fortuna output for the key derived from `WITNESS`
+const EXPECTED_FORTUNA: &str = "2cdd9bfb5ebc4fce081042c8f29d213cb4c638b8e1460fdae3691edb827ef889";
+
+/// Expected PRN bytes drawn from `WITNESS` with `USAGE_NUMBER`, in hex
+const EXPECTED_PRN: &str = "2cdd9bfb5ebc4fce";
+
+/// A check whose output differs from the recorded answer
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeterminismError {
+    /// Name of the failed check
+    pub check: &'static str,
+    /// Answer recorded on the reference platform
+    pub expected: String,
+    /// Answer computed on this platform
+    pub found: String,
+}
+
+impl fmt::Display for DeterminismError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Determinism check `{}` failed: expected {}, found {}",
+            self.check, self.expected, self.found
+        )
+    }
+}
+
+impl Error for DeterminismError {}
+
+/// Seed and usage number as they are laid out within a bincode-serialized round
+#[derive(Serialize)]
+struct SeedEncoding<'a> {
+    #[serde(with = "rug_integer")]
+    seed: &'a Integer,
+    usage_number: u128,
+}
+
+/// Compares a computed answer against the recorded one
+///
+/// ### Arguments
+///
+/// * `check`    - Name of the check
+/// * `expected` - Answer recorded on the reference platform
+/// * `found`    - Answer computed on this platform
+fn expect(check: &'static str, expected: &str, found: String) -> Result<(), DeterminismError> {
+    if found == expected {
+        return Ok(());
+    }
+
+    Err(DeterminismError {
+        check,
+        expected: expected.to_string(),
+        found,
+    })
+}
+
+/// Runs every known-answer check, returning the first whose output differs on this
+/// platform
+pub fn determinism_selftest() -> Result<(), DeterminismError> {
+    let witness = Integer::from_str_radix(WITNESS, 16).unwrap();
+
+    let digits = witness.to_digits::<u8>(Order::MsfBe);
+    expect("to_digits", EXPECTED_DIGITS, hex::encode(&digits))?;
+    expect(
+        "to_digits_lsf",
+        EXPECTED_DIGITS_LSF,
+        hex::encode(witness.to_digits::<u8>(Order::LsfLe)),
+    )?;
+    expect(
+        "from_digits",
+        EXPECTED_DIGITS,
+        Integer::from_digits(&digits, Order::MsfBe).to_string_radix(16),
+    )?;
+    expect("g_from_witness", EXPECTED_DIGITS, g_from_witness(&witness))?;
+
+    let seed = construct_seed(&SEED_INPUTS.map(String::from));
+    expect("construct_seed", EXPECTED_SEED, seed.to_string_radix(16))?;
+
+    let encoding = SeedEncoding {
+        seed: &seed,
+        usage_number: USAGE_NUMBER,
+    };
+    expect(
+        "bincode_seed",
+        EXPECTED_BINCODE,
+        hex::encode(bincode::serialize(&encoding).unwrap()),
+    )?;
+
+    let key = g_key(&witness).unwrap();
+    let stream = Fortuna::new(&key, USAGE_NUMBER)
+        .and_then(|mut fortuna| fortuna.get_bytes(32))
+        .map(hex::encode)
+        .unwrap_or_default();
+    expect("fortuna", EXPECTED_FORTUNA, stream)?;
+
+    let unicorn = UnicornInfo {
+        g_value: g_from_witness(&witness),
+        witness,
+        ..Default::default()
+    };
+    expect(
+        "prn_bytes",
+        EXPECTED_PRN,
+        get_unicorn_prn_bytes(&unicorn, USAGE_NUMBER)
+            .map(hex::encode)
+            .unwrap_or_default(),
+    )
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod selftest_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that this platform gives the recorded answers
+    fn selftest_passes() {
+        assert_eq!(determinism_selftest(), Ok(()));
+    }
+
+    #[test]
+    /// Checks that `g` values read from the limbs are big-endian and drop leading zeroes
+    /// around both 32-bit and 64-bit limb boundaries
+    fn limb_boundaries() {
+        let cases = [
+            (Integer::from(1), "01"),
+            (Integer::from(u32::MAX), "ffffffff"),
+            (Integer::from(1u64 << 32), "0100000000"),
+            (Integer::from(u64::MAX), "ffffffffffffffff"),
+            (Integer::from(1) << 64u32, "010000000000000000"),
+            (
+                (Integer::from(1) << 128u32) + 1u32,
+                "0100000000000000000000000000000001",
+            ),
+        ];
+
+        for (witness, expected) in cases {
+            assert_eq!(g_from_witness(&witness), expected);
+            assert_eq!(hex::encode(witness.to_digits::<u8>(Order::MsfBe)), expected);
+            assert_eq!(
+                hex::decode(expected).unwrap().len(),
+                witness.significant_digits::<u8>()
+            );
+        }
+    }
+
+    #[test]
+    /// Checks that a differing answer names the failed check
+    fn mismatch_reported() {
+        let err = expect("fortuna", EXPECTED_FORTUNA, "00".to_string()).unwrap_err();
+
+        assert_eq!(err.check, "fortuna");
+        assert_eq!(err.found, "00");
+    }
+}
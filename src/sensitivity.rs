@@ -0,0 +1,166 @@
+//! Seed sensitivity analysis, quantifying how far a single contributor can steer a draw.
+//!
+//! A contributor who sees everyone else's inputs before choosing their own can try many
+//! variants of it and keep the one that suits them best. `seed_sensitivity` simulates that:
+//! each trial flips one bit of the contributor's input, evaluates the resulting UNICORN and
+//! records the winner. If the winners of the trials are spread over the participants as their
+//! weights predict, a one-bit change is as good as a fresh draw, and a contributor able to
+//! try `n` variants wins with probability about `1 - (1 - share)^n` rather than `share`.
+//!
+//! That gap is the evidence for choosing commit-reveal, where contributions are fixed before
+//! any are revealed, over open contribution. The eval only delays the draw, so trials can be
+//! run with a low iteration count without changing the distribution of winners.
+
+use crate::config::validate_params;
+use crate::selection::{construct_seed_from, select, Selectable};
+use crate::unicorn::{construct_unicorn, UnicornFixedParam};
+use serde::{Deserialize, Serialize};
+
+/// Distribution of winners over the trials of a sensitivity analysis
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensitivityReport {
+    /// Number of trials run, one per flipped bit
+    pub trials: usize,
+    /// Index of the contributor whose input was perturbed
+    pub contributor: usize,
+    /// Number of trials won by each participant, by index
+    pub wins: Vec<usize>,
+    /// Share of the draw the contributor's weight entitles them to
+    pub expected_share: f64,
+    /// Number of participants that won at least one trial
+    pub distinct_winners: usize,
+    /// Pearson's chi-squared statistic of the wins against the weights, with one degree of
+    /// freedom fewer than the number of participants
+    pub chi_squared: f64,
+}
+
+impl SensitivityReport {
+    /// Share of the trials won by the contributor
+    pub fn contributor_share(&self) -> f64 {
+        self.wins[self.contributor] as f64 / self.trials as f64
+    }
+
+    /// Probability that the contributor wins at least once given `attempts` variants of
+    /// their input, at the observed share
+    ///
+    /// ### Arguments
+    ///
+    /// * `attempts` - Variants of the input the contributor can try
+    pub fn grinding_success(&self, attempts: u32) -> f64 {
+        1.0 - (1.0 - self.contributor_share()).powi(attempts as i32)
+    }
+}
+
+/// Participant standing in for another with its canonical bytes changed
+struct Perturbed {
+    bytes: Vec<u8>,
+    weight: Option<u64>,
+}
+
+impl Selectable for Perturbed {
+    fn canonical_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    fn weight(&self) -> Option<u64> {
+        self.weight
+    }
+}
+
+/// Flips one bit of a contributor's input per trial and reports the winners of the draws.
+/// Trials are capped at the number of bits in the input. Returns `None` if the parameters are
+/// invalid, the contributor is not a participant with a non-empty input, or no participant
+/// has any weight
+///
+/// ### Arguments
+///
+/// * `participants` - Participants in seed order
+/// * `contributor`  - Index of the participant whose input is perturbed
+/// * `params`       - UNICORN parameters of the trials
+/// * `usage_number` - Usage number of the draw
+/// * `trials`       - Number of trials
+pub fn seed_sensitivity<T: Selectable>(
+    participants: &[T],
+    contributor: usize,
+    params: &UnicornFixedParam,
+    usage_number: u128,
+    trials: usize,
+) -> Option<SensitivityReport> {
+    validate_params(params).ok()?;
+    let input = participants.get(contributor)?.canonical_bytes();
+    let trials = trials.min(input.len() * 8);
+    if trials == 0 {
+        return None;
+    }
+
+    let mut perturbed: Vec<Perturbed> = participants
+        .iter()
+        .map(|p| Perturbed {
+            bytes: p.canonical_bytes(),
+            weight: p.weight(),
+        })
+        .collect();
+    let weights: Vec<u64> = perturbed.iter().map(|p| p.weight.unwrap_or(1)).collect();
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut wins = vec![0; participants.len()];
+    for bit in 0..trials {
+        perturbed[contributor].bytes = input.clone();
+        perturbed[contributor].bytes[bit / 8] ^= 1 << (bit % 8);
+
+        let unicorn = construct_unicorn(construct_seed_from(&perturbed), params);
+        let (winner, _) = select(&unicorn, usage_number, &perturbed)?;
+        wins[winner] += 1;
+    }
+
+    let chi_squared = wins
+        .iter()
+        .zip(&weights)
+        .filter(|(_, w)| **w > 0)
+        .map(|(won, w)| {
+            let expected = trials as f64 * *w as f64 / total as f64;
+            (*won as f64 - expected).powi(2) / expected
+        })
+        .sum();
+
+    Some(SensitivityReport {
+        trials,
+        contributor,
+        distinct_winners: wins.iter().filter(|w| **w > 0).count(),
+        wins,
+        expected_share: weights[contributor] as f64 / total as f64,
+        chi_squared,
+    })
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod sensitivity_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that one-bit changes to an input spread the winners over the participants
+    fn one_bit_changes_spread_winners() {
+        let participants: Vec<String> = (0..4).map(|i| format!("miner_{}", i)).collect();
+        let params = UnicornFixedParam {
+            iterations: 10,
+            ..UnicornFixedParam::test()
+        };
+
+        let report = seed_sensitivity(&participants, 2, &params, 1, 56).unwrap();
+
+        assert_eq!(report.trials, 56);
+        assert_eq!(report.wins.iter().sum::<usize>(), 56);
+        assert_eq!(report.distinct_winners, 4);
+        assert_eq!(report.expected_share, 0.25);
+        // 16.27 is the 0.1% critical value with 3 degrees of freedom
+        assert!(report.chi_squared < 16.27);
+        assert!(report.grinding_success(8) > report.contributor_share());
+
+        assert_eq!(seed_sensitivity(&participants, 4, &params, 1, 8), None);
+    }
+}
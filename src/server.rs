@@ -0,0 +1,1023 @@
+//! HTTP API for services that use the lottery without linking against it.
+//!
+//! The server runs rounds with a `Daemon` and exposes the following routes, all of which
+//! consume and produce JSON:
+//!
+//! - `POST /rounds/{id}/contributions`: submits a `Contribution` to the collecting round
+//! - `GET /rounds/{id}/result`: the `SelectionTranscript` of a published round
+//! - `GET /rounds/{id}/feed`: the `FeedEntry` of a published round, for randomness feed
+//!   consumers; see `feed`
+//! - `POST /eval`: evaluates a UNICORN for `{seed, params}`, returning a `UnicornInfo`
+//! - `POST /verify`: audits a `SelectionTranscript` or `UnicornInfo`, returning an
+//!   `AuditReport`
+//! - `GET /events`: WebSocket pushing a `RoundEvent` as a JSON text message when a round is
+//!   sealed, when its eval starts and when its result is published
+//! - `GET /status`: a `ServerStatus` with the current round, the progress of a running eval,
+//!   the configured parameters and the last published `g` value
+//! - `POST /rpc`: JSON-RPC 2.0 endpoint, with the `jsonrpc` feature
+//! - `GET /openapi.json` and `GET /docs`: OpenAPI document of the routes above and Swagger
+//!   UI, with the `openapi` feature
+//!
+//! Contributions are checked by the state's `Authorizer` before they reach the daemon, with
+//! credentials taken from the request headers; see `auth`. Authorized contributions are then
+//! rate limited per identity as configured by the `[limits]` table. Accepted contributions
+//! and the lifecycle of each round are recorded in the audit log, if one is set. Errors are
+//! returned as `{"error": message}` with a 4xx status. Evals requested through `/eval` and
+//! audits requested through `/verify` may not run more iterations than the configured rounds,
+//! and both run on blocking threads, so that a single request cannot tie the server up
+//! indefinitely.
+
+use crate::audit::{audit_transcript, audit_unicorn, AuditReport};
+use crate::auditlog::{AuditEvent, AuditLog};
+use crate::auth::{AllowAll, AuthError, Authorizer, Credentials};
+use crate::config::{validate_params, LotteryConfig};
+use crate::daemon::{
+    Daemon, DaemonRequest, DaemonResponse, DaemonStatus, PublishedRound, RoundEvent,
+};
+use crate::feed::FeedEntry;
+use crate::ratelimit::{LimitError, RateLimiter};
+use crate::rounds::{Contribution, Round, RoundPhase};
+use crate::storage::{MemoryStore, RoundStore, StorageError};
+use crate::transcript::SelectionTranscript;
+use crate::unicorn::{Unicorn, UnicornFixedParam, UnicornInfo};
+#[cfg(feature = "openapi")]
+use aide::axum::routing::{get, post};
+#[cfg(feature = "openapi")]
+use aide::axum::ApiRouter;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+#[cfg(not(feature = "openapi"))]
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Number of round events buffered for slow subscribers
+const EVENT_BUFFER: usize = 16;
+
+/// Error response of a route
+type ApiError = (StatusCode, Json<Value>);
+
+/// Router of the documented routes, which is a plain router unless the `openapi` feature
+/// collects their documentation
+#[cfg(not(feature = "openapi"))]
+type ApiRouter<S> = Router<S>;
+
+/// `ApiRouter::api_route` for plain routers
+#[cfg(not(feature = "openapi"))]
+trait ApiRoute {
+    fn api_route(self, path: &str, method_router: axum::routing::MethodRouter<ServerState>)
+        -> Self;
+}
+
+#[cfg(not(feature = "openapi"))]
+impl ApiRoute for Router<ServerState> {
+    fn api_route(
+        self,
+        path: &str,
+        method_router: axum::routing::MethodRouter<ServerState>,
+    ) -> Self {
+        self.route(path, method_router)
+    }
+}
+
+/// Body of `POST /eval`
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EvalRequest {
+    /// Hex-encoded seed
+    pub seed: String,
+    pub params: UnicornFixedParam,
+}
+
+impl EvalRequest {
+    /// The UNICORN to evaluate, checking that the parameters are valid and do not exceed
+    /// the iteration limit
+    ///
+    /// ### Arguments
+    ///
+    /// * `max_iterations` - Most iterations that may be requested
+    pub fn unicorn(&self, max_iterations: u64) -> Result<Unicorn, String> {
+        validate_params(&self.params).map_err(|e| e.to_string())?;
+        if self.params.iterations > max_iterations {
+            return Err(format!(
+                "At most {} iterations may be requested",
+                max_iterations
+            ));
+        }
+
+        Ok(Unicorn {
+            seed: Integer::from_str_radix(&self.seed, 16).map_err(|_| "Seed is not hex")?,
+            modulus: Integer::from_str_radix(&self.params.modulus, 10)
+                .map_err(|_| "Modulus is not decimal")?,
+            iterations: self.params.iterations,
+            security_level: self.params.security,
+            primality_rounds: self.params.primality_rounds,
+        })
+    }
+}
+
+/// Body of `POST /verify`
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum VerifyRequest {
+    Transcript(Box<SelectionTranscript>),
+    Unicorn(UnicornInfo),
+}
+
+impl VerifyRequest {
+    /// Checks that the UNICORN to verify does not exceed the iteration limit
+    ///
+    /// ### Arguments
+    ///
+    /// * `max_iterations` - Most iterations that may be verified
+    pub fn check_iterations(&self, max_iterations: u64) -> Result<(), String> {
+        let iterations = match self {
+            VerifyRequest::Transcript(transcript) => transcript.fixed_params.iterations,
+            VerifyRequest::Unicorn(info) => info.unicorn.iterations,
+        };
+        if iterations > max_iterations {
+            return Err(format!(
+                "At most {} iterations may be verified",
+                max_iterations
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Body of `GET /status`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ServerStatus {
+    #[serde(flatten)]
+    pub round: DaemonStatus,
+    /// Progress of the running eval, if any
+    pub eval: Option<EvalStatus>,
+    /// UNICORN parameters of the rounds
+    pub params: UnicornFixedParam,
+    /// `g` value of the last published round
+    pub last_g_value: Option<String>,
+}
+
+/// Progress of a running eval
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EvalStatus {
+    pub round_id: u64,
+    pub iterations_done: u64,
+    pub iterations: u64,
+    pub percent: f64,
+    /// Eval speed since the eval started
+    pub iterations_per_sec: f64,
+}
+
+/// Progress counters of the eval run by the round loop
+#[derive(Default)]
+struct EvalProgress {
+    round_id: AtomicU64,
+    done: AtomicU64,
+    total: AtomicU64,
+    started: Mutex<Option<Instant>>,
+}
+
+/// State shared by the routes and the round loop
+#[derive(Clone)]
+pub struct ServerState {
+    config: Arc<LotteryConfig>,
+    daemon: Arc<Mutex<Daemon>>,
+    store: Arc<Mutex<Box<dyn RoundStore>>>,
+    events: broadcast::Sender<RoundEvent>,
+    progress: Arc<EvalProgress>,
+    authorizer: Arc<dyn Authorizer>,
+    limiter: Arc<Mutex<RateLimiter>>,
+    audit_log: Option<Arc<Mutex<AuditLog>>>,
+}
+
+impl ServerState {
+    /// Creates the state for a server collecting for its first round
+    ///
+    /// ### Arguments
+    ///
+    /// * `config`   - Lottery configuration
+    /// * `round_id` - Id of the first round
+    pub fn new(config: LotteryConfig, round_id: u64) -> Self {
+        Self {
+            daemon: Arc::new(Mutex::new(Daemon::new(
+                config.clone(),
+                round_id,
+                unix_now(),
+            ))),
+            limiter: Arc::new(Mutex::new(RateLimiter::new(config.limits))),
+            config: Arc::new(config),
+            store: Arc::new(Mutex::new(Box::new(MemoryStore::default()))),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            progress: Default::default(),
+            authorizer: Arc::new(AllowAll),
+            audit_log: None,
+        }
+    }
+
+    /// Keeps rounds and eval checkpoints in a store. If the store holds rounds from an
+    /// earlier run, collection continues with the round after the latest stored one, unless
+    /// the latest round was interrupted mid-eval and is still within its deadline, in which
+    /// case its eval is resumed by `run_rounds`
+    ///
+    /// ### Arguments
+    ///
+    /// * `store` - Round store
+    pub fn with_store(mut self, store: impl RoundStore + 'static) -> Result<Self, StorageError> {
+        let latest = match store.latest_round()? {
+            Some(id) => store.get_round(id)?,
+            None => None,
+        };
+        let mut daemon = self.daemon.lock().unwrap();
+        if let Some(round) = latest.filter(|round| round.id >= daemon.round().id) {
+            let now = unix_now();
+            let next = round.id + 1;
+            let resumed = Some(round)
+                .filter(|round| !round.is_timed_out(now))
+                .and_then(|round| Daemon::resume((*self.config).clone(), round));
+
+            *daemon = resumed.unwrap_or_else(|| Daemon::new((*self.config).clone(), next, now));
+        }
+        drop(daemon);
+
+        self.store = Arc::new(Mutex::new(Box::new(store)));
+        Ok(self)
+    }
+
+    /// Records accepted contributions and round events in an audit log
+    ///
+    /// ### Arguments
+    ///
+    /// * `log` - Opened audit log
+    pub fn with_audit_log(mut self, log: AuditLog) -> Self {
+        self.audit_log = Some(Arc::new(Mutex::new(log)));
+        self
+    }
+
+    /// Sets the policy deciding who may submit contributions
+    ///
+    /// ### Arguments
+    ///
+    /// * `authorizer` - Authorization policy
+    pub fn with_authorizer(mut self, authorizer: impl Authorizer + 'static) -> Self {
+        self.authorizer = Arc::new(authorizer);
+        self
+    }
+
+    /// Checks a contribution against the authorization policy
+    ///
+    /// ### Arguments
+    ///
+    /// * `credentials`  - Credentials presented with the request
+    /// * `contribution` - Submitted contribution
+    pub fn authorize(
+        &self,
+        credentials: &Credentials,
+        contribution: &Contribution,
+    ) -> Result<(), AuthError> {
+        self.authorizer.authorize(credentials, contribution)
+    }
+
+    /// Records a contribution attempt against the rate limits of its identity. Quotas are
+    /// counted for the daemon's collecting round, whichever round the contribution names
+    ///
+    /// ### Arguments
+    ///
+    /// * `contribution` - Submitted contribution
+    pub fn check_limits(&self, contribution: &Contribution) -> Result<(), LimitError> {
+        let round_id = self.daemon.lock().unwrap().round().id;
+        self.limiter
+            .lock()
+            .unwrap()
+            .check(&contribution.identity, round_id, unix_now())
+    }
+
+    /// Lottery configuration the server was started with
+    pub fn config(&self) -> &LotteryConfig {
+        &self.config
+    }
+
+    /// The daemon running the rounds
+    pub fn daemon(&self) -> &Arc<Mutex<Daemon>> {
+        &self.daemon
+    }
+
+    /// Submits a contribution to the collecting round, recording it in the audit log if it
+    /// is accepted
+    ///
+    /// ### Arguments
+    ///
+    /// * `contribution` - Contribution to submit
+    pub fn contribute(&self, contribution: Contribution) -> DaemonResponse {
+        let round_id = contribution.round_id;
+        let identity = contribution.identity.clone();
+        let request = DaemonRequest::Contribute(contribution);
+        let response = self.daemon.lock().unwrap().handle(request, unix_now());
+
+        if let DaemonResponse::Accepted { fingerprint } = &response {
+            self.record(AuditEvent::ContributionAccepted {
+                round_id,
+                identity,
+                fingerprint: fingerprint.clone(),
+            });
+        }
+        response
+    }
+
+    /// A published round, if it is known
+    ///
+    /// ### Arguments
+    ///
+    /// * `id` - Id of the round
+    pub fn published(&self, id: u64) -> Option<Round> {
+        let round = self.store.lock().unwrap().get_round(id);
+        let round = round.unwrap_or_else(|e| {
+            tracing::warn!("Could not read round {}: {}", id, e);
+            None
+        });
+
+        // Rounds are also stored while they are evaluated
+        round.filter(|round| round.transcript.is_some())
+    }
+
+    /// Subscribes to round lifecycle events
+    pub fn subscribe(&self) -> broadcast::Receiver<RoundEvent> {
+        self.events.subscribe()
+    }
+
+    /// The UNICORN of a round whose eval was interrupted in an earlier run, if the server
+    /// picked one up from its store
+    pub fn interrupted_eval(&self) -> Option<Unicorn> {
+        let daemon = self.daemon.lock().unwrap();
+        let round = daemon.round();
+
+        match round.phase {
+            RoundPhase::Evaluating => round.unicorn().ok(),
+            _ => None,
+        }
+    }
+
+    /// Advances the current round, returning the UNICORN to evaluate once the round has been
+    /// sealed at its cutoff and notifying subscribers that the eval has started. The sealed
+    /// round is stored, so that its eval can be resumed if the server is restarted
+    pub fn poll(&self) -> Option<Unicorn> {
+        let mut daemon = self.daemon.lock().unwrap();
+        let unicorn = daemon.poll(unix_now())?;
+        let round = daemon.round();
+
+        if let Err(e) = self.store.lock().unwrap().put_round(round) {
+            tracing::error!("Could not store round {}: {}", round.id, e);
+        }
+
+        self.record(AuditEvent::sealed(round));
+        self.emit(RoundEvent::Sealed {
+            round_id: round.id,
+            participants: round.registry.len(),
+            sealed_digest: round.sealed_digest.clone(),
+        });
+        self.emit(RoundEvent::EvalStarted {
+            round_id: round.id,
+            deadline: round.deadline,
+        });
+
+        Some(unicorn)
+    }
+
+    /// Records a published round, making its result available and notifying subscribers
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Published round
+    pub fn publish(&self, round: Round) {
+        for event in AuditEvent::published(&round) {
+            self.record(event);
+        }
+        if let Some(published) = PublishedRound::from_round(&round) {
+            self.emit(RoundEvent::Published(published));
+        }
+        if let Err(e) = self.store.lock().unwrap().put_round(&round) {
+            tracing::error!("Could not store round {}: {}", round.id, e);
+        }
+    }
+
+    /// Obtains a time-stamp token for a published round's transcript from the configured
+    /// TSA. The round is published without one if no TSA is configured or it cannot be reached
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Published round
+    #[cfg(feature = "timestamp")]
+    pub async fn timestamp(&self, mut round: Round) -> Round {
+        let (Some(tsa), Some(transcript)) =
+            (&self.config.daemon.timestamp_url, &mut round.transcript)
+        else {
+            return round;
+        };
+
+        let digest = transcript.publication_digest();
+        match crate::timestamp::request_timestamp(&reqwest::Client::new(), tsa, &digest).await {
+            Ok(token) => transcript.timestamp = Some(token),
+            Err(e) => tracing::warn!("Could not timestamp round {}: {}", round.id, e),
+        }
+
+        round
+    }
+
+    /// Status of the server, including the progress of a running eval
+    pub fn status(&self) -> ServerStatus {
+        let round = self.daemon.lock().unwrap().status();
+        let stored = self.store.lock().unwrap().list_rounds().unwrap_or_default();
+        let last_g_value = stored
+            .into_iter()
+            .rev()
+            .find_map(|id| self.published(id))
+            .and_then(|round| round.transcript)
+            .map(|transcript| transcript.g_value);
+
+        ServerStatus {
+            round,
+            eval: self.eval_status(),
+            params: self.config.unicorn.clone(),
+            last_g_value,
+        }
+    }
+
+    /// Evaluates a round's UNICORN on a blocking thread, tracking its progress for the
+    /// status route. The eval resumes from the round's stored checkpoint, if there is one, and
+    /// is checkpointed every `daemon.checkpoint_iterations` iterations until it completes
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round being evaluated
+    /// * `unicorn`  - UNICORN to evaluate
+    pub async fn eval_round(&self, round_id: u64, unicorn: Unicorn) -> Option<UnicornInfo> {
+        let checkpoint = self.store.lock().unwrap().get_checkpoint(round_id);
+        let state = match checkpoint {
+            Ok(Some(state)) if state.iterations_done <= unicorn.iterations => {
+                tracing::info!(
+                    "Resuming eval of round {} after {} iterations",
+                    round_id,
+                    state.iterations_done
+                );
+                state
+            }
+            Ok(_) => unicorn.start_state(),
+            Err(e) => {
+                tracing::warn!("Could not read checkpoint of round {}: {}", round_id, e);
+                unicorn.start_state()
+            }
+        };
+
+        let progress = self.progress.clone();
+        progress.round_id.store(round_id, Ordering::Relaxed);
+        progress
+            .done
+            .store(state.iterations_done, Ordering::Relaxed);
+        progress.total.store(unicorn.iterations, Ordering::Relaxed);
+        *progress.started.lock().unwrap() = Some(Instant::now());
+
+        let tracked = progress.clone();
+        let store = self.store.clone();
+        let interval = self.config.daemon.checkpoint_iterations;
+        let mode = self.config.daemon.eval_mode;
+        let info = tokio::task::spawn_blocking(move || {
+            let mut checkpointed = state.iterations_done;
+            let (witness, g_value) = unicorn.eval_from_with_mode(state, mode, |state| {
+                tracked.done.store(state.iterations_done, Ordering::Relaxed);
+
+                let due =
+                    interval > 0 && state.iterations_done >= checkpointed.saturating_add(interval);
+                if due && state.iterations_done < unicorn.iterations {
+                    checkpointed = state.iterations_done;
+                    if let Err(e) = store.lock().unwrap().put_checkpoint(round_id, state) {
+                        tracing::warn!("Could not checkpoint round {}: {}", round_id, e);
+                    }
+                }
+            })?;
+            Some(UnicornInfo::new(unicorn, witness, g_value))
+        })
+        .await
+        .ok()
+        .flatten();
+
+        if info.is_some() {
+            if let Err(e) = self.store.lock().unwrap().remove_checkpoint(round_id) {
+                tracing::warn!("Could not remove checkpoint of round {}: {}", round_id, e);
+            }
+        }
+        *progress.started.lock().unwrap() = None;
+        info
+    }
+
+    fn eval_status(&self) -> Option<EvalStatus> {
+        let started = (*self.progress.started.lock().unwrap())?;
+        let done = self.progress.done.load(Ordering::Relaxed);
+        let iterations = self.progress.total.load(Ordering::Relaxed);
+        let elapsed = started.elapsed().as_secs_f64();
+
+        Some(EvalStatus {
+            round_id: self.progress.round_id.load(Ordering::Relaxed),
+            iterations_done: done,
+            iterations,
+            percent: match iterations {
+                0 => 100.0,
+                _ => done as f64 * 100.0 / iterations as f64,
+            },
+            iterations_per_sec: match elapsed > 0.0 {
+                true => done as f64 / elapsed,
+                false => 0.0,
+            },
+        })
+    }
+
+    fn record(&self, event: AuditEvent) {
+        if let Some(log) = &self.audit_log {
+            if let Err(e) = log.lock().unwrap().append(event, unix_now()) {
+                tracing::error!("Could not append to audit log: {}", e);
+            }
+        }
+    }
+
+    fn emit(&self, event: RoundEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+}
+
+/// Router serving the API
+///
+/// ### Arguments
+///
+/// * `state` - Shared server state
+pub fn router(state: ServerState) -> Router {
+    #[cfg(feature = "openapi")]
+    let router = crate::openapi::serve(api_routes());
+    #[cfg(not(feature = "openapi"))]
+    let router = api_routes();
+
+    #[cfg(feature = "jsonrpc")]
+    let router = router.route("/rpc", axum::routing::post(crate::jsonrpc::rpc));
+
+    router.with_state(state)
+}
+
+/// Routes of the API described by the OpenAPI document
+pub(crate) fn api_routes() -> ApiRouter<ServerState> {
+    ApiRouter::new()
+        .api_route("/rounds/:id/contributions", post(contribute))
+        .api_route("/rounds/:id/result", get(result))
+        .api_route("/rounds/:id/feed", get(feed))
+        .api_route("/eval", post(eval))
+        .api_route("/verify", post(verify))
+        .api_route("/events", get(events))
+        .api_route("/status", get(status))
+}
+
+/// Runs rounds until the process is stopped, evaluating each on a blocking thread. A round
+/// whose eval was interrupted in an earlier run is finished first, and a round whose eval
+/// fails is aborted in favour of the next
+///
+/// ### Arguments
+///
+/// * `state` - Shared server state
+pub async fn run_rounds(state: ServerState) {
+    let mut interrupted = state.interrupted_eval();
+
+    loop {
+        let Some(unicorn) = interrupted.take().or_else(|| state.poll()) else {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        };
+
+        let round_id = state.daemon.lock().unwrap().round().id;
+        let Some(info) = state.eval_round(round_id, unicorn).await else {
+            state.daemon.lock().unwrap().abort(unix_now());
+            tracing::error!("Eval of round {} failed; aborting it", round_id);
+            continue;
+        };
+
+        let finished = state.daemon.lock().unwrap().finish(info, unix_now());
+        match finished {
+            #[cfg(feature = "timestamp")]
+            Ok(round) => state.publish(state.timestamp(round).await),
+            #[cfg(not(feature = "timestamp"))]
+            Ok(round) => state.publish(round),
+            Err(e) => tracing::warn!("Round failed: {}", e),
+        }
+    }
+}
+
+async fn contribute(
+    State(state): State<ServerState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+    Json(contribution): Json<Contribution>,
+) -> Result<Json<Value>, ApiError> {
+    if contribution.round_id != id {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "Contribution is for another round",
+        ));
+    }
+
+    let credentials =
+        Credentials::from_headers(|name| headers.get(name).and_then(|v| v.to_str().ok()));
+    state
+        .authorize(&credentials, &contribution)
+        .map_err(|e| match e {
+            AuthError::Forbidden(_) => error(StatusCode::FORBIDDEN, e.to_string()),
+            _ => error(StatusCode::UNAUTHORIZED, e.to_string()),
+        })?;
+    state
+        .check_limits(&contribution)
+        .map_err(|e| error(StatusCode::TOO_MANY_REQUESTS, e.to_string()))?;
+
+    match state.contribute(contribution) {
+        DaemonResponse::Accepted { fingerprint } => Ok(Json(json!({ "fingerprint": fingerprint }))),
+        DaemonResponse::Error { message } => Err(error(StatusCode::CONFLICT, message)),
+        _ => Err(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Unexpected response",
+        )),
+    }
+}
+
+async fn result(
+    State(state): State<ServerState>,
+    Path(id): Path<u64>,
+) -> Result<Json<SelectionTranscript>, ApiError> {
+    state
+        .published(id)
+        .and_then(|round| round.transcript)
+        .map(Json)
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Round has not been published"))
+}
+
+async fn feed(
+    State(state): State<ServerState>,
+    Path(id): Path<u64>,
+) -> Result<Json<FeedEntry>, ApiError> {
+    state
+        .published(id)
+        .as_ref()
+        .and_then(FeedEntry::from_round)
+        .map(Json)
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Round has not been published"))
+}
+
+async fn eval(
+    State(state): State<ServerState>,
+    Json(request): Json<EvalRequest>,
+) -> Result<Json<UnicornInfo>, ApiError> {
+    let bad_request = |message: String| error(StatusCode::BAD_REQUEST, message);
+    let unicorn = request
+        .unicorn(state.config.unicorn.iterations)
+        .map_err(bad_request)?;
+
+    eval_blocking(unicorn)
+        .await
+        .map(Json)
+        .ok_or_else(|| bad_request("Invalid UNICORN modulus".to_string()))
+}
+
+async fn verify(
+    State(state): State<ServerState>,
+    Json(request): Json<VerifyRequest>,
+) -> Result<Json<AuditReport>, ApiError> {
+    request
+        .check_iterations(state.config.unicorn.iterations)
+        .map_err(|e| error(StatusCode::BAD_REQUEST, e))?;
+
+    audit_blocking(request)
+        .await
+        .map(Json)
+        .ok_or_else(|| error(StatusCode::INTERNAL_SERVER_ERROR, "Audit failed"))
+}
+
+async fn status(State(state): State<ServerState>) -> Json<ServerStatus> {
+    Json(state.status())
+}
+
+async fn events(State(state): State<ServerState>, upgrade: WebSocketUpgrade) -> Response {
+    let events = state.subscribe();
+    upgrade.on_upgrade(move |socket| push_events(socket, events))
+}
+
+async fn push_events(mut socket: WebSocket, mut events: broadcast::Receiver<RoundEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            // Events missed by a lagging subscriber are skipped
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let text = serde_json::to_string(&event).unwrap();
+        if socket.send(Message::Text(text)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Evaluates a UNICORN on a blocking thread. Returns `None` if the modulus is not valid
+///
+/// ### Arguments
+///
+/// * `unicorn` - UNICORN to evaluate
+pub(crate) async fn eval_blocking(unicorn: Unicorn) -> Option<UnicornInfo> {
+    tokio::task::spawn_blocking(move || {
+        let (witness, g_value) = unicorn.eval()?;
+        Some(UnicornInfo::new(unicorn, witness, g_value))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Audits a transcript or UNICORN on a blocking thread. Returns `None` if the audit panicked
+///
+/// ### Arguments
+///
+/// * `request` - Transcript or UNICORN to audit
+pub(crate) async fn audit_blocking(request: VerifyRequest) -> Option<AuditReport> {
+    tokio::task::spawn_blocking(move || match request {
+        VerifyRequest::Transcript(transcript) => audit_transcript(&transcript),
+        VerifyRequest::Unicorn(info) => audit_unicorn(&info),
+    })
+    .await
+    .ok()
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(json!({ "error": message.into() })))
+}
+
+/// Current unix time in seconds
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use crate::unicorn::PROGRESS_INTERVAL;
+
+    #[tokio::test]
+    /// Checks that contributions, evals and verification round-trip through the routes
+    async fn api_routes() {
+        let state = ServerState::new(LotteryConfig::default(), 3);
+        let contribution = Contribution {
+            round_id: 3,
+            identity: "miner_a".to_string(),
+            timestamp: unix_now(),
+            nonce: None,
+        };
+
+        let accepted = contribute(
+            State(state.clone()),
+            Path(3),
+            HeaderMap::new(),
+            Json(contribution.clone()),
+        )
+        .await;
+        let wrong_round = contribute(
+            State(state.clone()),
+            Path(4),
+            HeaderMap::new(),
+            Json(contribution.clone()),
+        )
+        .await;
+        assert!(accepted.is_ok());
+        assert_eq!(wrong_round.unwrap_err().0, StatusCode::BAD_REQUEST);
+
+        let keys = [("k1".to_string(), "miner_b".to_string())].into();
+        let guarded = ServerState::new(LotteryConfig::default(), 3)
+            .with_authorizer(crate::auth::ApiKeyAuthorizer::new(keys));
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer k1".parse().unwrap());
+        let unauthenticated = contribute(
+            State(guarded.clone()),
+            Path(3),
+            HeaderMap::new(),
+            Json(contribution.clone()),
+        )
+        .await;
+        let forbidden =
+            contribute(State(guarded), Path(3), headers, Json(contribution.clone())).await;
+        assert_eq!(unauthenticated.unwrap_err().0, StatusCode::UNAUTHORIZED);
+        assert_eq!(forbidden.unwrap_err().0, StatusCode::FORBIDDEN);
+
+        let mut config = LotteryConfig::default();
+        config.limits.burst = 1;
+        let limited = ServerState::new(config, 3);
+        for expected in [StatusCode::OK, StatusCode::TOO_MANY_REQUESTS] {
+            let submitted = contribute(
+                State(limited.clone()),
+                Path(3),
+                HeaderMap::new(),
+                Json(contribution.clone()),
+            )
+            .await;
+            assert_eq!(submitted.map_or_else(|e| e.0, |_| StatusCode::OK), expected);
+        }
+
+        // Naming another round does not reset the quota of the collecting round
+        let mut config = LotteryConfig::default();
+        config.limits.round_quota = 1;
+        let quota = ServerState::new(config, 3);
+        for (round_id, expected) in [
+            (3, StatusCode::OK),
+            (4, StatusCode::TOO_MANY_REQUESTS),
+            (3, StatusCode::TOO_MANY_REQUESTS),
+        ] {
+            let submitted = contribute(
+                State(quota.clone()),
+                Path(round_id),
+                HeaderMap::new(),
+                Json(Contribution {
+                    round_id,
+                    ..contribution.clone()
+                }),
+            )
+            .await;
+            assert_eq!(submitted.map_or_else(|e| e.0, |_| StatusCode::OK), expected);
+        }
+
+        let request = EvalRequest {
+            seed: "1eeb30c7".to_string(),
+            params: UnicornFixedParam::test(),
+        };
+        let Json(info) = eval(State(state.clone()), Json(request.clone()))
+            .await
+            .unwrap();
+        let Json(report) = verify(
+            State(state.clone()),
+            Json(VerifyRequest::Unicorn(info.clone())),
+        )
+        .await
+        .unwrap();
+        assert!(report.passed());
+
+        let mut slow = info;
+        slow.unicorn.iterations = u64::MAX;
+        assert_eq!(
+            verify(State(state.clone()), Json(VerifyRequest::Unicorn(slow)))
+                .await
+                .unwrap_err()
+                .0,
+            StatusCode::BAD_REQUEST
+        );
+
+        let mut expensive = request;
+        expensive.params.iterations += 1;
+        assert!(eval(State(state.clone()), Json(expensive)).await.is_err());
+
+        assert_eq!(
+            result(State(state), Path(3)).await.unwrap_err().0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    /// Checks that a server with stored rounds serves them and continues after the latest
+    fn resumes_from_store() {
+        let config = LotteryConfig::default();
+        let mut store = MemoryStore::default();
+        let mut round = Daemon::new(config.clone(), 5, 0).round().clone();
+        round.transcript = Some(Default::default());
+        store.put_round(&round).unwrap();
+
+        let state = ServerState::new(config, 0).with_store(store).unwrap();
+
+        assert_eq!(state.daemon().lock().unwrap().round().id, 6);
+        assert_eq!(state.published(5), Some(round));
+        assert_eq!(state.status().last_g_value, Some(String::new()));
+    }
+
+    #[tokio::test]
+    /// Checks that a round interrupted mid-eval is picked up from the store and its eval
+    /// resumed from the stored checkpoint
+    async fn resumes_interrupted_eval() {
+        let mut config = LotteryConfig::default();
+        config.unicorn.iterations = 3 * PROGRESS_INTERVAL;
+        config.daemon.checkpoint_iterations = PROGRESS_INTERVAL;
+        let now = unix_now();
+
+        let mut round = Daemon::new(config.clone(), 5, now).round().clone();
+        round.contribute("miner_a", now).unwrap();
+        round.seal().unwrap();
+        let unicorn = round.start_eval(now).unwrap();
+        let mut checkpoint = None;
+        unicorn.eval_from(unicorn.start_state(), |state| {
+            checkpoint.get_or_insert_with(|| state.clone());
+        });
+
+        let mut store = MemoryStore::default();
+        store.put_round(&round).unwrap();
+        store.put_checkpoint(5, &checkpoint.unwrap()).unwrap();
+        let state = ServerState::new(config, 0).with_store(store).unwrap();
+
+        assert_eq!(state.daemon().lock().unwrap().round().id, 5);
+        assert_eq!(state.published(5), None);
+        assert_eq!(state.interrupted_eval(), Some(unicorn.clone()));
+
+        let info = state.eval_round(5, unicorn.clone()).await.unwrap();
+        let (witness, g_value) = unicorn.eval().unwrap();
+        assert_eq!(
+            (info.witness.clone(), info.g_value.clone()),
+            (witness, g_value)
+        );
+        assert_eq!(state.store.lock().unwrap().get_checkpoint(5), Ok(None));
+
+        let round = state
+            .daemon()
+            .lock()
+            .unwrap()
+            .finish(info, unix_now())
+            .unwrap();
+        state.publish(round);
+        assert!(state.published(5).is_some());
+        assert_eq!(state.interrupted_eval(), None);
+    }
+
+    #[tokio::test]
+    /// Checks that sealing, the eval start and publishing are pushed to subscribers in order
+    async fn lifecycle_events() {
+        let mut config = LotteryConfig::default();
+        config.daemon.collect_secs = 1;
+        let state = ServerState::new(config, 0);
+        let mut events = state.subscribe();
+
+        let round_id = state.daemon().lock().unwrap().round().id;
+        let contribution = Contribution {
+            round_id,
+            identity: "miner_a".to_string(),
+            timestamp: unix_now(),
+            nonce: None,
+        };
+        let accepted = contribute(
+            State(state.clone()),
+            Path(round_id),
+            HeaderMap::new(),
+            Json(contribution),
+        )
+        .await;
+        assert!(accepted.is_ok());
+
+        let unicorn = loop {
+            match state.poll() {
+                Some(unicorn) => break unicorn,
+                None => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        };
+        assert_eq!(state.status().round.phase, RoundPhase::Evaluating);
+        let info = state.eval_round(round_id, unicorn).await.unwrap();
+        let round = state
+            .daemon()
+            .lock()
+            .unwrap()
+            .finish(info, unix_now())
+            .unwrap();
+        state.publish(round);
+
+        let status = state.status();
+        assert_eq!(status.eval, None);
+        let published = state.published(round_id).and_then(|r| r.transcript);
+        assert!(status.last_g_value.is_some());
+        assert_eq!(status.last_g_value, published.map(|t| t.g_value));
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+
+        assert!(matches!(
+            received[..],
+            [
+                RoundEvent::Sealed {
+                    participants: 1,
+                    ..
+                },
+                RoundEvent::EvalStarted { .. },
+                RoundEvent::Published(_)
+            ]
+        ));
+    }
+}
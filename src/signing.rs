@@ -0,0 +1,196 @@
+//! Ed25519 signing of published lottery results.
+//!
+//! A coordinator signs the round transcript (or any other serializable result) with its
+//! Ed25519 key, so published results cannot be spoofed in transit. Signatures are over a
+//! fixed context string followed by the bincode encoding of the payload. Verifiers check the
+//! signature against a trusted coordinator key and then replay the draw itself, since a
+//! signature only vouches for who published a result, not for its correctness.
+
+use crate::rounds::Round;
+use crate::transcript::{SelectionTranscript, TranscriptStep};
+use bincode::serialize;
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Context prepended to every signed message
+pub const SIGNING_CONTEXT: &[u8] = b"miner-lottery/signed-result/v1";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SigningError {
+    /// Public key or signature could not be decoded.
+    Malformed,
+    /// The result was signed by a key other than the trusted one.
+    UntrustedSigner,
+    /// The signature does not match the payload.
+    BadSignature,
+    /// The signed round has no published transcript.
+    MissingTranscript,
+    /// The signed draw failed to replay at the given step.
+    Replay(TranscriptStep),
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::Malformed => write!(f, "Malformed public key or signature"),
+            SigningError::UntrustedSigner => write!(f, "Result signed by an untrusted key"),
+            SigningError::BadSignature => write!(f, "Invalid signature"),
+            SigningError::MissingTranscript => write!(f, "Signed round has no transcript"),
+            SigningError::Replay(step) => write!(f, "Signed draw failed replay at {:?}", step),
+        }
+    }
+}
+
+impl Error for SigningError {}
+
+/// A payload together with the coordinator's signature over it
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Signed<T> {
+    pub payload: T,
+    /// Hex-encoded Ed25519 public key of the signer
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature
+    pub signature: String,
+}
+
+impl<T: Serialize> Signed<T> {
+    /// Signs a payload with the coordinator key
+    ///
+    /// ### Arguments
+    ///
+    /// * `payload` - Payload to sign
+    /// * `keypair` - Coordinator key pair
+    pub fn sign(payload: T, keypair: &Keypair) -> Self {
+        let signature = keypair.sign(&signing_message(&payload));
+
+        Self {
+            payload,
+            public_key: hex::encode(keypair.public.as_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    /// Checks that the payload was signed by the trusted key
+    ///
+    /// ### Arguments
+    ///
+    /// * `trusted` - Trusted coordinator public key
+    pub fn verify_signature(&self, trusted: &PublicKey) -> Result<(), SigningError> {
+        let public_key = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+            .ok_or(SigningError::Malformed)?;
+        if public_key != *trusted {
+            return Err(SigningError::UntrustedSigner);
+        }
+
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::try_from(&bytes[..]).ok())
+            .ok_or(SigningError::Malformed)?;
+
+        trusted
+            .verify(&signing_message(&self.payload), &signature)
+            .map_err(|_| SigningError::BadSignature)
+    }
+}
+
+impl Signed<SelectionTranscript> {
+    /// Checks the signature and then replays the signed draw
+    ///
+    /// ### Arguments
+    ///
+    /// * `trusted` - Trusted coordinator public key
+    pub fn verify(&self, trusted: &PublicKey) -> Result<(), SigningError> {
+        self.verify_signature(trusted)?;
+        self.payload.replay().map_err(SigningError::Replay)
+    }
+}
+
+impl Signed<Round> {
+    /// Checks the signature and then replays the round's published draw
+    ///
+    /// ### Arguments
+    ///
+    /// * `trusted` - Trusted coordinator public key
+    pub fn verify(&self, trusted: &PublicKey) -> Result<(), SigningError> {
+        self.verify_signature(trusted)?;
+
+        let transcript = self
+            .payload
+            .transcript
+            .as_ref()
+            .ok_or(SigningError::MissingTranscript)?;
+        if transcript.seed != self.payload.seed {
+            return Err(SigningError::Replay(TranscriptStep::Seed));
+        }
+
+        transcript.replay().map_err(SigningError::Replay)
+    }
+}
+
+/// Message signed for a payload: the signing context followed by its bincode encoding
+fn signing_message<T: Serialize>(payload: &T) -> Vec<u8> {
+    let mut message = SIGNING_CONTEXT.to_vec();
+    message.extend(serialize(payload).unwrap());
+    message
+}
+
+/// Key pair whose secret key is `seed` repeated, for tests
+#[cfg(test)]
+pub(crate) fn test_keypair(seed: u8) -> Keypair {
+    let secret = ed25519_dalek::SecretKey::from_bytes(&[seed; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod signing_tests {
+    use super::*;
+    use crate::unicorn::UnicornFixedParam;
+
+    fn test_transcript() -> SelectionTranscript {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+
+        SelectionTranscript::record(&inputs, &params, 1).unwrap()
+    }
+
+    #[test]
+    /// Checks that signed transcripts verify only with the trusted key and untampered payload
+    fn signed_transcript() {
+        let coordinator = test_keypair(1);
+        let signed = Signed::sign(test_transcript(), &coordinator);
+
+        let mut tampered = signed.clone();
+        tampered.payload.winner = "miner_c".to_string();
+
+        assert_eq!(signed.verify(&coordinator.public), Ok(()));
+        assert_eq!(
+            signed.verify(&test_keypair(2).public),
+            Err(SigningError::UntrustedSigner)
+        );
+        assert_eq!(
+            tampered.verify(&coordinator.public),
+            Err(SigningError::BadSignature)
+        );
+    }
+
+    #[test]
+    /// Checks that a correctly signed but invalid draw is still rejected
+    fn signed_invalid_draw() {
+        let coordinator = test_keypair(1);
+        let mut transcript = test_transcript();
+        transcript.g_value = "00".to_string();
+        let signed = Signed::sign(transcript, &coordinator);
+
+        assert_eq!(
+            signed.verify(&coordinator.public),
+            Err(SigningError::Replay(TranscriptStep::GValue))
+        );
+    }
+}
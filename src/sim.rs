@@ -0,0 +1,213 @@
+//! Deterministic multi-round simulation.
+//!
+//! The simulation runs many rounds end to end through the round state machine, each with the
+//! same synthetic participants, and tallies how often each of them wins. Participant
+//! identities and the winning PoW hashes bound into each round's seed are derived from a
+//! single simulation seed, so a run with the same seed, parameters and VDF reproduces the same
+//! winners. That makes it both a fairness check, through the spread of the tallies around the
+//! uniform expectation, and a regression test, through the winners themselves.
+//!
+//! Evals go through a `Vdf`, so thousands of rounds can be run with few iterations, or
+//! instantly with `MockVdf` when only the orchestration and selection are of interest. Eval
+//! times are recorded alongside, but vary between runs.
+
+use crate::rounds::{Round, RoundError, RoundTimeouts};
+use crate::unicorn::UnicornFixedParam;
+use crate::vdf::Vdf;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+/// Settings of a simulation run
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SimConfig {
+    /// Number of rounds to run
+    pub rounds: u64,
+    /// Number of synthetic participants in every round
+    pub participants: usize,
+    /// Seed from which identities and winning PoW hashes are derived
+    pub seed: u64,
+    /// UNICORN parameters of every round
+    pub fixed_params: UnicornFixedParam,
+    /// Usage number of every winner draw
+    pub usage_number: u128,
+}
+
+/// Outcome of a simulation run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SimReport {
+    pub rounds: u64,
+    /// Synthetic participant identities, in the order of `wins`
+    pub identities: Vec<String>,
+    /// Number of rounds won by each participant
+    pub wins: Vec<u64>,
+    /// Index of the winner of each round, in round order
+    pub winners: Vec<usize>,
+    /// Total time spent in evals
+    pub total_eval_time: Duration,
+    /// Longest single eval
+    pub max_eval_time: Duration,
+}
+
+impl SimReport {
+    /// Number of wins each participant would get under a perfectly uniform draw
+    pub fn expected_wins(&self) -> f64 {
+        self.rounds as f64 / self.wins.len().max(1) as f64
+    }
+
+    /// Pearson's chi-squared statistic of the wins against the uniform expectation, with
+    /// one degree of freedom fewer than there are participants
+    pub fn chi_squared(&self) -> f64 {
+        let expected = self.expected_wins();
+        if expected == 0.0 {
+            return 0.0;
+        }
+
+        self.wins
+            .iter()
+            .map(|&w| (w as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    /// Largest relative deviation of any participant's wins from the uniform expectation
+    pub fn max_deviation(&self) -> f64 {
+        let expected = self.expected_wins();
+        if expected == 0.0 {
+            return 0.0;
+        }
+
+        self.wins
+            .iter()
+            .map(|&w| (w as f64 - expected).abs() / expected)
+            .fold(0.0, f64::max)
+    }
+
+    /// Mean time of an eval
+    pub fn mean_eval_time(&self) -> Duration {
+        self.total_eval_time / self.rounds.max(1) as u32
+    }
+}
+
+/// Identity of a synthetic participant
+///
+/// ### Arguments
+///
+/// * `seed`  - Simulation seed
+/// * `index` - Index of the participant
+pub fn sim_identity(seed: u64, index: usize) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"miner-lottery/sim/identity")
+        .chain_update(seed.to_be_bytes())
+        .chain_update((index as u64).to_be_bytes())
+        .finalize();
+    hex::encode(digest)
+}
+
+/// Winning PoW hash bound into the seed of a simulated round
+///
+/// ### Arguments
+///
+/// * `seed`     - Simulation seed
+/// * `round_id` - Id of the round
+pub fn sim_pow_hash(seed: u64, round_id: u64) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"miner-lottery/sim/pow")
+        .chain_update(seed.to_be_bytes())
+        .chain_update(round_id.to_be_bytes())
+        .finalize();
+    hex::encode(digest)
+}
+
+/// Runs the simulated rounds, evaluating each with the given VDF. Fails on the first round
+/// that cannot be run, such as when there are no participants or the parameters are invalid
+///
+/// ### Arguments
+///
+/// * `config` - Settings of the run
+/// * `vdf`    - VDF evaluating each round's UNICORN
+pub fn simulate(config: &SimConfig, vdf: &impl Vdf) -> Result<SimReport, RoundError> {
+    let identities: Vec<String> = (0..config.participants)
+        .map(|i| sim_identity(config.seed, i))
+        .collect();
+    let timeouts = RoundTimeouts {
+        collect_secs: 1,
+        eval_secs: u64::MAX,
+    };
+
+    let mut report = SimReport {
+        rounds: config.rounds,
+        identities,
+        wins: vec![0; config.participants],
+        winners: Vec::with_capacity(config.rounds as usize),
+        total_eval_time: Duration::ZERO,
+        max_eval_time: Duration::ZERO,
+    };
+
+    for round_id in 0..config.rounds {
+        let now = round_id.saturating_mul(2);
+        let mut round = Round::new(
+            round_id,
+            config.fixed_params.clone(),
+            config.usage_number,
+            timeouts,
+            now,
+        )
+        .with_last_winning_hashes(vec![sim_pow_hash(config.seed, round_id)]);
+        for identity in &report.identities {
+            round.contribute(identity, now)?;
+        }
+        round.seal()?;
+
+        let started = Instant::now();
+        let winner = round.evaluate_with(vdf, now)?.winner.clone();
+        let elapsed = started.elapsed();
+
+        let index = report
+            .identities
+            .iter()
+            .position(|identity| *identity == winner)
+            .ok_or(RoundError::VerificationFailed)?;
+        report.wins[index] += 1;
+        report.winners.push(index);
+        report.total_eval_time += elapsed;
+        report.max_eval_time = report.max_eval_time.max(elapsed);
+    }
+
+    Ok(report)
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod sim_tests {
+    use super::*;
+    use crate::vdf::MockVdf;
+
+    fn config(seed: u64) -> SimConfig {
+        SimConfig {
+            rounds: 2_000,
+            participants: 8,
+            seed,
+            fixed_params: UnicornFixedParam::test(),
+            usage_number: 1,
+        }
+    }
+
+    #[test]
+    /// Checks that a run is reproducible from its seed and spreads wins plausibly evenly
+    fn deterministic_fair_run() {
+        let report = simulate(&config(7), &MockVdf).unwrap();
+
+        assert_eq!(
+            report.winners,
+            simulate(&config(7), &MockVdf).unwrap().winners
+        );
+        assert_ne!(
+            report.winners,
+            simulate(&config(8), &MockVdf).unwrap().winners
+        );
+        assert_eq!(report.wins.iter().sum::<u64>(), 2_000);
+        // 7 degrees of freedom; exceeded by chance with probability below 1e-6
+        assert!(report.chi_squared() < 40.0, "{}", report.chi_squared());
+    }
+}
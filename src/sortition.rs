@@ -0,0 +1,211 @@
+//! Stake-weighted committee sortition, as per Algorand's committee sampling.
+//!
+//! Every unit of stake is treated as a sub-user that is selected independently with
+//! probability `expected_size / total_stake`. The number of selected sub-users of a
+//! participant, their votes, therefore follows a binomial distribution over their stake, and
+//! the committee size concentrates around `expected_size` while staying proportional to stake.
+//!
+//! Each participant's draw comes from its own usage number of the UNICORN, so a membership
+//! claim can be checked knowing only the claimant's index and stake and the total stake.
+//!
+//! The binomial CDF is computed with basic floating point operations only, avoiding `powf`
+//! and friends, so that every platform computes identical vote counts.
+
+use crate::unicorn::UnicornInfo;
+use crate::utils::unicorn_selection::get_unicorn_prn;
+use serde::{Deserialize, Serialize};
+
+/// Usage number of the first participant's draw; participant `i` uses `SORTITION_USAGE_BASE + i`
+pub const SORTITION_USAGE_BASE: u128 = 1 << 32;
+
+/// Claim that a participant was selected into the committee
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MembershipClaim {
+    /// Index of the participant
+    pub index: usize,
+    /// Stake of the participant
+    pub stake: u64,
+    /// Number of selected sub-users of the participant
+    pub votes: u64,
+}
+
+/// Draws the committee, returning a claim for every participant with at least one vote.
+/// Returns `None` if the UNICORN's `g` value is too short to key its CSPRNG
+///
+/// ### Arguments
+///
+/// * `stakes`        - Stake of each participant
+/// * `expected_size` - Expected total number of votes in the committee
+/// * `unicorn`       - UNICORN to draw from
+pub fn committee(
+    stakes: &[u64],
+    expected_size: u64,
+    unicorn: &UnicornInfo,
+) -> Option<Vec<MembershipClaim>> {
+    let total_stake = stakes.iter().fold(0u64, |acc, s| acc.saturating_add(*s));
+
+    let claims = stakes
+        .iter()
+        .enumerate()
+        .map(|(index, stake)| {
+            Some(MembershipClaim {
+                index,
+                stake: *stake,
+                votes: votes_for(unicorn, index, *stake, total_stake, expected_size)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(claims.into_iter().filter(|claim| claim.votes > 0).collect())
+}
+
+/// Verifies an individual membership claim
+///
+/// ### Arguments
+///
+/// * `claim`         - Claim to verify
+/// * `total_stake`   - Total stake of all participants
+/// * `expected_size` - Expected total number of votes in the committee
+/// * `unicorn`       - UNICORN the committee was drawn from
+pub fn verify_claim(
+    claim: &MembershipClaim,
+    total_stake: u64,
+    expected_size: u64,
+    unicorn: &UnicornInfo,
+) -> bool {
+    claim.votes > 0
+        && claim.stake <= total_stake
+        && votes_for(
+            unicorn,
+            claim.index,
+            claim.stake,
+            total_stake,
+            expected_size,
+        ) == Some(claim.votes)
+}
+
+/// Computes the votes of a single participant. Returns `None` if the UNICORN's `g` value is
+/// too short to key its CSPRNG
+///
+/// ### Arguments
+///
+/// * `unicorn`       - UNICORN to draw from
+/// * `index`         - Index of the participant
+/// * `stake`         - Stake of the participant
+/// * `total_stake`   - Total stake of all participants
+/// * `expected_size` - Expected total number of votes in the committee
+pub fn votes_for(
+    unicorn: &UnicornInfo,
+    index: usize,
+    stake: u64,
+    total_stake: u64,
+    expected_size: u64,
+) -> Option<u64> {
+    if stake == 0 || total_stake == 0 || expected_size == 0 {
+        return Some(0);
+    }
+    if expected_size >= total_stake {
+        return Some(stake);
+    }
+
+    let prn = get_unicorn_prn(unicorn, SORTITION_USAGE_BASE + index as u128)?;
+    let u = prn as f64 / 18_446_744_073_709_551_616.0; // 2^64
+    let p = expected_size as f64 / total_stake as f64;
+
+    Some(binomial_inverse_cdf(u, stake, p))
+}
+
+/// Smallest `j` such that `u` is below the CDF of Binomial(`n`, `p`) at `j`
+fn binomial_inverse_cdf(u: f64, n: u64, p: f64) -> u64 {
+    let ratio = p / (1.0 - p);
+    let mean = n as f64 * p;
+
+    let mut prob = pow_u64(1.0 - p, n);
+    let mut cdf = prob;
+    let mut j = 0;
+
+    while u >= cdf && j < n {
+        prob *= (n - j) as f64 / (j + 1) as f64 * ratio;
+        j += 1;
+        cdf += prob;
+
+        // Past the mean, a vanishing term means the CDF can no longer grow
+        if prob == 0.0 && j as f64 > mean {
+            break;
+        }
+    }
+
+    j
+}
+
+/// Raises `base` to `exp` by repeated squaring
+fn pow_u64(mut base: f64, mut exp: u64) -> f64 {
+    let mut result = 1.0;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        exp >>= 1;
+    }
+
+    result
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod sortition_tests {
+    use super::*;
+
+    fn test_unicorn() -> UnicornInfo {
+        UnicornInfo {
+            g_value: "0106834db40e90d1cafaa9e4c1981873186ebf019629852059aaf8e4ca35da01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that the committee size concentrates around the expected size
+    fn committee_size() {
+        let stakes: Vec<u64> = (1..=500).collect();
+        let members = committee(&stakes, 100, &test_unicorn()).unwrap();
+        let votes: u64 = members.iter().map(|m| m.votes).sum();
+
+        assert!(votes > 60 && votes < 140);
+        assert_eq!(committee(&stakes, 0, &test_unicorn()), Some(vec![]));
+        assert_eq!(committee(&[3, 4], 10, &test_unicorn()).unwrap().len(), 2);
+
+        let short_g = UnicornInfo {
+            g_value: "0106".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(committee(&stakes, 100, &short_g), None);
+    }
+
+    #[test]
+    /// Checks that membership claims verify only when untampered
+    fn membership_claims() {
+        let stakes: Vec<u64> = (1..=200).collect();
+        let total_stake = stakes.iter().sum();
+        let unicorn = test_unicorn();
+        let members = committee(&stakes, 50, &unicorn).unwrap();
+
+        let mut inflated = members[0];
+        inflated.votes += 1;
+
+        assert!(members
+            .iter()
+            .all(|m| verify_claim(m, total_stake, 50, &unicorn)));
+        assert!(!verify_claim(&inflated, total_stake, 50, &unicorn));
+    }
+
+    #[test]
+    /// Checks the inverse binomial CDF at its extremes
+    fn inverse_cdf_bounds() {
+        assert_eq!(binomial_inverse_cdf(0.0, 10, 0.5), 0);
+        assert_eq!(binomial_inverse_cdf(0.999_999_999, 10, 0.5), 10);
+        assert_eq!(pow_u64(0.5, 3), 0.125);
+    }
+}
@@ -0,0 +1,478 @@
+//! Persistence of rounds and eval checkpoints.
+//!
+//! A `RoundStore` keeps every published round by id, along with checkpoints of evals that are
+//! still running, so that a node can pick up where it left off after a restart. `MemoryStore`
+//! keeps everything in memory for tests and short-lived nodes; with the `sled` feature,
+//! `SledStore` keeps it in an embedded database on disk. With the `sqlite` feature,
+//! `SqliteStore` keeps it in an SQLite database whose `rounds` table can be queried directly,
+//! e.g. `SELECT id, winner FROM rounds WHERE phase = 'Published'`.
+//!
+//! Rounds and checkpoints are stored as JSON, so that stored records can be inspected with
+//! the same tools as published artifacts.
+
+use crate::rounds::Round;
+use crate::unicorn::EvalState;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StorageError {
+    /// The storage backend failed.
+    Backend(String),
+    /// A stored record could not be decoded.
+    Corrupt(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Backend(e) => write!(f, "Storage backend error: {}", e),
+            StorageError::Corrupt(e) => write!(f, "Corrupt stored record: {}", e),
+        }
+    }
+}
+
+impl Error for StorageError {}
+
+/// Store of rounds and eval checkpoints
+pub trait RoundStore: Send {
+    /// Stores a round, replacing any stored round with the same id
+    ///
+    /// ### Arguments
+    ///
+    /// * `round` - Round to store
+    fn put_round(&mut self, round: &Round) -> Result<(), StorageError>;
+
+    /// A stored round, if there is one
+    ///
+    /// ### Arguments
+    ///
+    /// * `id` - Id of the round
+    fn get_round(&self, id: u64) -> Result<Option<Round>, StorageError>;
+
+    /// Ids of the stored rounds, in ascending order
+    fn list_rounds(&self) -> Result<Vec<u64>, StorageError>;
+
+    /// Stores the checkpoint of a round's eval, replacing any earlier one
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round being evaluated
+    /// * `state`    - State of the eval
+    fn put_checkpoint(&mut self, round_id: u64, state: &EvalState) -> Result<(), StorageError>;
+
+    /// The checkpoint of a round's eval, if there is one
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round being evaluated
+    fn get_checkpoint(&self, round_id: u64) -> Result<Option<EvalState>, StorageError>;
+
+    /// Removes the checkpoint of a round's eval, once it is no longer needed
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Id of the round that was evaluated
+    fn remove_checkpoint(&mut self, round_id: u64) -> Result<(), StorageError>;
+
+    /// Id of the latest stored round, if any
+    fn latest_round(&self) -> Result<Option<u64>, StorageError> {
+        Ok(self.list_rounds()?.last().copied())
+    }
+}
+
+/// Store keeping everything in memory
+#[derive(Default, Debug, Clone)]
+pub struct MemoryStore {
+    rounds: BTreeMap<u64, Round>,
+    checkpoints: BTreeMap<u64, EvalState>,
+}
+
+impl RoundStore for MemoryStore {
+    fn put_round(&mut self, round: &Round) -> Result<(), StorageError> {
+        self.rounds.insert(round.id, round.clone());
+        Ok(())
+    }
+
+    fn get_round(&self, id: u64) -> Result<Option<Round>, StorageError> {
+        Ok(self.rounds.get(&id).cloned())
+    }
+
+    fn list_rounds(&self) -> Result<Vec<u64>, StorageError> {
+        Ok(self.rounds.keys().copied().collect())
+    }
+
+    fn put_checkpoint(&mut self, round_id: u64, state: &EvalState) -> Result<(), StorageError> {
+        self.checkpoints.insert(round_id, state.clone());
+        Ok(())
+    }
+
+    fn get_checkpoint(&self, round_id: u64) -> Result<Option<EvalState>, StorageError> {
+        Ok(self.checkpoints.get(&round_id).cloned())
+    }
+
+    fn remove_checkpoint(&mut self, round_id: u64) -> Result<(), StorageError> {
+        self.checkpoints.remove(&round_id);
+        Ok(())
+    }
+}
+
+/// Store keeping rounds and checkpoints in a sled database
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledStore {
+    rounds: sled::Tree,
+    checkpoints: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledStore {
+    /// Opens or creates the database at a path
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Directory of the database
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, StorageError> {
+        Self::from_db(sled::open(path).map_err(backend)?)
+    }
+
+    /// Uses an opened database
+    ///
+    /// ### Arguments
+    ///
+    /// * `db` - Opened database
+    pub fn from_db(db: sled::Db) -> Result<Self, StorageError> {
+        Ok(Self {
+            rounds: db.open_tree("rounds").map_err(backend)?,
+            checkpoints: db.open_tree("checkpoints").map_err(backend)?,
+        })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl RoundStore for SledStore {
+    fn put_round(&mut self, round: &Round) -> Result<(), StorageError> {
+        self.rounds
+            .insert(round.id.to_be_bytes(), encode(round))
+            .map_err(backend)?;
+        self.rounds.flush().map_err(backend)?;
+        Ok(())
+    }
+
+    fn get_round(&self, id: u64) -> Result<Option<Round>, StorageError> {
+        match self.rounds.get(id.to_be_bytes()).map_err(backend)? {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn list_rounds(&self) -> Result<Vec<u64>, StorageError> {
+        // Big-endian keys iterate in ascending order
+        self.rounds
+            .iter()
+            .keys()
+            .map(|key| {
+                let key = key.map_err(backend)?;
+                let bytes = <[u8; 8]>::try_from(&key[..])
+                    .map_err(|_| StorageError::Corrupt("round key".to_string()))?;
+                Ok(u64::from_be_bytes(bytes))
+            })
+            .collect()
+    }
+
+    fn put_checkpoint(&mut self, round_id: u64, state: &EvalState) -> Result<(), StorageError> {
+        self.checkpoints
+            .insert(round_id.to_be_bytes(), encode(state))
+            .map_err(backend)?;
+        self.checkpoints.flush().map_err(backend)?;
+        Ok(())
+    }
+
+    fn get_checkpoint(&self, round_id: u64) -> Result<Option<EvalState>, StorageError> {
+        match self
+            .checkpoints
+            .get(round_id.to_be_bytes())
+            .map_err(backend)?
+        {
+            Some(bytes) => decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn remove_checkpoint(&mut self, round_id: u64) -> Result<(), StorageError> {
+        self.checkpoints
+            .remove(round_id.to_be_bytes())
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+/// Schema migrations of the SQLite store, applied in order. The number of migrations applied
+/// to a database is kept in its `user_version`
+#[cfg(feature = "sqlite")]
+const SQLITE_MIGRATIONS: &[&str] = &[
+    "CREATE TABLE rounds (
+        id INTEGER PRIMARY KEY,
+        phase TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        participants INTEGER NOT NULL,
+        g_value TEXT,
+        winner TEXT,
+        record TEXT NOT NULL
+    );
+    CREATE TABLE checkpoints (
+        round_id INTEGER PRIMARY KEY,
+        iterations_done INTEGER NOT NULL,
+        state TEXT NOT NULL
+    );",
+    "CREATE INDEX rounds_winner ON rounds (winner);",
+];
+
+/// Store keeping rounds and checkpoints in an SQLite database
+///
+/// Besides the full JSON `record`, each row of the `rounds` table holds the round's `phase`,
+/// `created_at` time, number of `participants` and, once it is published, its `g_value` and
+/// `winner`, so that historical rounds can be queried without decoding them.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Opens or creates the database at a path, migrating it to the current schema
+    ///
+    /// ### Arguments
+    ///
+    /// * `path` - Database file
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, StorageError> {
+        Self::from_connection(rusqlite::Connection::open(path).map_err(backend)?)
+    }
+
+    /// Creates a database in memory
+    pub fn in_memory() -> Result<Self, StorageError> {
+        Self::from_connection(rusqlite::Connection::open_in_memory().map_err(backend)?)
+    }
+
+    /// Uses an opened database, migrating it to the current schema
+    ///
+    /// ### Arguments
+    ///
+    /// * `conn` - Opened database
+    pub fn from_connection(mut conn: rusqlite::Connection) -> Result<Self, StorageError> {
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(backend)?;
+        if version > SQLITE_MIGRATIONS.len() {
+            return Err(StorageError::Backend(format!(
+                "database schema version {} is newer than this build supports",
+                version
+            )));
+        }
+
+        for (applied, migration) in SQLITE_MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction().map_err(backend)?;
+            tx.execute_batch(migration).map_err(backend)?;
+            tx.pragma_update(None, "user_version", applied + 1)
+                .map_err(backend)?;
+            tx.commit().map_err(backend)?;
+        }
+
+        Ok(Self { conn })
+    }
+
+    /// Version of the database schema, as the number of migrations applied
+    pub fn schema_version(&self) -> Result<usize, StorageError> {
+        self.conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(backend)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl RoundStore for SqliteStore {
+    fn put_round(&mut self, round: &Round) -> Result<(), StorageError> {
+        let transcript = round.transcript.as_ref();
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO rounds
+                    (id, phase, created_at, participants, g_value, winner, record)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    round.id,
+                    format!("{:?}", round.phase),
+                    round.created_at,
+                    round.registry.len(),
+                    transcript.map(|t| &t.g_value),
+                    transcript.map(|t| &t.winner),
+                    serde_json::to_string(round).unwrap(),
+                ],
+            )
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    fn get_round(&self, id: u64) -> Result<Option<Round>, StorageError> {
+        let record = self
+            .conn
+            .query_row("SELECT record FROM rounds WHERE id = ?1", [id], |row| {
+                row.get::<_, String>(0)
+            });
+        match record {
+            Ok(record) => decode(record.as_bytes()).map(Some),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    fn list_rounds(&self) -> Result<Vec<u64>, StorageError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id FROM rounds ORDER BY id")
+            .map_err(backend)?;
+        let ids = statement.query_map([], |row| row.get(0)).map_err(backend)?;
+
+        ids.map(|id| id.map_err(backend)).collect()
+    }
+
+    fn put_checkpoint(&mut self, round_id: u64, state: &EvalState) -> Result<(), StorageError> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO checkpoints (round_id, iterations_done, state)
+                 VALUES (?1, ?2, ?3)",
+                rusqlite::params![
+                    round_id,
+                    state.iterations_done,
+                    serde_json::to_string(state).unwrap(),
+                ],
+            )
+            .map_err(backend)?;
+        Ok(())
+    }
+
+    fn get_checkpoint(&self, round_id: u64) -> Result<Option<EvalState>, StorageError> {
+        let state = self.conn.query_row(
+            "SELECT state FROM checkpoints WHERE round_id = ?1",
+            [round_id],
+            |row| row.get::<_, String>(0),
+        );
+        match state {
+            Ok(state) => decode(state.as_bytes()).map(Some),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    fn remove_checkpoint(&mut self, round_id: u64) -> Result<(), StorageError> {
+        self.conn
+            .execute("DELETE FROM checkpoints WHERE round_id = ?1", [round_id])
+            .map_err(backend)?;
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "sled", feature = "sqlite"))]
+fn backend(e: impl fmt::Display) -> StorageError {
+    StorageError::Backend(e.to_string())
+}
+
+/// Encodes a stored record
+#[cfg(feature = "sled")]
+fn encode<T: serde::Serialize>(record: &T) -> Vec<u8> {
+    serde_json::to_vec(record).unwrap()
+}
+
+/// Decodes a stored record
+#[cfg(any(feature = "sled", feature = "sqlite"))]
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    serde_json::from_slice(bytes).map_err(|e| StorageError::Corrupt(e.to_string()))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod storage_tests {
+    use super::*;
+    use crate::rounds::RoundTimeouts;
+    use crate::unicorn::UnicornFixedParam;
+    use rug::Integer;
+
+    fn round(id: u64) -> Round {
+        Round::new(
+            id,
+            UnicornFixedParam::default(),
+            1,
+            RoundTimeouts::default(),
+            0,
+        )
+    }
+
+    /// Runs the same checks against any store
+    fn check_store(store: &mut dyn RoundStore) {
+        assert_eq!(store.latest_round(), Ok(None));
+
+        store.put_round(&round(2)).unwrap();
+        store.put_round(&round(1)).unwrap();
+        store.put_round(&round(300)).unwrap();
+        assert_eq!(store.list_rounds(), Ok(vec![1, 2, 300]));
+        assert_eq!(store.get_round(2), Ok(Some(round(2))));
+        assert_eq!(store.get_round(3), Ok(None));
+        assert_eq!(store.latest_round(), Ok(Some(300)));
+
+        let state = EvalState {
+            iterations_done: 10,
+            w: Integer::from(0x5eed),
+        };
+        store.put_checkpoint(300, &state).unwrap();
+        assert_eq!(store.get_checkpoint(300), Ok(Some(state)));
+        store.remove_checkpoint(300).unwrap();
+        assert_eq!(store.get_checkpoint(300), Ok(None));
+    }
+
+    #[test]
+    /// Checks the in-memory store
+    fn memory_store() {
+        check_store(&mut MemoryStore::default());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    /// Checks the sled store, including that rounds survive reopening
+    fn sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let mut store = SledStore::from_db(db.clone()).unwrap();
+        check_store(&mut store);
+
+        let reopened = SledStore::from_db(db).unwrap();
+        assert_eq!(reopened.list_rounds(), Ok(vec![1, 2, 300]));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    /// Checks the SQLite store, including that it is migrated and that its columns can be
+    /// queried
+    fn sqlite_store() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        assert_eq!(store.schema_version(), Ok(SQLITE_MIGRATIONS.len()));
+        check_store(&mut store);
+
+        let mut published = round(4);
+        published.transcript = Some(crate::transcript::SelectionTranscript {
+            winner: "miner_a".to_string(),
+            ..Default::default()
+        });
+        store.put_round(&published).unwrap();
+        let winner: String = store
+            .conn
+            .query_row("SELECT winner FROM rounds WHERE id = 4", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(winner, "miner_a");
+
+        let store = SqliteStore::from_connection(store.conn).unwrap();
+        assert_eq!(store.list_rounds(), Ok(vec![1, 2, 4, 300]));
+    }
+}
@@ -0,0 +1,362 @@
+//! Threshold BLS seeds, in the style of drand.
+//!
+//! As an alternative to composing the seed by hashing every contributor's secret, a group of
+//! N contributors can hold shares of a single BLS key on BLS12-381, any `threshold` of which
+//! can sign. For each round every contributor signs the round id with their share, and any
+//! `threshold` valid partial signatures are interpolated into the group signature. BLS
+//! signatures are unique, so the group signature is the same whichever shares took part, and
+//! up to `threshold - 1` colluding contributors can neither predict nor bias it. The seed is
+//! the SHA-256 of the group signature, as drand derives its randomness.
+//!
+//! Public keys are compressed G1 points and signatures compressed G2 points, both hex-encoded.
+//! The round id is signed as the SHA-256 of its big-endian bytes, hashed to G2 with the
+//! standard `BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_` suite. Shares are handed out by a
+//! trusted dealer with `deal`; a distributed key generation can produce the same
+//! `ThresholdKey` without one.
+
+use blstrs::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use ff::Field;
+use group::{Curve, Group};
+use rand_core::OsRng;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Domain separation tag for hashing messages to G2
+pub const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ThresholdError {
+    /// Threshold is zero or larger than the number of shares.
+    InvalidThreshold { threshold: usize, shares: usize },
+    /// A public key is not a valid compressed G1 point.
+    MalformedKey,
+    /// Fewer valid partial signatures than the threshold.
+    InsufficientPartials { valid: usize, threshold: usize },
+}
+
+impl fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThresholdError::InvalidThreshold { threshold, shares } => write!(
+                f,
+                "Threshold {} is invalid for {} shares",
+                threshold, shares
+            ),
+            ThresholdError::MalformedKey => write!(f, "Public key is not a valid G1 point"),
+            ThresholdError::InsufficientPartials { valid, threshold } => write!(
+                f,
+                "Only {} valid partial signatures; {} required",
+                valid, threshold
+            ),
+        }
+    }
+}
+
+impl Error for ThresholdError {}
+
+/// Public setup of a threshold group
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ThresholdKey {
+    /// Partial signatures needed for the group signature
+    pub threshold: usize,
+    /// Hex-encoded group public key
+    pub public_key: String,
+    /// Hex-encoded public key of each share, the first being share 1
+    pub public_shares: Vec<String>,
+}
+
+/// A contributor's share of the group secret key
+#[derive(Debug, Clone)]
+pub struct KeyShare {
+    /// Index of the share, starting at 1
+    pub index: u64,
+    secret: Scalar,
+}
+
+/// A contributor's signature of a round id with their share
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PartialSignature {
+    /// Index of the share that signed
+    pub index: u64,
+    /// Hex-encoded signature
+    pub signature: String,
+}
+
+/// Splits a fresh group secret into shares, any `threshold` of which can sign
+///
+/// ### Arguments
+///
+/// * `threshold` - Partial signatures needed for the group signature
+/// * `shares`    - Number of shares to hand out
+pub fn deal(
+    threshold: usize,
+    shares: usize,
+) -> Result<(ThresholdKey, Vec<KeyShare>), ThresholdError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ThresholdError::InvalidThreshold { threshold, shares });
+    }
+
+    let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(OsRng)).collect();
+    let key_shares: Vec<KeyShare> = (1..=shares as u64)
+        .map(|index| {
+            // Horner's rule for the polynomial at the share index
+            let x = Scalar::from(index);
+            let secret = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::ZERO, |acc, c| acc * x + c);
+            KeyShare { index, secret }
+        })
+        .collect();
+
+    let key = ThresholdKey {
+        threshold,
+        public_key: public_key(&coefficients[0]),
+        public_shares: key_shares.iter().map(|s| public_key(&s.secret)).collect(),
+    };
+    Ok((key, key_shares))
+}
+
+impl KeyShare {
+    /// Signs a round id with the share
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round to sign
+    pub fn sign(&self, round_id: u64) -> PartialSignature {
+        PartialSignature {
+            index: self.index,
+            signature: encode_signature(&(round_point(round_id) * self.secret)),
+        }
+    }
+}
+
+impl ThresholdKey {
+    /// Checks a partial signature of a round id against its public share
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round the partial signature is for
+    /// * `partial`  - Partial signature to check
+    pub fn verify_partial(&self, round_id: u64, partial: &PartialSignature) -> bool {
+        let share = (partial.index as usize)
+            .checked_sub(1)
+            .and_then(|i| self.public_shares.get(i));
+        match (
+            share.and_then(|s| decode_key(s)),
+            decode_signature(&partial.signature),
+        ) {
+            (Some(key), Some(signature)) => verifies(&key, round_id, &signature),
+            _ => false,
+        }
+    }
+
+    /// Interpolates the group signature of a round id from partial signatures. Partials from
+    /// unknown shares or that do not verify are skipped, so that no single contributor can
+    /// block the round, and repeated ones from the same share are counted once
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round the partial signatures are for
+    /// * `partials` - Partial signatures from at least `threshold` shares
+    pub fn aggregate(
+        &self,
+        round_id: u64,
+        partials: &[PartialSignature],
+    ) -> Result<String, ThresholdError> {
+        let mut valid = BTreeMap::new();
+        for partial in partials {
+            if !self.verify_partial(round_id, partial) {
+                continue;
+            }
+            let signature = decode_signature(&partial.signature).unwrap();
+            valid.insert(partial.index, G2Projective::from(signature));
+        }
+        if valid.len() < self.threshold {
+            return Err(ThresholdError::InsufficientPartials {
+                valid: valid.len(),
+                threshold: self.threshold,
+            });
+        }
+
+        let signers: Vec<(u64, G2Projective)> = valid.into_iter().take(self.threshold).collect();
+        let signature = signers
+            .iter()
+            .map(|(index, signature)| signature * lagrange_at_zero(*index, &signers))
+            .sum();
+        Ok(encode_signature(&signature))
+    }
+
+    /// Checks a group signature of a round id against the group public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id`  - Round the signature is for
+    /// * `signature` - Hex-encoded group signature
+    pub fn verify(&self, round_id: u64, signature: &str) -> bool {
+        match (decode_key(&self.public_key), decode_signature(signature)) {
+            (Some(key), Some(signature)) => verifies(&key, round_id, &signature),
+            _ => false,
+        }
+    }
+
+    /// Seed of a round from partial signatures of its id
+    ///
+    /// ### Arguments
+    ///
+    /// * `round_id` - Round the partial signatures are for
+    /// * `partials` - Partial signatures from at least `threshold` shares
+    pub fn seed(
+        &self,
+        round_id: u64,
+        partials: &[PartialSignature],
+    ) -> Result<Integer, ThresholdError> {
+        if decode_key(&self.public_key).is_none() {
+            return Err(ThresholdError::MalformedKey);
+        }
+        let signature = self.aggregate(round_id, partials)?;
+        Ok(seed_from_signature(&signature).unwrap())
+    }
+}
+
+/// Seed derived from a group signature, as the SHA-256 of its compressed bytes. Returns `None`
+/// if the signature is not hex
+///
+/// ### Arguments
+///
+/// * `signature` - Hex-encoded group signature
+pub fn seed_from_signature(signature: &str) -> Option<Integer> {
+    let bytes = hex::decode(signature).ok()?;
+    let digest = hex::encode(Sha256::digest(bytes));
+    Some(Integer::from_str_radix(&digest, 16).unwrap())
+}
+
+/// Point of G2 a round id is signed as
+fn round_point(round_id: u64) -> G2Projective {
+    let message = Sha256::digest(round_id.to_be_bytes());
+    G2Projective::hash_to_curve(&message, SIGNATURE_DST, &[])
+}
+
+/// Whether `e(g1, signature) == e(key, H(round_id))`
+fn verifies(key: &G1Affine, round_id: u64, signature: &G2Affine) -> bool {
+    let message = round_point(round_id).to_affine();
+    pairing(&G1Affine::from(G1Projective::generator()), signature) == pairing(key, &message)
+}
+
+/// Lagrange coefficient of a share for interpolating at zero
+fn lagrange_at_zero(index: u64, signers: &[(u64, G2Projective)]) -> Scalar {
+    let x = Scalar::from(index);
+    let (numerator, denominator) = signers
+        .iter()
+        .filter(|(other, _)| *other != index)
+        .map(|(other, _)| Scalar::from(*other))
+        .fold((Scalar::ONE, Scalar::ONE), |(num, den), other| {
+            (num * other, den * (other - x))
+        });
+    // Share indices are distinct and non-zero, so the denominator is invertible
+    numerator * denominator.invert().unwrap()
+}
+
+fn public_key(secret: &Scalar) -> String {
+    hex::encode(
+        (G1Projective::generator() * secret)
+            .to_affine()
+            .to_compressed(),
+    )
+}
+
+fn decode_key(key: &str) -> Option<G1Affine> {
+    let bytes: [u8; 48] = hex::decode(key).ok()?.try_into().ok()?;
+    Option::from(G1Affine::from_compressed(&bytes))
+}
+
+fn encode_signature(signature: &G2Projective) -> String {
+    hex::encode(signature.to_affine().to_compressed())
+}
+
+fn decode_signature(signature: &str) -> Option<G2Affine> {
+    let bytes: [u8; 96] = hex::decode(signature).ok()?.try_into().ok()?;
+    Option::from(G2Affine::from_compressed(&bytes))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod threshold_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that any threshold of shares yields the same verifying signature and seed
+    fn any_threshold_agrees() {
+        let (key, shares) = deal(3, 5).unwrap();
+        let partials: Vec<_> = shares.iter().map(|s| s.sign(9)).collect();
+
+        let first = key.aggregate(9, &partials[..3]).unwrap();
+        let last = key.aggregate(9, &partials[2..]).unwrap();
+        let spread = [
+            partials[4].clone(),
+            partials[0].clone(),
+            partials[2].clone(),
+        ];
+
+        assert_eq!(first, last);
+        assert_eq!(key.aggregate(9, &spread), Ok(first.clone()));
+        assert!(key.verify(9, &first));
+        assert!(!key.verify(10, &first));
+        assert_eq!(
+            key.seed(9, &partials),
+            Ok(seed_from_signature(&first).unwrap())
+        );
+        let next: Vec<_> = shares.iter().map(|s| s.sign(10)).collect();
+        assert_ne!(key.seed(10, &next), key.seed(9, &partials));
+    }
+
+    #[test]
+    /// Checks that forged or unknown partial signatures are skipped, and that too few valid
+    /// ones are refused
+    fn refuses_bad_partials() {
+        let (key, shares) = deal(2, 3).unwrap();
+        let partial = shares[0].sign(4);
+
+        assert_eq!(
+            deal(4, 3).err(),
+            Some(ThresholdError::InvalidThreshold {
+                threshold: 4,
+                shares: 3
+            })
+        );
+        assert_eq!(
+            key.aggregate(4, &[partial.clone(), partial.clone()]),
+            Err(ThresholdError::InsufficientPartials {
+                valid: 1,
+                threshold: 2
+            })
+        );
+
+        let mut forged = shares[1].sign(5);
+        forged.index = 2;
+        assert!(!key.verify_partial(4, &forged));
+        let mut unknown = shares[2].sign(4);
+        unknown.index = 7;
+        assert_eq!(
+            key.aggregate(4, &[partial.clone(), forged.clone(), unknown.clone()]),
+            Err(ThresholdError::InsufficientPartials {
+                valid: 1,
+                threshold: 2
+            })
+        );
+
+        // Invalid partials are skipped rather than blocking the round
+        let group = key
+            .aggregate(4, &[partial.clone(), shares[1].sign(4)])
+            .unwrap();
+        assert_eq!(
+            key.aggregate(4, &[forged, unknown, partial, shares[2].sign(4)]),
+            Ok(group)
+        );
+    }
+}
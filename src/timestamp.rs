@@ -0,0 +1,405 @@
+//! RFC 3161 timestamping of published rounds.
+//!
+//! When a round is published, the digest of its transcript can be sent to a time-stamping
+//! authority (TSA), whose signed token proves that the witness existed by the time the TSA
+//! attests. The token is kept in the transcript, so that a dispute about a late re-evaluation
+//! can be settled from the transcript alone.
+//!
+//! Requests and responses are DER, encoded and decoded here without an ASN.1 library: the
+//! request carries the SHA-256 imprint of the digest, a random nonce and asks for the TSA's
+//! certificate. A response is accepted if it was granted and its `TSTInfo` holds the same
+//! imprint and nonce. The token's CMS signature is not checked here; the token is kept as the
+//! TSA sent it so that it can be checked against the TSA's certificate with standard tooling,
+//! such as `openssl ts -verify`.
+//!
+//! Requests are sent over HTTP with the `timestamp` feature.
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// DER of the SHA-256 `AlgorithmIdentifier`
+const SHA256_ALGORITHM: [u8; 15] = [
+    0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+];
+
+/// Media type of time-stamp requests
+pub const REQUEST_CONTENT_TYPE: &str = "application/timestamp-query";
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const BOOLEAN: u8 = 0x01;
+const GENERALIZED_TIME: u8 = 0x18;
+const EXPLICIT_0: u8 = 0xa0;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TimestampError {
+    /// The response is not valid DER or not a time-stamp response.
+    Malformed,
+    /// The TSA refused the request with the given status.
+    Rejected { status: u8 },
+    /// The token is for another digest.
+    ImprintMismatch,
+    /// The token does not echo the request's nonce.
+    NonceMismatch,
+    /// The TSA could not be reached.
+    Http(String),
+}
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampError::Malformed => write!(f, "Malformed time-stamp response"),
+            TimestampError::Rejected { status } => {
+                write!(f, "Time-stamp request rejected with status {}", status)
+            }
+            TimestampError::ImprintMismatch => write!(f, "Time-stamp token is for another digest"),
+            TimestampError::NonceMismatch => write!(f, "Time-stamp token does not echo the nonce"),
+            TimestampError::Http(e) => write!(f, "Could not reach the TSA: {}", e),
+        }
+    }
+}
+
+impl Error for TimestampError {}
+
+/// Time-stamp token obtained for a round
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TimestampToken {
+    /// URL of the TSA that issued the token
+    pub tsa: String,
+    /// Hex-encoded digest the token was issued for
+    pub digest: String,
+    /// Time attested by the TSA, as a `GeneralizedTime` such as `20240102030405Z`
+    pub gen_time: String,
+    /// Hex-encoded DER of the token, as sent by the TSA
+    pub token: String,
+}
+
+impl TimestampToken {
+    /// Attested time in unix seconds, ignoring fractions of a second. Returns `None` if the
+    /// time is not a UTC `GeneralizedTime`
+    pub fn gen_time_unix(&self) -> Option<u64> {
+        generalized_time_unix(&self.gen_time)
+    }
+
+    /// Whether the token was issued for a digest, checked against the token itself rather
+    /// than the recorded digest
+    ///
+    /// ### Arguments
+    ///
+    /// * `digest` - Digest the token should attest
+    pub fn attests(&self, digest: &[u8; 32]) -> bool {
+        hex::decode(&self.token)
+            .ok()
+            .and_then(|token| tst_info(&token).map(|info| info.imprint == digest.as_slice()))
+            .unwrap_or(false)
+    }
+}
+
+/// Encodes a time-stamp request for a SHA-256 digest
+///
+/// ### Arguments
+///
+/// * `digest` - SHA-256 digest to timestamp
+/// * `nonce`  - Nonce the TSA must echo
+pub fn encode_request(digest: &[u8; 32], nonce: u64) -> Vec<u8> {
+    let mut imprint = SHA256_ALGORITHM.to_vec();
+    imprint.extend(tlv(OCTET_STRING, digest));
+
+    let mut request = tlv(INTEGER, &[1]);
+    request.extend(tlv(SEQUENCE, &imprint));
+    request.extend(tlv(INTEGER, &unsigned_integer(nonce)));
+    request.extend(tlv(BOOLEAN, &[0xff]));
+
+    tlv(SEQUENCE, &request)
+}
+
+/// Decodes a time-stamp response, checking that it was granted for the digest and nonce of
+/// the request
+///
+/// ### Arguments
+///
+/// * `response` - DER response from the TSA
+/// * `digest`   - SHA-256 digest that was timestamped
+/// * `nonce`    - Nonce of the request
+/// * `tsa`      - URL of the TSA
+pub fn decode_response(
+    response: &[u8],
+    digest: &[u8; 32],
+    nonce: u64,
+    tsa: &str,
+) -> Result<TimestampToken, TimestampError> {
+    let (_, body, _) = read(response, SEQUENCE).ok_or(TimestampError::Malformed)?;
+    let (_, status_info, rest) = read(body, SEQUENCE).ok_or(TimestampError::Malformed)?;
+    let (_, status, _) = read(status_info, INTEGER).ok_or(TimestampError::Malformed)?;
+    match status {
+        [0] | [1] => (),
+        [status] => return Err(TimestampError::Rejected { status: *status }),
+        _ => return Err(TimestampError::Malformed),
+    }
+
+    let token = rest;
+    read(token, SEQUENCE).ok_or(TimestampError::Malformed)?;
+    let info = tst_info(token).ok_or(TimestampError::Malformed)?;
+    if info.imprint != digest.as_slice() {
+        return Err(TimestampError::ImprintMismatch);
+    }
+    if info.nonce != Some(unsigned_integer(nonce).as_slice()) {
+        return Err(TimestampError::NonceMismatch);
+    }
+
+    Ok(TimestampToken {
+        tsa: tsa.to_string(),
+        digest: hex::encode(digest),
+        gen_time: String::from_utf8(info.gen_time.to_vec())
+            .map_err(|_| TimestampError::Malformed)?,
+        token: hex::encode(token),
+    })
+}
+
+/// Requests a time-stamp token for a digest from a TSA
+///
+/// ### Arguments
+///
+/// * `http`   - HTTP client to send the request with
+/// * `tsa`    - URL of the TSA
+/// * `digest` - SHA-256 digest to timestamp
+#[cfg(feature = "timestamp")]
+pub async fn request_timestamp(
+    http: &reqwest::Client,
+    tsa: &str,
+    digest: &[u8; 32],
+) -> Result<TimestampToken, TimestampError> {
+    let mut nonce = [0; 8];
+    getrandom::getrandom(&mut nonce).map_err(|e| TimestampError::Http(e.to_string()))?;
+    let nonce = u64::from_be_bytes(nonce);
+
+    let http_error = |e: reqwest::Error| TimestampError::Http(e.to_string());
+    let response = http
+        .post(tsa)
+        .header(reqwest::header::CONTENT_TYPE, REQUEST_CONTENT_TYPE)
+        .body(encode_request(digest, nonce))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(http_error)?
+        .bytes()
+        .await
+        .map_err(http_error)?;
+
+    decode_response(&response, digest, nonce, tsa)
+}
+
+/// Fields of a `TSTInfo` checked against the request
+struct TstInfo<'a> {
+    imprint: &'a [u8],
+    gen_time: &'a [u8],
+    nonce: Option<&'a [u8]>,
+}
+
+/// Finds the `TSTInfo` within a token: the content of its signed data's encapsulated content
+fn tst_info(token: &[u8]) -> Option<TstInfo<'_>> {
+    let (_, content_info, _) = read(token, SEQUENCE)?;
+    let (_, _, rest) = read(content_info, 0x06)?;
+    let (_, signed_data, _) = read(rest, EXPLICIT_0)?;
+    let (_, signed_data, _) = read(signed_data, SEQUENCE)?;
+    let (_, _, rest) = read(signed_data, INTEGER)?;
+    let (_, _, rest) = read(rest, 0x31)?;
+    let (_, encap, _) = read(rest, SEQUENCE)?;
+    let (_, _, rest) = read(encap, 0x06)?;
+    let (_, econtent, _) = read(rest, EXPLICIT_0)?;
+    let (_, info, _) = read(econtent, OCTET_STRING)?;
+
+    let (_, info, _) = read(info, SEQUENCE)?;
+    let (_, _, rest) = read(info, INTEGER)?;
+    let (_, _, rest) = read(rest, 0x06)?;
+    let (_, imprint, rest) = read(rest, SEQUENCE)?;
+    let (_, _, imprint) = read(imprint, SEQUENCE)?;
+    let (_, imprint, _) = read(imprint, OCTET_STRING)?;
+    let (_, _, rest) = read(rest, INTEGER)?;
+    let (_, gen_time, mut rest) = read(rest, GENERALIZED_TIME)?;
+
+    // Optional accuracy and ordering come before the nonce
+    let mut nonce = None;
+    while let Some((tag, value, next)) = next_tlv(rest) {
+        if tag == INTEGER {
+            nonce = Some(value);
+            break;
+        }
+        rest = next;
+    }
+
+    Some(TstInfo {
+        imprint,
+        gen_time,
+        nonce,
+    })
+}
+
+/// Reads a TLV with the expected tag, returning its tag, value and the bytes after it
+fn read(der: &[u8], tag: u8) -> Option<(u8, &[u8], &[u8])> {
+    next_tlv(der).filter(|(t, _, _)| *t == tag)
+}
+
+/// Reads the next TLV, returning its tag, value and the bytes after it
+fn next_tlv(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = der.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, &rest[count..])
+    };
+
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Encodes a TLV
+fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    match value.len() {
+        len if len < 0x80 => der.push(len as u8),
+        len => {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            der.push(0x80 | (bytes.len() - skip) as u8);
+            der.extend(&bytes[skip..]);
+        }
+    }
+    der.extend(value);
+    der
+}
+
+/// Minimal DER content of a non-negative integer
+fn unsigned_integer(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let mut content = bytes[skip..].to_vec();
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    content
+}
+
+/// Unix seconds of a UTC `GeneralizedTime`, ignoring fractions of a second
+fn generalized_time_unix(time: &str) -> Option<u64> {
+    let time = time.strip_suffix('Z')?;
+    let whole = time.split('.').next()?;
+    if whole.len() != 14 || !whole.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let field = |range: std::ops::Range<usize>| whole[range].parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // Days from the civil date, after Howard Hinnant's algorithm
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days * 86_400 + hour * 3_600 + minute * 60 + second).ok()
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod timestamp_tests {
+    use super::*;
+
+    /// Builds a granted response as a TSA would, for a digest and nonce
+    fn response(digest: &[u8; 32], nonce: u64, gen_time: &str) -> Vec<u8> {
+        let mut imprint = SHA256_ALGORITHM.to_vec();
+        imprint.extend(tlv(OCTET_STRING, digest));
+
+        let mut info = tlv(INTEGER, &[1]);
+        info.extend(tlv(0x06, &[0x2a, 0x03, 0x04]));
+        info.extend(tlv(SEQUENCE, &imprint));
+        info.extend(tlv(INTEGER, &[0x42]));
+        info.extend(tlv(GENERALIZED_TIME, gen_time.as_bytes()));
+        info.extend(tlv(SEQUENCE, &tlv(INTEGER, &[1])));
+        info.extend(tlv(INTEGER, &unsigned_integer(nonce)));
+
+        let mut encap = tlv(
+            0x06,
+            &[
+                0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04,
+            ],
+        );
+        encap.extend(tlv(EXPLICIT_0, &tlv(OCTET_STRING, &tlv(SEQUENCE, &info))));
+        let mut signed_data = tlv(INTEGER, &[3]);
+        signed_data.extend(tlv(0x31, &SHA256_ALGORITHM));
+        signed_data.extend(tlv(SEQUENCE, &encap));
+        signed_data.extend(tlv(0x31, &[0; 200]));
+
+        let mut token = tlv(
+            0x06,
+            &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02],
+        );
+        token.extend(tlv(EXPLICIT_0, &tlv(SEQUENCE, &signed_data)));
+
+        let mut body = tlv(SEQUENCE, &tlv(INTEGER, &[0]));
+        body.extend(tlv(SEQUENCE, &token));
+        tlv(SEQUENCE, &body)
+    }
+
+    #[test]
+    /// Checks that requests are valid DER and that granted responses are only accepted for
+    /// the requested digest and nonce
+    fn timestamp_round_trip() {
+        let digest = [7; 32];
+        let nonce = 0x8000_0000_0000_0001;
+
+        let request = encode_request(&digest, nonce);
+        let (_, body, rest) = read(&request, SEQUENCE).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(body.len(), 3 + 51 + 11 + 3);
+
+        let response = response(&digest, nonce, "20240102030405.5Z");
+        let token = decode_response(&response, &digest, nonce, "https://tsa.example").unwrap();
+        assert_eq!(token.gen_time, "20240102030405.5Z");
+        assert_eq!(token.gen_time_unix(), Some(1_704_164_645));
+        assert!(token.attests(&digest));
+        assert!(!token.attests(&[8; 32]));
+
+        assert_eq!(
+            decode_response(&response, &[8; 32], nonce, "https://tsa.example"),
+            Err(TimestampError::ImprintMismatch)
+        );
+        assert_eq!(
+            decode_response(&response, &digest, 1, "https://tsa.example"),
+            Err(TimestampError::NonceMismatch)
+        );
+        assert_eq!(
+            decode_response(&response[..40], &digest, nonce, "https://tsa.example"),
+            Err(TimestampError::Malformed)
+        );
+
+        let rejected = tlv(SEQUENCE, &tlv(SEQUENCE, &tlv(INTEGER, &[2])));
+        assert_eq!(
+            decode_response(&rejected, &digest, nonce, "https://tsa.example"),
+            Err(TimestampError::Rejected { status: 2 })
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Delay towers: a single long-running eval that emits an output every `k` iterations.
+//!
+//! Rather than starting a fresh eval for every round, a tower keeps iterating the same sloth
+//! chain from one seed and publishes a level every `k` iterations, `k` being the iterations of
+//! the tower's parameters. A level holds the iterated value after `height * k` iterations and
+//! its output, the `g` value (hash) of that state. Each level is the UNICORN of its
+//! predecessor's state run for `k` iterations, so it can be checked against the level below
+//! it in `k` fast squarings without replaying the chain.
+//!
+//! The iterated value at any level is also the witness of the tower seed after that many
+//! iterations, so a lottery can anchor to a level published after its inputs were fixed and
+//! draw from `anchor` like from any other evaluated UNICORN.
+
+use crate::unicorn::{g_from_witness, Unicorn, UnicornFixedParam, UnicornInfo};
+use crate::utils::rug_integer;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+
+/// A level of a delay tower
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TowerLevel {
+    /// Number of levels up to and including this one
+    pub height: u64,
+    /// Iterations from the tower seed to this level
+    pub iterations_done: u64,
+    /// Iterated value at this level
+    #[serde(with = "rug_integer")]
+    pub state: Integer,
+    /// `g` value of the state
+    pub output: String,
+}
+
+/// A delay tower over a seed
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct DelayTower {
+    /// Seed the chain starts from
+    #[serde(with = "rug_integer")]
+    pub seed: Integer,
+    /// UNICORN parameters; the iterations are those between levels
+    pub fixed_params: UnicornFixedParam,
+}
+
+impl DelayTower {
+    /// Creates a tower over a seed
+    ///
+    /// ### Arguments
+    ///
+    /// * `seed`         - Seed the chain starts from
+    /// * `fixed_params` - UNICORN parameters, with the iterations between levels
+    pub fn new(seed: Integer, fixed_params: UnicornFixedParam) -> Self {
+        Self { seed, fixed_params }
+    }
+
+    /// Builds levels on top of the given one, or from the seed, until `emit` returns `false`
+    /// for a level or the iteration count would overflow. Returns the last level built, or
+    /// `None` if none was, as when the parameters are invalid
+    ///
+    /// ### Arguments
+    ///
+    /// * `from` - Level to build on, or `None` to start from the seed
+    /// * `emit` - Called with each level as it is built; returns whether to continue
+    pub fn run(
+        &self,
+        from: Option<TowerLevel>,
+        mut emit: impl FnMut(&TowerLevel) -> bool,
+    ) -> Option<TowerLevel> {
+        let mut last = None;
+        let mut below = from;
+
+        loop {
+            let Some(level) = self.next_level(below.as_ref()) else {
+                return last;
+            };
+            let more = emit(&level);
+            below = Some(level.clone());
+            last = Some(level);
+            if !more {
+                return last;
+            }
+        }
+    }
+
+    /// Builds the level on top of the given one, or the first level
+    ///
+    /// ### Arguments
+    ///
+    /// * `below` - Level to build on, or `None` to start from the seed
+    pub fn next_level(&self, below: Option<&TowerLevel>) -> Option<TowerLevel> {
+        let (height, iterations_done) = self.position(below)?;
+        let (state, output) = self.step(below).eval()?;
+
+        Some(TowerLevel {
+            height,
+            iterations_done,
+            state,
+            output,
+        })
+    }
+
+    /// Checks a level against the one below it, or against the seed for the first level
+    ///
+    /// ### Arguments
+    ///
+    /// * `below` - Level below, or `None` if `level` is the first
+    /// * `level` - Level to check
+    pub fn verify_level(&self, below: Option<&TowerLevel>, level: &TowerLevel) -> bool {
+        let step = self.step(below);
+
+        self.position(below) == Some((level.height, level.iterations_done))
+            && level.output == g_from_witness(&level.state)
+            && step.verify_ref(&step.seed, &level.state)
+    }
+
+    /// Checks a run of consecutive levels, the first being built on `below`
+    ///
+    /// ### Arguments
+    ///
+    /// * `below`  - Level below the first, or `None` if the run starts from the seed
+    /// * `levels` - Consecutive levels
+    pub fn verify_levels(&self, below: Option<&TowerLevel>, levels: &[TowerLevel]) -> bool {
+        let mut below = below;
+        for level in levels {
+            if !self.verify_level(below, level) {
+                return false;
+            }
+            below = Some(level);
+        }
+
+        true
+    }
+
+    /// Evaluated UNICORN of the tower seed up to a level, for a lottery anchored to it
+    ///
+    /// ### Arguments
+    ///
+    /// * `level` - Level the lottery is anchored to
+    pub fn anchor(&self, level: &TowerLevel) -> UnicornInfo {
+        let unicorn = self.unicorn(self.seed.clone(), level.iterations_done);
+        UnicornInfo::new(unicorn, level.state.clone(), level.output.clone())
+    }
+
+    /// Height and iteration count of the level above `below`, if the tower can grow
+    fn position(&self, below: Option<&TowerLevel>) -> Option<(u64, u64)> {
+        if self.fixed_params.iterations == 0 {
+            return None;
+        }
+        let (height, iterations_done) = below.map_or((0, 0), |b| (b.height, b.iterations_done));
+        Some((
+            height.checked_add(1)?,
+            iterations_done.checked_add(self.fixed_params.iterations)?,
+        ))
+    }
+
+    /// UNICORN taking the level below to the next one
+    fn step(&self, below: Option<&TowerLevel>) -> Unicorn {
+        let seed = below.map_or_else(|| self.seed.clone(), |b| b.state.clone());
+        self.unicorn(seed, self.fixed_params.iterations)
+    }
+
+    fn unicorn(&self, seed: Integer, iterations: u64) -> Unicorn {
+        Unicorn {
+            seed,
+            modulus: Integer::from_str_radix(&self.fixed_params.modulus, 10).unwrap_or_default(),
+            iterations,
+            security_level: self.fixed_params.security,
+            primality_rounds: self.fixed_params.primality_rounds,
+        }
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod tower_tests {
+    use super::*;
+    use crate::unicorn::construct_seed;
+
+    fn tower() -> DelayTower {
+        let params = UnicornFixedParam {
+            iterations: 50,
+            ..UnicornFixedParam::test()
+        };
+        DelayTower::new(construct_seed(&["miner_a".to_string()]), params)
+    }
+
+    #[test]
+    /// Checks that levels match a single eval of the whole chain, verify link by link and
+    /// resume from any level
+    fn levels_chain() {
+        let tower = tower();
+        let mut levels = Vec::new();
+        tower.run(None, |level| {
+            levels.push(level.clone());
+            levels.len() < 4
+        });
+
+        let anchored = tower.anchor(&levels[3]);
+        let (witness, g_value) = anchored.unicorn.eval().unwrap();
+        assert_eq!(levels[3].iterations_done, 200);
+        assert_eq!(
+            (witness, g_value),
+            (levels[3].state.clone(), levels[3].output.clone())
+        );
+        assert!(anchored.unicorn.verify_ref(&tower.seed, &levels[3].state));
+        assert!(tower.verify_levels(None, &levels));
+
+        let resumed = tower.run(Some(levels[1].clone()), |level| level.height < 4);
+        assert_eq!(resumed.as_ref(), levels.last());
+    }
+
+    #[test]
+    /// Checks that levels out of place or with a wrong output are refused
+    fn refuses_bad_levels() {
+        let tower = tower();
+        let first = tower.next_level(None).unwrap();
+        let second = tower.next_level(Some(&first)).unwrap();
+
+        assert!(tower.verify_level(Some(&first), &second));
+        assert!(!tower.verify_level(None, &second));
+
+        let mut relabelled = second.clone();
+        relabelled.height = 3;
+        assert!(!tower.verify_level(Some(&first), &relabelled));
+
+        let mut wrong = second.clone();
+        wrong.output = first.output.clone();
+        assert!(!tower.verify_levels(None, &[first, wrong]));
+    }
+}
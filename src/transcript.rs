@@ -0,0 +1,489 @@
+//! Selection transcripts, recording every input and intermediate value of a lottery draw.
+//!
+//! A transcript holds the public key inputs, the winning PoW hashes bound into the seed, the
+//! fixed UNICORN parameters, the seed and its commitment, the witness and `g` value, the PRN
+//! bytes drawn for the usage number and the resulting winner, along with the usage numbers
+//! handed out for other draws from the same UNICORN. When the seed commits to a Merkle root of
+//! the inputs instead of the full list, the root is recorded too and inclusion proofs can be
+//! produced for the winner. It serializes to pretty JSON so that a disputed draw can be handed
+//! to an auditor, who can then replay it step by step without trusting the party that ran it.
+
+use crate::merkle::{construct_seed_with_root, InclusionProof, MerkleTree};
+use crate::roughtime::ClockAttestation;
+use crate::rounds::{Contribution, GracePolicy};
+use crate::selection::uniform_index;
+use crate::timestamp::TimestampToken;
+use crate::unicorn::{self, g_matches, SeedMode, UnicornFixedParam, UnicornInfo};
+use crate::usage::{UsageAllocation, UsageAllocator};
+use crate::utils::rug_integer;
+use crate::utils::unicorn_selection::{get_unicorn_csprng, get_unicorn_prn_bytes};
+use bincode::serialize;
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Steps of a draw, in the order they are checked during replay
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum TranscriptStep {
+    Seed,
+    Commitment,
+    Witness,
+    GValue,
+    UsageNumber,
+    PrnBytes,
+    Winner,
+}
+
+/// Full record of a single lottery draw
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SelectionTranscript {
+    /// Public key inputs of the draw, in seed order
+    pub inputs: Vec<String>,
+    /// Hashes of the winning PoWs from 2 blocks ago, bound into the seed
+    #[serde(default)]
+    pub last_winning_hashes: Vec<String>,
+    /// UNICORN parameters used for the eval
+    pub fixed_params: UnicornFixedParam,
+    /// Seed constructed from the inputs
+    #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub seed: Integer,
+    /// Commitment value `c` for the seed
+    pub commitment: String,
+    /// Witness produced by the eval
+    #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
+    pub witness: Integer,
+    /// `g` value derived from the witness
+    pub g_value: String,
+    /// Usage number of the PRN draw
+    pub usage_number: u128,
+    /// Hex-encoded PRN bytes drawn from Fortuna
+    pub prn_bytes: String,
+    /// Index of the winner within `inputs`
+    pub winner_index: usize,
+    /// Winning input
+    pub winner: String,
+    /// Usage numbers handed out for draws from the same UNICORN, if recorded
+    #[serde(default)]
+    pub usage_allocations: Vec<UsageAllocation>,
+    /// Merkle root of the inputs, if the seed commits to the root rather than the full list
+    #[serde(default)]
+    pub merkle_root: Option<String>,
+    /// How the seed was built from the inputs, when it does not commit to a Merkle root
+    #[serde(default)]
+    pub seed_mode: SeedMode,
+    /// RFC 3161 time-stamp token for the publication digest, if one was obtained
+    #[serde(default)]
+    pub timestamp: Option<TimestampToken>,
+    /// Attested clock reading the round was sealed at, if its cutoff was anchored to one.
+    /// Its nonce commits to the inputs and the last winning PoW hashes
+    #[serde(default)]
+    pub cutoff_attestation: Option<ClockAttestation>,
+    /// Time after which contributions to the round were late
+    #[serde(default)]
+    pub cutoff: u64,
+    /// How the round treated late contributions
+    #[serde(default)]
+    pub grace: GracePolicy,
+    /// Contributions accepted after the cutoff of the round they were made for, with their
+    /// recorded timestamps: those flagged as late, and those carried over from the previous
+    /// round
+    #[serde(default)]
+    pub late_contributions: Vec<Contribution>,
+}
+
+impl SelectionTranscript {
+    /// Runs a full draw over the inputs and records every step. Returns `None` if there
+    /// are no inputs or the UNICORN could not be evaluated
+    ///
+    /// ### Arguments
+    ///
+    /// * `inputs`       - Public key inputs for the draw
+    /// * `fixed_params` - UNICORN parameters to use
+    /// * `usage_number` - Usage number of the PRN draw
+    pub fn record(
+        inputs: &[String],
+        fixed_params: &UnicornFixedParam,
+        usage_number: u128,
+    ) -> Option<Self> {
+        Self::record_with_pow(inputs, &[], fixed_params, usage_number)
+    }
+
+    /// Runs a full draw over the inputs and the last winning PoW hashes, and records every
+    /// step. Returns `None` if there are no inputs or the UNICORN could not be evaluated
+    ///
+    /// ### Arguments
+    ///
+    /// * `inputs`              - Public key inputs for the draw
+    /// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+    /// * `fixed_params`        - UNICORN parameters to use
+    /// * `usage_number`        - Usage number of the PRN draw
+    pub fn record_with_pow(
+        inputs: &[String],
+        last_winning_hashes: &[String],
+        fixed_params: &UnicornFixedParam,
+        usage_number: u128,
+    ) -> Option<Self> {
+        if inputs.is_empty() {
+            return None;
+        }
+
+        let seed = unicorn::construct_seed_with_pow(inputs, last_winning_hashes);
+        let modulus = Integer::from_str_radix(&fixed_params.modulus, 10).ok()?;
+        let uni = unicorn::Unicorn {
+            seed,
+            modulus,
+            iterations: fixed_params.iterations,
+            security_level: fixed_params.security,
+            primality_rounds: fixed_params.primality_rounds,
+        };
+        let (witness, g_value) = uni.eval()?;
+
+        let info = UnicornInfo::new(uni, witness, g_value);
+
+        Self::from_unicorn(
+            inputs,
+            last_winning_hashes,
+            fixed_params,
+            &info,
+            usage_number,
+        )
+    }
+
+    /// Records a draw from an already evaluated UNICORN. Returns `None` if there are no
+    /// inputs to draw from or the `g` value is too short to key the CSPRNG
+    ///
+    /// ### Arguments
+    ///
+    /// * `inputs`              - Public key inputs the UNICORN was seeded with
+    /// * `last_winning_hashes` - PoW hashes the UNICORN was seeded with
+    /// * `fixed_params`        - UNICORN parameters used for the eval
+    /// * `info`                - Evaluated UNICORN
+    /// * `usage_number`        - Usage number of the PRN draw
+    pub fn from_unicorn(
+        inputs: &[String],
+        last_winning_hashes: &[String],
+        fixed_params: &UnicornFixedParam,
+        info: &UnicornInfo,
+        usage_number: u128,
+    ) -> Option<Self> {
+        if inputs.is_empty() {
+            return None;
+        }
+
+        let prn_bytes = get_unicorn_prn_bytes(info, usage_number)?;
+        let winner_index = winner_index(info, usage_number, inputs.len())?;
+
+        Some(Self {
+            inputs: inputs.to_vec(),
+            last_winning_hashes: last_winning_hashes.to_vec(),
+            fixed_params: fixed_params.clone(),
+            seed: info.unicorn.seed.clone(),
+            commitment: info.unicorn.commitment(),
+            witness: info.witness.clone(),
+            g_value: info.g_value.clone(),
+            usage_number,
+            prn_bytes: hex::encode(prn_bytes),
+            winner_index,
+            winner: inputs[winner_index].clone(),
+            usage_allocations: Vec::new(),
+            merkle_root: None,
+            seed_mode: SeedMode::Serialized,
+            timestamp: None,
+            cutoff_attestation: None,
+            cutoff: 0,
+            grace: GracePolicy::default(),
+            late_contributions: Vec::new(),
+        })
+    }
+
+    /// Records the usage numbers handed out for draws from the same UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `allocator` - Allocator the usage numbers were handed out by
+    pub fn with_usage_allocations(mut self, allocator: &UsageAllocator) -> Self {
+        self.usage_allocations = allocator.allocations().to_vec();
+        self
+    }
+
+    /// Seed expected for the recorded inputs: committing to their Merkle root if one is
+    /// recorded, or to the full list in the recorded seed mode otherwise
+    pub fn expected_seed(&self) -> Integer {
+        match &self.merkle_root {
+            Some(_) => construct_seed_with_root(
+                &MerkleTree::new(&self.inputs).root(),
+                &self.last_winning_hashes,
+            ),
+            None => unicorn::construct_seed_with_mode(
+                self.seed_mode,
+                &self.inputs,
+                &self.last_winning_hashes,
+            ),
+        }
+    }
+
+    /// Creates the proof that the winner is included under the recorded Merkle root. Returns
+    /// `None` if the seed does not commit to a Merkle root
+    pub fn winner_proof(&self) -> Option<InclusionProof> {
+        self.merkle_root.as_ref()?;
+        MerkleTree::new(&self.inputs).prove_inclusion(&self.winner)
+    }
+
+    /// Digest of the transcript as published, which a time-stamp token attests: the SHA-256
+    /// of its bincode encoding without the token
+    pub fn publication_digest(&self) -> [u8; 32] {
+        let mut published = self.clone();
+        published.timestamp = None;
+        Sha256::digest(serialize(&published).unwrap()).into()
+    }
+
+    /// Serializes the transcript to pretty JSON
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Deserializes a transcript from JSON
+    ///
+    /// ### Arguments
+    ///
+    /// * `json` - JSON produced by `to_json_pretty`
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Replays the draw step by step from the recorded inputs, returning the first step
+    /// whose recorded value does not match. The eval is re-run in full, so replay takes as
+    /// long as the original draw
+    pub fn replay(&self) -> Result<(), TranscriptStep> {
+        self.check_steps(|uni, witness| matches!(uni.eval(), Some((w, _)) if w == *witness))
+    }
+
+    /// Checks the draw step by step like `replay`, but checks the witness with the fast
+    /// `verify` path instead of re-running the eval
+    pub fn verify(&self) -> Result<(), TranscriptStep> {
+        self.check_steps(|uni, witness| uni.verify_ref(&uni.seed, witness))
+    }
+
+    /// Checks every step of the draw, with `witness_ok` deciding whether the witness belongs
+    /// to the UNICORN
+    fn check_steps(
+        &self,
+        witness_ok: impl FnOnce(&unicorn::Unicorn, &Integer) -> bool,
+    ) -> Result<(), TranscriptStep> {
+        let seed = self.expected_seed();
+        let root_matches = match &self.merkle_root {
+            Some(root) => *root == MerkleTree::new(&self.inputs).root(),
+            None => true,
+        };
+        if seed != self.seed || !root_matches {
+            return Err(TranscriptStep::Seed);
+        }
+
+        let modulus = Integer::from_str_radix(&self.fixed_params.modulus, 10)
+            .map_err(|_| TranscriptStep::Witness)?;
+        let uni = unicorn::Unicorn {
+            seed,
+            modulus,
+            iterations: self.fixed_params.iterations,
+            security_level: self.fixed_params.security,
+            primality_rounds: self.fixed_params.primality_rounds,
+        };
+
+        if uni.commitment() != self.commitment {
+            return Err(TranscriptStep::Commitment);
+        }
+
+        if !witness_ok(&uni, &self.witness) {
+            return Err(TranscriptStep::Witness);
+        }
+
+        if !g_matches(&self.witness, &self.g_value) {
+            return Err(TranscriptStep::GValue);
+        }
+
+        let info = UnicornInfo::new(uni, self.witness.clone(), self.g_value.clone());
+        if !self.usage_allocations.is_empty() && !self.usage_number_allocated() {
+            return Err(TranscriptStep::UsageNumber);
+        }
+
+        get_unicorn_prn_bytes(&info, self.usage_number)
+            .filter(|bytes| hex::encode(bytes) == self.prn_bytes)
+            .ok_or(TranscriptStep::PrnBytes)?;
+
+        if self.inputs.is_empty() {
+            return Err(TranscriptStep::Winner);
+        }
+
+        let winner_index = winner_index(&info, self.usage_number, self.inputs.len())
+            .ok_or(TranscriptStep::PrnBytes)?;
+        if winner_index != self.winner_index || self.inputs[winner_index] != self.winner {
+            return Err(TranscriptStep::Winner);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the usage number is among the recorded allocations, each handed out once
+    fn usage_number_allocated(&self) -> bool {
+        let mut allocator = UsageAllocator::new();
+        self.usage_allocations
+            .iter()
+            .all(|a| allocator.claim(a.usage_number).is_ok())
+            && allocator.contains(self.usage_number)
+    }
+}
+
+/// Index of the winner among `len` inputs, drawn as `selection::select` draws among
+/// unweighted participants. Returns `None` if the `g` value is too short to key the CSPRNG
+///
+/// ### Arguments
+///
+/// * `info`         - Evaluated UNICORN
+/// * `usage_number` - Usage number of the PRN draw
+/// * `len`          - Number of inputs, at least one
+pub(crate) fn winner_index(info: &UnicornInfo, usage_number: u128, len: usize) -> Option<usize> {
+    let mut csprng = get_unicorn_csprng(info, usage_number)?;
+    Some(uniform_index(&mut csprng, len))
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod transcript_tests {
+    use super::*;
+
+    fn test_inputs() -> Vec<String> {
+        vec![
+            "miner_a".to_string(),
+            "miner_b".to_string(),
+            "miner_c".to_string(),
+        ]
+    }
+
+    #[test]
+    /// Checks that a recorded transcript survives a JSON round trip and replays cleanly
+    fn record_and_replay() {
+        let transcript =
+            SelectionTranscript::record(&test_inputs(), &UnicornFixedParam::test(), 1).unwrap();
+
+        let json = transcript.to_json_pretty().unwrap();
+        let restored = SelectionTranscript::from_json(&json).unwrap();
+
+        assert_eq!(restored, transcript);
+        assert_eq!(restored.replay(), Ok(()));
+        assert_eq!(restored.verify(), Ok(()));
+    }
+
+    #[test]
+    /// Checks that the transcript's winner is the one `selection::select` draws from the same
+    /// UNICORN and usage number
+    fn winner_matches_select() {
+        let inputs: Vec<String> = (0..7).map(|i| format!("miner_{}", i)).collect();
+        let seed = unicorn::construct_seed(&inputs);
+        let info = unicorn::construct_unicorn(seed, &UnicornFixedParam::test());
+
+        for usage_number in 0..32 {
+            let transcript = SelectionTranscript::from_unicorn(
+                &inputs,
+                &[],
+                &UnicornFixedParam::test(),
+                &info,
+                usage_number,
+            )
+            .unwrap();
+            let (index, winner) = crate::selection::select(&info, usage_number, &inputs).unwrap();
+
+            assert_eq!(transcript.winner_index, index);
+            assert_eq!(&transcript.winner, winner);
+        }
+    }
+
+    #[test]
+    /// Checks that replay pinpoints tampered steps
+    fn replay_detects_tampering() {
+        let transcript =
+            SelectionTranscript::record(&test_inputs(), &UnicornFixedParam::test(), 1).unwrap();
+
+        let mut bad_witness = transcript.clone();
+        bad_witness.witness = Integer::from(8);
+
+        let mut bad_winner = transcript.clone();
+        bad_winner.winner_index = (transcript.winner_index + 1) % transcript.inputs.len();
+
+        let mut bad_inputs = transcript;
+        bad_inputs.inputs.push("miner_d".to_string());
+
+        assert_eq!(bad_witness.replay(), Err(TranscriptStep::Witness));
+        assert_eq!(bad_witness.verify(), Err(TranscriptStep::Witness));
+        assert_eq!(bad_winner.replay(), Err(TranscriptStep::Winner));
+        assert_eq!(bad_inputs.replay(), Err(TranscriptStep::Seed));
+    }
+
+    #[test]
+    /// Checks that the PoW hashes are bound into the replayed seed
+    fn replay_binds_pow_hashes() {
+        let hashes = vec!["00ab".to_string(), "00cd".to_string()];
+        let transcript = SelectionTranscript::record_with_pow(
+            &test_inputs(),
+            &hashes,
+            &UnicornFixedParam::test(),
+            1,
+        )
+        .unwrap();
+
+        let mut dropped = transcript.clone();
+        dropped.last_winning_hashes.pop();
+
+        assert_eq!(transcript.replay(), Ok(()));
+        assert_eq!(dropped.replay(), Err(TranscriptStep::Seed));
+    }
+
+    #[test]
+    /// Checks that replay refuses usage numbers that were not allocated or were reused
+    fn replay_checks_usage_allocations() {
+        let mut allocator = UsageAllocator::new();
+        let usage = allocator.allocate(crate::usage::WINNER_NAMESPACE);
+        let transcript =
+            SelectionTranscript::record(&test_inputs(), &UnicornFixedParam::test(), usage)
+                .unwrap()
+                .with_usage_allocations(&allocator);
+
+        let mut reused = transcript.clone();
+        reused
+            .usage_allocations
+            .push(transcript.usage_allocations[0]);
+        let mut missing = transcript.clone();
+        missing.usage_allocations[0].usage_number = 2;
+
+        assert_eq!(transcript.replay(), Ok(()));
+        assert_eq!(reused.replay(), Err(TranscriptStep::UsageNumber));
+        assert_eq!(missing.replay(), Err(TranscriptStep::UsageNumber));
+    }
+
+    #[test]
+    /// Checks that a draw without inputs is rejected
+    fn record_without_inputs() {
+        assert_eq!(
+            SelectionTranscript::record(&[], &UnicornFixedParam::test(), 1),
+            None
+        );
+
+        let transcript =
+            SelectionTranscript::record(&test_inputs(), &UnicornFixedParam::test(), 1).unwrap();
+        let uni = unicorn::Unicorn {
+            seed: transcript.seed,
+            modulus: Integer::from_str_radix(&UnicornFixedParam::test().modulus, 10).unwrap(),
+            iterations: 1_000,
+            security_level: 1,
+            ..Default::default()
+        };
+        let info = UnicornInfo::new(uni, transcript.witness, transcript.g_value);
+        assert_eq!(
+            SelectionTranscript::from_unicorn(&[], &[], &UnicornFixedParam::test(), &info, 1),
+            None
+        );
+    }
+}
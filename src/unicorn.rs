@@ -15,33 +15,193 @@
 //!
 //! Given the seed and witness values, anybody is able to verify the authenticity of the number
 //! generated.
+//!
+//! Iteration counts may be large enough for multi-hour evals, up to `u64::MAX`. An eval
+//! reports its state every `PROGRESS_INTERVAL` iterations so that it can be checkpointed and
+//! resumed with `eval_from`, and no count arithmetic can overflow. `verify` runs the same
+//! count of iterations as the eval, each a squaring instead of a square root, so its cost
+//! also grows linearly with the count: when choosing a count for a long eval, check the
+//! verify time it implies too, with `calibrate::Benchmark::verify_time`.
 
+use crate::fortuna::Fortuna;
 use crate::utils::rug_integer;
 use bincode::serialize;
 use rug::integer::{IsPrime, Order};
-use rug::Integer;
+use rug::ops::SubFrom;
+use rug::{Assign, Integer};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tracing::error;
+use zeroize::Zeroize;
 
-/// Number of rounds for Miller Rabin primality testing
+/// Default number of rounds for Miller Rabin primality testing
 pub const MR_PRIME_ITERS: u32 = 15;
 
+/// Primality rounds of parameters written before they were configurable
+fn default_primality_rounds() -> u32 {
+    MR_PRIME_ITERS
+}
+
+/// Number of eval iterations between progress reports
+pub const PROGRESS_INTERVAL: u64 = 1_024;
+
 /// Constructs the seed for a new lottery Unicorn
 ///
 /// ### Arguments
 ///
-/// * `tx_inputs` - Input transactions
-/// * `participant_list` - List of miners participating in block round
+/// * `public_key_inputs` - Public keys of the miners participating in the block round
+pub fn construct_seed(public_key_inputs: &[String]) -> Integer {
+    // Transaction inputs (sOot)
+    let pki = hex::encode(Sha256::digest(serialize(public_key_inputs).unwrap()));
+    Integer::from_str_radix(&pki, 16).unwrap()
+}
+
+/// Constructs the seed for a new lottery Unicorn, binding the hashes of the winning PoWs
+/// from 2 blocks ago alongside the public key inputs. Without any hashes the seed is the
+/// same as the one from `construct_seed`
+///
+/// ### Arguments
+///
+/// * `public_key_inputs`   - Public keys of the miners participating in the block round
 /// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
-pub fn construct_seed(
-    public_key_inputs: &[String]
+pub fn construct_seed_with_pow(
+    public_key_inputs: &[String],
+    last_winning_hashes: &[String],
 ) -> Integer {
-    // Transaction inputs (sOot)
-    let pki = hex::encode(Sha256::digest(&serialize(public_key_inputs).unwrap()));
+    let preimage = seed_preimage(SeedMode::Serialized, public_key_inputs, last_winning_hashes);
+    let pki = hex::encode(Sha256::digest(preimage));
     Integer::from_str_radix(&pki, 16).unwrap()
 }
 
+/// Inputs above which the digests of a tree-hashed seed are computed across threads
+pub const TREE_HASH_PARALLEL_MIN: usize = 4_096;
+
+/// How a seed is built from its inputs
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SeedMode {
+    /// Hash of the whole serialized input list
+    #[default]
+    Serialized,
+    /// Hash of the sorted digests of each input, so that each input can be committed to on
+    /// its own and large lists are hashed in parallel
+    TreeHash,
+}
+
+/// Digest committing to a single seed input, as hashed into a tree-hashed seed
+///
+/// ### Arguments
+///
+/// * `input` - Seed input
+pub fn input_digest(input: &str) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([0])
+        .chain_update(input.as_bytes())
+        .finalize()
+        .into()
+}
+
+/// Digests of the seed inputs in sorted order, computed across threads for large lists.
+/// SHA-256 itself uses the CPU's SHA extensions where they are available
+///
+/// ### Arguments
+///
+/// * `inputs` - Seed inputs
+pub fn input_digests(inputs: &[String]) -> Vec<[u8; 32]> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut digests: Vec<[u8; 32]> = if inputs.len() < TREE_HASH_PARALLEL_MIN || threads == 1 {
+        inputs.iter().map(|i| input_digest(i)).collect()
+    } else {
+        let chunk = inputs.len().div_ceil(threads);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = inputs
+                .chunks(chunk)
+                .map(|c| scope.spawn(move || c.iter().map(|i| input_digest(i)).collect::<Vec<_>>()))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        })
+    };
+
+    digests.sort_unstable();
+    digests
+}
+
+/// Constructs a seed from the sorted digests of its inputs rather than the serialized list,
+/// binding the hashes of the winning PoWs from 2 blocks ago alongside them. The seed does not
+/// depend on the order of the inputs
+///
+/// ### Arguments
+///
+/// * `public_key_inputs`   - Public keys of the miners participating in the block round
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn construct_seed_tree(
+    public_key_inputs: &[String],
+    last_winning_hashes: &[String],
+) -> Integer {
+    let preimage = seed_preimage(SeedMode::TreeHash, public_key_inputs, last_winning_hashes);
+    let seed = hex::encode(Sha256::digest(preimage));
+    Integer::from_str_radix(&seed, 16).unwrap()
+}
+
+/// Exact bytes hashed into a seed built in the given mode, whose SHA-256 is the seed
+///
+/// ### Arguments
+///
+/// * `mode`                - How the seed is built from its inputs
+/// * `public_key_inputs`   - Public keys of the miners participating in the block round
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn seed_preimage(
+    mode: SeedMode,
+    public_key_inputs: &[String],
+    last_winning_hashes: &[String],
+) -> Vec<u8> {
+    match mode {
+        SeedMode::Serialized if last_winning_hashes.is_empty() => {
+            serialize(public_key_inputs).unwrap()
+        }
+        SeedMode::Serialized => serialize(&(public_key_inputs, last_winning_hashes)).unwrap(),
+        SeedMode::TreeHash => tree_preimage(input_digests(public_key_inputs), last_winning_hashes),
+    }
+}
+
+/// Pre-image of a tree-hashed seed from the sorted digests of its inputs
+///
+/// ### Arguments
+///
+/// * `digests`             - Sorted digests of the seed inputs
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+fn tree_preimage(digests: Vec<[u8; 32]>, last_winning_hashes: &[String]) -> Vec<u8> {
+    let mut preimage = Vec::with_capacity(1 + digests.len() * 32);
+    preimage.push(1);
+    for digest in digests {
+        preimage.extend_from_slice(&digest);
+    }
+    preimage.extend(serialize(last_winning_hashes).unwrap());
+    preimage
+}
+
+/// Constructs the seed for a new lottery Unicorn in the given mode
+///
+/// ### Arguments
+///
+/// * `mode`                - How the seed is built from its inputs
+/// * `public_key_inputs`   - Public keys of the miners participating in the block round
+/// * `last_winning_hashes` - The hashes of the winning PoWs from 2 blocks ago
+pub fn construct_seed_with_mode(
+    mode: SeedMode,
+    public_key_inputs: &[String],
+    last_winning_hashes: &[String],
+) -> Integer {
+    match mode {
+        SeedMode::Serialized => construct_seed_with_pow(public_key_inputs, last_winning_hashes),
+        SeedMode::TreeHash => construct_seed_tree(public_key_inputs, last_winning_hashes),
+    }
+}
+
 /// Constructs the lottery Unicorn
 ///
 /// ### Arguments
@@ -54,6 +214,7 @@ pub fn construct_unicorn(seed: Integer, fixed_params: &UnicornFixedParam) -> Uni
         modulus: Integer::from_str_radix(&fixed_params.modulus, 10).unwrap(),
         iterations: fixed_params.iterations,
         security_level: fixed_params.security,
+        primality_rounds: fixed_params.primality_rounds,
     };
 
     let (w, g): (Integer, String) = match unicorn.eval() {
@@ -61,15 +222,119 @@ pub fn construct_unicorn(seed: Integer, fixed_params: &UnicornFixedParam) -> Uni
         None => panic!("UNICORN construction failed"),
     };
 
-    UnicornInfo {
-        unicorn,
-        witness: w,
-        g_value: g,
+    UnicornInfo::new(unicorn, w, g)
+}
+
+/// Length in bytes of the Fortuna key derived from a `g` value
+pub const G_KEY_LEN: usize = 32;
+
+/// Lowercase hex digits, as written by `hex::encode`
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Big-endian bytes of a witness without leading zeroes, read straight from its limbs
+///
+/// ### Arguments
+///
+/// * `witness` - Witness value produced by `eval`
+fn witness_bytes(witness: &Integer) -> impl Iterator<Item = u8> + '_ {
+    witness
+        .as_limbs()
+        .iter()
+        .rev()
+        .flat_map(|limb| limb.to_be_bytes())
+        .skip_while(|b| *b == 0)
+}
+
+/// Hex digits of a witness, most significant first
+fn witness_hex(witness: &Integer) -> impl Iterator<Item = u8> + '_ {
+    witness_bytes(witness).flat_map(|b| {
+        [
+            HEX_DIGITS[(b >> 4) as usize],
+            HEX_DIGITS[(b & 0xf) as usize],
+        ]
+    })
+}
+
+/// Computes the `g` value for a witness, as hex-encoded big-endian digits. The digits are
+/// written straight from the witness's limbs into the returned string
+///
+/// ### Arguments
+///
+/// * `witness` - Witness value produced by `eval`
+pub fn g_from_witness(witness: &Integer) -> String {
+    let mut g = String::with_capacity(2 * witness.significant_digits::<u8>());
+    g.extend(witness_hex(witness).map(char::from));
+    g
+}
+
+/// Whether a `g` value is the one for a witness, compared without building the expected one
+///
+/// ### Arguments
+///
+/// * `witness` - Witness value produced by `eval`
+/// * `g_value` - Claimed `g` value
+pub fn g_matches(witness: &Integer, g_value: &str) -> bool {
+    witness_hex(witness).eq(g_value.bytes())
+}
+
+/// Fortuna key for a witness: the leading `G_KEY_LEN` bytes of its `g` value, derived
+/// without building the `g` value. Returns `None` if the `g` value is shorter than a key
+///
+/// ### Arguments
+///
+/// * `witness` - Witness value produced by `eval`
+pub fn g_key(witness: &Integer) -> Option<[u8; G_KEY_LEN]> {
+    let mut key = [0; G_KEY_LEN];
+    let mut digits = witness_hex(witness);
+    for k in key.iter_mut() {
+        *k = digits.next()?;
     }
+
+    Some(key)
 }
 
-/// Fixed parameters for unicorn
+/// Overwrites every allocated limb of an integer with zeroes and sets it to 0
+///
+/// ### Arguments
+///
+/// * `value` - Integer to clear
+fn zeroize_integer(value: &mut Integer) {
+    // SAFETY: GMP keeps `alloc` initialised limbs behind `d`, and a zero `size` is a valid 0
+    unsafe {
+        let raw = value.as_raw_mut();
+        let limbs = std::slice::from_raw_parts_mut((*raw).d.as_ptr(), (*raw).alloc as usize);
+        limbs.zeroize();
+        (*raw).size = 0;
+    }
+}
+
+/// How an eval guards against timing side channels. Both modes give the same result
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvalMode {
+    /// Fastest eval, whose timing depends on the iterated value
+    #[default]
+    Standard,
+    /// Slower eval whose timing depends as little as GMP allows on the iterated value, for
+    /// coordinators evaluating on shared hardware. The overflow permutation takes the same
+    /// steps for every value, and each exponentiation is constant-time with a freshly
+    /// blinded exponent
+    Hardened,
+}
+
+/// Intermediate state of an eval, from which it can be resumed
 #[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EvalState {
+    /// Number of iterations completed
+    pub iterations_done: u64,
+    /// Iterated value `w` after `iterations_done` iterations
+    #[serde(with = "rug_integer")]
+    pub w: Integer,
+}
+
+/// Fixed parameters for unicorn
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct UnicornFixedParam {
     /// UNICORN modulus number
     pub modulus: String,
@@ -77,15 +342,86 @@ pub struct UnicornFixedParam {
     pub iterations: u64,
     /// UNICORN security level
     pub security: u32,
+    /// Miller Rabin rounds the modulus must pass before an eval
+    #[serde(default = "default_primality_rounds")]
+    pub primality_rounds: u32,
+}
+
+impl Default for UnicornFixedParam {
+    fn default() -> Self {
+        Self {
+            modulus: String::new(),
+            iterations: 0,
+            security: 0,
+            primality_rounds: MR_PRIME_ITERS,
+        }
+    }
 }
 
 /// UNICORN-relevant info for use on a RAFT
 #[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct UnicornInfo {
     pub unicorn: Unicorn,
     pub g_value: String,
     #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub witness: Integer,
+    /// Digest of the round inputs the seed was constructed from, as given by
+    /// `Round::inputs_digest`, if the UNICORN was evaluated for a round
+    #[serde(default)]
+    pub input_digest: Option<String>,
+    /// Parameters the UNICORN was constructed with
+    #[serde(default)]
+    pub fixed_params: Option<UnicornFixedParam>,
+}
+
+impl UnicornInfo {
+    /// Evaluated UNICORN, recording the parameters it was constructed with
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn` - Evaluated UNICORN
+    /// * `witness` - Witness value produced by `eval`
+    /// * `g_value` - `g` value of the witness
+    pub fn new(unicorn: Unicorn, witness: Integer, g_value: String) -> Self {
+        Self {
+            fixed_params: Some(UnicornFixedParam::from(&unicorn)),
+            unicorn,
+            g_value,
+            witness,
+            input_digest: None,
+        }
+    }
+
+    /// Records the digest of the round inputs the seed was constructed from
+    ///
+    /// ### Arguments
+    ///
+    /// * `input_digest` - Digest given by `Round::inputs_digest`
+    pub fn with_input_digest(mut self, input_digest: Option<String>) -> Self {
+        self.input_digest = input_digest;
+        self
+    }
+
+    /// Whether the recorded parameters, if there are any, are the ones of the UNICORN
+    pub fn params_match(&self) -> bool {
+        self.fixed_params
+            .as_ref()
+            .is_none_or(|params| *params == UnicornFixedParam::from(&self.unicorn))
+    }
+}
+
+/// Parameters of a UNICORN, with its modulus in decimal
+impl From<&Unicorn> for UnicornFixedParam {
+    fn from(unicorn: &Unicorn) -> Self {
+        Self {
+            modulus: unicorn.modulus.to_string_radix(10),
+            iterations: unicorn.iterations,
+            security: unicorn.security_level,
+            primality_rounds: unicorn.primality_rounds,
+        }
+    }
 }
 
 /// UNICORN struct, with the following fields:
@@ -95,14 +431,61 @@ pub struct UnicornInfo {
 /// - seed (`s`)
 /// - witness (`w`)
 /// - security_level (`k`)
-#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Unicorn {
     pub iterations: u64,
     pub security_level: u32,
     #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub seed: Integer,
     #[serde(with = "rug_integer")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub modulus: Integer,
+    /// Miller Rabin rounds the modulus must pass before an eval
+    #[serde(default = "default_primality_rounds")]
+    pub primality_rounds: u32,
+}
+
+impl Default for Unicorn {
+    fn default() -> Self {
+        Self {
+            iterations: 0,
+            security_level: 0,
+            seed: Integer::new(),
+            modulus: Integer::new(),
+            primality_rounds: MR_PRIME_ITERS,
+        }
+    }
+}
+
+/// Clears the seed and modulus limbs, so a dropped UNICORN leaves no trace of its seed
+impl Zeroize for Unicorn {
+    fn zeroize(&mut self) {
+        self.iterations.zeroize();
+        self.security_level.zeroize();
+        zeroize_integer(&mut self.seed);
+        zeroize_integer(&mut self.modulus);
+    }
+}
+
+impl Zeroize for UnicornInfo {
+    fn zeroize(&mut self) {
+        self.unicorn.zeroize();
+        self.g_value.zeroize();
+        zeroize_integer(&mut self.witness);
+        self.input_digest.zeroize();
+        if let Some(mut params) = self.fixed_params.take() {
+            params.modulus.zeroize();
+        }
+    }
+}
+
+impl Zeroize for EvalState {
+    fn zeroize(&mut self) {
+        self.iterations_done.zeroize();
+        zeroize_integer(&mut self.w);
+    }
 }
 
 impl Unicorn {
@@ -113,12 +496,15 @@ impl Unicorn {
     ///
     /// * `seed`    - Seed to set
     pub fn set_seed(&mut self, seed: Integer) -> String {
-        let u = hex::encode(Sha256::digest(&serialize(&seed.to_u64()).unwrap()));
-        let c = hex::encode(Sha256::digest(u.as_bytes()));
-
         self.seed = seed;
+        self.commitment()
+    }
 
-        c
+    /// Returns the commitment value `c` for the current seed, as per
+    /// Lenstra and Wesolowski recommendations. The commitment hashes the seed's big-endian
+    /// bytes, so it binds every bit of the seed whatever its size
+    pub fn commitment(&self) -> String {
+        hex::encode(Sha256::digest(self.seed.to_digits::<u8>(Order::MsfBe)))
     }
 
     /// Evaluation of the Sloth VDF given internal params and a seed value,
@@ -134,26 +520,113 @@ impl Unicorn {
     /// - Let g ← hash(wl) and w ← wl.
     /// - Return g and w as the output and quit.
     pub fn eval(&self) -> Option<(Integer, String)> {
+        self.eval_with_progress(|_| {})
+    }
+
+    /// Evaluation as in `eval`, in the given mode
+    ///
+    /// ### Arguments
+    ///
+    /// * `mode` - How the eval guards against timing side channels
+    pub fn eval_with_mode(&self, mode: EvalMode) -> Option<(Integer, String)> {
+        self.eval_from_with_mode(self.start_state(), mode, |_| {})
+    }
+
+    /// Evaluation as in `eval`, reporting the number of completed iterations every
+    /// `PROGRESS_INTERVAL` iterations and once the eval is done
+    ///
+    /// ### Arguments
+    ///
+    /// * `progress` - Called with the number of completed iterations
+    pub fn eval_with_progress(&self, mut progress: impl FnMut(u64)) -> Option<(Integer, String)> {
+        self.eval_from(self.start_state(), |state| progress(state.iterations_done))
+    }
+
+    /// State of an eval that has not started yet
+    pub fn start_state(&self) -> EvalState {
+        EvalState {
+            iterations_done: 0,
+            w: self.seed.clone().div_rem_floor(self.modulus.clone()).1,
+        }
+    }
+
+    /// Evaluation as in `eval`, resuming from an intermediate state. The state is reported
+    /// every `PROGRESS_INTERVAL` iterations and once the eval is done, so that it can be
+    /// checkpointed. Returns `None` if the state is past the last iteration
+    ///
+    /// ### Arguments
+    ///
+    /// * `state`    - State to resume from, as given by `start_state` or an earlier report
+    /// * `progress` - Called with the state after the completed iterations
+    pub fn eval_from(
+        &self,
+        state: EvalState,
+        progress: impl FnMut(&EvalState),
+    ) -> Option<(Integer, String)> {
+        self.eval_from_with_mode(state, EvalMode::Standard, progress)
+    }
+
+    /// Evaluation as in `eval_from`, in the given mode
+    ///
+    /// ### Arguments
+    ///
+    /// * `state`    - State to resume from, as given by `start_state` or an earlier report
+    /// * `mode`     - How the eval guards against timing side channels
+    /// * `progress` - Called with the state after the completed iterations
+    pub fn eval_from_with_mode(
+        &self,
+        mut state: EvalState,
+        mode: EvalMode,
+        mut progress: impl FnMut(&EvalState),
+    ) -> Option<(Integer, String)> {
         if !self.is_valid_modulus() {
             error!("Modulus for UNICORN eval invalid");
             return None;
         }
+        if state.iterations_done > self.iterations {
+            error!("Eval state is past the last UNICORN iteration");
+            return None;
+        }
 
-        let mut w = self.seed.clone().div_rem_floor(self.modulus.clone()).1;
-
-        // The slow modular square root
-        let exponent = (self.modulus.clone() + 1) / 4;
+        // The slow modular square root. Buffers used by the loop are allocated up front, so
+        // that iterations run without allocating
+        let exponent = Integer::from(&self.modulus + 1u32) / 4;
+        let mut blinding = match mode {
+            EvalMode::Standard => None,
+            EvalMode::Hardened => Some(ExponentBlinding::new(&self.modulus)?),
+        };
+        let bits = self.modulus.significant_bits() as usize;
+        let mut scratch = Integer::with_capacity(bits);
+        let mut blinded = Integer::with_capacity(bits + 64);
+        #[cfg(feature = "fixed-window")]
+        let mut window = crate::window::FixedWindow::new(&self.modulus, &exponent);
 
-        for _ in 0..self.iterations {
-            self.xor_for_overflow(&mut w);
+        while state.iterations_done < self.iterations {
+            match &mut blinding {
+                None => {
+                    self.xor_for_overflow(&mut state.w);
+                    #[cfg(feature = "fixed-window")]
+                    window.pow_mut(&mut state.w);
+                    #[cfg(not(feature = "fixed-window"))]
+                    state.w.pow_mod_mut(&exponent, &self.modulus).unwrap();
+                }
+                Some(blinding) => {
+                    self.xor_for_overflow_fixed(&mut state.w, &mut scratch);
+                    blinding.blind(&exponent, &mut blinded)?;
+                    state.w.secure_pow_mod_mut(&blinded, &self.modulus);
+                }
+            }
+            state.iterations_done += 1;
 
-            w.pow_mod_mut(&exponent, &self.modulus).unwrap();
+            if state.iterations_done.is_multiple_of(PROGRESS_INTERVAL) {
+                progress(&state);
+            }
         }
+        progress(&state);
 
-        let digits = w.to_digits::<u8>(Order::MsfBe);
-        let g = hex::encode(digits);
+        let g = g_from_witness(&state.w);
 
-        Some((w, g))
+        Some((state.w, g))
     }
 
     /// Verifies a particular unicorn given a witness value. This is the "trapdoor"
@@ -170,19 +643,58 @@ impl Unicorn {
     /// * `seed`    - Seed to verify
     /// * `witness` - Witness value for trapdoor verification
     pub fn verify(&self, seed: Integer, witness: Integer) -> bool {
+        self.verify_owned(seed, witness)
+    }
+
+    /// Verifies a particular unicorn given a witness value, as `verify` does, without taking
+    /// ownership of the seed or witness. The reduced seed and the witness are each copied
+    /// once into the buffers the verification runs in
+    ///
+    /// ### Arguments
+    ///
+    /// * `seed`    - Seed to verify
+    /// * `witness` - Witness value for trapdoor verification
+    pub fn verify_ref(&self, seed: &Integer, witness: &Integer) -> bool {
+        self.verify_owned(Integer::from(seed % &self.modulus), witness.clone())
+    }
+
+    /// Runs the verification in the buffers of the given seed and witness
+    ///
+    /// ### Arguments
+    ///
+    /// * `seed`    - Seed to verify
+    /// * `witness` - Witness value for trapdoor verification
+    fn verify_owned(&self, mut seed: Integer, mut w: Integer) -> bool {
         let square: Integer = 2u64.into();
-        let mut w = witness;
+        seed %= &self.modulus;
+        if seed < 0 {
+            seed += &self.modulus;
+        }
+        let mut root = Integer::with_capacity(self.modulus.significant_bits() as usize);
 
-        for _ in 0..self.iterations {
+        for i in 0..self.iterations {
             // Fast squaring modulo
             w.pow_mod_mut(&square, &self.modulus).unwrap();
 
-            let inv_w = -w;
-            w = inv_w.div_rem_floor(self.modulus.clone()).1;
+            // The first eval step maps both `x` and `-x` onto the same root, so the
+            // seed may sit on either side of the final squaring
+            if i + 1 == self.iterations {
+                root.assign(&w);
+                self.xor_for_overflow(&mut root);
+
+                if root == seed {
+                    return true;
+                }
+            }
+
+            // Negation modulo p, in place
+            if w != 0 {
+                w.sub_from(&self.modulus);
+            }
             self.xor_for_overflow(&mut w);
         }
 
-        w == seed.div_rem_floor(self.modulus.clone()).1
+        w == seed
     }
 
     /// Predicate for a valid modulus `p`
@@ -192,7 +704,11 @@ impl Unicorn {
     /// - `p >= 2^2k` where `k` is a chosen security level
     fn is_valid_modulus(&self) -> bool {
         self.modulus >= 2u64.pow(2 * self.security_level)
-            && !matches!(self.modulus.is_probably_prime(MR_PRIME_ITERS), IsPrime::No)
+            && self.primality_rounds > 0
+            && !matches!(
+                self.modulus.is_probably_prime(self.primality_rounds),
+                IsPrime::No
+            )
     }
 
     /// Performs a XOR of the input `x` as a basic secure permutation
@@ -208,6 +724,63 @@ impl Unicorn {
             *w ^= 1;
         }
     }
+
+    /// Performs the same permutation as `xor_for_overflow` without branching on `w`. Only
+    /// `w = 1` and `w = p - 1` are out of range once XORed, and both are left as they are
+    ///
+    /// ### Arguments
+    ///
+    /// * `w`       - Input to XOR, below the modulus
+    /// * `flipped` - Buffer the XORed input is written to
+    fn xor_for_overflow_fixed(&self, w: &mut Integer, flipped: &mut Integer) {
+        flipped.assign(&*w ^ 1u32);
+        let keep = (*flipped >= self.modulus) as u32 | (*flipped == 0) as u32;
+
+        *w ^= 1 - keep;
+    }
+}
+
+/// Random multiples of `p - 1` added to the eval exponent, so that the exponent used by
+/// each iteration of a hardened eval differs without changing the result: `w^(p - 1) = 1`
+/// for every `w` not divisible by the prime `p`, and `0` stays `0`
+struct ExponentBlinding {
+    csprng: Fortuna,
+    order: Integer,
+}
+
+impl ExponentBlinding {
+    /// Blinding keyed from the operating system's entropy
+    ///
+    /// ### Arguments
+    ///
+    /// * `modulus` - UNICORN modulus `p`
+    fn new(modulus: &Integer) -> Option<Self> {
+        let mut key = [0u8; 32];
+        if let Err(e) = getrandom::getrandom(&mut key) {
+            error!("Could not seed exponent blinding: {}", e);
+            return None;
+        }
+
+        Some(Self {
+            csprng: Fortuna::new(&key, 0).ok()?,
+            order: Integer::from(modulus - 1u32),
+        })
+    }
+
+    /// Writes the exponent plus a fresh random multiple of `p - 1` to `blinded`
+    ///
+    /// ### Arguments
+    ///
+    /// * `exponent` - Exponent to blind
+    /// * `blinded`  - Buffer the blinded exponent is written to
+    fn blind(&mut self, exponent: &Integer, blinded: &mut Integer) -> Option<()> {
+        let mut factor = [0; 8];
+        self.csprng.fill_bytes(&mut factor).ok()?;
+        blinded.assign(&self.order * u64::from_be_bytes(factor));
+        *blinded += exponent;
+
+        Some(())
+    }
 }
 
 /*---- TESTS ----*/
@@ -215,6 +788,8 @@ impl Unicorn {
 #[cfg(test)]
 mod unicorn_tests {
     use super::*;
+    use crate::utils::unicorn_selection::{get_unicorn_csprng, get_witness_csprng};
+    use num_bigint::BigUint;
 
     const TEST_HASH: &str = "1eeb30c7163271850b6d018e8282093ac6755a771da6267edf6c9b4fce9242ba";
     const WITNESS: &str = "3519722601447054908751517254890810869415446534615259770378249754169022895693105944708707316137352415946228979178396400856098248558222287197711860247275230167";
@@ -229,9 +804,168 @@ mod unicorn_tests {
             iterations: 1_000,
             security_level: 1,
             seed,
+            ..Default::default()
         }
     }
 
+    #[test]
+    /// Checks that `g` values and Fortuna keys derived straight from the witness limbs match
+    /// the ones derived from its hex-encoded digits
+    fn g_from_witness_limbs() {
+        let digits = |w: &Integer| hex::encode(w.to_digits::<u8>(Order::MsfBe));
+        let witnesses = [
+            Integer::new(),
+            Integer::from(0xabu32),
+            Integer::from(u64::MAX),
+            (Integer::from(1) << 256u32) - 1u32,
+            Integer::from_str_radix(WITNESS, 10).unwrap(),
+        ];
+
+        for witness in &witnesses {
+            let g = digits(witness);
+            assert_eq!(g_from_witness(witness), g);
+            assert!(g_matches(witness, &g));
+            assert!(!g_matches(witness, &format!("{}0", g)));
+            assert_eq!(
+                g_key(witness).map(|k| k.to_vec()),
+                g.as_bytes().get(..G_KEY_LEN).map(|k| k.to_vec())
+            );
+        }
+
+        let unicorn = create_unicorn();
+        let (witness, g_value) = unicorn.eval().unwrap();
+        let info = UnicornInfo::new(unicorn, witness, g_value);
+        let mut keyed = get_witness_csprng(&info.witness, 3).unwrap();
+        assert_eq!(
+            keyed.get_bytes(64).unwrap(),
+            get_unicorn_csprng(&info, 3).unwrap().get_bytes(64).unwrap()
+        );
+    }
+
+    #[test]
+    /// Checks that the commitment changes with every bit of seeds too large for a `u64`
+    fn commitment_binds_seed() {
+        let mut uni = create_unicorn();
+        let commitment = uni.commitment();
+        assert_eq!(
+            commitment,
+            hex::encode(Sha256::digest(uni.seed.to_digits::<u8>(Order::MsfBe)))
+        );
+
+        let seed = uni.seed.clone();
+        assert_ne!(uni.set_seed(seed.clone() ^ Integer::from(1)), commitment);
+        assert_ne!(
+            uni.set_seed(seed ^ (Integer::from(1) << 255u32)),
+            commitment
+        );
+    }
+
+    #[test]
+    /// Checks that a tree-hashed seed ignores input order, differs from the serialized seed
+    /// and is the same whether or not the digests are computed in parallel
+    fn tree_hash_seed() {
+        let inputs: Vec<String> = (0..TREE_HASH_PARALLEL_MIN * 2)
+            .map(|i| format!("miner_{}", i))
+            .collect();
+        let mut reversed = inputs.clone();
+        reversed.reverse();
+
+        let seed = construct_seed_tree(&inputs, &[]);
+        assert_eq!(seed, construct_seed_tree(&reversed, &[]));
+        assert_ne!(seed, construct_seed(&inputs));
+        assert_ne!(seed, construct_seed_tree(&inputs, &["pow".to_string()]));
+
+        let mut sequential: Vec<[u8; 32]> = inputs.iter().map(|i| input_digest(i)).collect();
+        sequential.sort_unstable();
+        assert_eq!(input_digests(&inputs), sequential);
+        assert!(sequential.binary_search(&input_digest("miner_42")).is_ok());
+    }
+
+    #[test]
+    /// Checks that progress is reported at every interval and on completion
+    fn eval_reports_progress() {
+        let mut uni = create_unicorn();
+        uni.iterations = 2 * PROGRESS_INTERVAL + 10;
+        let mut reports = Vec::new();
+
+        let result = uni.eval_with_progress(|done| reports.push(done));
+
+        assert_eq!(result, uni.eval());
+        assert_eq!(
+            reports,
+            vec![PROGRESS_INTERVAL, 2 * PROGRESS_INTERVAL, uni.iterations]
+        );
+    }
+
+    #[test]
+    /// Checks that an eval resumed from a reported state matches an uninterrupted one
+    fn eval_resumes_from_state() {
+        let mut uni = create_unicorn();
+        uni.iterations = PROGRESS_INTERVAL + 10;
+        let mut checkpoint = None;
+
+        uni.eval_from(uni.start_state(), |state| {
+            checkpoint.get_or_insert_with(|| state.clone());
+        });
+        let checkpoint = checkpoint.unwrap();
+
+        assert_eq!(checkpoint.iterations_done, PROGRESS_INTERVAL);
+        assert_eq!(uni.eval_from(checkpoint, |_| {}), uni.eval());
+
+        let past = EvalState {
+            iterations_done: uni.iterations + 1,
+            w: Integer::from(2),
+        };
+        assert!(uni.eval_from(past, |_| {}).is_none());
+    }
+
+    #[test]
+    /// Checks that evals at the top of the iteration range finish without overflowing
+    fn eval_near_max_iterations() {
+        let mut uni = create_unicorn();
+        uni.iterations = 3;
+        let start = uni.start_state();
+        let expected = uni.eval_from(start.clone(), |_| {});
+
+        uni.iterations = u64::MAX;
+        let mut reports = Vec::new();
+        let state = EvalState {
+            iterations_done: u64::MAX - 3,
+            w: start.w,
+        };
+        let resumed = uni.eval_from(state, |s| reports.push(s.iterations_done));
+
+        assert_eq!(resumed, expected);
+        assert_eq!(reports, vec![u64::MAX]);
+    }
+
+    #[test]
+    /// Checks a long eval, over a small modulus to keep it fast, resumed from a checkpoint
+    /// halfway through and verified over the same count
+    fn long_eval_stress() {
+        let uni = Unicorn {
+            seed: construct_seed(&["miner_a".to_string()]),
+            modulus: (Integer::from(1) << 127u32) - 1u32,
+            iterations: 1 << 18,
+            security_level: 1,
+            ..Default::default()
+        };
+        let mut checkpoint = None;
+
+        let (witness, g) = uni
+            .eval_from(uni.start_state(), |state| {
+                if state.iterations_done == uni.iterations / 2 {
+                    checkpoint = Some(state.clone());
+                }
+            })
+            .unwrap();
+        let resumed = uni.eval_from(checkpoint.unwrap(), |_| {}).unwrap();
+
+        assert_eq!(resumed, (witness.clone(), g));
+        assert!(uni.verify_ref(&uni.seed, &witness));
+        assert!(!uni.verify_ref(&uni.seed, &Integer::from(&witness + 1)));
+    }
+
     #[test]
     /// Checks that a valid unicorn can be evaluated
     fn eval_valid_unicorn() {
@@ -248,6 +982,60 @@ mod unicorn_tests {
         );
     }
 
+    #[test]
+    /// Checks that the hardened eval gives the same result as the standard one, including
+    /// for values whose overflow permutation is out of range
+    fn hardened_eval_matches() {
+        let mut uni = create_unicorn();
+        assert_eq!(uni.eval_with_mode(EvalMode::Hardened), uni.eval());
+
+        uni.iterations = 10;
+        for seed in [
+            Integer::from(1),
+            Integer::from(&uni.modulus - 1u32),
+            0.into(),
+        ] {
+            uni.seed = seed;
+            assert_eq!(uni.eval_with_mode(EvalMode::Hardened), uni.eval());
+        }
+
+        let mut w = Integer::from(&uni.modulus - 1u32);
+        let mut fixed = w.clone();
+        uni.xor_for_overflow(&mut w);
+        uni.xor_for_overflow_fixed(&mut fixed, &mut Integer::new());
+        assert_eq!(fixed, w);
+    }
+
+    #[test]
+    /// Checks that zeroizing clears every field, including the limbs behind each integer
+    fn zeroize_clears_limbs() {
+        let uni = create_unicorn();
+        let (witness, g_value) = uni.eval().unwrap();
+        let mut info = UnicornInfo::new(uni, witness, g_value);
+        let mut state = EvalState {
+            iterations_done: 10,
+            w: info.witness.clone(),
+        };
+
+        info.zeroize();
+        state.zeroize();
+
+        assert_eq!(info, UnicornInfo::default());
+        assert_eq!(state, EvalState::default());
+        for value in [
+            &info.unicorn.seed,
+            &info.unicorn.modulus,
+            &info.witness,
+            &state.w,
+        ] {
+            let raw = value.as_raw();
+            // SAFETY: GMP keeps `alloc` limbs behind `d`
+            let limbs =
+                unsafe { std::slice::from_raw_parts((*raw).d.as_ptr(), (*raw).alloc as usize) };
+            assert!(limbs.iter().all(|limb| *limb == 0));
+        }
+    }
+
     #[test]
     /// Checks that an invalid modulus fail eval
     fn eval_invalid_modulus_unicorn() {
@@ -259,6 +1047,49 @@ mod unicorn_tests {
         assert_eq!(eval, None);
     }
 
+    #[test]
+    /// Checks that parameters written without primality rounds get the default, and that a
+    /// UNICORN with no rounds does not eval
+    fn primality_rounds_default() {
+        let params: UnicornFixedParam =
+            serde_json::from_str(r#"{"modulus":"7","iterations":1,"security":1}"#).unwrap();
+        let mut uni = create_unicorn();
+        uni.iterations = 10;
+
+        assert_eq!(params.primality_rounds, MR_PRIME_ITERS);
+        assert!(uni.eval().is_some());
+        uni.primality_rounds = 1;
+        assert!(uni.eval().is_some());
+        uni.primality_rounds = 0;
+        assert_eq!(uni.eval(), None);
+    }
+
+    #[test]
+    /// Checks that an evaluated UNICORN records its parameters, and that older encodings
+    /// without them still decode
+    fn info_records_params() {
+        let mut uni = create_unicorn();
+        uni.iterations = 10;
+        let (witness, g_value) = uni.eval().unwrap();
+        let mut info = UnicornInfo::new(uni, witness, g_value);
+
+        let params = info.fixed_params.clone().unwrap();
+        assert_eq!(params.iterations, 10);
+        assert_eq!(params.modulus, info.unicorn.modulus.to_string_radix(10));
+        assert!(info.params_match());
+
+        let mut value = serde_json::to_value(&info).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("fixed_params");
+        object.remove("input_digest");
+        let legacy: UnicornInfo = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.fixed_params, None);
+        assert!(legacy.params_match());
+
+        info.unicorn.iterations = 11;
+        assert!(!info.params_match());
+    }
+
     #[test]
     /// Checks that unicorn is succeed only with correct witness
     fn verify_unicorn() {
@@ -275,4 +1106,64 @@ mod unicorn_tests {
 
         assert_eq!((good, bad), (true, false));
     }
+
+    #[test]
+    /// Checks that eval output verifies for seeds on either side of the first square root,
+    /// whether or not the seed and witness are borrowed
+    fn verify_any_seed() {
+        let mut uni = create_unicorn();
+        uni.iterations = 10;
+
+        for i in 0..8 {
+            uni.seed = construct_seed(&[format!("miner_{}", i)]);
+            let (witness, _) = uni.eval().unwrap();
+
+            assert!(uni.verify(uni.seed.clone(), witness.clone()));
+            assert!(uni.verify_ref(&uni.seed, &witness));
+            assert!(uni.verify_ref(&Integer::from(&uni.seed - &uni.modulus), &witness));
+            assert!(!uni.verify_ref(&Integer::from(&uni.seed + 2), &witness));
+            assert!(!uni.verify(uni.seed.clone() + 2, witness));
+        }
+    }
+
+    #[test]
+    /// Checks that the `no_std` core verifies and recomputes `g` as this module does
+    fn core_verify_matches() {
+        let mut uni = create_unicorn();
+        uni.iterations = 10;
+        let big = |x: &Integer| BigUint::from_bytes_be(&x.to_digits::<u8>(Order::Msf));
+        let core = miner_lottery_core::verify::Unicorn {
+            iterations: uni.iterations,
+            modulus: big(&uni.modulus),
+        };
+
+        for i in 0..8 {
+            uni.seed = construct_seed(&[format!("miner_{}", i)]);
+            let (witness, g) = uni.eval().unwrap();
+            let bad_seed = uni.seed.clone() + 2;
+
+            assert!(core.verify(&big(&uni.seed), &big(&witness)));
+            assert!(!core.verify(&big(&bad_seed), &big(&witness)));
+            assert_eq!(
+                miner_lottery_core::verify::g_from_witness(&big(&witness)),
+                g
+            );
+        }
+    }
+
+    #[test]
+    /// Checks that PoW hashes change the seed only when present
+    fn seed_with_pow_hashes() {
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let hashes = vec!["00ab".to_string()];
+
+        assert_eq!(
+            construct_seed_with_pow(&inputs, &[]),
+            construct_seed(&inputs)
+        );
+        assert_ne!(
+            construct_seed_with_pow(&inputs, &hashes),
+            construct_seed(&inputs)
+        );
+    }
 }
@@ -0,0 +1,168 @@
+//! Allocation of usage numbers for draws from a single UNICORN.
+//!
+//! Every draw from a UNICORN must use its own usage number, or two draws would read the same
+//! PRN stream. Usage numbers are namespaced by the purpose of the draw: the low 64 bits hold
+//! the namespace and the high 64 bits a sequence number within it. The first draw of each
+//! namespace therefore uses the namespace itself as its usage number, so the winner draw keeps
+//! usage number 1.
+//!
+//! The allocations made for a UNICORN can be recorded in a `SelectionTranscript`, letting an
+//! auditor check that no usage number was handed out twice.
+
+use crate::selection::SelectionError;
+use serde::{Deserialize, Serialize};
+
+/// Namespace of the winner draw
+pub const WINNER_NAMESPACE: u64 = 1;
+
+/// Namespace of backup winner draws
+pub const BACKUPS_NAMESPACE: u64 = 2;
+
+/// Namespace of payout draws
+pub const PAYOUTS_NAMESPACE: u64 = 3;
+
+/// Computes the usage number of a draw within a namespace
+///
+/// ### Arguments
+///
+/// * `namespace` - Namespace of the draw
+/// * `sequence`  - Sequence number of the draw within the namespace
+pub fn usage_number(namespace: u64, sequence: u64) -> u128 {
+    ((sequence as u128) << 64) | namespace as u128
+}
+
+/// A usage number handed out by a `UsageAllocator`
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UsageAllocation {
+    pub namespace: u64,
+    pub sequence: u64,
+    pub usage_number: u128,
+}
+
+impl UsageAllocation {
+    /// Splits a usage number into its namespace and sequence number
+    ///
+    /// ### Arguments
+    ///
+    /// * `usage_number` - Usage number to split
+    pub fn from_usage_number(usage_number: u128) -> Self {
+        Self {
+            namespace: usage_number as u64,
+            sequence: (usage_number >> 64) as u64,
+            usage_number,
+        }
+    }
+}
+
+/// Hands out non-colliding usage numbers for draws from a single UNICORN
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct UsageAllocator {
+    allocations: Vec<UsageAllocation>,
+}
+
+impl UsageAllocator {
+    /// Creates an allocator with no usage numbers handed out
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocates the next free usage number in a namespace
+    ///
+    /// ### Arguments
+    ///
+    /// * `namespace` - Namespace of the draw
+    pub fn allocate(&mut self, namespace: u64) -> u128 {
+        let mut sequence = self
+            .allocations
+            .iter()
+            .filter(|a| a.namespace == namespace)
+            .map(|a| a.sequence + 1)
+            .max()
+            .unwrap_or(0);
+
+        while self.contains(usage_number(namespace, sequence)) {
+            sequence += 1;
+        }
+
+        let allocation = UsageAllocation {
+            namespace,
+            sequence,
+            usage_number: usage_number(namespace, sequence),
+        };
+        self.allocations.push(allocation);
+
+        allocation.usage_number
+    }
+
+    /// Records a usage number chosen outside the allocator, such as those of
+    /// `select_by_quota`. Fails if the usage number was already handed out
+    ///
+    /// ### Arguments
+    ///
+    /// * `usage_number` - Usage number to record
+    pub fn claim(&mut self, usage_number: u128) -> Result<(), SelectionError> {
+        if self.contains(usage_number) {
+            return Err(SelectionError::UsageNumberReused(usage_number));
+        }
+
+        self.allocations
+            .push(UsageAllocation::from_usage_number(usage_number));
+
+        Ok(())
+    }
+
+    /// Whether a usage number has been handed out
+    ///
+    /// ### Arguments
+    ///
+    /// * `usage_number` - Usage number to look up
+    pub fn contains(&self, usage_number: u128) -> bool {
+        self.allocations
+            .iter()
+            .any(|a| a.usage_number == usage_number)
+    }
+
+    /// Usage numbers handed out, in allocation order
+    pub fn allocations(&self) -> &[UsageAllocation] {
+        &self.allocations
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod usage_tests {
+    use super::*;
+
+    #[test]
+    /// Checks that the first draw of each namespace uses the namespace as its usage number
+    fn namespaced_allocation() {
+        let mut allocator = UsageAllocator::new();
+
+        assert_eq!(allocator.allocate(WINNER_NAMESPACE), 1);
+        assert_eq!(allocator.allocate(BACKUPS_NAMESPACE), 2);
+        assert_eq!(allocator.allocate(PAYOUTS_NAMESPACE), 3);
+        assert_eq!(
+            allocator.allocate(BACKUPS_NAMESPACE),
+            usage_number(BACKUPS_NAMESPACE, 1)
+        );
+        assert_eq!(allocator.allocations().len(), 4);
+    }
+
+    #[test]
+    /// Checks that claimed usage numbers are never handed out again
+    fn claims_do_not_collide() {
+        let mut allocator = UsageAllocator::new();
+        allocator.claim(WINNER_NAMESPACE as u128).unwrap();
+
+        assert_eq!(
+            allocator.claim(WINNER_NAMESPACE as u128),
+            Err(SelectionError::UsageNumberReused(1))
+        );
+        assert_eq!(
+            allocator.allocate(WINNER_NAMESPACE),
+            usage_number(WINNER_NAMESPACE, 1)
+        );
+    }
+}
@@ -1,12 +1,48 @@
 pub mod rug_integer {
     use rug::Integer;
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    #[cfg(feature = "cbor")]
+    use std::cell::Cell;
+
+    #[cfg(feature = "cbor")]
+    thread_local! {
+        /// Whether big ints are currently written as byte strings rather than hex
+        static BYTE_INTEGERS: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Runs `f` with big ints (de)serialised as big-endian byte strings instead of hex
+    /// strings, as in the compact CBOR profile. Only affects the current thread.
+    ///
+    /// ### Arguments
+    ///
+    /// * `f` - Serialisation to run
+    #[cfg(feature = "cbor")]
+    pub(crate) fn with_byte_integers<R>(f: impl FnOnce() -> R) -> R {
+        struct Reset(bool);
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                BYTE_INTEGERS.with(|b| b.set(self.0));
+            }
+        }
+
+        let _reset = Reset(BYTE_INTEGERS.with(|b| b.replace(true)));
+        f()
+    }
 
     /// Serialisation function for big ints
     pub fn serialize<S>(x: &Integer, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        #[cfg(feature = "cbor")]
+        if BYTE_INTEGERS.with(Cell::get) {
+            if x.is_negative() {
+                return Err(serde::ser::Error::custom("Negative big ints have no byte encoding"));
+            }
+            return s.serialize_bytes(&x.to_digits::<u8>(rug::integer::Order::Msf));
+        }
+
         let value: String = x.to_string_radix(16);
         value.serialize(s)
     }
@@ -16,23 +52,97 @@ pub mod rug_integer {
     where
         D: Deserializer<'de>,
     {
+        #[cfg(feature = "cbor")]
+        if BYTE_INTEGERS.with(Cell::get) {
+            return d.deserialize_bytes(BytesVisitor);
+        }
+
         let value: String = Deserialize::deserialize(d)?;
         Integer::from_str_radix(&value, 16).map_err(serde::de::Error::custom)
     }
+
+    /// Visitor for big ints written as big-endian byte strings
+    #[cfg(feature = "cbor")]
+    struct BytesVisitor;
+
+    #[cfg(feature = "cbor")]
+    impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+        type Value = Integer;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a big-endian byte string")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Integer, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(Integer::from_digits(v, rug::integer::Order::Msf))
+        }
+    }
 }
 
 pub mod unicorn_selection {
-    use crate::unicorn::UnicornInfo;
+    use crate::unicorn::{g_key, UnicornInfo};
     use crate::fortuna::Fortuna;
+    use rug::Integer;
+    use miner_lottery_core::verify::csprng;
 
-    pub fn get_unicorn_prn(unicorn: &UnicornInfo, usage_number: u128) -> u64 {
-        let prn_seed: [u8; 32] = unicorn.g_value.as_bytes()[..32]
-            .try_into()
-            .unwrap();
+    /// Length in bytes of the PRN drawn from a UNICORN
+    pub const PRN_LEN: usize = 8;
+
+    /// Draws the raw PRN bytes for a given usage number of the UNICORN. Returns `None` if
+    /// the `g` value is too short to key the CSPRNG
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn`      - UNICORN to draw from
+    /// * `usage_number` - Usage number of the draw
+    pub fn get_unicorn_prn_bytes(
+        unicorn: &UnicornInfo,
+        usage_number: u128,
+    ) -> Option<[u8; PRN_LEN]> {
+        let mut csprng = get_unicorn_csprng(unicorn, usage_number)?;
+
+        let val = csprng.get_bytes(PRN_LEN).unwrap();
+        val[0..PRN_LEN].try_into().ok()
+    }
 
-        let mut csprng = Fortuna::new(&prn_seed, usage_number).unwrap();
+    /// Creates the Fortuna CSPRNG for a given usage number of the UNICORN. The first
+    /// `PRN_LEN` bytes of its output are the PRN returned by `get_unicorn_prn`. Returns
+    /// `None` if the `g` value is too short to key it
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn`      - UNICORN to draw from
+    /// * `usage_number` - Usage number of the draw
+    pub fn get_unicorn_csprng(unicorn: &UnicornInfo, usage_number: u128) -> Option<Fortuna> {
+        csprng(&unicorn.g_value, usage_number)
+    }
+
+    /// Creates the same Fortuna CSPRNG as `get_unicorn_csprng`, keyed straight from the
+    /// witness rather than its hex-encoded `g` value. Returns `None` if the witness is too
+    /// short to key it
+    ///
+    /// ### Arguments
+    ///
+    /// * `witness`      - Witness of the UNICORN to draw from
+    /// * `usage_number` - Usage number of the draw
+    pub fn get_witness_csprng(witness: &Integer, usage_number: u128) -> Option<Fortuna> {
+        Fortuna::new(&g_key(witness)?, usage_number).ok()
+    }
+
+    /// Draws the next PRN from a CSPRNG created by `get_unicorn_csprng`
+    ///
+    /// ### Arguments
+    ///
+    /// * `csprng` - CSPRNG to draw from
+    pub fn next_prn(csprng: &mut Fortuna) -> u64 {
+        let val = csprng.get_bytes(PRN_LEN).unwrap();
+        u64::from_be_bytes(val[0..PRN_LEN].try_into().unwrap())
+    }
 
-        let val = csprng.get_bytes(8).unwrap();
-        u64::from_be_bytes(val[0..8].try_into().unwrap())
+    pub fn get_unicorn_prn(unicorn: &UnicornInfo, usage_number: u128) -> Option<u64> {
+        get_unicorn_prn_bytes(unicorn, usage_number).map(u64::from_be_bytes)
     }
 }
\ No newline at end of file
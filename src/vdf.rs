@@ -0,0 +1,123 @@
+//! Verifiable delay functions producing the witness of a UNICORN.
+//!
+//! Rounds evaluate their UNICORN through the `Vdf` trait, so that round orchestration and
+//! selection can be driven by something other than the real eval. `UnicornVdf` is the Sloth
+//! eval of `unicorn`, and is what rounds use unless told otherwise.
+//!
+//! With the `test-utils` feature, `MockVdf` returns a deterministic witness at once, whatever
+//! the number of iterations, so that downstream crates can test their orchestration and
+//! selection logic without waiting on real evals. Its witnesses are not Sloth witnesses, so
+//! rounds evaluated with it do not pass `Round::verify` or transcript replay.
+
+use crate::unicorn::{EvalMode, Unicorn};
+use rug::Integer;
+
+/// Function evaluating a UNICORN to its witness and checking witnesses
+pub trait Vdf {
+    /// Evaluates the UNICORN, returning its witness and `g` value. Returns `None` if the
+    /// UNICORN cannot be evaluated
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn` - UNICORN to evaluate
+    fn eval(&self, unicorn: &Unicorn) -> Option<(Integer, String)>;
+
+    /// Checks that a witness is the output of the UNICORN
+    ///
+    /// ### Arguments
+    ///
+    /// * `unicorn` - UNICORN the witness is for
+    /// * `witness` - Witness to check
+    fn verify(&self, unicorn: &Unicorn, witness: &Integer) -> bool;
+}
+
+/// The Sloth eval of a UNICORN, in the given mode
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnicornVdf {
+    pub mode: EvalMode,
+}
+
+impl Vdf for UnicornVdf {
+    fn eval(&self, unicorn: &Unicorn) -> Option<(Integer, String)> {
+        unicorn.eval_with_mode(self.mode)
+    }
+
+    fn verify(&self, unicorn: &Unicorn, witness: &Integer) -> bool {
+        unicorn.verify_ref(&unicorn.seed, witness)
+    }
+}
+
+/// Instant, deterministic stand-in for the eval. The witness is the SHA-256 of the seed,
+/// modulus and number of iterations, reduced modulo the modulus
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MockVdf;
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockVdf {
+    /// Witness the mock gives for a UNICORN
+    fn witness(unicorn: &Unicorn) -> Option<Integer> {
+        use sha2::{Digest, Sha256};
+
+        if unicorn.modulus <= 1 {
+            return None;
+        }
+
+        let digest = Sha256::new()
+            .chain_update(unicorn.seed.to_string_radix(16))
+            .chain_update(unicorn.modulus.to_string_radix(16))
+            .chain_update(unicorn.iterations.to_be_bytes())
+            .finalize();
+        let witness = Integer::from_digits(&digest, rug::integer::Order::Msf);
+
+        Some(witness % &unicorn.modulus)
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl Vdf for MockVdf {
+    fn eval(&self, unicorn: &Unicorn) -> Option<(Integer, String)> {
+        let witness = Self::witness(unicorn)?;
+        let g_value = crate::unicorn::g_from_witness(&witness);
+
+        Some((witness, g_value))
+    }
+
+    fn verify(&self, unicorn: &Unicorn, witness: &Integer) -> bool {
+        Self::witness(unicorn).as_ref() == Some(witness)
+    }
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod vdf_tests {
+    use super::*;
+    use crate::config::DEFAULT_MODULUS;
+
+    fn unicorn(seed: u64, iterations: u64) -> Unicorn {
+        Unicorn {
+            iterations,
+            security_level: 1,
+            seed: Integer::from(seed),
+            modulus: Integer::from_str_radix(DEFAULT_MODULUS, 10).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that the real eval verifies through the trait, and that the mock is instant,
+    /// deterministic and only verifies its own witnesses
+    fn vdf_implementations() {
+        let small = unicorn(7, 100);
+        let (witness, g_value) = UnicornVdf::default().eval(&small).unwrap();
+        assert_eq!(small.eval(), Some((witness.clone(), g_value)));
+        assert!(UnicornVdf::default().verify(&small, &witness));
+
+        let huge = unicorn(7, u64::MAX);
+        let (mock_witness, mock_g) = MockVdf.eval(&huge).unwrap();
+        assert_eq!(MockVdf.eval(&huge), Some((mock_witness.clone(), mock_g)));
+        assert!(MockVdf.verify(&huge, &mock_witness));
+        assert!(!MockVdf.verify(&unicorn(8, u64::MAX), &mock_witness));
+    }
+}
@@ -0,0 +1,348 @@
+//! Verifiable random function (VRF) based self-sortition.
+//!
+//! Instead of a single draw selecting the winner, each participant evaluates a VRF with their
+//! own key over the UNICORN `g` value and the round id, and is selected if the output falls
+//! below a threshold, as in Algorand's cryptographic sortition. Anybody holding the
+//! participant's public key can check the proof and recompute the output.
+//!
+//! The VRF is RSA-FDH-VRF-SHA256 as described in RFC 9381, section 4, with MGF1 as the mask
+//! generation function. Hash inputs are prefixed with the suite string `0x01`. Keys are
+//! derived deterministically from a 32-byte secret through the Fortuna CSPRNG.
+//!
+//! RFC 9381 RSA-FDH-VRF only guarantees a unique output per input for trusted keys, whose
+//! modulus was validated or registered before the draw. With an exponent not coprime to
+//! `λ(n)`, several proofs verify for the same input and their holder can pick their output,
+//! so verification refuses any exponent but `VRF_PUBLIC_EXPONENT`. The modulus itself must
+//! still come from a registered key.
+
+use crate::fortuna::Fortuna;
+use crate::unicorn::UnicornInfo;
+use crate::utils::rug_integer;
+use rug::integer::{IsPrime, Order};
+use rug::Integer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Public exponent of VRF keys
+pub const VRF_PUBLIC_EXPONENT: u32 = 65_537;
+
+/// Usage number of the Fortuna CSPRNG used for key generation
+const KEYGEN_USAGE: u128 = 0;
+
+/// Number of rounds for Miller Rabin primality testing of key primes
+const KEYGEN_PRIME_ITERS: u32 = 25;
+
+/// Suite string of RSA-FDH-VRF-SHA256, as per RFC 9381
+const SUITE_STRING: u8 = 0x01;
+
+/// Domain separation prefixes, as per RFC 9381
+const MGF_DOMAIN: u8 = 0x01;
+const PROOF_TO_HASH_DOMAIN: u8 = 0x02;
+
+/// Public VRF key
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct VrfPublicKey {
+    #[serde(with = "rug_integer")]
+    pub n: Integer,
+    #[serde(with = "rug_integer")]
+    pub e: Integer,
+}
+
+/// Secret VRF key
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct VrfSecretKey {
+    pub public: VrfPublicKey,
+    #[serde(with = "rug_integer")]
+    d: Integer,
+}
+
+/// Ticket proving a participant's sortition result for a round
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SortitionTicket {
+    pub round_id: u64,
+    /// Hex-encoded VRF proof
+    pub proof: String,
+    /// Hex-encoded VRF output
+    pub output: String,
+}
+
+impl VrfPublicKey {
+    /// Length of the modulus in bytes
+    pub fn byte_len(&self) -> usize {
+        (self.n.significant_bits() as usize).div_ceil(8)
+    }
+
+    /// Verifies a VRF proof over `alpha`, returning the VRF output if valid. Keys with an
+    /// exponent other than `VRF_PUBLIC_EXPONENT` are refused
+    ///
+    /// ### Arguments
+    ///
+    /// * `alpha` - Input the proof claims to be over
+    /// * `proof` - VRF proof
+    pub fn verify(&self, alpha: &[u8], proof: &[u8]) -> Option<[u8; 32]> {
+        if self.e != VRF_PUBLIC_EXPONENT || proof.len() != self.byte_len() {
+            return None;
+        }
+
+        let s = Integer::from_digits(proof, Order::MsfBe);
+        if s >= self.n {
+            return None;
+        }
+
+        let m = s.pow_mod(&self.e, &self.n).ok()?;
+        if m != self.encode(alpha) {
+            return None;
+        }
+
+        Some(proof_to_hash(proof))
+    }
+
+    /// Full domain hash of `alpha` onto the key's modulus
+    fn encode(&self, alpha: &[u8]) -> Integer {
+        let k = self.byte_len();
+        let mut seed = vec![SUITE_STRING, MGF_DOMAIN];
+        seed.extend((k as u32).to_be_bytes());
+        seed.extend(i2osp(&self.n, k));
+        seed.extend(alpha);
+
+        Integer::from_digits(&mgf1(&seed, k - 1), Order::MsfBe)
+    }
+}
+
+impl VrfSecretKey {
+    /// Derives a key pair with a modulus of `bits` bits from a 32-byte secret. Returns `None`
+    /// if `bits` is too small to hold an RFC 9381 encoding
+    ///
+    /// ### Arguments
+    ///
+    /// * `secret` - Secret to derive the key from
+    /// * `bits`   - Size of the modulus in bits; must be a multiple of 16
+    pub fn generate(secret: &[u8; 32], bits: u32) -> Option<Self> {
+        if bits < 512 || !bits.is_multiple_of(16) {
+            return None;
+        }
+
+        let mut csprng = Fortuna::new(secret, KEYGEN_USAGE).ok()?;
+        let e = Integer::from(VRF_PUBLIC_EXPONENT);
+
+        let p = random_prime(&mut csprng, bits / 2, &e)?;
+        let mut q = random_prime(&mut csprng, bits / 2, &e)?;
+        while q == p {
+            q = random_prime(&mut csprng, bits / 2, &e)?;
+        }
+
+        let lambda = Integer::from(&p - 1u32).lcm(&Integer::from(&q - 1u32));
+        let d = e.clone().invert(&lambda).ok()?;
+
+        Some(Self {
+            public: VrfPublicKey { n: p * q, e },
+            d,
+        })
+    }
+
+    /// Produces a VRF proof over `alpha`
+    ///
+    /// ### Arguments
+    ///
+    /// * `alpha` - Input to evaluate the VRF on
+    pub fn prove(&self, alpha: &[u8]) -> Vec<u8> {
+        let m = self.public.encode(alpha);
+        let s = m.pow_mod(&self.d, &self.public.n).unwrap();
+
+        i2osp(&s, self.public.byte_len())
+    }
+}
+
+/// Computes the VRF output for a proof
+///
+/// ### Arguments
+///
+/// * `proof` - VRF proof
+pub fn proof_to_hash(proof: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([SUITE_STRING, PROOF_TO_HASH_DOMAIN]);
+    hasher.update(proof);
+    hasher.finalize().into()
+}
+
+/// Constructs the VRF input for a round from the UNICORN `g` value and the round id
+///
+/// ### Arguments
+///
+/// * `unicorn`  - UNICORN of the round
+/// * `round_id` - Round identifier
+pub fn sortition_input(unicorn: &UnicornInfo, round_id: u64) -> Vec<u8> {
+    let mut alpha = unicorn.g_value.as_bytes().to_vec();
+    alpha.extend(round_id.to_be_bytes());
+    alpha
+}
+
+/// Whether a VRF output selects its owner, given that `expected` out of `population`
+/// participants should be selected on average. The first 8 bytes of the output are read as a
+/// fraction of 2^64 and compared exactly against `expected / population`
+///
+/// ### Arguments
+///
+/// * `output`     - VRF output
+/// * `expected`   - Expected number of selected participants
+/// * `population` - Total number of participants
+pub fn is_selected(output: &[u8; 32], expected: u64, population: u64) -> bool {
+    let value = u64::from_be_bytes(output[..8].try_into().unwrap()) as u128;
+    value * (population as u128) < (expected as u128) << 64
+}
+
+/// Evaluates the VRF for a round and returns a ticket if the participant is selected
+///
+/// ### Arguments
+///
+/// * `key`        - Participant's secret key
+/// * `unicorn`    - UNICORN of the round
+/// * `round_id`   - Round identifier
+/// * `expected`   - Expected number of selected participants
+/// * `population` - Total number of participants
+pub fn self_select(
+    key: &VrfSecretKey,
+    unicorn: &UnicornInfo,
+    round_id: u64,
+    expected: u64,
+    population: u64,
+) -> Option<SortitionTicket> {
+    let proof = key.prove(&sortition_input(unicorn, round_id));
+    let output = proof_to_hash(&proof);
+
+    is_selected(&output, expected, population).then(|| SortitionTicket {
+        round_id,
+        proof: hex::encode(proof),
+        output: hex::encode(output),
+    })
+}
+
+/// Verifies a sortition ticket against the participant's public key
+///
+/// ### Arguments
+///
+/// * `key`        - Participant's public key
+/// * `unicorn`    - UNICORN of the round
+/// * `ticket`     - Ticket to verify
+/// * `expected`   - Expected number of selected participants
+/// * `population` - Total number of participants
+pub fn verify_ticket(
+    key: &VrfPublicKey,
+    unicorn: &UnicornInfo,
+    ticket: &SortitionTicket,
+    expected: u64,
+    population: u64,
+) -> bool {
+    let Ok(proof) = hex::decode(&ticket.proof) else {
+        return false;
+    };
+
+    match key.verify(&sortition_input(unicorn, ticket.round_id), &proof) {
+        Some(output) => {
+            hex::encode(output) == ticket.output && is_selected(&output, expected, population)
+        }
+        None => false,
+    }
+}
+
+/// Draws a random prime of exactly `bits` bits with `p - 1` coprime to `e`
+fn random_prime(csprng: &mut Fortuna, bits: u32, e: &Integer) -> Option<Integer> {
+    loop {
+        let bytes = csprng.get_bytes(bits as usize / 8).ok()?;
+        let mut candidate = Integer::from_digits(&bytes, Order::MsfBe);
+
+        // Set the top two bits so the product of two primes has the full size
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(bits - 2, true);
+        candidate.next_prime_mut();
+
+        if candidate.significant_bits() == bits
+            && !matches!(candidate.is_probably_prime(KEYGEN_PRIME_ITERS), IsPrime::No)
+            && Integer::from(&candidate - 1u32).gcd(e) == 1
+        {
+            return Some(candidate);
+        }
+    }
+}
+
+/// Integer to octet string of length `len`, as per RFC 8017
+fn i2osp(x: &Integer, len: usize) -> Vec<u8> {
+    let digits = x.to_digits::<u8>(Order::MsfBe);
+    let mut out = vec![0; len.saturating_sub(digits.len())];
+    out.extend(digits);
+    out
+}
+
+/// MGF1 with SHA-256, as per RFC 8017
+fn mgf1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + 32);
+    let mut counter = 0u32;
+
+    while out.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        out.extend(hasher.finalize());
+        counter += 1;
+    }
+
+    out.truncate(len);
+    out
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod vrf_tests {
+    use super::*;
+
+    fn test_unicorn() -> UnicornInfo {
+        UnicornInfo {
+            g_value: "0106834db40e90d1cafaa9e4c1981873186ebf019629852059aaf8e4ca35da01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    /// Checks that proofs verify only for the right key and input
+    fn prove_and_verify() {
+        let key = VrfSecretKey::generate(&[1; 32], 512).unwrap();
+        let other = VrfSecretKey::generate(&[2; 32], 512).unwrap();
+        let proof = key.prove(b"alpha");
+
+        assert_eq!(key.public.n.significant_bits(), 512);
+        assert_eq!(
+            key.public.verify(b"alpha", &proof),
+            Some(proof_to_hash(&proof))
+        );
+        assert_eq!(key.public.verify(b"beta", &proof), None);
+        assert_eq!(other.public.verify(b"alpha", &proof), None);
+
+        // With `e = 1` the encoding of any input is its own proof
+        let trivial = VrfPublicKey {
+            n: key.public.n.clone(),
+            e: Integer::from(1),
+        };
+        let forged = i2osp(&trivial.encode(b"alpha"), trivial.byte_len());
+        assert_eq!(trivial.verify(b"alpha", &forged), None);
+        assert_eq!(VrfSecretKey::generate(&[1; 32], 512), Some(key));
+    }
+
+    #[test]
+    /// Checks that sortition tickets verify and follow the threshold
+    fn self_sortition() {
+        let key = VrfSecretKey::generate(&[3; 32], 512).unwrap();
+        let unicorn = test_unicorn();
+
+        let tickets: Vec<SortitionTicket> = (0..64)
+            .filter_map(|round_id| self_select(&key, &unicorn, round_id, 1, 2))
+            .collect();
+
+        assert!(tickets.len() > 16 && tickets.len() < 48);
+        assert!(tickets
+            .iter()
+            .all(|t| verify_ticket(&key.public, &unicorn, t, 1, 2)));
+        assert!(self_select(&key, &unicorn, 0, 0, 2).is_none());
+        assert!(self_select(&key, &unicorn, 0, 2, 2).is_some());
+    }
+}
@@ -0,0 +1,137 @@
+//! Browser verification through wasm-bindgen, built with the `wasm` feature.
+//!
+//! `verify_round` audits a published round, selection transcript or `UnicornInfo` given as
+//! JSON, so that block explorers can let users check a lottery outcome without trusting the
+//! explorer's server. Nothing is fetched: the page passes in the artifact it already shows.
+//!
+//! ```js
+//! import init, { verify_round } from "./miner_lottery.js";
+//!
+//! await init();
+//! const report = verify_round(await (await fetch(url)).text());
+//! if (report.passed) console.log(`winner: ${report.winner}`);
+//! ```
+//!
+//! The audit runs on GMP through `rug`, so the module must be built for a wasm target GMP
+//! can be compiled for.
+
+use crate::audit::{Artifact, AuditReport};
+use wasm_bindgen::prelude::*;
+
+/// Outcome of verifying a published artifact in the browser
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationReport {
+    artifact: Artifact,
+    report: AuditReport,
+}
+
+#[wasm_bindgen]
+impl VerificationReport {
+    /// Whether every check passed
+    #[wasm_bindgen(getter)]
+    pub fn passed(&self) -> bool {
+        self.report.passed()
+    }
+
+    /// Kind of the verified artifact: `round`, `transcript` or `unicorn`
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        match self.artifact {
+            Artifact::Round(_) => "round",
+            Artifact::Transcript(_) => "transcript",
+            Artifact::Unicorn(_) => "unicorn",
+        }
+        .to_string()
+    }
+
+    /// The `g` value of the UNICORN the outcome was drawn from
+    #[wasm_bindgen(getter, js_name = gValue)]
+    pub fn g_value(&self) -> Option<String> {
+        match &self.artifact {
+            Artifact::Unicorn(info) => Some(info.g_value.clone()),
+            artifact => artifact.transcript().map(|t| t.g_value.clone()),
+        }
+    }
+
+    /// The winner, if the artifact records a draw. Only meaningful if the verification passed
+    #[wasm_bindgen(getter)]
+    pub fn winner(&self) -> Option<String> {
+        self.artifact.transcript().map(|t| t.winner.clone())
+    }
+
+    /// Descriptions of the failed checks
+    #[wasm_bindgen(getter)]
+    pub fn failures(&self) -> Vec<String> {
+        self.report
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| match &c.detail {
+                Some(detail) => format!("{:?}: {}", c.check, detail),
+                None => format!("{:?}", c.check),
+            })
+            .collect()
+    }
+
+    /// The outcome of every check, as the `AuditReport` JSON served by `POST /verify`
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.report).unwrap()
+    }
+}
+
+/// Audits a published round, selection transcript or `UnicornInfo` given as JSON
+///
+/// ### Arguments
+///
+/// * `json` - JSON of the artifact
+#[wasm_bindgen]
+pub fn verify_round(json: &str) -> Result<VerificationReport, JsError> {
+    verify_json(json).map_err(|e| JsError::new(&e))
+}
+
+fn verify_json(json: &str) -> Result<VerificationReport, String> {
+    let artifact = Artifact::from_json(json)
+        .map_err(|e| format!("Not a round, transcript or UNICORN: {}", e))?;
+    let report = artifact
+        .audit()
+        .ok_or_else(|| "Round has not been published".to_string())?;
+
+    Ok(VerificationReport { artifact, report })
+}
+
+/*---- TESTS ----*/
+
+#[cfg(test)]
+mod wasm_tests {
+    use super::*;
+    use crate::transcript::SelectionTranscript;
+    use crate::unicorn::UnicornFixedParam;
+
+    #[test]
+    /// Checks that a transcript verifies, a tampered one reports its failures and a round
+    /// that was never published is refused
+    fn verifies_artifacts() {
+        let params = UnicornFixedParam::test();
+        let inputs = vec!["miner_a".to_string(), "miner_b".to_string()];
+        let transcript = SelectionTranscript::record(&inputs, &params, 1).unwrap();
+
+        let report = verify_json(&serde_json::to_string(&transcript).unwrap()).unwrap();
+        assert!(report.passed());
+        assert_eq!(report.kind(), "transcript");
+        assert_eq!(report.winner(), Some(transcript.winner.clone()));
+        assert_eq!(report.g_value(), Some(transcript.g_value.clone()));
+        assert!(report.failures().is_empty());
+
+        let mut tampered = transcript;
+        tampered.g_value = "00".to_string();
+        let report = verify_json(&serde_json::to_string(&tampered).unwrap()).unwrap();
+        assert!(!report.passed());
+        assert!(!report.failures().is_empty());
+
+        let round = crate::rounds::Round::new(1, params, 1, Default::default(), 0);
+        assert!(verify_json(&round.to_json_pretty().unwrap()).is_err());
+        assert!(verify_json("{}").is_err());
+    }
+}